@@ -0,0 +1,46 @@
+//! The runtime shared by parsers generated with `peg-pack --runtime crate`.
+//!
+//! Parsers generated by a version of peg-pack depend on the same version of this crate, which is
+//! the runtime peg-pack otherwise copies into the output directory. Its API exists to serve the
+//! generated code, so only use it through a generated parser.
+
+// The unsafe functions of the runtime are only called by generated code, which upholds their
+// contracts, and `Input` deliberately asks implementors for as little as possible
+#![allow(clippy::missing_safety_doc, clippy::len_without_is_empty)]
+
+mod runtime;
+
+pub use runtime::*;
+
+/// Pairs the name of a runtime source file with its contents
+macro_rules! source_file {
+    ($name:literal) => {
+        ($name, include_bytes!(concat!("runtime/", $name)))
+    };
+}
+
+/// The source files of the runtime, which peg-pack copies next to parsers that don't depend on
+/// this crate
+#[doc(hidden)]
+pub const SOURCE_FILES: &[(&str, &[u8])] = &[
+    source_file!("mod.rs"),
+    source_file!("context.rs"),
+    source_file!("grammar.rs"),
+    source_file!("input.rs"),
+    source_file!("line_map.rs"),
+    source_file!("options.rs"),
+    source_file!("result.rs"),
+    source_file!("buffered_iter.rs"),
+    source_file!("array_vec.rs"),
+    source_file!("small_vec.rs"),
+    source_file!("stack.rs"),
+    source_file!("cache.rs"),
+    source_file!("refc.rs"),
+    source_file!("text.rs"),
+    source_file!("generate.rs"),
+];
+
+/// The macro expanding to the grammar specific parts of the runtime, which peg-pack embeds in
+/// parsers that depend on this crate
+#[doc(hidden)]
+pub const GENERATE_MACRO: &str = include_str!("runtime/generate.rs");
@@ -1,7 +1,7 @@
 use std::hint::unreachable_unchecked;
 use std::iter::FusedIterator;
-use std::mem;
 use std::mem::MaybeUninit;
+use std::ptr;
 
 pub struct ArrayVec<T, const N: usize> {
     len: u8,
@@ -103,7 +103,7 @@ impl<T, const N: usize> ArrayVec<T, N> {
     fn take_all_maybe_uninit(&mut self) -> [MaybeUninit<T>; N] {
         self.assert_invariants();
         self.len = 0;
-        unsafe { mem::replace(&mut self.values, MaybeUninit::uninit().assume_init()) }
+        unsafe { ptr::read(&self.values) }
     }
 }
 
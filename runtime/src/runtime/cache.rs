@@ -0,0 +1,205 @@
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+
+use super::refc::Refc;
+use super::Grammar;
+use super::{Match, ParseResult};
+
+/// Where the results of cache points and growth points are kept, chosen by the grammar
+pub trait CacheStore<G: Grammar> {
+    fn new(grammar: &G) -> Self;
+
+    /// Starts counting hits, misses and saved work for each slot
+    fn enable_profiling(&mut self);
+
+    fn take_profile(&mut self) -> Option<CacheProfile>;
+
+    fn get(&mut self, slot: u32, position: u32) -> Option<ParseResult<G>>;
+
+    /// Looks up a result without counting it as a hit or miss
+    fn peek(&self, slot: u32, position: u32) -> Option<ParseResult<G>>;
+
+    /// Stores a result, where `work` is the work originally needed to compute it
+    fn insert(
+        &mut self,
+        slot: u32,
+        position: u32,
+        result: ParseResult<G>,
+        work: u32,
+    ) -> ParseResult<G>;
+}
+
+pub struct Cache<G: Grammar> {
+    mappings: Box<[BTreeMap<u32, Entry<G>>]>,
+    profile: Option<Box<[SlotCounters]>>,
+}
+
+/// How effective each cache slot was over a parse, indexed by slot.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CacheProfile {
+    slots: Vec<SlotProfile>,
+}
+
+#[allow(unused)]
+impl CacheProfile {
+    /// The statistics for each cache slot, indexed by slot.
+    pub fn slots(&self) -> &[SlotProfile] {
+        &self.slots
+    }
+}
+
+/// How effective a single cache slot was over a parse.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct SlotProfile {
+    /// The number of lookups that found a cached result.
+    pub hits: u64,
+    /// The number of lookups that found nothing.
+    pub misses: u64,
+    /// The number of results stored. Cheap results are not stored.
+    pub insertions: u64,
+    /// The total work of the cached results that hits avoided recomputing.
+    pub work_saved: u64,
+}
+
+#[derive(Default)]
+struct SlotCounters {
+    profile: SlotProfile,
+    /// The work each stored result originally took, by position
+    work: BTreeMap<u32, u32>,
+}
+
+impl<G: Grammar> CacheStore<G> for Cache<G> {
+    fn new(grammar: &G) -> Self {
+        let mut mappings = Vec::with_capacity(grammar.cache_slots());
+
+        for _ in 0..grammar.cache_slots() {
+            mappings.push(BTreeMap::new());
+        }
+
+        Self {
+            mappings: mappings.into_boxed_slice(),
+            profile: None,
+        }
+    }
+
+    fn enable_profiling(&mut self) {
+        let counters = (0..self.mappings.len()).map(|_| SlotCounters::default());
+        self.profile = Some(counters.collect());
+    }
+
+    fn take_profile(&mut self) -> Option<CacheProfile> {
+        let counters = self.profile.take()?;
+        let slots = counters.iter().map(|counters| counters.profile).collect();
+        Some(CacheProfile { slots })
+    }
+
+    fn get(&mut self, slot: u32, position: u32) -> Option<ParseResult<G>> {
+        if let Some(profile) = &mut self.profile {
+            let counters = unsafe { profile.get_unchecked_mut(slot as usize) };
+
+            match counters.work.get(&position) {
+                Some(work) => {
+                    counters.profile.hits += 1;
+                    counters.profile.work_saved += *work as u64;
+                }
+                None => counters.profile.misses += 1,
+            }
+        }
+
+        self.peek(slot, position)
+    }
+
+    fn peek(&self, slot: u32, position: u32) -> Option<ParseResult<G>> {
+        let slot_mappings = unsafe { self.mappings.get_unchecked(slot as usize) };
+
+        match slot_mappings.get(&position)? {
+            Entry::Matched(value) => {
+                let value = Match::unboxed(value);
+                Some(ParseResult::Matched(value))
+            }
+            Entry::Unmatched {
+                scan_distance,
+                work,
+            } => Some(ParseResult::Unmatched {
+                scan_distance: *scan_distance,
+                work: *work,
+            }),
+        }
+    }
+
+    fn insert(
+        &mut self,
+        slot: u32,
+        position: u32,
+        result: ParseResult<G>,
+        work: u32,
+    ) -> ParseResult<G> {
+        if let Some(profile) = &mut self.profile {
+            let counters = unsafe { profile.get_unchecked_mut(slot as usize) };
+            counters.profile.insertions += 1;
+            counters.work.insert(position, work);
+        }
+
+        let (insertion, result) = match result {
+            ParseResult::Matched(value) => {
+                let boxed = Match::boxed(value);
+                let result = Match::unboxed(&boxed);
+                (Entry::Matched(boxed), ParseResult::Matched(result))
+            }
+            ParseResult::Unmatched {
+                scan_distance,
+                work,
+            } => {
+                let insertion = Entry::Unmatched {
+                    scan_distance,
+                    work,
+                };
+                (insertion, result)
+            }
+        };
+
+        let slot_mappings = unsafe { self.mappings.get_unchecked_mut(slot as usize) };
+        slot_mappings.insert(position, insertion);
+
+        result
+    }
+}
+
+/// Stands in for a cache in grammars without any cache points or growth points, which never
+/// look a result up, so that their parsers carry no cache at all
+pub struct NoCache<G: Grammar>(PhantomData<G>);
+
+impl<G: Grammar> CacheStore<G> for NoCache<G> {
+    fn new(_grammar: &G) -> Self {
+        Self(PhantomData)
+    }
+
+    fn enable_profiling(&mut self) {}
+
+    fn take_profile(&mut self) -> Option<CacheProfile> {
+        Some(CacheProfile { slots: Vec::new() })
+    }
+
+    fn get(&mut self, _slot: u32, _position: u32) -> Option<ParseResult<G>> {
+        None
+    }
+
+    fn peek(&self, _slot: u32, _position: u32) -> Option<ParseResult<G>> {
+        None
+    }
+
+    fn insert(
+        &mut self,
+        _slot: u32,
+        _position: u32,
+        result: ParseResult<G>,
+        _work: u32,
+    ) -> ParseResult<G> {
+        result
+    }
+}
+
+enum Entry<G: Grammar> {
+    Matched(Refc<Match<G>>),
+    Unmatched { scan_distance: u32, work: u32 },
+}
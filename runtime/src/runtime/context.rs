@@ -0,0 +1,943 @@
+use std::mem;
+use std::mem::MaybeUninit;
+
+use super::cache::{CacheProfile, CacheStore};
+use super::grammar::Grammar;
+use super::input::Input;
+use super::options::{Abort, ParseOptions};
+use super::result::Match;
+use super::result::ParseResult;
+use super::stack::Stack;
+use super::{
+    State, CACHE_WORK, CHOICE_WORK, LABEL_WORK, MARK_ERROR_WORK, MAX_UNCACHED_WORK, NOT_AHEAD_WORK,
+    PROGRESS_INTERVAL, REPEAT_WORK, SEQ_WORK, SERIES_WORK,
+};
+
+/// The states at the bottom of the stack that don't count towards its depth,
+/// which are the finish state and the start state of the grammar
+const BASE_STATES: usize = 2;
+
+#[allow(non_snake_case)]
+fn FINISH_STATE<I: Input<G::Symbol> + ?Sized, G: Grammar>(_ctx: &mut Context<I, G>) {}
+
+pub struct Context<'a, I: Input<G::Symbol> + ?Sized, G: Grammar> {
+    input: &'a I,
+    grammar: &'a G,
+    /// The position parsing began at, which the result is relative to
+    start: u32,
+    position: u32,
+    state_stack: Stack<State<I, G>>,
+    result_stack: Stack<MaybeUninit<ParseResult<G>>>,
+    cache: G::Cache,
+    max_depth: usize,
+    /// The number of times an error may still be marked
+    remaining_error_marks: u32,
+    /// Whether failed parses are reported as the best partial match followed
+    /// by an error, which either of the lenient and match failures options ask for
+    match_failures: bool,
+    /// The furthest reaching match of a prefix of the input discarded while
+    /// backtracking, and the depth of the state stack it was discarded at
+    partial: Option<(Match<G>, usize)>,
+    abort: Option<Abort>,
+    trace_depth: usize,
+    /// The positions that left recursive rules are growing seeds at, innermost
+    /// last. Each is at or after the ones before it
+    growing: Vec<u32>,
+    /// The number of items matched by each list being matched, innermost last
+    list_lengths: Vec<u32>,
+}
+
+impl<'a, I: Input<G::Symbol> + ?Sized, G: Grammar> Context<'a, I, G> {
+    #[allow(unused)]
+    pub fn run(input: &I, grammar: &G, options: &ParseOptions) -> Result<ParseResult<G>, Abort> {
+        // Scan distances may extend one byte past the end of the input, so
+        // that position must be representable too
+        if input.len() >= u32::MAX as usize {
+            return Err(Abort::InputTooLong);
+        }
+
+        Context::new(input, grammar, options).finish(None)
+    }
+
+    /// Like [`run`](Self::run), but periodically reports the furthest position
+    /// reached so far to a callback
+    #[allow(unused)]
+    pub fn run_with_progress(
+        input: &I,
+        grammar: &G,
+        options: &ParseOptions,
+        progress: &mut dyn FnMut(u32),
+    ) -> Result<ParseResult<G>, Abort> {
+        if input.len() >= u32::MAX as usize {
+            return Err(Abort::InputTooLong);
+        }
+
+        Context::new(input, grammar, options).finish(Some(progress))
+    }
+
+    /// Like [`run`](Self::run), but also profiles how effective each cache slot was
+    #[allow(unused)]
+    pub fn run_profiled(
+        input: &I,
+        grammar: &G,
+        options: &ParseOptions,
+    ) -> (Result<ParseResult<G>, Abort>, CacheProfile) {
+        let mut context = Context::new(input, grammar, options);
+        context.cache.enable_profiling();
+
+        let result = if input.len() >= u32::MAX as usize {
+            Err(Abort::InputTooLong)
+        } else {
+            context.finish(None)
+        };
+
+        (result, context.cache.take_profile().unwrap())
+    }
+
+    fn finish(
+        &mut self,
+        mut progress: Option<&mut dyn FnMut(u32)>,
+    ) -> Result<ParseResult<G>, Abort> {
+        // States run since progress was last reported, and the furthest
+        // position reported so far
+        let mut steps = 0;
+        let mut furthest = self.start;
+
+        unsafe {
+            loop {
+                let current_state = self.state();
+                let finish_state: State<I, G> = FINISH_STATE::<I, G>;
+
+                if current_state as usize == finish_state as usize {
+                    break;
+                }
+
+                current_state(self);
+
+                if let Some(report) = &mut progress {
+                    steps += 1;
+
+                    if steps == PROGRESS_INTERVAL {
+                        steps = 0;
+                        furthest = furthest.max(self.position);
+                        report(furthest);
+                    }
+                }
+
+                if let Some(abort) = self.abort {
+                    self.discard_results();
+                    return Err(abort);
+                }
+            }
+
+            let result = self.take_result();
+
+            match result {
+                ParseResult::Unmatched {
+                    scan_distance,
+                    work,
+                } if self.match_failures => Ok(self.failure_match(scan_distance, work)),
+                result => Ok(result),
+            }
+        }
+    }
+
+    /// The best partial match followed by an error covering the rest of the
+    /// input, standing in for an unmatched parse
+    fn failure_match(&mut self, scan_distance: u32, work: u32) -> ParseResult<G> {
+        let length = self.input.len() as u32 - self.start;
+
+        let prefix = match self.partial.take() {
+            Some((partial, _)) => partial,
+            None => Match::empty(0, 0),
+        };
+
+        let remainder = length - prefix.distance();
+        let error = Match::error_free(remainder, remainder, 0);
+        let error = ParseResult::Matched(error).mark_error(self.grammar.start_expected());
+
+        let value = match error {
+            ParseResult::Matched(error) => Match::combine(prefix, error),
+            ParseResult::Unmatched { .. } => unreachable!(),
+        };
+
+        ParseResult::Matched(value.extend_scan_distance(scan_distance).with_work(work))
+    }
+
+    /// Keeps a match discarded while backtracking if it matches a prefix of
+    /// the input that reaches further than any kept so far. Of matches
+    /// reaching equally far, the one discarded closest to the start rule wins
+    fn retain_partial(&mut self, partial: Match<G>) {
+        let depth = self.state_stack.len();
+
+        let better = match &self.partial {
+            Some((best, best_depth)) => {
+                partial.distance() > best.distance()
+                    || (partial.distance() == best.distance() && depth < *best_depth)
+            }
+            None => true,
+        };
+
+        if better {
+            self.partial = Some((partial, depth));
+        }
+    }
+
+    fn new(input: &'a I, grammar: &'a G, options: &ParseOptions) -> Self {
+        let mut states = Stack::<State<I, G>>::of(FINISH_STATE::<I, G>);
+        states.push(grammar.start_state());
+
+        let start = Self::start_position(input, options);
+
+        Self {
+            input,
+            grammar,
+            start,
+            position: start,
+            state_stack: states,
+            result_stack: Stack::of(MaybeUninit::uninit()),
+            cache: G::Cache::new(grammar),
+            max_depth: options.max_depth.unwrap_or(usize::MAX),
+            remaining_error_marks: options.max_error_marks.unwrap_or(u32::MAX),
+            match_failures: options.match_failures || options.lenient,
+            partial: None,
+            abort: None,
+            trace_depth: 0,
+            growing: Vec::new(),
+            list_lengths: Vec::new(),
+        }
+    }
+
+    /// Where parsing begins, which is at the end of the input if the requested
+    /// start is past it
+    pub fn start_position(input: &I, options: &ParseOptions) -> u32 {
+        options.start.min(input.len() as u32)
+    }
+
+    fn state(&self) -> State<I, G> {
+        unsafe { *self.state_stack.top().unwrap_unchecked() }
+    }
+
+    fn state_mut(&mut self) -> &mut State<I, G> {
+        unsafe { self.state_stack.top_mut().unwrap_unchecked() }
+    }
+
+    fn push_state(&mut self, state: State<I, G>) {
+        if self.state_stack.len().saturating_sub(BASE_STATES) >= self.max_depth {
+            self.abort = Some(Abort::DepthExceeded);
+            return;
+        }
+
+        self.state_stack.push(state);
+    }
+
+    unsafe fn pop_state(&mut self) {
+        self.state_stack.pop();
+    }
+
+    /// Moves forward over input that has been matched. The input length is
+    /// checked up front, so this can only overflow if the grammar is buggy
+    fn advance(&mut self, distance: u32) {
+        debug_assert!(
+            self.position.checked_add(distance).is_some(),
+            "position overflowed"
+        );
+
+        self.position = self.position.wrapping_add(distance);
+    }
+
+    /// Moves back over input that was matched by a result being discarded
+    fn rewind(&mut self, distance: u32) {
+        debug_assert!(distance <= self.position, "position underflowed");
+        self.position = self.position.wrapping_sub(distance);
+
+        if distance > 0 {
+            self.grammar.backtracked(self.position);
+        }
+    }
+
+    unsafe fn result(&self) -> &ParseResult<G> {
+        self.result_stack.top().unwrap_unchecked().assume_init_ref()
+    }
+
+    fn set_result(&mut self, result: ParseResult<G>) {
+        unsafe {
+            *self.result_stack.top_mut().unwrap_unchecked() = MaybeUninit::new(result);
+        }
+    }
+
+    fn stash_result(&mut self) {
+        self.result_stack.push(MaybeUninit::uninit());
+    }
+
+    unsafe fn pop_result(&mut self) -> ParseResult<G> {
+        self.result_stack.pop().unwrap_unchecked().assume_init()
+    }
+
+    unsafe fn take_result(&mut self) -> ParseResult<G> {
+        let top = self.result_stack.top_mut().unwrap_unchecked();
+        mem::replace(top, MaybeUninit::uninit()).assume_init()
+    }
+
+    /// Drops the results of an abandoned parse. States are only ever pushed while the top result
+    /// is uninitialized, and every result beneath it is a stashed result, so this must only be
+    /// called after an abort in `push_state`
+    unsafe fn discard_results(&mut self) {
+        self.result_stack.pop();
+
+        while let Some(result) = self.result_stack.pop() {
+            mem::drop(result.assume_init());
+        }
+    }
+}
+
+#[allow(unused)]
+impl<'a, I: Input<G::Symbol> + ?Sized, G: Grammar> Context<'a, I, G> {
+    pub unsafe fn state_seq_start(&mut self, first: State<I, G>, continuation: State<I, G>) {
+        *self.state_mut() = continuation;
+        self.push_state(first);
+    }
+
+    pub unsafe fn state_seq_middle(&mut self, second: State<I, G>, continuation: State<I, G>) {
+        if self.result().is_match() {
+            self.stash_result();
+            *self.state_mut() = continuation;
+            self.push_state(second);
+        } else {
+            let result = self.take_result().add_work(SEQ_WORK);
+            self.set_result(result);
+            self.pop_state();
+        }
+    }
+
+    pub unsafe fn state_seq_end(&mut self) {
+        let second = self.pop_result();
+        let first = self.take_result().unwrap_match_unchecked();
+
+        match second {
+            ParseResult::Matched(second) => {
+                let result = Match::combine(first, second).add_work(SEQ_WORK);
+                self.set_result(ParseResult::Matched(result));
+            }
+            ParseResult::Unmatched {
+                scan_distance,
+                work,
+            } => {
+                self.rewind(first.distance());
+
+                let scan_distance =
+                    u32::max(first.scan_distance(), first.distance() + scan_distance);
+
+                let work = work + first.work() + SEQ_WORK;
+                self.set_result(ParseResult::Unmatched {
+                    scan_distance,
+                    work,
+                });
+
+                if self.match_failures && self.position == self.start {
+                    self.retain_partial(first);
+                }
+            }
+        }
+
+        self.pop_state();
+    }
+
+    pub unsafe fn state_choice_start(&mut self, first: State<I, G>, continuation: State<I, G>) {
+        *self.state_mut() = continuation;
+        self.push_state(first);
+    }
+
+    pub unsafe fn state_choice_middle(&mut self, second: State<I, G>, continuation: State<I, G>) {
+        if self.result().is_error_free() {
+            let result = self.take_result().add_work(CHOICE_WORK);
+            self.set_result(result);
+            self.pop_state();
+        } else {
+            self.rewind(self.result().distance());
+            self.stash_result();
+            *self.state_mut() = continuation;
+            self.push_state(second);
+        }
+    }
+
+    pub unsafe fn state_choice_end(&mut self) {
+        let mut second = self.pop_result();
+        let first = self.take_result();
+
+        let work = first.work() + second.work() + CHOICE_WORK;
+
+        if !first.is_match() {
+            let result = second
+                .extend_scan_distance(first.scan_distance())
+                .with_work(work);
+            self.set_result(result);
+            self.pop_state();
+            return;
+        }
+
+        let first = first.unwrap_match_unchecked();
+
+        if !second.is_match() {
+            self.advance(first.distance());
+            let result = first
+                .extend_scan_distance(second.scan_distance())
+                .with_work(work);
+            self.set_result(ParseResult::Matched(result));
+            self.pop_state();
+            return;
+        }
+
+        let second = second.unwrap_match_unchecked();
+
+        let first_dist = first.error_distance().unwrap_unchecked();
+        let second_dist = second.error_distance();
+
+        let use_second = match second_dist {
+            Some(second_dist) => first_dist > second_dist,
+            None => true,
+        };
+
+        if use_second {
+            let result = second
+                .extend_scan_distance(first.scan_distance())
+                .with_work(work);
+            self.set_result(ParseResult::Matched(result));
+        } else {
+            self.rewind(second.distance());
+            self.advance(first.distance());
+            let result = first
+                .extend_scan_distance(second.scan_distance())
+                .with_work(work);
+            self.set_result(ParseResult::Matched(result));
+        }
+
+        self.pop_state();
+    }
+
+    pub unsafe fn state_first_choice_start(
+        &mut self,
+        first: State<I, G>,
+        continuation: State<I, G>,
+    ) {
+        *self.state_mut() = continuation;
+        self.push_state(first);
+    }
+
+    pub unsafe fn state_first_choice_middle(&mut self, second: State<I, G>) {
+        let result = self.take_result();
+
+        if result.is_match() {
+            let result = result.add_work(CHOICE_WORK);
+            self.set_result(result);
+            self.pop_state();
+        } else {
+            self.rewind(result.distance());
+            *self.state_mut() = second;
+        }
+    }
+
+    /// Runs the state chosen by the symbol at the current position, failing if none is chosen. At
+    /// most one alternative of the choice this replaces can start with any symbol, so the others
+    /// need not be tried
+    pub unsafe fn state_dispatch(
+        &mut self,
+        dispatch: impl FnOnce(Option<G::Symbol>) -> Option<State<I, G>>,
+    ) {
+        match dispatch(self.input.get(self.position)) {
+            Some(target) => *self.state_mut() = target,
+            None => {
+                self.set_result(ParseResult::Unmatched {
+                    scan_distance: 1,
+                    work: CHOICE_WORK,
+                });
+                self.pop_state();
+            }
+        }
+    }
+
+    pub unsafe fn state_not_ahead_start(&mut self, target: State<I, G>, continuation: State<I, G>) {
+        *self.state_mut() = continuation;
+        self.push_state(target)
+    }
+
+    pub unsafe fn state_not_ahead_end(&mut self) {
+        let result = self.take_result();
+        self.rewind(result.distance());
+        let result = result.negate().add_work(NOT_AHEAD_WORK);
+        self.set_result(result);
+
+        self.pop_state();
+    }
+
+    /// Matches only at the end of input, in place of a negative lookahead for any symbol. The
+    /// length of the input decides it, so no symbol is read
+    pub unsafe fn state_end_of_input(&mut self) {
+        let work = SERIES_WORK + NOT_AHEAD_WORK;
+
+        let result = if self.position as usize >= self.input.len() {
+            ParseResult::Matched(Match::empty(1, work))
+        } else {
+            ParseResult::Unmatched {
+                scan_distance: 1,
+                work,
+            }
+        };
+
+        self.set_result(result);
+        self.pop_state();
+    }
+
+    pub unsafe fn state_error_start(&mut self, target: State<I, G>, continuation: State<I, G>) {
+        *self.state_mut() = continuation;
+        self.push_state(target);
+    }
+
+    pub unsafe fn state_error_end(&mut self, expected: G::Expected) {
+        let mut result = self.take_result();
+
+        if let ParseResult::Matched(_) = result {
+            if self.remaining_error_marks == 0 {
+                self.rewind(result.distance());
+                result = ParseResult::Unmatched {
+                    scan_distance: result.scan_distance(),
+                    work: result.work(),
+                };
+            } else {
+                self.remaining_error_marks -= 1;
+            }
+        }
+
+        let result = result.mark_error(expected).add_work(MARK_ERROR_WORK);
+        self.set_result(result);
+        self.pop_state();
+    }
+
+    pub unsafe fn state_label_start(&mut self, target: State<I, G>, continuation: State<I, G>) {
+        *self.state_mut() = continuation;
+        self.push_state(target);
+    }
+
+    pub unsafe fn state_label_end(&mut self, label: G::Label) {
+        let result = self.take_result();
+        let result = result.label(label).add_work(LABEL_WORK);
+        self.set_result(result);
+        self.pop_state();
+    }
+
+    pub unsafe fn state_cache_start(
+        &mut self,
+        slot: u32,
+        target: State<I, G>,
+        continuation: State<I, G>,
+    ) {
+        if let Some(result) = self.cache.get(slot, self.position) {
+            self.advance(result.distance());
+            self.set_result(result);
+            self.pop_state();
+            return;
+        }
+
+        *self.state_mut() = continuation;
+        self.push_state(target);
+    }
+
+    pub unsafe fn state_cache_end(&mut self, slot: u32) {
+        let position = self.position - self.result().distance();
+
+        // Results at a position where a seed is growing may depend on the seed,
+        // which is about to be replaced by a longer match
+        if self.result().work() > MAX_UNCACHED_WORK && self.growing.last() != Some(&position) {
+            let work = self.result().work();
+            let result = self.take_result().with_work(CACHE_WORK);
+            let result = self.cache.insert(slot, position, result, work);
+            self.set_result(result);
+        }
+
+        self.pop_state();
+    }
+
+    /// Begins matching a left recursive rule, unless it has already been matched at this
+    /// position. The seed starts out unmatched, so the recursive call fails and the target can
+    /// only match through an alternative that isn't recursive
+    pub unsafe fn state_grow_start(
+        &mut self,
+        slot: u32,
+        target: State<I, G>,
+        continuation: State<I, G>,
+    ) {
+        if let Some(result) = self.cache.get(slot, self.position) {
+            self.advance(result.distance());
+            self.set_result(result);
+            self.pop_state();
+            return;
+        }
+
+        let seed = ParseResult::Unmatched {
+            scan_distance: 0,
+            work: 0,
+        };
+
+        self.cache.insert(slot, self.position, seed, 0);
+        self.growing.push(self.position);
+
+        *self.state_mut() = continuation;
+        self.push_state(target);
+    }
+
+    /// Replaces the seed with the target's match and tries again while the match gets longer,
+    /// finishing with the longest seed once it stops
+    pub unsafe fn state_grow_middle(&mut self, slot: u32, target: State<I, G>) {
+        let result = self.take_result();
+        self.rewind(result.distance());
+
+        let seed = self.cache.peek(slot, self.position).unwrap_unchecked();
+
+        let grew = result.is_match() && (!seed.is_match() || result.distance() > seed.distance());
+
+        if grew {
+            let work = result.work();
+            self.cache.insert(slot, self.position, result, work);
+            self.push_state(target);
+            return;
+        }
+
+        let work = seed.work() + result.work() + CACHE_WORK;
+        let seed = seed
+            .extend_scan_distance(result.scan_distance())
+            .with_work(work);
+        let seed = self.cache.insert(slot, self.position, seed, work);
+
+        self.growing.pop();
+        self.advance(seed.distance());
+        self.set_result(seed);
+        self.pop_state();
+    }
+
+    pub unsafe fn state_repeat_start(&mut self, target: State<I, G>, continuation: State<I, G>) {
+        self.set_result(ParseResult::Matched(Match::empty(0, REPEAT_WORK)));
+        self.stash_result();
+        *self.state_mut() = continuation;
+        self.push_state(target);
+    }
+
+    pub unsafe fn state_repeat_middle(&mut self, target: State<I, G>) {
+        let iteration = self.pop_result();
+        let accumulated = self.take_result().unwrap_match_unchecked();
+
+        match iteration {
+            ParseResult::Matched(iteration) if iteration.distance() > 0 => {
+                let result = Match::combine(accumulated, iteration).add_work(REPEAT_WORK);
+                self.set_result(ParseResult::Matched(result));
+                self.stash_result();
+                self.push_state(target);
+            }
+            iteration => {
+                // An iteration that matches without consuming input would loop forever, so it
+                // ends the repetition just like a failed one
+                self.rewind(iteration.distance());
+
+                let scan_distance = accumulated.distance() + iteration.scan_distance();
+                let result = accumulated
+                    .extend_scan_distance(scan_distance)
+                    .add_work(iteration.work());
+
+                self.set_result(ParseResult::Matched(result));
+                self.pop_state();
+            }
+        }
+    }
+
+    pub unsafe fn state_sep_by_start(&mut self, item: State<I, G>, continuation: State<I, G>) {
+        self.set_result(ParseResult::Matched(Match::empty(0, REPEAT_WORK)));
+        self.stash_result();
+        self.list_lengths.push(0);
+        *self.state_mut() = continuation;
+        self.push_state(item);
+    }
+
+    pub unsafe fn state_sep_by_first_item(
+        &mut self,
+        sep: State<I, G>,
+        continuation: State<I, G>,
+        min: u32,
+    ) {
+        let item = self.pop_result();
+        let accumulated = self.take_result().unwrap_match_unchecked();
+
+        match item {
+            ParseResult::Matched(item) => {
+                *self.list_lengths.last_mut().unwrap_unchecked() = 1;
+
+                let result = Match::combine(accumulated, item).add_work(REPEAT_WORK);
+                self.set_result(ParseResult::Matched(result));
+                self.stash_result();
+                *self.state_mut() = continuation;
+                self.push_state(sep);
+            }
+            item => {
+                let result = accumulated
+                    .extend_scan_distance(item.scan_distance())
+                    .add_work(item.work());
+
+                self.finish_sep_by(result, min);
+            }
+        }
+    }
+
+    pub unsafe fn state_sep_by_separator(
+        &mut self,
+        item: State<I, G>,
+        continuation: State<I, G>,
+        min: u32,
+    ) {
+        if self.result().is_match() {
+            self.stash_result();
+            *self.state_mut() = continuation;
+            self.push_state(item);
+            return;
+        }
+
+        let sep = self.pop_result();
+        let accumulated = self.take_result().unwrap_match_unchecked();
+
+        let scan_distance = accumulated.distance() + sep.scan_distance();
+        let result = accumulated
+            .extend_scan_distance(scan_distance)
+            .add_work(sep.work());
+
+        self.finish_sep_by(result, min);
+    }
+
+    pub unsafe fn state_sep_by_item(
+        &mut self,
+        sep: State<I, G>,
+        continuation: State<I, G>,
+        allow_trailing: bool,
+        min: u32,
+    ) {
+        let item = self.pop_result();
+        let sep_match = self.pop_result().unwrap_match_unchecked();
+        let accumulated = self.take_result().unwrap_match_unchecked();
+
+        match item {
+            ParseResult::Matched(item) if sep_match.distance() + item.distance() > 0 => {
+                let length = self.list_lengths.last_mut().unwrap_unchecked();
+                *length = length.saturating_add(1);
+
+                let iteration = Match::combine(sep_match, item);
+                let result = Match::combine(accumulated, iteration).add_work(REPEAT_WORK);
+                self.set_result(ParseResult::Matched(result));
+                self.stash_result();
+                *self.state_mut() = continuation;
+                self.push_state(sep);
+            }
+            item => {
+                // Like a repeat, an iteration that consumes no input ends the list
+                self.rewind(item.distance());
+
+                let scan_distance = sep_match.distance() + item.scan_distance();
+                let sep_match = sep_match
+                    .extend_scan_distance(scan_distance)
+                    .add_work(item.work());
+
+                let result = if allow_trailing && sep_match.distance() > 0 {
+                    Match::combine(accumulated, sep_match)
+                } else {
+                    self.rewind(sep_match.distance());
+
+                    let scan_distance = accumulated.distance() + sep_match.scan_distance();
+                    accumulated
+                        .extend_scan_distance(scan_distance)
+                        .add_work(sep_match.work())
+                };
+
+                self.finish_sep_by(result, min);
+            }
+        }
+    }
+
+    /// Ends a list, which fails if too few items were matched
+    unsafe fn finish_sep_by(&mut self, result: Match<G>, min: u32) {
+        let length = self.list_lengths.pop().unwrap_unchecked();
+
+        if length >= min {
+            self.set_result(ParseResult::Matched(result));
+        } else {
+            self.rewind(result.distance());
+            self.set_result(ParseResult::Unmatched {
+                scan_distance: result.scan_distance(),
+                work: result.work(),
+            });
+
+            if self.match_failures && self.position == self.start {
+                self.retain_partial(result);
+            }
+        }
+
+        self.pop_state();
+    }
+
+    pub unsafe fn state_delegate(&mut self, target: State<I, G>) {
+        *self.state_mut() = target;
+    }
+
+    pub unsafe fn state_trace_enter(
+        &mut self,
+        description: &str,
+        target: State<I, G>,
+        exit: State<I, G>,
+    ) {
+        eprintln!(
+            "{:indent$}Entered {} at {}",
+            "",
+            description,
+            self.position,
+            indent = self.trace_depth * 2
+        );
+
+        self.trace_depth += 1;
+        *self.state_mut() = exit;
+        self.push_state(target);
+    }
+
+    pub unsafe fn state_trace_exit(&mut self, description: &str) {
+        self.trace_depth -= 1;
+
+        match self.result() {
+            ParseResult::Matched(result) => eprintln!(
+                "{:indent$}Matched {} byte(s) at {} for {}",
+                "",
+                result.distance(),
+                self.position - result.distance(),
+                description,
+                indent = self.trace_depth * 2
+            ),
+            ParseResult::Unmatched { .. } => eprintln!(
+                "{:indent$}Failed at {} for {}",
+                "",
+                self.position,
+                description,
+                indent = self.trace_depth * 2
+            ),
+        }
+
+        self.pop_state();
+    }
+
+    pub unsafe fn state_series(&mut self, matcher: impl FnOnce(&I, u32) -> (bool, u32)) {
+        self.match_series(matcher, |length| {
+            Match::error_free(length, length, SERIES_WORK)
+        });
+    }
+
+    pub unsafe fn state_token(
+        &mut self,
+        matcher: impl FnOnce(&I, u32) -> (bool, u32),
+        kind: G::Label,
+    ) {
+        self.match_series(matcher, |length| {
+            Match::token(length, length, SERIES_WORK, kind)
+        });
+    }
+
+    pub unsafe fn state_consume_until(&mut self, scanner: impl FnOnce(&I, u32) -> (u32, u32)) {
+        let (length, scan_distance) = scanner(self.input, self.position);
+        let work = SERIES_WORK.saturating_mul(length.saturating_add(1));
+
+        self.advance(length);
+        self.set_result(ParseResult::Matched(Match::error_free(
+            length,
+            scan_distance,
+            work,
+        )));
+
+        self.pop_state();
+    }
+
+    pub unsafe fn state_balanced(
+        &mut self,
+        open: impl Fn(&I, u32) -> (bool, u32),
+        close: impl Fn(&I, u32) -> (bool, u32),
+    ) {
+        let start = self.position;
+        let mut position = start;
+        let mut depth = 0u32;
+        let mut scan_distance = 0;
+
+        // Delimiters that match empty input are ignored, so each step consumes input
+        let matched = loop {
+            let (opened, length) = open(self.input, position);
+            scan_distance = scan_distance.max(position - start + length);
+
+            if opened && length > 0 {
+                depth += 1;
+                position += length;
+                continue;
+            }
+
+            if depth == 0 {
+                break false;
+            }
+
+            let (closed, length) = close(self.input, position);
+            scan_distance = scan_distance.max(position - start + length);
+
+            if closed && length > 0 {
+                depth -= 1;
+                position += length;
+
+                if depth == 0 {
+                    break true;
+                }
+
+                continue;
+            }
+
+            if self.input.get(position).is_none() {
+                break false;
+            }
+
+            position += 1;
+        };
+
+        let length = position - start;
+        let work = SERIES_WORK.saturating_mul(length.saturating_add(1));
+
+        if matched {
+            self.advance(length);
+            self.set_result(ParseResult::Matched(Match::error_free(
+                length,
+                scan_distance,
+                work,
+            )));
+        } else {
+            self.set_result(ParseResult::Unmatched {
+                scan_distance: scan_distance.max(length + 1),
+                work,
+            });
+        }
+
+        self.pop_state();
+    }
+
+    unsafe fn match_series(
+        &mut self,
+        matcher: impl FnOnce(&I, u32) -> (bool, u32),
+        create: impl FnOnce(u32) -> Match<G>,
+    ) {
+        let (matched, length) = matcher(self.input, self.position);
+
+        if matched {
+            self.advance(length);
+            self.set_result(ParseResult::Matched(create(length)));
+        } else {
+            self.set_result(ParseResult::Unmatched {
+                scan_distance: length,
+                work: SERIES_WORK,
+            })
+        }
+
+        self.pop_state();
+    }
+}
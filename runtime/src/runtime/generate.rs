@@ -0,0 +1,1297 @@
+// The parts of a generated parser that depend on its grammar, such as the types wrapping the
+// runtime's generic parse trees. Parsers using the runtime crate embed this file, so it may only
+// use public items of the runtime, which it refers to through glob imports or `$runtime`
+#[allow(unused)]
+macro_rules! generate {
+    ($runtime:ident, $start:expr, $cache:ident, $cache_slots:expr, $start_expected:expr) => {
+        // Re-exported for users of the parser, which may not use them itself
+        #[allow(unused_imports)]
+        pub use $runtime::{Input, SkipBom, SymbolType, WithSentinel};
+
+        impl std::fmt::Debug for Expected {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                let mut tuple = f.debug_tuple("Error");
+
+                for label in self.labels() {
+                    tuple.field(label);
+                }
+
+                for literal in self.literals() {
+                    tuple.field(&format_args!("{}", Symbol::describe(literal)));
+                }
+
+                if let Some(message) = self.message() {
+                    tuple.field(&message);
+                }
+
+                tuple.finish()
+            }
+        }
+
+        struct Impl;
+
+        // Checks the promises made about threads by the documentation of `parse`
+        const _: fn() = || {
+            fn thread_safe<T: Send + Sync>() {}
+            thread_safe::<Impl>();
+            thread_safe::<ParseOptions>();
+            thread_safe::<FlatTree>();
+        };
+
+        impl Grammar for Impl {
+            type Symbol = Symbol;
+            type Label = Label;
+            type Expected = Expected;
+            type Cache = $runtime::$cache<Self>;
+
+            fn start_state<I: Input<Symbol> + ?Sized>(&self) -> State<I, Self> {
+                $start
+            }
+
+            fn cache_slots(&self) -> usize {
+                $cache_slots
+            }
+
+            fn start_expected(&self) -> Expected {
+                $start_expected
+            }
+
+            #[inline(always)]
+            fn backtracked(&self, position: u32) {
+                record_backtrack(position)
+            }
+        }
+
+        /// The result of a successful or unsuccessful parse.
+        ///
+        /// Match on the variants to garner more information about the parse.
+        #[derive(Debug)]
+        pub enum Parse {
+            /// The result of a parse that successfully matched at least some of the input.
+            Matched(ParseMatch),
+            /// Indicates that the parse did not match the input.
+            Unmatched,
+            /// Indicates that the parse was abandoned because it exceeded the limit set with
+            /// [`ParseOptions::max_depth`].
+            DepthExceeded,
+            /// Indicates that the input was too long to be parsed, since positions in the parse
+            /// tree could not be represented. Inputs must be shorter than `u32::MAX` bytes.
+            InputTooLong,
+        }
+
+        impl Parse {
+            /// Unwraps the [`Matched`](Parse::Matched) variant, panicking if the parse did not match.
+            #[track_caller]
+            #[allow(unused)]
+            pub fn unwrap(self) -> ParseMatch {
+                match self {
+                    Self::Matched(result) => result,
+                    Self::Unmatched => panic!("parse did not match"),
+                    Self::DepthExceeded => panic!("parse exceeded the maximum depth"),
+                    Self::InputTooLong => panic!("input was too long to parse"),
+                }
+            }
+        }
+
+        /// The result of a parse that successfully matched.
+        ///
+        /// Although this represents a parse that matched the input the result may still contain
+        /// errors.
+        pub struct ParseMatch(GenParseMatch<Impl>);
+
+        #[allow(unused)]
+        impl ParseMatch {
+            /// Creates a cursor that points to the root of the parse tree.
+            ///
+            /// This cursor's grouping will always be [`Grouping::Root`]. See [`Cursor`] for more
+            /// information.
+            pub fn root(&self) -> Cursor {
+                Cursor(self.0.root())
+            }
+
+            /// Iterates over the top-level nodes of the parse tree, yielding a cursor for each of
+            /// them.
+            ///
+            /// Equivalent to `self.root().children()`. See [`Cursor::children`] for more
+            /// information.
+            pub fn children(&self) -> impl Iterator<Item = Cursor> + '_ {
+                self.root().children()
+            }
+
+            /// Iterates over the top-level nodes of the parse tree that have the provided label.
+            ///
+            /// This is the usual entry point for walking a parse, such as to visit each statement
+            /// in a file. Equivalent to `self.root().labelled(label)`.
+            pub fn top_level(&self, label: Label) -> impl Iterator<Item = Cursor> + '_ {
+                self.root().labelled(label)
+            }
+
+            /// Walks over the parse tree invoking the appropriate methods in the visitor.
+            ///
+            /// See the [`Visitor`] trait for more details.
+            pub fn visit<V: Visitor>(&self, visitor: &mut V) {
+                self.0.visit(&mut VisitorAdapter(visitor))
+            }
+
+            /// Streams the parse tree to a handler as events, in the order that nodes start and
+            /// end in the input.
+            ///
+            /// The events are produced by walking the finished tree, so the tree is still held in
+            /// memory in full while parsing. Beyond the tree, streaming only needs memory
+            /// proportional to its depth, which suits handlers that fold a large parse into their
+            /// own representation. See the [`StreamHandler`] trait for the events produced.
+            pub fn stream<H: StreamHandler>(&self, handler: &mut H) {
+                self.0.visit(&mut StreamAdapter {
+                    handler,
+                    labels: Vec::new(),
+                })
+            }
+
+            /// Renders the matched input with a marker such as `«error: expected "]"»` inserted
+            /// at the start of each error.
+            ///
+            /// The input must be the bytes that were parsed, and is rendered as UTF-8, replacing
+            /// invalid sequences. This is meant for showing what the parser understood, such as in
+            /// bug reports, and the format may change. This only makes sense for parsers that read
+            /// bytes, and panics if the match lies outside the input.
+            pub fn annotated_source(&self, input: &[u8]) -> String {
+                self.annotate(input, false)
+            }
+
+            /// Renders the matched input like [`ParseMatch::annotated_source`], additionally
+            /// bracketing each labelled node with markers such as `«value»` and `«/value»`.
+            pub fn annotated_source_with_labels(&self, input: &[u8]) -> String {
+                self.annotate(input, true)
+            }
+
+            fn annotate(&self, input: &[u8], labels: bool) -> String {
+                let root = self.root();
+                let mut adapter = AnnotateAdapter {
+                    input,
+                    labels,
+                    written: root.position() as usize,
+                    output: String::new(),
+                };
+
+                self.0.visit(&mut adapter);
+                adapter.write_until(root.position() + root.length());
+                adapter.output
+            }
+
+            /// Creates an iterator over the errors in the parse tree.
+            ///
+            /// No effort is made to coalesce adjacent errors into one.
+            pub fn unmerged_errors(&self) -> impl Iterator<Item = ErrorInfo> + '_ {
+                return self.0.unmerged_errors().map(|info| ErrorInfo {
+                    expected_labels: info.expected_labels,
+                    expected_literals: info.expected_literals,
+                    expected_message: info.expected_message,
+                    position: info.position,
+                    length: info.length,
+                    enclosing_label: info.enclosing_label,
+                    _private: (),
+                });
+            }
+
+            /// Creates an iterator over the errors in the parse tree, in order of position and then
+            /// length.
+            ///
+            /// [`ParseMatch::unmerged_errors`] yields errors in the order the grammar nests them,
+            /// which is not always the order they appear in the input. This collects and sorts them
+            /// up front, which suits printing diagnostics from top to bottom. Errors with the same
+            /// position and length are yielded in the order they were found.
+            pub fn errors_sorted(&self) -> impl Iterator<Item = ErrorInfo> {
+                let mut errors = self.unmerged_errors().collect::<Vec<_>>();
+                errors.sort_by_key(|info| (info.position, info.length));
+                errors.into_iter()
+            }
+
+            /// Creates an iterator over the tokens in the parse tree, in the order they appear in the
+            /// input.
+            ///
+            /// Tokens are produced by literals and character classes that were given a kind, which
+            /// makes this a cheap way to classify the input, such as for syntax highlighting.
+            pub fn tokens(&self) -> impl Iterator<Item = TokenInfo> + '_ {
+                return self.0.tokens().map(|info| TokenInfo {
+                    kind: info.kind,
+                    position: info.position,
+                    length: info.length,
+                    _private: (),
+                });
+            }
+
+            /// Creates an iterator that splits the matched input into leaves, in the order they
+            /// appear in the input.
+            ///
+            /// Every byte of the match belongs to exactly one leaf, and each leaf carries the
+            /// innermost grouping enclosing it. Where groupings overlap, the most deeply nested one
+            /// wins, so the input between the children of a label belongs to that label while the
+            /// children's input belongs to them. Leaves are maximal, so neighbouring leaves always
+            /// differ in grouping or are split by the start or end of a node. Input outside every
+            /// label, error and token has a grouping of [`Grouping::Root`].
+            ///
+            /// Unlike [`ParseMatch::tokens`], this covers the whole input, which suits editors
+            /// wanting a flat token stream even where the grammar gives no token kinds.
+            pub fn leaves(&self) -> impl Iterator<Item = LeafInfo> {
+                return self.0.leaves().into_iter().map(|leaf| LeafInfo {
+                    grouping: Cursor::convert_grouping(leaf.grouping),
+                    position: leaf.position,
+                    length: leaf.length,
+                    _private: (),
+                });
+            }
+
+            /// Serializes the parse tree as indented S-expressions.
+            ///
+            /// Each node is written on its own line as a list of its kind, its name, its position
+            /// and its length, followed by its children. Labels are written as `(label name pos
+            /// len ...)`, tokens as `(token kind pos len)` and errors as `(error (expected ...)
+            /// pos len ...)`, where expected labels are symbols and expected literals are quoted
+            /// strings. The whole tree is wrapped in `(root pos len ...)`. The output only depends
+            /// on the tree, which makes it suitable for snapshot tests.
+            pub fn to_sexp(&self) -> String {
+                self.0.to_sexp()
+            }
+
+            /// Copies the parse tree into a flat array of nodes.
+            ///
+            /// See [`FlatTree`] for more information.
+            pub fn flatten(&self) -> FlatTree {
+                let nodes = self.0.flatten().into_iter().map(|node| {
+                    let grouping = Cursor::convert_grouping(node.grouping);
+
+                    FlatNode {
+                        grouping,
+                        label_id: grouping.label_id(),
+                        position: node.position,
+                        length: node.length,
+                        first_child: node.first_child,
+                        next_sibling: node.next_sibling,
+                    }
+                });
+
+                FlatTree(nodes.collect())
+            }
+
+            /// An estimate of the work the parser did to match the input, counted in steps such as
+            /// matching a literal or trying an alternative.
+            ///
+            /// Unlike timing a parse, this only depends on the grammar and the input, so comparing
+            /// it before and after changing a grammar reliably shows whether the parser now
+            /// backtracks more. Work reused from the cache is not counted again.
+            pub fn work(&self) -> u32 {
+                self.0.root().work()
+            }
+
+            /// Shifts every position reported by the parse tree forward by the provided amount.
+            ///
+            /// This is useful when the parsed input was a suffix of a larger buffer, such as when
+            /// parsing through a [`SkipBom`]. Passing [`SkipBom::offset`] here makes positions
+            /// refer to the original buffer again.
+            pub fn offset_by(self, amount: u32) -> Self {
+                Self(self.0.offset_by(amount))
+            }
+        }
+
+        impl std::fmt::Debug for ParseMatch {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+
+        /// Attempts to parse some input, returning a [`Parse`] that represents the result.
+        ///
+        /// See [`Input`] for information on what can be passed to this function.
+        ///
+        /// Every parse keeps its state to itself, so this and the other parse functions can be
+        /// called from any number of threads at once, sharing the input and options between them
+        /// where they are `Sync`. The [`ParseMatch`] produced shares nodes through reference counts
+        /// that aren't atomic, so it can't leave the thread that parsed it. Use
+        /// [`ParseMatch::flatten`] to get a [`FlatTree`] that can be sent to other threads.
+        #[allow(unused)]
+        pub fn parse<I: Input<Symbol> + ?Sized>(input: &I) -> Parse {
+            parse_with(input, &ParseOptions::new())
+        }
+
+        /// Attempts to parse some input with a set of [`ParseOptions`], returning a [`Parse`]
+        /// that represents the result.
+        ///
+        /// See [`parse`] for more information.
+        #[allow(unused)]
+        pub fn parse_with<I: Input<Symbol> + ?Sized>(input: &I, options: &ParseOptions) -> Parse {
+            let grammar = Impl;
+            let start = Context::<I, Impl>::start_position(input, options);
+            Parse::from_result(Context::run(input, &grammar, options), start)
+        }
+
+        /// Attempts to parse the input beginning at a byte offset, such as to reparse one region
+        /// of a larger buffer.
+        ///
+        /// Positions in the result are measured from the beginning of the input, not from the
+        /// offset. See [`ParseOptions::start_at`] for more information.
+        #[allow(unused)]
+        pub fn parse_from<I: Input<Symbol> + ?Sized>(input: &I, offset: u32) -> Parse {
+            parse_with(input, &ParseOptions::new().start_at(offset))
+        }
+
+        /// Attempts to parse some input, reporting input that fails to match as the longest prefix
+        /// of it that did match followed by an error.
+        ///
+        /// Rather than just knowing the input is invalid, this gives back everything understood
+        /// before the parse went wrong. See [`ParseOptions::lenient`] for more information.
+        #[allow(unused)]
+        pub fn parse_lenient<I: Input<Symbol> + ?Sized>(input: &I) -> Parse {
+            parse_with(input, &ParseOptions::new().lenient())
+        }
+
+        /// Attempts to parse some input like [`parse_with`], additionally recording how
+        /// effective each cache slot was.
+        ///
+        /// Profiling adds overhead, so this should only be used to decide whether caching is
+        /// paying off. See [`CACHE_SLOTS`] for the rules each slot belongs to.
+        #[allow(unused)]
+        pub fn parse_profiled<I: Input<Symbol> + ?Sized>(
+            input: &I,
+            options: &ParseOptions,
+        ) -> (Parse, CacheProfile) {
+            let grammar = Impl;
+            let start = Context::<I, Impl>::start_position(input, options);
+            let (result, profile) = Context::run_profiled(input, &grammar, options);
+            (Parse::from_result(result, start), profile)
+        }
+
+        /// Attempts to parse some input like [`parse`], periodically calling `progress` with the
+        /// furthest position the parser has reached.
+        ///
+        /// The callback runs once every 65,536 steps of the parser, where a step is roughly one unit
+        /// of work such as matching a literal or trying an alternative, and the positions it
+        /// receives never decrease. The parser may backtrack, so a position is not a promise that
+        /// the input before it matched, and the parse may finish without ever reporting the end of
+        /// the input. This is intended for displaying progress while parsing large inputs.
+        #[allow(unused)]
+        pub fn parse_with_progress<I: Input<Symbol> + ?Sized>(
+            input: &I,
+            progress: &mut impl FnMut(u32),
+        ) -> Parse {
+            let grammar = Impl;
+            let options = ParseOptions::new();
+            let start = Context::<I, Impl>::start_position(input, &options);
+            let result = Context::run_with_progress(input, &grammar, &options, progress);
+            Parse::from_result(result, start)
+        }
+
+        impl Parse {
+            fn from_result(result: Result<ParseResult<Impl>, Abort>, start: u32) -> Self {
+                match result {
+                    Ok(ParseResult::Matched(value)) => {
+                        let value = GenParseMatch::new(value).offset_by(start);
+                        Parse::Matched(ParseMatch(value))
+                    }
+                    Ok(ParseResult::Unmatched { .. }) => Parse::Unmatched,
+                    Err(Abort::DepthExceeded) => Parse::DepthExceeded,
+                    Err(Abort::InputTooLong) => Parse::InputTooLong,
+                }
+            }
+        }
+
+        /// Attempts to parse some input, returning an error unless the parse matched without
+        /// errors.
+        ///
+        /// This is a convenience over [`parse`] for callers that only care whether the input was
+        /// valid. See [`ParseError`] for the information reported on failure.
+        #[allow(unused)]
+        pub fn try_parse<I: Input<Symbol> + ?Sized>(input: &I) -> Result<ParseMatch, ParseError> {
+            let grammar = Impl;
+            let result = Context::run(input, &grammar, &ParseOptions::new());
+            let result = match result {
+                Ok(ParseResult::Matched(value)) => ParseMatch(GenParseMatch::new(value)),
+                // The furthest position examined is the symbol before the scan distance
+                Ok(ParseResult::Unmatched { scan_distance, .. }) => {
+                    return Err(ParseError {
+                        position: scan_distance.saturating_sub(1),
+                        scan_distance,
+                        expected_labels: Vec::new(),
+                        expected_literals: Vec::new(),
+                        expected_messages: Vec::new(),
+                        _private: (),
+                    });
+                }
+                Err(Abort::DepthExceeded) => unreachable!("no depth limit was set"),
+                // Reported at the first position that cannot be represented
+                Err(Abort::InputTooLong) => {
+                    return Err(ParseError {
+                        position: u32::MAX,
+                        scan_distance: u32::MAX,
+                        expected_labels: Vec::new(),
+                        expected_literals: Vec::new(),
+                        expected_messages: Vec::new(),
+                        _private: (),
+                    });
+                }
+            };
+
+            let position = match result.unmerged_errors().map(|info| info.position).max() {
+                Some(position) => position,
+                None => return Ok(result),
+            };
+
+            let mut expected_labels = Vec::new();
+            let mut expected_literals = Vec::new();
+            let mut expected_messages = Vec::new();
+
+            for info in result.unmerged_errors() {
+                if info.position != position {
+                    continue;
+                }
+
+                for label in info.expected_labels {
+                    if !expected_labels.contains(label) {
+                        expected_labels.push(*label);
+                    }
+                }
+
+                for literal in info.expected_literals {
+                    if !expected_literals.contains(literal) {
+                        expected_literals.push(*literal);
+                    }
+                }
+
+                if let Some(message) = info.expected_message {
+                    if !expected_messages.contains(&message) {
+                        expected_messages.push(message);
+                    }
+                }
+            }
+
+            Err(ParseError {
+                position,
+                scan_distance: result.0.scan_distance(),
+                expected_labels,
+                expected_literals,
+                expected_messages,
+                _private: (),
+            })
+        }
+
+        /// The error returned by [`try_parse`] when a parse did not match or matched with errors.
+        #[allow(unused)]
+        #[derive(Debug)]
+        pub struct ParseError {
+            /// The position of the furthest error, or of the last symbol examined if the parse
+            /// did not match. Inputs too long to parse report an error at `u32::MAX`.
+            pub position: u32,
+            /// How far into the input the parser examined, one past the last symbol it read.
+            pub scan_distance: u32,
+            /// The labels that were expected at the error's position, merged across every error
+            /// reported there. This is empty if the parse did not match.
+            pub expected_labels: Vec<Label>,
+            /// The literals that were expected at the error's position, merged across every error
+            /// reported there. This is empty if the parse did not match.
+            pub expected_literals: Vec<&'static [Symbol]>,
+            /// The messages written for the errors at the error's position, which replace what
+            /// those errors expected. This is empty if the parse did not match.
+            pub expected_messages: Vec<&'static str>,
+            _private: (),
+        }
+
+        impl std::fmt::Display for ParseError {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "syntax error at position {}", self.position)?;
+
+                let mut first = true;
+
+                for label in &self.expected_labels {
+                    write!(
+                        f,
+                        "{}{}",
+                        if first { ", expected " } else { " or " },
+                        label
+                    )?;
+                    first = false;
+                }
+
+                for literal in &self.expected_literals {
+                    write!(f, "{}", if first { ", expected " } else { " or " })?;
+                    first = false;
+
+                    write!(f, "{}", Symbol::describe(literal))?;
+                }
+
+                for message in &self.expected_messages {
+                    write!(f, "{}", if first { ", expected " } else { " or " })?;
+                    first = false;
+
+                    write!(f, "{}", message)?;
+                }
+
+                Ok(())
+            }
+        }
+
+        impl std::error::Error for ParseError {}
+
+        #[allow(unused_imports)]
+        pub use $runtime::{
+            CacheProfile, CursorExt, LineColumn, LineMap, LineOptions, ParseOptions, SlotProfile,
+            TextError, VisitResult,
+        };
+
+        /// An interface for walking a [`ParseMatch`] using the
+        /// [visitor pattern](https://en.wikipedia.org/wiki/Visitor_pattern).
+        pub trait Visitor {
+            /// Called when entering a labelled node.
+            ///
+            /// A [`VisitResult`] is returned to indicate whether this node's descendants should
+            /// be skipped, whether the traversal should be halted, or whether the traversal should
+            /// continue.
+            ///
+            /// The default implementation simply returns [`Continue`](VisitResult::Continue).
+            fn enter(&mut self, info: VisitorEnterInfo) -> VisitResult {
+                let _ = info;
+                VisitResult::Continue
+            }
+
+            /// Called when all of a labelled node's descendants have been traversed.
+            ///
+            /// This is called even if the node has no descendants, but is not called if the
+            /// corresponding `enter` returned [`Skip`](VisitResult::Skip).
+            fn exit(&mut self, info: VisitorExitInfo) {
+                let _ = info;
+            }
+
+            /// Called when entering an error node.
+            ///
+            /// A [`VisitResult`] is returned to indicate whether this node's descendants should
+            /// be skipped, whether the traversal should be halted, or whether the traversal should
+            /// continue.
+            ///
+            /// The default implementation simply returns [`Continue`](VisitResult::Continue).
+            fn enter_error(&mut self, info: VisitorEnterErrorInfo) -> VisitResult {
+                let _ = info;
+                VisitResult::Continue
+            }
+
+            /// Called when all of an error node's descendants have been traversed.
+            ///
+            /// This is called even if the node has no descendants, but is not called if the
+            /// corresponding `enter_error` returned [`Skip`](VisitResult::Skip).
+            fn exit_error(&mut self, info: VisitorExitErrorInfo) {
+                let _ = info;
+            }
+
+            /// Called when visiting a token.
+            ///
+            /// Tokens have no descendants, so there is no corresponding exit method.
+            fn token(&mut self, info: VisitorTokenInfo) {
+                let _ = info;
+            }
+        }
+
+        /// Receives the events of a parse tree from [`ParseMatch::stream`].
+        ///
+        /// Unlike a [`Visitor`], a handler can't skip parts of the tree, and each event carries
+        /// only what is known once the parser has reached that point in the input. Every method
+        /// does nothing by default.
+        pub trait StreamHandler {
+            /// Called at the start of a labelled node, before any of its descendants.
+            fn on_enter(&mut self, label: Label, position: u32) {
+                let _ = (label, position);
+            }
+
+            /// Called at the end of a labelled node, after all of its descendants.
+            fn on_exit(&mut self, label: Label, position: u32, length: u32) {
+                let _ = (label, position, length);
+            }
+
+            /// Called at the end of an error node, after all of its descendants.
+            fn on_error(&mut self, info: ErrorInfo) {
+                let _ = info;
+            }
+
+            /// Called for each token.
+            fn on_token(&mut self, kind: Label, position: u32, length: u32) {
+                let _ = (kind, position, length);
+            }
+        }
+
+        /// Information about an error yielded by [`ParseMatch::unmerged_errors`] and
+        /// [`ParseMatch::errors_sorted`].
+        #[allow(unused)]
+        #[derive(Debug)]
+        pub struct ErrorInfo {
+            /// The set of labels that were excepted at the error's position in the input stream.
+            pub expected_labels: &'static [Label],
+            /// The set of literals that were excepted at the error's position in the input stream.
+            pub expected_literals: &'static [&'static [Symbol]],
+            /// The message written for the error's rule, which replaces what it expected.
+            pub expected_message: Option<&'static str>,
+            /// The position at which the error occurred.
+            pub position: u32,
+            /// The length of the input covered by the error.
+            pub length: u32,
+            /// The label of the innermost labelled node containing the error, if there is one.
+            pub enclosing_label: Option<Label>,
+            _private: (),
+        }
+
+        /// Information about a token yielded by [`ParseMatch::tokens`].
+        #[allow(unused)]
+        #[derive(Debug)]
+        pub struct TokenInfo {
+            /// The kind of the token.
+            pub kind: Label,
+            /// The position at which the token was matched.
+            pub position: u32,
+            /// The length of input covered by the token.
+            pub length: u32,
+            _private: (),
+        }
+
+        /// A run of input yielded by [`ParseMatch::leaves`].
+        #[allow(unused)]
+        #[derive(Debug)]
+        pub struct LeafInfo {
+            /// The innermost grouping enclosing the run.
+            pub grouping: Grouping,
+            /// The position at which the run starts.
+            pub position: u32,
+            /// The length of the run.
+            pub length: u32,
+            _private: (),
+        }
+
+        /// Lets a [`Visitor`] walk the runtime's parse tree, which only knows about
+        /// [`GenVisitor`]s.
+        struct VisitorAdapter<'a, V>(&'a mut V);
+
+        impl<'a, V: Visitor> GenVisitor<Impl> for VisitorAdapter<'a, V> {
+            fn enter(
+                &mut self,
+                label: Label,
+                position: u32,
+                length: u32,
+                has_error: bool,
+            ) -> VisitResult {
+                self.0.enter(VisitorEnterInfo {
+                    label,
+                    position,
+                    length,
+                    has_error,
+                    _private: (),
+                })
+            }
+
+            fn exit(&mut self, label: Label, position: u32, length: u32, has_error: bool) {
+                self.0.exit(VisitorExitInfo {
+                    label,
+                    position,
+                    length,
+                    has_error,
+                    _private: (),
+                })
+            }
+
+            fn enter_error(
+                &mut self,
+                expected_labels: &'static [Label],
+                expected_literals: &'static [&'static [Symbol]],
+                expected_message: Option<&'static str>,
+                position: u32,
+                length: u32,
+            ) -> VisitResult {
+                self.0.enter_error(VisitorEnterErrorInfo {
+                    expected_labels,
+                    expected_literals,
+                    expected_message,
+                    position,
+                    length,
+                    _private: (),
+                })
+            }
+
+            fn exit_error(
+                &mut self,
+                expected_labels: &'static [Label],
+                expected_literals: &'static [&'static [Symbol]],
+                expected_message: Option<&'static str>,
+                position: u32,
+                length: u32,
+            ) {
+                self.0.exit_error(VisitorExitErrorInfo {
+                    expected_labels,
+                    expected_literals,
+                    expected_message,
+                    position,
+                    length,
+                    _private: (),
+                })
+            }
+
+            fn token(&mut self, kind: Label, position: u32, length: u32) {
+                self.0.token(VisitorTokenInfo {
+                    kind,
+                    position,
+                    length,
+                    _private: (),
+                })
+            }
+        }
+
+        /// Drives a [`StreamHandler`] from a walk of the runtime's parse tree.
+        struct StreamAdapter<'a, H> {
+            handler: &'a mut H,
+            // The labels of the nodes currently being streamed, innermost last
+            labels: Vec<Label>,
+        }
+
+        impl<'a, H: StreamHandler> GenVisitor<Impl> for StreamAdapter<'a, H> {
+            fn enter(&mut self, label: Label, position: u32, _: u32, _: bool) -> VisitResult {
+                self.labels.push(label);
+                self.handler.on_enter(label, position);
+                VisitResult::Continue
+            }
+
+            // Nodes are exited at their end, but handlers are given their start
+            fn exit(&mut self, label: Label, end: u32, length: u32, _: bool) {
+                self.labels.pop();
+                self.handler.on_exit(label, end - length, length);
+            }
+
+            fn enter_error(
+                &mut self,
+                _: &'static [Label],
+                _: &'static [&'static [Symbol]],
+                _: Option<&'static str>,
+                _: u32,
+                _: u32,
+            ) -> VisitResult {
+                VisitResult::Continue
+            }
+
+            fn exit_error(
+                &mut self,
+                expected_labels: &'static [Label],
+                expected_literals: &'static [&'static [Symbol]],
+                expected_message: Option<&'static str>,
+                end: u32,
+                length: u32,
+            ) {
+                self.handler.on_error(ErrorInfo {
+                    expected_labels,
+                    expected_literals,
+                    expected_message,
+                    position: end - length,
+                    length,
+                    enclosing_label: self.labels.last().copied(),
+                    _private: (),
+                })
+            }
+
+            fn token(&mut self, kind: Label, position: u32, length: u32) {
+                self.handler.on_token(kind, position, length);
+            }
+        }
+
+        /// Renders the input between the nodes of a parse tree, for
+        /// [`ParseMatch::annotated_source`].
+        struct AnnotateAdapter<'a> {
+            input: &'a [u8],
+            labels: bool,
+            // The position up to which the input has been rendered
+            written: usize,
+            output: String,
+        }
+
+        impl<'a> AnnotateAdapter<'a> {
+            fn write_until(&mut self, position: u32) {
+                let position = position as usize;
+
+                if position > self.written {
+                    let text = &self.input[self.written..position];
+                    self.output.push_str(&String::from_utf8_lossy(text));
+                    self.written = position;
+                }
+            }
+        }
+
+        impl<'a> GenVisitor<Impl> for AnnotateAdapter<'a> {
+            fn enter(&mut self, label: Label, position: u32, _: u32, _: bool) -> VisitResult {
+                if self.labels {
+                    self.write_until(position);
+                    self.output.push_str(&format!("«{}»", label));
+                }
+
+                VisitResult::Continue
+            }
+
+            fn exit(&mut self, label: Label, end: u32, _: u32, _: bool) {
+                if self.labels {
+                    self.write_until(end);
+                    self.output.push_str(&format!("«/{}»", label));
+                }
+            }
+
+            fn enter_error(
+                &mut self,
+                expected_labels: &'static [Label],
+                expected_literals: &'static [&'static [Symbol]],
+                expected_message: Option<&'static str>,
+                position: u32,
+                _: u32,
+            ) -> VisitResult {
+                self.write_until(position);
+
+                let expected =
+                    describe_expected(expected_labels, expected_literals, expected_message);
+
+                if expected.is_empty() {
+                    self.output.push_str("«error»");
+                } else {
+                    let expected = expected.join(" or ");
+                    self.output.push_str(&format!("«error: expected {}»", expected));
+                }
+
+                VisitResult::Continue
+            }
+
+            fn exit_error(
+                &mut self,
+                _: &'static [Label],
+                _: &'static [&'static [Symbol]],
+                _: Option<&'static str>,
+                _: u32,
+                _: u32,
+            ) {
+            }
+
+            fn token(&mut self, _: Label, _: u32, _: u32) {}
+        }
+
+        /// Information about a labelled node passed to [`Visitor::enter`].
+        #[derive(Debug)]
+        pub struct VisitorEnterInfo {
+            /// The label applied to the section of input.
+            pub label: Label,
+            /// The position at which the label was applied.
+            pub position: u32,
+            /// The length of input covered by the label.
+            pub length: u32,
+            /// Whether any descendants of the node contain an error
+            pub has_error: bool,
+            _private: (),
+        }
+
+        /// Information about a labelled node passed to [`Visitor::exit`].
+        #[derive(Debug)]
+        pub struct VisitorExitInfo {
+            /// The label applied to the section of input.
+            pub label: Label,
+            /// The position at which the label was applied.
+            pub position: u32,
+            /// The length of input covered by the label.
+            pub length: u32,
+            /// Whether any descendants of the node contain an error
+            pub has_error: bool,
+            _private: (),
+        }
+
+        /// Information about an error node passed to [`Visitor::enter_error`].
+        #[derive(Debug)]
+        pub struct VisitorEnterErrorInfo {
+            /// The set of labels that were excepted at the error's position in the input stream.
+            pub expected_labels: &'static [Label],
+            /// The set of literals that were excepted at the error's position in the input stream.
+            pub expected_literals: &'static [&'static [Symbol]],
+            /// The message written for the error's rule, which replaces what it expected.
+            pub expected_message: Option<&'static str>,
+            /// The position at which the error occurred.
+            pub position: u32,
+            /// The length of the input covered by the error.
+            pub length: u32,
+            _private: (),
+        }
+
+        /// Information about an error node passed to [`Visitor::exit_error`].
+        #[derive(Debug)]
+        pub struct VisitorExitErrorInfo {
+            /// The set of labels that were excepted at the error's position in the input stream.
+            pub expected_labels: &'static [Label],
+            /// The set of literals that were excepted at the error's position in the input stream.
+            pub expected_literals: &'static [&'static [Symbol]],
+            /// The message written for the error's rule, which replaces what it expected.
+            pub expected_message: Option<&'static str>,
+            /// The position at which the error occurred.
+            pub position: u32,
+            /// The length of the input covered by the error.
+            pub length: u32,
+            _private: (),
+        }
+
+        /// Information about a token passed to [`Visitor::token`].
+        #[derive(Debug)]
+        pub struct VisitorTokenInfo {
+            /// The kind of the token.
+            pub kind: Label,
+            /// The position at which the token was matched.
+            pub position: u32,
+            /// The length of input covered by the token.
+            pub length: u32,
+            _private: (),
+        }
+
+        /// Points to a node in a parse tree.
+        ///
+        /// A cursor can point to three different types of node: a label node, an error node, or the
+        /// root node. Label and error nodes designate points in the parse tree where a label or
+        /// error was produced respectively. The root node is a special node which contains all
+        /// other nodes in the parse tree.
+        ///
+        /// Use the [`grouping`](Cursor::grouping) method to determine what the cursor points to.
+        #[derive(Clone)]
+        pub struct Cursor<'a>(GenCursor<'a, Impl>);
+
+        #[allow(unused)]
+        impl<'a> Cursor<'a> {
+            /// Produces an enum describing the node the cursor points to.
+            ///
+            /// See [`Grouping`] for more information.
+            pub fn grouping(&self) -> Grouping {
+                Self::convert_grouping(self.0.grouping())
+            }
+
+            fn convert_grouping(grouping: GenGrouping<Label, Expected>) -> Grouping {
+                match grouping {
+                    GenGrouping::Label(label) => Grouping::Label(label),
+                    GenGrouping::Error(error) => Grouping::Error {
+                        expected_labels: error.labels(),
+                        expected_literals: error.literals(),
+                        expected_message: error.message(),
+                    },
+                    GenGrouping::Token(kind) => Grouping::Token(kind),
+                    GenGrouping::None => Grouping::Root,
+                }
+            }
+
+            /// Renders what the node expected, if it is an error node.
+            ///
+            /// See [`Grouping::expected`] for more information.
+            pub fn expected(&self) -> Vec<String> {
+                self.grouping().expected()
+            }
+
+            /// The label corresponding to the node the cursor points to, or `None` if the cursor
+            /// points to an error or the root node.
+            pub fn label(&self) -> Option<Label> {
+                self.0.label()
+            }
+
+            /// Iterates over the immediate children of this node that have the provided label.
+            pub fn labelled(&self, label: Label) -> impl Iterator<Item = Cursor<'a>> {
+                self.children()
+                    .filter(move |child| child.label() == Some(label))
+            }
+
+            /// Finds the first immediate child of this node that has the provided label, if any.
+            pub fn first(&self, label: Label) -> Option<Cursor<'a>> {
+                self.labelled(label).next()
+            }
+
+            /// Determines the position of the node in the input stream.
+            pub fn position(&self) -> u32 {
+                self.0.position()
+            }
+
+            /// Determines the length of the node.
+            pub fn length(&self) -> u32 {
+                self.0.length()
+            }
+
+            /// Determines whether another node lies entirely within this one.
+            ///
+            /// Nodes span the half-open interval `[position, position + length)`, so a node
+            /// contains itself and any empty node positioned at either of its ends.
+            pub fn contains(&self, other: &Cursor) -> bool {
+                self.position() <= other.position()
+                    && other.position() + other.length() <= self.position() + self.length()
+            }
+
+            /// Determines whether this node and another share at least one symbol of input.
+            ///
+            /// Nodes span the half-open interval `[position, position + length)`, so nodes that
+            /// merely touch do not overlap, and empty nodes never overlap anything.
+            pub fn overlaps(&self, other: &Cursor) -> bool {
+                self.0.overlaps(&other.0)
+            }
+
+            /// Determines whether the node has any error node descendants, including the node
+            /// itself.
+            ///
+            /// If the node is an error node, this will always return `true`.
+            pub fn has_error(&self) -> bool {
+                self.0.has_error()
+            }
+
+            /// The number of label and error nodes that enclose this node.
+            ///
+            /// The root node has a depth of zero and its children have a depth of one. Token nodes
+            /// have a depth like any other node, but never enclose one, since they are leaves.
+            pub fn depth(&self) -> u32 {
+                self.0.depth()
+            }
+
+            /// Takes a snapshot of everything the parser recorded about the node.
+            ///
+            /// This includes the bookkeeping behind the parse, such as how far ahead the parser
+            /// looked and how much work it did, which is useful for building alternative
+            /// renderings of the tree or debugging tools. See [`NodeInfo`] for more information.
+            pub fn info(&self) -> NodeInfo {
+                NodeInfo {
+                    grouping: self.grouping(),
+                    position: self.position(),
+                    length: self.length(),
+                    scan_distance: self.0.scan_distance(),
+                    work: self.0.work(),
+                    error_distance: self.0.error_distance(),
+                    depth: self.depth(),
+                    _private: (),
+                }
+            }
+
+            /// The greatest [`depth`](Cursor::depth) of this node or any of its descendants,
+            /// including token nodes.
+            ///
+            /// This walks the entire sub-tree below the node, so it takes time proportional to the
+            /// size of the sub-tree.
+            pub fn max_depth(&self) -> u32 {
+                self.0.max_depth()
+            }
+
+            /// Visits each node in the sub-tree below the node using the [`Visitor`] API.
+            ///
+            /// If the referenced node is not the root node, then the node itself is also visited.
+            /// See [`ParseMatch::visit`] for more information.
+            pub fn visit<V: Visitor>(&self, visitor: &mut V) {
+                self.0.visit(&mut VisitorAdapter(visitor))
+            }
+
+            /// Searches the parse tree for matching descendants.
+            ///
+            /// Performs a depth first search over the descendants of the node, yielding a cursor to
+            /// any descendants on which the predicate returns `true`. If the predicate returns
+            /// `true` on a node, it's descendants are skipped for the remainder of the search.
+            pub fn search<F>(&self, mut predicate: F) -> impl Iterator<Item = Cursor<'a>>
+            where
+                F: FnMut(Cursor) -> bool,
+            {
+                self.0
+                    .search(move |cursor| predicate(Cursor(cursor)))
+                    .map(Cursor)
+            }
+
+            /// Iterates over the immediate children of this node, yielding a cursor for each of
+            /// them.
+            pub fn children(&self) -> impl Iterator<Item = Cursor<'a>> {
+                self.search(|_| true)
+            }
+
+            /// Iterates over the immediate children of this node, skipping those labelled as
+            /// trivia by the grammar, such as whitespace and comments.
+            ///
+            /// Trivia nodes remain in the tree, so the input can still be reconstructed from it,
+            /// and can be found with [`children`](Self::children). See [`Label::is_trivia`].
+            pub fn significant_children(&self) -> impl Iterator<Item = Cursor<'a>> {
+                self.children()
+                    .filter(|child| !child.label().map_or(false, |label| label.is_trivia()))
+            }
+        }
+
+        impl<'a> CursorExt for Cursor<'a> {
+            fn span(&self) -> std::ops::Range<usize> {
+                let start = self.position() as usize;
+                start..start + self.length() as usize
+            }
+        }
+
+        /// A copy of a parse tree laid out in a flat array, produced by [`ParseMatch::flatten`].
+        ///
+        /// Nodes are stored in the order they begin, so a node is always followed by its
+        /// descendants. Rather than pointing to each other, nodes refer to their first child and
+        /// next sibling by index, which makes the tree cheap to iterate over, store, and pass
+        /// between programs. The root node is not included, so the top-level nodes are the first
+        /// node and its siblings.
+        #[allow(unused)]
+        #[derive(Debug, Clone, Eq, PartialEq, Hash)]
+        pub struct FlatTree(Vec<FlatNode>);
+
+        #[allow(unused)]
+        impl FlatTree {
+            /// Every node in the tree, in the order they begin.
+            pub fn nodes(&self) -> &[FlatNode] {
+                &self.0
+            }
+
+            /// The node at an index, such as one taken from [`FlatNode::first_child`].
+            pub fn get(&self, index: u32) -> Option<&FlatNode> {
+                self.0.get(index as usize)
+            }
+
+            /// The number of nodes in the tree.
+            pub fn len(&self) -> usize {
+                self.0.len()
+            }
+
+            /// Whether the tree has no nodes, which is the case when nothing was labelled.
+            pub fn is_empty(&self) -> bool {
+                self.0.is_empty()
+            }
+
+            /// Iterates over the top-level nodes of the tree.
+            pub fn top_level(&self) -> impl Iterator<Item = &FlatNode> + '_ {
+                self.siblings_from(if self.0.is_empty() { None } else { Some(0) })
+            }
+
+            /// Iterates over the immediate children of a node.
+            pub fn children<'a>(
+                &'a self,
+                node: &FlatNode,
+            ) -> impl Iterator<Item = &'a FlatNode> + 'a {
+                self.siblings_from(node.first_child)
+            }
+
+            fn siblings_from(&self, first: Option<u32>) -> impl Iterator<Item = &FlatNode> + '_ {
+                std::iter::successors(first.map(|index| &self.0[index as usize]), |node| {
+                    node.next_sibling.map(|index| &self.0[index as usize])
+                })
+            }
+        }
+
+        /// A node in a [`FlatTree`].
+        #[allow(unused)]
+        #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+        pub struct FlatNode {
+            /// What the node represents. This is never [`Grouping::Root`].
+            pub grouping: Grouping,
+            /// The [`Label::label_id`] of the node's label or token kind, if it has one, which
+            /// suits consumers that can't use [`Label`] directly.
+            pub label_id: Option<u16>,
+            /// The position of the node in the input stream.
+            pub position: u32,
+            /// The length of input covered by the node.
+            pub length: u32,
+            /// The index of the node's first child, if it has any.
+            pub first_child: Option<u32>,
+            /// The index of the next node with the same parent, if there is one.
+            pub next_sibling: Option<u32>,
+        }
+
+        impl CursorExt for FlatNode {
+            fn span(&self) -> std::ops::Range<usize> {
+                let start = self.position as usize;
+                start..start + self.length as usize
+            }
+        }
+
+        /// A snapshot of a node in the parse tree, produced by [`Cursor::info`].
+        #[allow(unused)]
+        #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+        pub struct NodeInfo {
+            /// What the node represents.
+            pub grouping: Grouping,
+            /// The position of the node in the input stream.
+            pub position: u32,
+            /// The length of input covered by the node.
+            pub length: u32,
+            /// How far past the node's position the parser examined the input while matching it,
+            /// which exceeds the node's length when the parser looked ahead.
+            pub scan_distance: u32,
+            /// An estimate of the work the parser did to match the node, which the parser uses to
+            /// decide what to cache.
+            pub work: u32,
+            /// How far past the node's position its first error begins, or `None` if neither the
+            /// node nor its descendants contain an error.
+            pub error_distance: Option<u32>,
+            /// The number of label and error nodes that enclose the node.
+            pub depth: u32,
+            _private: (),
+        }
+
+        impl<'a> std::fmt::Debug for Cursor<'a> {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                #[allow(unused)]
+                #[derive(Debug)]
+                struct Cursor {
+                    grouping: Grouping,
+                    position: u32,
+                    length: u32,
+                    has_error: bool,
+                }
+
+                write!(
+                    f,
+                    "{:?}",
+                    Cursor {
+                        grouping: self.grouping(),
+                        position: self.position(),
+                        length: self.length(),
+                        has_error: self.has_error(),
+                    }
+                )
+            }
+        }
+
+        /// The type of a node reference by a [`Cursor`].
+        #[allow(unused)]
+        #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+        pub enum Grouping {
+            /// Represents a labelled segment of the parse tree.
+            Label(Label),
+            /// Represents a soft-error in the parse tree.
+            Error {
+                /// The set of labels that were excepted at the error's position in the input stream.
+                expected_labels: &'static [Label],
+                /// The set of literals that were excepted at the error's position in the input stream.
+                expected_literals: &'static [&'static [Symbol]],
+                /// The message written for the error's rule, which replaces what it expected.
+                expected_message: Option<&'static str>,
+            },
+            /// Represents a token matched by a literal or character class that was given a kind.
+            ///
+            /// Tokens never have children.
+            Token(Label),
+            /// Identifies the root node of parse tree.
+            ///
+            /// For any given parse tree, there is one cursor whose [`grouping`](Cursor::grouping)
+            /// method returns this variant.
+            Root,
+        }
+
+        #[allow(unused)]
+        impl Grouping {
+            /// Renders what an error node expected as a list suitable for a message such as
+            /// "expected one of: ...".
+            ///
+            /// Labels are rendered by name, literals as quoted strings and a message written for the
+            /// error as is, and the list is sorted without duplicates. The list is empty unless this
+            /// is an [`Error`](Grouping::Error).
+            pub fn expected(&self) -> Vec<String> {
+                match self {
+                    Grouping::Error {
+                        expected_labels,
+                        expected_literals,
+                        expected_message,
+                    } => describe_expected(expected_labels, expected_literals, *expected_message),
+                    _ => Vec::new(),
+                }
+            }
+
+            /// The [`Label::label_id`] of a label or token node's label, or `None` for any other
+            /// kind of node.
+            pub fn label_id(&self) -> Option<u16> {
+                match self {
+                    Grouping::Label(label) | Grouping::Token(label) => Some(label.label_id()),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
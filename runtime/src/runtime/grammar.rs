@@ -0,0 +1,42 @@
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+
+use super::cache::CacheStore;
+use super::{Input, State, SymbolType};
+
+pub trait Grammar: Sized {
+    type Symbol: SymbolType;
+    type Label: LabelType + 'static;
+    type Expected: ExpectedType<Self::Label, Symbol = Self::Symbol> + 'static;
+    /// Where results are cached, which is [`NoCache`](super::NoCache) unless the grammar has
+    /// cache points or growth points
+    type Cache: CacheStore<Self>;
+
+    fn start_state<I: Input<Self::Symbol> + ?Sized>(&self) -> State<I, Self>;
+
+    fn cache_slots(&self) -> usize;
+
+    /// What the start of the grammar expects, reported when a failed parse is treated as an error
+    fn start_expected(&self) -> Self::Expected;
+
+    /// Called each time the parser moves back over input it had matched, with the position it
+    /// moved back to. This does nothing unless the parser was generated with tracing
+    #[inline(always)]
+    fn backtracked(&self, _position: u32) {}
+}
+
+pub trait LabelType: Debug + Display + Copy + Eq + Hash {}
+
+pub trait ExpectedType<L: LabelType>: Debug + Copy + Eq + Hash {
+    type Symbol: SymbolType;
+
+    fn literals(&self) -> &'static [&'static [Self::Symbol]];
+
+    fn labels(&self) -> &'static [L];
+
+    /// A message written for the error in place of what it expected, which is
+    /// `None` unless the parser was generated with a message for its rule
+    fn message(&self) -> Option<&'static str> {
+        None
+    }
+}
@@ -0,0 +1,353 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+/// A type of symbol that input can consist of.
+///
+/// Parsers read bytes unless they were generated with `--symbols u32`, in which case they read
+/// 32-bit symbols such as the token kinds produced by a separate lexer. Every symbol type can
+/// represent a byte.
+pub trait SymbolType: Copy + Eq + Ord + Hash + Debug + From<u8> + 'static {
+    /// Renders a literal for a human, as a quoted string where possible.
+    fn describe(literal: &[Self]) -> String;
+
+    /// Renders a literal as an S-expression atom.
+    fn to_sexp(literal: &[Self]) -> String;
+}
+
+/// Renders a byte as it would appear in a Rust string literal.
+///
+/// Printable ASCII is written as is, except for backslashes and double quotes. Newlines, tabs,
+/// carriage returns and nul bytes use their usual escapes, and every other byte is written as
+/// `\xNN`. Expected literals in error messages and classes in visualizations of the grammar both
+/// use this, so a byte looks the same in either.
+pub fn render_byte(byte: u8) -> String {
+    match byte {
+        b'\n' => String::from("\\n"),
+        b'\t' => String::from("\\t"),
+        b'\r' => String::from("\\r"),
+        b'\0' => String::from("\\0"),
+        b'\\' => String::from("\\\\"),
+        b'"' => String::from("\\\""),
+        b' '..=b'~' => String::from(byte as char),
+        _ => format!("\\x{:02x}", byte),
+    }
+}
+
+impl SymbolType for u8 {
+    /// Quotes the literal like a Rust string, rendering ASCII with [`render_byte`]. Other
+    /// characters are written as is unless Rust would escape them, and any bytes that aren't valid
+    /// UTF-8 are written as `\xNN` escapes.
+    fn describe(literal: &[u8]) -> String {
+        let mut result = String::from("\"");
+        let mut rest = literal;
+
+        while !rest.is_empty() {
+            let (valid, invalid) = match std::str::from_utf8(rest) {
+                Ok(valid) => (valid, 0),
+                Err(err) => {
+                    let valid = std::str::from_utf8(&rest[..err.valid_up_to()]).unwrap();
+                    let invalid = err.error_len().unwrap_or(rest.len() - err.valid_up_to());
+                    (valid, invalid)
+                }
+            };
+
+            for char in valid.chars() {
+                if char.is_ascii() {
+                    result.push_str(&render_byte(char as u8));
+                } else {
+                    result.extend(char.escape_debug());
+                }
+            }
+
+            for byte in &rest[valid.len()..valid.len() + invalid] {
+                result.push_str(&render_byte(*byte));
+            }
+
+            rest = &rest[valid.len() + invalid..];
+        }
+
+        result.push('"');
+        result
+    }
+
+    /// Quotes the literal, escaping quotes, backslashes and any byte outside printable ASCII as
+    /// a hex escape.
+    fn to_sexp(literal: &[u8]) -> String {
+        let mut result = String::from("\"");
+
+        for &byte in literal {
+            match byte {
+                b'"' => result.push_str("\\\""),
+                b'\\' => result.push_str("\\\\"),
+                b' '..=b'~' => result.push(byte as char),
+                _ => result.push_str(&format!("\\x{:02x};", byte)),
+            }
+        }
+
+        result.push('"');
+        result
+    }
+}
+
+impl SymbolType for u32 {
+    fn describe(literal: &[u32]) -> String {
+        format!("{:?}", literal)
+    }
+
+    /// Writes the literal as a vector of numbers, such as `#(3 14)`.
+    fn to_sexp(literal: &[u32]) -> String {
+        let symbols = literal
+            .iter()
+            .map(|symbol| symbol.to_string())
+            .collect::<Vec<_>>();
+
+        format!("#({})", symbols.join(" "))
+    }
+}
+
+/// An indexable buffer of symbols that can be parsed, which are bytes unless specified otherwise.
+///
+/// The parser does not perform any internal buffering on top of this, so implementations should be
+/// as performant as possible. Implementations are provided for slices and arrays of symbols, and
+/// for `str`, so in-memory input can be parsed directly:
+///
+/// ```ignore
+/// let bytes = parser::parse(b"some input");
+/// let text = parser::parse("some input");
+/// ```
+///
+/// A `str` is parsed as its UTF-8 encoded bytes, and all positions in the result are byte offsets.
+///
+/// # Safety
+///
+/// An incorrect implementation may cause undefined behavior if parsed.
+#[allow(unused)]
+pub unsafe trait Input<S: SymbolType = u8> {
+    /// Gets a symbol at a particular index if the index is in bounds.
+    ///
+    /// This must return `Some` if `position < self.len()` and must return `None` if
+    /// `position >= self.len()`. The symbol at any given index must be constant within a parse.
+    fn get(&self, position: u32) -> Option<S>;
+
+    /// Determines the length of the input.
+    ///
+    /// This must be constant within a parse, and agree with [`get`](Self::get), since checks for
+    /// the end of input are decided by the length alone. Positions are 32 bits wide, so the parse
+    /// is abandoned with [`Abort::InputTooLong`](super::Abort::InputTooLong) if the input is
+    /// `u32::MAX` symbols or longer.
+    ///
+    /// This returns a `usize` rather than a position so that input too long to parse can be
+    /// reported at all.
+    fn len(&self) -> usize;
+
+    /// Compares the input starting at a position against a literal.
+    ///
+    /// Returns whether the literal matched, along with how far ahead of the position was examined.
+    /// This is the literal's length on a match, and one past the first differing symbol otherwise.
+    /// Implementations may override this to compare in bulk, but must produce the same result as
+    /// comparing a symbol at a time with [`get`](Self::get).
+    fn matches_at(&self, position: u32, literal: &[S]) -> (bool, u32) {
+        if let Some(slice) = self.slice(position, literal.len() as u32) {
+            return slice_matches_at(slice, 0, literal);
+        }
+
+        let mut length = 0;
+
+        for symbol in literal {
+            if self.get(position + length) != Some(*symbol) {
+                return (false, length + 1);
+            }
+
+            length += 1;
+        }
+
+        (true, length)
+    }
+
+    /// Borrows up to `len` symbols of the input starting at a position, if they are stored
+    /// contiguously.
+    ///
+    /// The slice must hold the same symbols as [`get`](Self::get), and must only be shorter than
+    /// `len` where it reaches the end of the input. Series are matched against the slice when one
+    /// is available, which avoids a call to `get` per symbol. The default implementation returns
+    /// `None`, so that series are matched a symbol at a time.
+    fn slice(&self, start: u32, len: u32) -> Option<&[S]> {
+        None
+    }
+}
+
+/// Borrows up to `len` symbols of a slice starting at a position, stopping at its end
+fn slice_at<S>(slice: &[S], start: u32, len: u32) -> &[S] {
+    let start = (start as usize).min(slice.len());
+    let end = start.saturating_add(len as usize).min(slice.len());
+    &slice[start..end]
+}
+
+/// Compares a slice against a literal, falling back to a symbol at a time comparison to find the
+/// first differing symbol only when the literal doesn't match
+fn slice_matches_at<S: SymbolType>(slice: &[S], position: u32, literal: &[S]) -> (bool, u32) {
+    let start = position as usize;
+
+    if slice.get(start..start + literal.len()) == Some(literal) {
+        return (true, literal.len() as u32);
+    }
+
+    let length = slice[start.min(slice.len())..]
+        .iter()
+        .zip(literal)
+        .take_while(|(actual, expected)| actual == expected)
+        .count();
+
+    (false, length as u32 + 1)
+}
+
+unsafe impl<S: SymbolType> Input<S> for [S] {
+    fn get(&self, position: u32) -> Option<S> {
+        self.get(position as usize).copied()
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn matches_at(&self, position: u32, literal: &[S]) -> (bool, u32) {
+        slice_matches_at(self, position, literal)
+    }
+
+    fn slice(&self, start: u32, len: u32) -> Option<&[S]> {
+        Some(slice_at(self, start, len))
+    }
+}
+
+unsafe impl<S: SymbolType, const N: usize> Input<S> for [S; N] {
+    fn get(&self, position: u32) -> Option<S> {
+        self.as_slice().get(position as usize).copied()
+    }
+
+    fn len(&self) -> usize {
+        N
+    }
+
+    fn matches_at(&self, position: u32, literal: &[S]) -> (bool, u32) {
+        slice_matches_at(self, position, literal)
+    }
+
+    fn slice(&self, start: u32, len: u32) -> Option<&[S]> {
+        Some(slice_at(self, start, len))
+    }
+}
+
+unsafe impl Input for str {
+    fn get(&self, position: u32) -> Option<u8> {
+        self.as_bytes().get(position as usize).copied()
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn matches_at(&self, position: u32, literal: &[u8]) -> (bool, u32) {
+        slice_matches_at(self.as_bytes(), position, literal)
+    }
+
+    fn slice(&self, start: u32, len: u32) -> Option<&[u8]> {
+        Some(slice_at(self.as_bytes(), start, len))
+    }
+}
+
+/// The byte order mark that may appear at the start of UTF-8 encoded text.
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+
+/// Wraps an [`Input`], hiding a leading UTF-8 byte order mark if one is present.
+///
+/// Positions in a parse of this input are relative to the end of the byte order mark. Use
+/// [`offset`](SkipBom::offset) to map them back to positions in the wrapped input.
+#[allow(unused)]
+pub struct SkipBom<'a, I: Input<S> + ?Sized, S: SymbolType = u8> {
+    input: &'a I,
+    offset: u32,
+    symbol: PhantomData<S>,
+}
+
+#[allow(unused)]
+impl<'a, I: Input<S> + ?Sized, S: SymbolType> SkipBom<'a, I, S> {
+    /// Wraps an input, detecting whether it starts with a byte order mark.
+    pub fn new(input: &'a I) -> Self {
+        let has_bom = UTF8_BOM
+            .iter()
+            .enumerate()
+            .all(|(i, byte)| input.get(i as u32) == Some(S::from(*byte)));
+
+        let offset = if has_bom { UTF8_BOM.len() as u32 } else { 0 };
+
+        Self {
+            input,
+            offset,
+            symbol: PhantomData,
+        }
+    }
+
+    /// The number of symbols skipped at the start of the wrapped input.
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+}
+
+unsafe impl<'a, I: Input<S> + ?Sized, S: SymbolType> Input<S> for SkipBom<'a, I, S> {
+    fn get(&self, position: u32) -> Option<S> {
+        self.input.get(position.checked_add(self.offset)?)
+    }
+
+    fn len(&self) -> usize {
+        self.input.len() - self.offset as usize
+    }
+
+    fn slice(&self, start: u32, len: u32) -> Option<&[S]> {
+        self.input.slice(start.checked_add(self.offset)?, len)
+    }
+}
+
+/// Wraps an [`Input`], appending a sentinel symbol after its end so that grammars can match the
+/// end of input as a concrete symbol.
+///
+/// The sentinel is matched by any class that contains it, including the any-symbol class. A
+/// grammar that uses `any` to discard input will therefore consume the sentinel too, and `eof`
+/// only matches after the sentinel. Use a class excluding the sentinel where this matters, and
+/// make sure the sentinel cannot otherwise appear in the input.
+#[allow(unused)]
+pub struct WithSentinel<'a, I: Input<S> + ?Sized, S: SymbolType = u8> {
+    input: &'a I,
+    sentinel: S,
+}
+
+#[allow(unused)]
+impl<'a, I: Input<S> + ?Sized, S: SymbolType> WithSentinel<'a, I, S> {
+    /// Wraps an input, appending the provided sentinel symbol.
+    pub fn new(input: &'a I, sentinel: S) -> Self {
+        Self { input, sentinel }
+    }
+}
+
+unsafe impl<'a, I: Input<S> + ?Sized, S: SymbolType> Input<S> for WithSentinel<'a, I, S> {
+    fn get(&self, position: u32) -> Option<S> {
+        if position as usize == self.input.len() {
+            Some(self.sentinel)
+        } else {
+            self.input.get(position)
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.input.len() + 1
+    }
+
+    /// Only slices that end before the sentinel can be borrowed from the wrapped input.
+    fn slice(&self, start: u32, len: u32) -> Option<&[S]> {
+        if start as usize + len as usize <= self.input.len() {
+            self.input.slice(start, len)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,159 @@
+/// Options that control how a [`LineMap`] divides input into lines and counts columns.
+///
+/// Use [`LineOptions::new`] to create a set of options with the default behavior, and then
+/// customize them with the builder methods.
+#[allow(unused)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct LineOptions {
+    carriage_returns: bool,
+    tab_width: u32,
+}
+
+#[allow(unused)]
+impl LineOptions {
+    /// Creates a set of options where `\n`, `\r\n` and a lone `\r` each end a line, and a tab
+    /// counts as one column.
+    pub fn new() -> Self {
+        Self {
+            carriage_returns: true,
+            tab_width: 1,
+        }
+    }
+
+    /// Only ends lines at `\n`, so a lone `\r` counts as a column like any other character.
+    ///
+    /// A `\r` before a `\n` still ends up at the end of its line, where it is out of the way.
+    pub fn newlines_only(mut self) -> Self {
+        self.carriage_returns = false;
+        self
+    }
+
+    /// Counts each tab as a number of columns, since editors differ in how wide they show tabs.
+    pub fn tab_width(mut self, width: u32) -> Self {
+        self.tab_width = width;
+        self
+    }
+}
+
+impl Default for LineOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A line and column in the input, both counted from one.
+#[allow(unused)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct LineColumn {
+    pub line: u32,
+    pub column: u32,
+}
+
+/// Translates positions in text into lines and columns, as shown by an editor.
+///
+/// Line starts are found once up front, so looking up a line is a binary search. Columns count
+/// characters rather than bytes, treating the input as UTF-8, and are found by scanning from the
+/// start of the line.
+#[allow(unused)]
+#[derive(Debug, Clone)]
+pub struct LineMap<'a> {
+    input: &'a [u8],
+    line_starts: Vec<u32>,
+    options: LineOptions,
+}
+
+#[allow(unused)]
+impl<'a> LineMap<'a> {
+    /// Finds the lines of some input with the default [`LineOptions`].
+    pub fn new(input: &'a [u8]) -> Self {
+        Self::with_options(input, LineOptions::new())
+    }
+
+    /// Finds the lines of some input.
+    pub fn with_options(input: &'a [u8], options: LineOptions) -> Self {
+        let mut line_starts = vec![0];
+
+        for (i, byte) in input.iter().enumerate() {
+            let ends_line = match byte {
+                b'\n' => true,
+                // The newline of a `\r\n` ends the line instead
+                b'\r' => options.carriage_returns && input.get(i + 1) != Some(&b'\n'),
+                _ => false,
+            };
+
+            if ends_line {
+                line_starts.push(i as u32 + 1);
+            }
+        }
+
+        Self {
+            input,
+            line_starts,
+            options,
+        }
+    }
+
+    /// The number of lines in the input. Input ending in a line terminator has an empty last line,
+    /// and empty input is a single empty line.
+    pub fn line_count(&self) -> u32 {
+        self.line_starts.len() as u32
+    }
+
+    /// The position where a line starts, counting lines from one.
+    pub fn line_start(&self, line: u32) -> Option<u32> {
+        let index = line.checked_sub(1)?;
+        self.line_starts.get(index as usize).copied()
+    }
+
+    /// The line and column of a position. Positions past the end of the input are treated as the
+    /// end of the input.
+    pub fn line_column(&self, position: u32) -> LineColumn {
+        let (line, before) = self.line_prefix(position);
+
+        let mut column = 1;
+
+        for byte in before {
+            column += match byte {
+                b'\t' => self.options.tab_width,
+                // Continuation bytes belong to the character before them
+                0x80..=0xBF => 0,
+                _ => 1,
+            };
+        }
+
+        LineColumn { line, column }
+    }
+
+    /// The column of a position as an editor draws it, counting from one, with each tab advancing
+    /// to the next multiple of `tab_width` columns. This ignores [`LineOptions::tab_width`], and
+    /// is what places a caret under the right character in a terminal.
+    ///
+    /// Like [`line_column`](Self::line_column), this scans from the start of the line, so it
+    /// takes time proportional to the column.
+    pub fn visual_column(&self, position: u32, tab_width: u32) -> u32 {
+        let (_, before) = self.line_prefix(position);
+        let tab_width = tab_width.max(1);
+
+        let mut column = 0;
+
+        for byte in before {
+            match byte {
+                b'\t' => column = (column / tab_width + 1) * tab_width,
+                0x80..=0xBF => {}
+                _ => column += 1,
+            }
+        }
+
+        column + 1
+    }
+
+    /// The line of a position, along with the part of the line before it.
+    fn line_prefix(&self, position: u32) -> (u32, &'a [u8]) {
+        let position = position.min(self.input.len() as u32);
+
+        let line = self.line_starts.partition_point(|start| *start <= position);
+        let start = self.line_starts[line - 1];
+
+        (line as u32, &self.input[start as usize..position as usize])
+    }
+}
@@ -0,0 +1,891 @@
+//! Runtime common to all generated parsers. Copied into the build directory
+//! when generating a parser
+
+use std::fmt::{self, Debug, Display, Formatter};
+use std::iter::FusedIterator;
+
+use buffered_iter::BufferedIter;
+#[allow(unused)]
+pub use cache::{Cache, CacheProfile, NoCache, SlotProfile};
+pub use context::Context;
+pub use grammar::*;
+pub use input::*;
+#[allow(unused)]
+pub use line_map::*;
+#[allow(unused)]
+pub use options::*;
+use result::{EnterExit, Walk};
+pub use result::{Grouping as GenGrouping, Match, ParseResult};
+#[allow(unused)]
+pub use text::*;
+
+mod array_vec;
+mod buffered_iter;
+mod cache;
+mod context;
+mod grammar;
+mod input;
+mod line_map;
+mod options;
+mod refc;
+mod result;
+mod small_vec;
+mod stack;
+mod text;
+
+/// The version of peg-pack the runtime belongs to. Generated parsers check at
+/// compile time that it matches the version that generated them
+pub const RUNTIME_VERSION: &str = "0.2.0";
+
+/// Whether the runtime belongs to the given version of peg-pack, usable in
+/// constants where string comparison isn't
+#[allow(unused)]
+pub const fn runtime_version_is(version: &str) -> bool {
+    let (left, right) = (RUNTIME_VERSION.as_bytes(), version.as_bytes());
+
+    if left.len() != right.len() {
+        return false;
+    }
+
+    let mut i = 0;
+    while i < left.len() {
+        if left[i] != right[i] {
+            return false;
+        }
+
+        i += 1;
+    }
+
+    true
+}
+
+// Public so that peg-pack can weigh states the same way when inserting cache points
+#[doc(hidden)]
+pub const SERIES_WORK: u32 = 1;
+#[doc(hidden)]
+pub const CACHE_WORK: u32 = 25;
+#[doc(hidden)]
+pub const LABEL_WORK: u32 = 50;
+#[doc(hidden)]
+pub const MARK_ERROR_WORK: u32 = 50;
+#[doc(hidden)]
+pub const NOT_AHEAD_WORK: u32 = 1;
+#[doc(hidden)]
+pub const CHOICE_WORK: u32 = 1;
+#[doc(hidden)]
+pub const SEQ_WORK: u32 = 1;
+#[doc(hidden)]
+pub const REPEAT_WORK: u32 = 1;
+#[doc(hidden)]
+pub const MAX_UNCACHED_WORK: u32 = 250;
+/// The number of states run between progress reports. Most states do about a
+/// unit of work
+pub(super) const PROGRESS_INTERVAL: u32 = 1 << 16;
+
+pub struct GenParseMatch<G: Grammar> {
+    // The match must always have no grouping
+    node: Match<G>,
+    // The position the root of the match is reported at
+    base: u32,
+}
+
+impl<G: Grammar> GenParseMatch<G> {
+    #[allow(unused)]
+    pub fn new(mut node: Match<G>) -> Self {
+        if node.grouping() != GenGrouping::None {
+            node = node.wrap();
+        }
+
+        Self { node, base: 0 }
+    }
+
+    #[allow(unused)]
+    pub fn offset_by(mut self, amount: u32) -> Self {
+        self.base += amount;
+        self
+    }
+
+    pub fn root(&self) -> GenCursor<'_, G> {
+        GenCursor {
+            node: &self.node,
+            position: self.base,
+            depth: 0,
+        }
+    }
+
+    #[allow(unused)]
+    pub fn scan_distance(&self) -> u32 {
+        self.base + self.node.scan_distance()
+    }
+
+    fn walk(&self) -> Walk<'_, G> {
+        self.node.walk_from(self.base)
+    }
+
+    #[allow(unused)]
+    pub fn visit<V: GenVisitor<G>>(&self, visitor: &mut V) {
+        self.root().visit(visitor);
+    }
+
+    #[allow(unused)]
+    pub fn tokens(&self) -> impl Iterator<Item = GenTokenInfo<G>> + '_ {
+        self.walk()
+            .filter_map(|(position, node, state)| match node.grouping() {
+                GenGrouping::Token(kind) if state == EnterExit::Enter => Some(GenTokenInfo {
+                    kind,
+                    position,
+                    length: node.distance(),
+                }),
+                _ => None,
+            })
+    }
+
+    /// Splits the matched input into maximal runs that share the innermost
+    /// label, error or token enclosing them, in order. Input outside every
+    /// grouping forms runs of its own
+    #[allow(unused)]
+    pub fn leaves(&self) -> Vec<GenLeaf<G>> {
+        let root = self.root();
+        let mut leaves = Vec::new();
+        let mut enclosing = vec![GenGrouping::None];
+        let mut covered = root.position();
+
+        let mut cover = |enclosing: &[GenGrouping<_, _>], position: u32| {
+            if position > covered {
+                leaves.push(GenLeaf {
+                    grouping: *enclosing.last().unwrap(),
+                    position: covered,
+                    length: position - covered,
+                });
+            }
+
+            covered = position;
+        };
+
+        for (position, node, state) in self.walk() {
+            if node.grouping() == GenGrouping::None {
+                continue;
+            }
+
+            cover(&enclosing, position);
+
+            match state {
+                EnterExit::Enter => enclosing.push(node.grouping()),
+                EnterExit::Exit => {
+                    enclosing.pop();
+                }
+            }
+        }
+
+        cover(&enclosing, root.position() + root.length());
+        leaves
+    }
+
+    /// Lays the label, error and token nodes out in an array in the order they
+    /// are entered, linking each to its first child and next sibling
+    #[allow(unused)]
+    pub fn flatten(&self) -> Vec<GenFlatNode<G>> {
+        let mut nodes = Vec::<GenFlatNode<G>>::new();
+        // The index of each open node along with its last child so far. The
+        // bottom entry stands in for the root, which isn't included
+        let mut open = vec![(None, None::<u32>)];
+
+        for (position, node, state) in self.walk() {
+            if node.grouping() == GenGrouping::None {
+                continue;
+            }
+
+            match state {
+                EnterExit::Enter => {
+                    let index = nodes.len() as u32;
+                    let (parent, last_child) = open.last_mut().unwrap();
+
+                    match last_child {
+                        Some(sibling) => nodes[*sibling as usize].next_sibling = Some(index),
+                        None => {
+                            if let Some(parent) = parent {
+                                nodes[*parent as usize].first_child = Some(index);
+                            }
+                        }
+                    }
+
+                    *last_child = Some(index);
+
+                    nodes.push(GenFlatNode {
+                        grouping: node.grouping(),
+                        position,
+                        length: node.distance(),
+                        first_child: None,
+                        next_sibling: None,
+                    });
+
+                    open.push((Some(index), None));
+                }
+                EnterExit::Exit => {
+                    open.pop();
+                }
+            }
+        }
+
+        nodes
+    }
+
+    #[allow(unused)]
+    pub fn unmerged_errors(&self) -> impl Iterator<Item = GenErrorInfo<G>> + '_ {
+        ErrorIter {
+            walk: self.walk(),
+            labels: Vec::new(),
+        }
+    }
+
+    /// Writes the tree as indented S-expressions, one node per line. Every
+    /// node is a list of its kind, its name, its position and its length,
+    /// followed by its children
+    #[allow(unused)]
+    pub fn to_sexp(&self) -> String {
+        let root = self.root();
+        let mut result = format!("(root {} {}", root.position(), root.length());
+        let mut depth = 1;
+
+        let iter = self
+            .walk()
+            .filter(|(_, node, _)| node.grouping() != GenGrouping::None);
+
+        for (position, node, state) in iter {
+            match state {
+                EnterExit::Enter => {
+                    result.push('\n');
+
+                    for _ in 0..depth {
+                        result.push_str("  ");
+                    }
+
+                    let head = match node.grouping() {
+                        GenGrouping::Label(label) => format!("label {}", sexp_symbol(label)),
+                        GenGrouping::Token(kind) => format!("token {}", sexp_symbol(kind)),
+                        GenGrouping::Error(expected) if expected.message().is_some() => {
+                            let message = expected.message().unwrap().as_bytes();
+                            format!("error (message {})", u8::to_sexp(message))
+                        }
+                        GenGrouping::Error(expected) => {
+                            let labels = expected.labels().iter().map(sexp_symbol);
+                            let literals = expected
+                                .literals()
+                                .iter()
+                                .map(|literal| G::Symbol::to_sexp(literal));
+
+                            let mut head = String::from("error (expected");
+
+                            for item in labels.chain(literals) {
+                                head.push(' ');
+                                head.push_str(&item);
+                            }
+
+                            head.push(')');
+                            head
+                        }
+                        GenGrouping::None => unreachable!(),
+                    };
+
+                    result.push_str(&format!("({} {} {}", head, position, node.distance()));
+                    depth += 1;
+                }
+                EnterExit::Exit => {
+                    result.push(')');
+                    depth -= 1;
+                }
+            }
+        }
+
+        result.push(')');
+        result
+    }
+
+    fn write_node(&self, f: &mut Formatter, start: u32, node: &Match<G>) -> fmt::Result {
+        let end = start + node.distance();
+
+        match node.grouping() {
+            GenGrouping::Label(label) | GenGrouping::Token(label) => {
+                write!(f, "{:?}[{}-{}]", label, start, end)
+            }
+            GenGrouping::Error(expected) => write!(f, "{:?}[{}-{}]", expected, start, end),
+            GenGrouping::None => Ok(()),
+        }
+    }
+
+    fn next_is_enter<'b>(
+        &self,
+        iter: &mut BufferedIter<impl Iterator<Item = (u32, &'b Match<G>, EnterExit)>>,
+    ) -> bool
+    where
+        G: 'b,
+    {
+        iter.peek()
+            .map(|(_, _, state)| *state == EnterExit::Enter)
+            .unwrap_or(false)
+    }
+
+    fn delimit_normal<'b>(
+        &self,
+        f: &mut Formatter,
+        iter: &mut BufferedIter<impl Iterator<Item = (u32, &'b Match<G>, EnterExit)>>,
+    ) -> fmt::Result
+    where
+        G: 'b,
+    {
+        if self.next_is_enter(iter) {
+            write!(f, ", ")?;
+        }
+
+        Ok(())
+    }
+
+    fn delimit_pretty<'b>(
+        &self,
+        f: &mut Formatter,
+        iter: &mut BufferedIter<impl Iterator<Item = (u32, &'b Match<G>, EnterExit)>>,
+    ) -> fmt::Result
+    where
+        G: 'b,
+    {
+        if iter.peek().is_some() {
+            write!(f, ",")?;
+        }
+
+        Ok(())
+    }
+
+    fn newline_indent(&self, f: &mut Formatter, amount: usize) -> fmt::Result {
+        writeln!(f)?;
+
+        for _ in 0..amount {
+            write!(f, "    ")?;
+        }
+
+        Ok(())
+    }
+
+    fn fmt_normal<'b>(
+        &self,
+        f: &mut Formatter,
+        iter: &mut BufferedIter<impl Iterator<Item = (u32, &'b Match<G>, EnterExit)>>,
+    ) -> fmt::Result
+    where
+        G: 'b,
+    {
+        while let Some((position, node, state)) = iter.next() {
+            match state {
+                EnterExit::Enter => {
+                    self.write_node(f, position, node)?;
+
+                    if self.next_is_enter(iter) {
+                        write!(f, "(")?;
+                    } else {
+                        iter.next();
+                        self.delimit_normal(f, iter)?;
+                    }
+                }
+                EnterExit::Exit => {
+                    write!(f, ")")?;
+                    self.delimit_normal(f, iter)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn fmt_pretty<'b>(
+        &self,
+        f: &mut Formatter,
+        iter: &mut BufferedIter<impl Iterator<Item = (u32, &'b Match<G>, EnterExit)>>,
+    ) -> fmt::Result
+    where
+        G: 'b,
+    {
+        let mut indent = 0;
+        let mut start = true;
+
+        while let Some((position, node, state)) = iter.next() {
+            match state {
+                EnterExit::Enter => {
+                    if start {
+                        start = false;
+                    } else {
+                        self.newline_indent(f, indent)?;
+                    }
+
+                    self.write_node(f, position, node)?;
+
+                    if self.next_is_enter(iter) {
+                        write!(f, "(")?;
+                        indent += 1;
+                    } else {
+                        iter.next();
+                        self.delimit_pretty(f, iter)?;
+                    }
+                }
+                EnterExit::Exit => {
+                    indent -= 1;
+                    self.newline_indent(f, indent)?;
+                    write!(f, ")")?;
+                    self.delimit_pretty(f, iter)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes a label name as an S-expression symbol, quoting it between bars if
+/// it contains anything other than letters, digits and underscores
+fn sexp_symbol(label: impl Display) -> String {
+    let name = label.to_string();
+
+    if !name.is_empty()
+        && name
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'_')
+    {
+        return name;
+    }
+
+    let mut result = String::from("|");
+
+    for char in name.chars() {
+        if char == '|' || char == '\\' {
+            result.push('\\');
+        }
+
+        result.push(char);
+    }
+
+    result.push('|');
+    result
+}
+
+impl<G: Grammar> Debug for GenParseMatch<G> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        struct Inner<'a, G: Grammar>(&'a GenParseMatch<G>);
+
+        impl<'a, G: Grammar> Debug for Inner<'a, G> {
+            fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+                let iter = self
+                    .0
+                    .walk()
+                    .filter(|(_, node, _)| node.grouping() != GenGrouping::None);
+
+                let mut iter = BufferedIter::new(iter);
+
+                if f.alternate() {
+                    self.0.fmt_pretty(f, &mut iter)
+                } else {
+                    self.0.fmt_normal(f, &mut iter)
+                }
+            }
+        }
+
+        let has_elements = self
+            .walk()
+            .find(|(_, node, _)| node.grouping() != GenGrouping::None)
+            .is_some();
+
+        if has_elements {
+            f.debug_tuple("ParseMatch").field(&Inner(self)).finish()
+        } else {
+            f.debug_tuple("ParseMatch").finish()
+        }
+    }
+}
+
+pub trait GenVisitor<G: Grammar> {
+    fn enter(
+        &mut self,
+        label: G::Label,
+        position: u32,
+        length: u32,
+        has_error: bool,
+    ) -> VisitResult;
+
+    fn exit(&mut self, label: G::Label, position: u32, length: u32, has_error: bool);
+
+    fn enter_error(
+        &mut self,
+        expected_labels: &'static [G::Label],
+        expected_literals: &'static [&'static [G::Symbol]],
+        expected_message: Option<&'static str>,
+        position: u32,
+        length: u32,
+    ) -> VisitResult;
+
+    fn exit_error(
+        &mut self,
+        expected_labels: &'static [G::Label],
+        expected_literals: &'static [&'static [G::Symbol]],
+        expected_message: Option<&'static str>,
+        position: u32,
+        length: u32,
+    );
+
+    fn token(&mut self, kind: G::Label, position: u32, length: u32);
+}
+
+#[allow(unused)]
+pub struct GenTokenInfo<G: Grammar> {
+    pub kind: G::Label,
+    pub position: u32,
+    pub length: u32,
+}
+
+#[allow(unused)]
+pub struct GenLeaf<G: Grammar> {
+    pub grouping: GenGrouping<G::Label, G::Expected>,
+    pub position: u32,
+    pub length: u32,
+}
+
+#[allow(unused)]
+pub struct GenFlatNode<G: Grammar> {
+    pub grouping: GenGrouping<G::Label, G::Expected>,
+    pub position: u32,
+    pub length: u32,
+    pub first_child: Option<u32>,
+    pub next_sibling: Option<u32>,
+}
+
+#[allow(unused)]
+pub struct GenErrorInfo<G: Grammar> {
+    pub expected_labels: &'static [G::Label],
+    pub expected_literals: &'static [&'static [G::Symbol]],
+    pub expected_message: Option<&'static str>,
+    pub position: u32,
+    pub length: u32,
+    pub enclosing_label: Option<G::Label>,
+}
+
+struct ErrorIter<'a, G: Grammar> {
+    walk: Walk<'a, G>,
+    // The labels of the nodes currently being walked, innermost last
+    labels: Vec<G::Label>,
+}
+
+impl<'a, G: Grammar> Iterator for ErrorIter<'a, G> {
+    type Item = GenErrorInfo<G>;
+
+    fn next(&mut self) -> Option<GenErrorInfo<G>> {
+        while let Some((position, node, state)) = self.walk.next() {
+            let node: &'a Match<G> = node;
+
+            if state == EnterExit::Exit {
+                if let GenGrouping::Label(_) = node.grouping() {
+                    self.labels.pop();
+                }
+
+                continue;
+            }
+
+            if let GenGrouping::Error(error) = node.grouping() {
+                return Some(GenErrorInfo {
+                    position,
+                    expected_labels: error.labels(),
+                    expected_literals: error.literals(),
+                    expected_message: error.message(),
+                    length: node.distance(),
+                    enclosing_label: self.labels.last().copied(),
+                });
+            }
+
+            if node.error_distance().is_none() {
+                // Skipped nodes are never exited, so their labels must not be pushed
+                unsafe {
+                    self.walk.skip_node();
+                }
+            } else if let GenGrouping::Label(label) = node.grouping() {
+                self.labels.push(label);
+            }
+        }
+
+        None
+    }
+}
+
+impl<'a, G: Grammar> FusedIterator for ErrorIter<'a, G> {}
+
+/// Directs the control flow when visiting a node.
+///
+/// Can be used to skip over a sub-tree or exit entirely.
+#[allow(unused)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum VisitResult {
+    /// Descends into the sub-tree.
+    Continue,
+    /// Skips over the sub-tree, without calling `exit`.
+    Skip,
+    /// Immediately returns from without visiting anything else.
+    Exit,
+}
+
+pub struct GenCursor<'a, G: Grammar> {
+    node: &'a Match<G>,
+    position: u32,
+    // The number of label and error nodes enclosing this node
+    depth: u32,
+}
+
+impl<'a, G: Grammar> GenCursor<'a, G> {
+    #[allow(unused)]
+    pub fn grouping(&self) -> GenGrouping<G::Label, G::Expected> {
+        self.node.grouping()
+    }
+
+    #[allow(unused)]
+    pub fn label(&self) -> Option<G::Label> {
+        match self.grouping() {
+            GenGrouping::Label(label) => Some(label),
+            _ => None,
+        }
+    }
+
+    #[allow(unused)]
+    pub fn position(&self) -> u32 {
+        self.position
+    }
+
+    #[allow(unused)]
+    pub fn length(&self) -> u32 {
+        self.node.distance()
+    }
+
+    #[allow(unused)]
+    pub fn has_error(&self) -> bool {
+        self.node.error_distance().is_some()
+    }
+
+    #[allow(unused)]
+    pub fn scan_distance(&self) -> u32 {
+        self.node.scan_distance()
+    }
+
+    #[allow(unused)]
+    pub fn work(&self) -> u32 {
+        self.node.work()
+    }
+
+    #[allow(unused)]
+    pub fn error_distance(&self) -> Option<u32> {
+        self.node.error_distance()
+    }
+
+    #[allow(unused)]
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// Whether this node and another share at least one symbol of input,
+    /// which empty nodes never do even when positioned inside another node
+    #[allow(unused)]
+    pub fn overlaps(&self, other: &GenCursor<G>) -> bool {
+        self.length() > 0
+            && other.length() > 0
+            && self.position < other.position + other.length()
+            && other.position < self.position + self.length()
+    }
+
+    #[allow(unused)]
+    pub fn max_depth(&self) -> u32 {
+        let mut walk = self.node.walk_from(self.position);
+        walk.next();
+
+        let mut nesting = 0;
+        let mut max_nesting = 0;
+
+        for (_, node, state) in walk {
+            if node.grouping() == GenGrouping::None {
+                continue;
+            }
+
+            match state {
+                EnterExit::Enter => {
+                    nesting += 1;
+                    max_nesting = u32::max(max_nesting, nesting);
+                }
+                // The node the walk started from is exited last, while nesting is zero
+                EnterExit::Exit => nesting = u32::saturating_sub(nesting, 1),
+            }
+        }
+
+        self.depth + max_nesting
+    }
+
+    #[allow(unused)]
+    pub fn search<F: FnMut(GenCursor<'a, G>) -> bool>(
+        &self,
+        filter: F,
+    ) -> impl Iterator<Item = GenCursor<'a, G>> {
+        let mut walk = self.node.walk_from(self.position);
+        walk.next();
+
+        FindIter {
+            walk,
+            filter,
+            depth: self.depth,
+            nesting: 0,
+        }
+    }
+
+    pub fn visit<V: GenVisitor<G>>(&self, visitor: &mut V) {
+        let mut walk = self.node.walk_from(self.position);
+
+        while let Some((position, node, state)) = walk.next() {
+            let result = match node.grouping() {
+                GenGrouping::Label(label) => match state {
+                    EnterExit::Enter => visitor.enter(
+                        label,
+                        position,
+                        node.distance(),
+                        node.error_distance().is_some(),
+                    ),
+                    EnterExit::Exit => {
+                        visitor.exit(
+                            label,
+                            position,
+                            node.distance(),
+                            node.error_distance().is_some(),
+                        );
+                        continue;
+                    }
+                },
+                GenGrouping::Error(error) => match state {
+                    EnterExit::Enter => visitor.enter_error(
+                        error.labels(),
+                        error.literals(),
+                        error.message(),
+                        position,
+                        node.distance(),
+                    ),
+                    EnterExit::Exit => {
+                        visitor.exit_error(
+                            error.labels(),
+                            error.literals(),
+                            error.message(),
+                            position,
+                            node.distance(),
+                        );
+                        continue;
+                    }
+                },
+                // Tokens are leaves, so there is nothing to skip
+                GenGrouping::Token(kind) => {
+                    if state == EnterExit::Enter {
+                        visitor.token(kind, position, node.distance());
+                    }
+
+                    continue;
+                }
+                GenGrouping::None => continue,
+            };
+
+            match result {
+                VisitResult::Continue => {}
+                VisitResult::Skip => unsafe { walk.skip_node() },
+                VisitResult::Exit => return,
+            }
+        }
+    }
+}
+
+impl<'a, G: Grammar> Clone for GenCursor<'a, G> {
+    fn clone(&self) -> Self {
+        Self {
+            node: self.node,
+            position: self.position,
+            depth: self.depth,
+        }
+    }
+}
+
+struct FindIter<'a, G: Grammar, F: FnMut(GenCursor<'a, G>) -> bool> {
+    walk: Walk<'a, G>,
+    filter: F,
+    // The depth of the node the search started from
+    depth: u32,
+    // The number of label, error and token nodes entered but not yet exited by the walk
+    nesting: u32,
+}
+
+impl<'a, G: Grammar, F: FnMut(GenCursor<'a, G>) -> bool> Iterator for FindIter<'a, G, F> {
+    type Item = GenCursor<'a, G>;
+
+    fn next(&mut self) -> Option<GenCursor<'a, G>> {
+        while let Some((position, node, state)) = self.walk.next() {
+            if node.grouping() == GenGrouping::None {
+                continue;
+            }
+
+            if state == EnterExit::Exit {
+                // The node the search started from is exited last, while nesting is zero
+                self.nesting = u32::saturating_sub(self.nesting, 1);
+                continue;
+            }
+
+            let depth = self.depth + self.nesting + 1;
+            let cursor = GenCursor {
+                node,
+                position,
+                depth,
+            };
+
+            if (self.filter)(cursor) {
+                // Skipped nodes are never exited, so they must not count towards nesting
+                unsafe {
+                    self.walk.skip_node();
+                }
+
+                return Some(GenCursor {
+                    node,
+                    position,
+                    depth,
+                });
+            }
+
+            self.nesting += 1;
+        }
+
+        None
+    }
+}
+
+impl<'a, G: Grammar, F: FnMut(GenCursor<'a, G>) -> bool> FusedIterator for FindIter<'a, G, F> {}
+
+pub type State<I, G> = unsafe fn(ctx: &mut Context<I, G>);
+
+/// Renders expected labels by name, literals as quoted strings and any message
+/// written for the error as is, sorted and without duplicates
+#[allow(unused)]
+pub fn describe_expected<L: Display, S: SymbolType>(
+    labels: &[L],
+    literals: &[&[S]],
+    message: Option<&str>,
+) -> Vec<String> {
+    let mut result = Vec::with_capacity(labels.len() + literals.len() + 1);
+
+    for label in labels {
+        result.push(label.to_string());
+    }
+
+    for literal in literals {
+        result.push(S::describe(literal));
+    }
+
+    result.extend(message.map(String::from));
+
+    result.sort();
+    result.dedup();
+    result
+}
+
+include!("generate.rs");
+
+#[allow(unused)]
+pub(super) use generate;
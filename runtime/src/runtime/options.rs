@@ -0,0 +1,104 @@
+/// Options that control how a parse is run.
+///
+/// Use [`ParseOptions::new`] to create a set of options with the default behavior, and then
+/// customize them with the builder methods.
+#[allow(unused)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ParseOptions {
+    pub(super) max_depth: Option<usize>,
+    pub(super) max_error_marks: Option<u32>,
+    pub(super) match_failures: bool,
+    pub(super) lenient: bool,
+    pub(super) start: u32,
+}
+
+#[allow(unused)]
+impl ParseOptions {
+    /// Creates a set of options with the default behavior.
+    pub fn new() -> Self {
+        Self {
+            max_depth: None,
+            max_error_marks: None,
+            match_failures: false,
+            lenient: false,
+            start: 0,
+        }
+    }
+
+    /// Limits the depth the parser's internal stack may reach.
+    ///
+    /// The stack grows as the parser descends into nested rules, so deeply nested or adversarial
+    /// input can otherwise exhaust memory. If the limit is exceeded the parse is abandoned.
+    ///
+    /// Each unit of depth is one state of the parser entered from within another, such as an
+    /// alternative of a choice or the second half of a sequence. The start of the grammar is at a
+    /// depth of zero, so a limit of zero only allows grammars that never enter a nested state.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Limits how many times the parser may mark an error.
+    ///
+    /// Grammars that recover from errors can mark thousands of them on badly broken input, each
+    /// of which costs memory and time. Once the limit is reached, anything that would be marked as
+    /// an error fails to match instead, so recovery stops and the parse usually ends early or
+    /// fails. This limits the marks made while parsing rather than the errors in the parse tree:
+    /// errors marked in alternatives that are later backtracked out of still count, while a cached
+    /// result reused after the limit is reached keeps its errors without counting them again. Which
+    /// errors the tree holds once the limit is reached therefore depends on how the grammar
+    /// backtracks and where it caches, not only on the input. Combine this with
+    /// [`ParseOptions::lenient`] to keep what was parsed before recovery stopped.
+    pub fn max_error_marks(mut self, marks: u32) -> Self {
+        self.max_error_marks = Some(marks);
+        self
+    }
+
+    /// Reports input that fails to match as a match rather than as an unmatched parse.
+    ///
+    /// The match consists of the furthest reaching prefix of the input that the grammar matched
+    /// before backtracking, followed by a single error spanning the rest of the input. The prefix
+    /// keeps the labels, tokens and errors found within it, and the trailing error expects
+    /// whatever the start of the grammar expects. This lets failed parses be inspected with the
+    /// same API as any other error, which is useful for tools that report diagnostics. Keeping
+    /// track of the prefix costs a little time even when the input matches.
+    pub fn match_failures(mut self) -> Self {
+        self.match_failures = true;
+        self
+    }
+
+    /// Reports input that fails to match as the longest prefix of it that did match, followed by
+    /// a single error spanning the rest of the input.
+    ///
+    /// This is the same as [`ParseOptions::match_failures`], under the name used by
+    /// `parse_lenient`.
+    pub fn lenient(mut self) -> Self {
+        self.lenient = true;
+        self
+    }
+
+    /// Starts parsing at a byte offset into the input rather than at its beginning.
+    ///
+    /// Positions in the resulting parse tree are still measured from the beginning of the input,
+    /// so the root of the tree is at the offset. Offsets past the end of the input are treated as
+    /// the end of the input.
+    pub fn start_at(mut self, offset: u32) -> Self {
+        self.start = offset;
+        self
+    }
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The reason a parse was abandoned before it could finish.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Abort {
+    /// The depth limit configured with [`ParseOptions::max_depth`] was exceeded.
+    DepthExceeded,
+    /// The input was too long for positions within it to be represented.
+    InputTooLong,
+}
@@ -196,6 +196,17 @@ impl<G: Grammar> Match<G> {
         }
     }
 
+    pub fn token(distance: u32, scan_distance: u32, work: u32, kind: G::Label) -> Self {
+        Self {
+            scan_distance,
+            work,
+            distance,
+            grouping: Grouping::Token(kind),
+            error_distance: None,
+            children: ArrayVec::new(),
+        }
+    }
+
     pub fn combine(first: Self, second: Self) -> Self {
         let scan_distance = u32::max(first.scan_distance, first.distance + second.scan_distance);
 
@@ -315,11 +326,7 @@ impl<G: Grammar> Match<G> {
         self.error_distance
     }
 
-    pub fn walk(&self) -> Walk<G> {
-        self.walk_from(0)
-    }
-
-    pub fn walk_from(&self, position: u32) -> Walk<G> {
+    pub fn walk_from(&self, position: u32) -> Walk<'_, G> {
         let mut parents = SmallVec::new();
         parents.push((position, self, 0));
 
@@ -335,13 +342,15 @@ pub enum Grouping<L: LabelType, E: ExpectedType<L>> {
     None,
     Label(L),
     Error(E),
+    // Only ever found on leaf nodes
+    Token(L),
 }
 
 impl<L: LabelType, E: ExpectedType<L>> Grouping<L, E> {
     fn is_none(&self) -> bool {
         match self {
             Grouping::None => true,
-            Grouping::Label(_) | Grouping::Error(_) => false,
+            Grouping::Label(_) | Grouping::Error(_) | Grouping::Token(_) => false,
         }
     }
 }
@@ -28,4 +28,8 @@ impl<T> Stack<T> {
     pub fn pop(&mut self) -> Option<T> {
         self.values.pop()
     }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
 }
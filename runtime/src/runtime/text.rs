@@ -0,0 +1,61 @@
+use std::error::Error;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::ops::Range;
+use std::str::{FromStr, Utf8Error};
+
+/// Reads the text of nodes in a parse tree, such as to convert a number token into an integer.
+///
+/// The parse tree only records where each node is, so these methods take the input that was
+/// parsed and operate on the node's byte span of it, from its position up to its position plus its
+/// length. They panic if the span lies outside the input, which can happen when passed different
+/// input to that which was parsed. This only makes sense for parsers that read bytes.
+///
+/// ```ignore
+/// use parser::CursorExt;
+///
+/// let value = cursor.parse::<i64>(input)?;
+/// ```
+#[allow(unused)]
+pub trait CursorExt {
+    /// The range of input covered by the node.
+    fn span(&self) -> Range<usize>;
+
+    /// The bytes of input covered by the node.
+    fn text<'i>(&self, input: &'i [u8]) -> &'i [u8] {
+        &input[self.span()]
+    }
+
+    /// The input covered by the node as a string, failing if it is not valid UTF-8.
+    fn as_str<'i>(&self, input: &'i [u8]) -> Result<&'i str, Utf8Error> {
+        std::str::from_utf8(self.text(input))
+    }
+
+    /// Parses the input covered by the node into any type implementing [`FromStr`].
+    fn parse<T: FromStr>(&self, input: &[u8]) -> Result<T, TextError<T::Err>> {
+        self.as_str(input)
+            .map_err(TextError::Utf8)?
+            .parse()
+            .map_err(TextError::Parse)
+    }
+}
+
+/// The reason [`CursorExt::parse`] failed.
+#[allow(unused)]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum TextError<E> {
+    /// The node's text was not valid UTF-8.
+    Utf8(Utf8Error),
+    /// The node's text was valid UTF-8, but could not be parsed.
+    Parse(E),
+}
+
+impl<E: Display> Display for TextError<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TextError::Utf8(err) => write!(f, "node text is not valid UTF-8: {}", err),
+            TextError::Parse(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl<E: Debug + Display> Error for TextError<E> {}
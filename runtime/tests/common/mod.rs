@@ -0,0 +1,125 @@
+//! Grammars for the runtime's tests are written out by hand in the way generated parsers are. Each
+//! test declares the states of its grammars, and shares the rest from here.
+
+// Each test only uses some of what is shared
+#![allow(dead_code, unused_macros, unused_imports)]
+
+use std::fmt::{self, Display, Formatter};
+
+use peg_pack_runtime::{Context, ExpectedType, Grammar, Input, LabelType};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Label {
+    A,
+    B,
+    C,
+    E,
+    Letter,
+}
+
+impl Display for Label {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Label::A => write!(f, "a"),
+            Label::B => write!(f, "b"),
+            Label::C => write!(f, "c"),
+            Label::E => write!(f, "e"),
+            Label::Letter => write!(f, "letter"),
+        }
+    }
+}
+
+impl LabelType for Label {}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Expected {
+    /// The literal `a`
+    A,
+    /// The literal `ab`
+    Ab,
+    /// A node labelled [`Label::Letter`]
+    Letter,
+}
+
+impl ExpectedType<Label> for Expected {
+    type Symbol = u8;
+
+    fn literals(&self) -> &'static [&'static [u8]] {
+        match self {
+            Expected::A => &[b"a"],
+            Expected::Ab => &[b"ab"],
+            Expected::Letter => &[],
+        }
+    }
+
+    fn labels(&self) -> &'static [Label] {
+        match self {
+            Expected::A | Expected::Ab => &[],
+            Expected::Letter => &[Label::Letter],
+        }
+    }
+}
+
+/// Declares a grammar over bytes from its start state and what it expects at the start of the
+/// input, along with the number of cache slots it uses when it uses any
+macro_rules! grammar {
+    (
+        $(#[$meta:meta])*
+        $name:ident { start: $start:ident, expected: $expected:expr $(,)? }
+    ) => {
+        grammar!(@declare $(#[$meta])* $name, $start, $expected, peg_pack_runtime::NoCache<Self>, 0);
+    };
+    (
+        $(#[$meta:meta])*
+        $name:ident { start: $start:ident, expected: $expected:expr, cache_slots: $slots:expr $(,)? }
+    ) => {
+        grammar!(@declare $(#[$meta])* $name, $start, $expected, peg_pack_runtime::Cache<Self>, $slots);
+    };
+    (@declare $(#[$meta:meta])* $name:ident, $start:ident, $expected:expr, $cache:ty, $slots:expr) => {
+        $(#[$meta])*
+        struct $name;
+
+        impl peg_pack_runtime::Grammar for $name {
+            type Symbol = u8;
+            type Label = $crate::common::Label;
+            type Expected = $crate::common::Expected;
+            type Cache = $cache;
+
+            fn start_state<I: peg_pack_runtime::Input + ?Sized>(
+                &self,
+            ) -> peg_pack_runtime::State<I, Self> {
+                $start
+            }
+
+            fn cache_slots(&self) -> usize {
+                $slots
+            }
+
+            fn start_expected(&self) -> $crate::common::Expected {
+                $expected
+            }
+        }
+    };
+}
+
+pub(crate) use grammar;
+
+/// Matches a single `a`
+pub unsafe fn a<I: Input + ?Sized, G: Grammar<Symbol = u8>>(ctx: &mut Context<I, G>) {
+    ctx.state_series(|input, position| input.matches_at(position, b"a"));
+}
+
+/// Matches a single `b`
+pub unsafe fn b<I: Input + ?Sized, G: Grammar<Symbol = u8>>(ctx: &mut Context<I, G>) {
+    ctx.state_series(|input, position| input.matches_at(position, b"b"));
+}
+
+/// Matches the empty string
+pub unsafe fn empty<I: Input + ?Sized, G: Grammar<Symbol = u8>>(ctx: &mut Context<I, G>) {
+    ctx.state_series(|_, _| (true, 0));
+}
+
+/// Ends any sequence
+pub unsafe fn seq_end<I: Input + ?Sized, G: Grammar<Symbol = u8>>(ctx: &mut Context<I, G>) {
+    ctx.state_seq_end();
+}
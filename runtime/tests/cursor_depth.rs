@@ -0,0 +1,73 @@
+use peg_pack_runtime::{Context, GenGrouping, GenParseMatch, Input, ParseOptions, ParseResult};
+
+use common::{grammar, seq_end, Expected, Label};
+
+mod common;
+
+grammar! {
+    /// A grammar matching `ab` as `A[Letter(a)] Letter(b)`, where `Letter` is a token
+    Tokens { start: start, expected: Expected::A }
+}
+
+unsafe fn start<I: Input + ?Sized>(ctx: &mut Context<I, Tokens>) {
+    ctx.state_seq_start(a_node, start_middle);
+}
+
+unsafe fn start_middle<I: Input + ?Sized>(ctx: &mut Context<I, Tokens>) {
+    ctx.state_seq_middle(b_token, seq_end);
+}
+
+unsafe fn a_node<I: Input + ?Sized>(ctx: &mut Context<I, Tokens>) {
+    ctx.state_label_start(a_token, a_end);
+}
+
+unsafe fn a_end<I: Input + ?Sized>(ctx: &mut Context<I, Tokens>) {
+    ctx.state_label_end(Label::A);
+}
+
+unsafe fn a_token<I: Input + ?Sized>(ctx: &mut Context<I, Tokens>) {
+    ctx.state_token(
+        |input, position| input.matches_at(position, b"a"),
+        Label::Letter,
+    );
+}
+
+unsafe fn b_token<I: Input + ?Sized>(ctx: &mut Context<I, Tokens>) {
+    ctx.state_token(
+        |input, position| input.matches_at(position, b"b"),
+        Label::Letter,
+    );
+}
+
+/// Tokens have a depth like any other node, and count towards the maximum depth of the nodes
+/// enclosing them
+#[test]
+fn tokens_under_labels() {
+    let result = match Context::run(b"ab".as_slice(), &Tokens, &ParseOptions::new()).unwrap() {
+        ParseResult::Matched(result) => GenParseMatch::new(result),
+        ParseResult::Unmatched { .. } => panic!("the input should match"),
+    };
+
+    let root = result.root();
+
+    let top = root.search(|_| true).collect::<Vec<_>>();
+    let [a, b] = top.as_slice() else {
+        panic!("expected two top level nodes");
+    };
+
+    let inner = a.search(|_| true).collect::<Vec<_>>();
+    let [token] = inner.as_slice() else {
+        panic!("expected one node inside A");
+    };
+
+    assert_eq!(
+        [a, b, token].map(|node| (node.grouping(), node.depth(), node.max_depth())),
+        [
+            (GenGrouping::Label(Label::A), 1, 2),
+            (GenGrouping::Token(Label::Letter), 1, 1),
+            (GenGrouping::Token(Label::Letter), 2, 2),
+        ]
+    );
+
+    assert_eq!((root.depth(), root.max_depth()), (0, 2));
+}
@@ -0,0 +1,123 @@
+use peg_pack_runtime::{Context, GenParseMatch, Input, ParseOptions, ParseResult};
+
+use common::{a, b, empty, grammar, seq_end, Expected, Label};
+
+mod common;
+
+grammar! {
+    /// A grammar matching `abc` as `A[a] B[C[b] E[] c]`
+    Nested { start: start, expected: Expected::A }
+}
+
+unsafe fn start<I: Input + ?Sized>(ctx: &mut Context<I, Nested>) {
+    ctx.state_seq_start(a_node, start_middle);
+}
+
+unsafe fn start_middle<I: Input + ?Sized>(ctx: &mut Context<I, Nested>) {
+    ctx.state_seq_middle(b_node, seq_end);
+}
+
+unsafe fn a_node<I: Input + ?Sized>(ctx: &mut Context<I, Nested>) {
+    ctx.state_label_start(a, a_end);
+}
+
+unsafe fn a_end<I: Input + ?Sized>(ctx: &mut Context<I, Nested>) {
+    ctx.state_label_end(Label::A);
+}
+
+unsafe fn b_node<I: Input + ?Sized>(ctx: &mut Context<I, Nested>) {
+    ctx.state_label_start(b_inner, b_end);
+}
+
+unsafe fn b_end<I: Input + ?Sized>(ctx: &mut Context<I, Nested>) {
+    ctx.state_label_end(Label::B);
+}
+
+unsafe fn b_inner<I: Input + ?Sized>(ctx: &mut Context<I, Nested>) {
+    ctx.state_seq_start(c_node, b_middle);
+}
+
+unsafe fn b_middle<I: Input + ?Sized>(ctx: &mut Context<I, Nested>) {
+    ctx.state_seq_middle(b_rest, seq_end);
+}
+
+unsafe fn b_rest<I: Input + ?Sized>(ctx: &mut Context<I, Nested>) {
+    ctx.state_seq_start(e_node, b_rest_middle);
+}
+
+unsafe fn b_rest_middle<I: Input + ?Sized>(ctx: &mut Context<I, Nested>) {
+    ctx.state_seq_middle(c_series, seq_end);
+}
+
+unsafe fn c_node<I: Input + ?Sized>(ctx: &mut Context<I, Nested>) {
+    ctx.state_label_start(b, c_end);
+}
+
+unsafe fn c_end<I: Input + ?Sized>(ctx: &mut Context<I, Nested>) {
+    ctx.state_label_end(Label::C);
+}
+
+unsafe fn c_series<I: Input + ?Sized>(ctx: &mut Context<I, Nested>) {
+    ctx.state_series(|input, position| input.matches_at(position, b"c"));
+}
+
+unsafe fn e_node<I: Input + ?Sized>(ctx: &mut Context<I, Nested>) {
+    ctx.state_label_start(empty, e_end);
+}
+
+unsafe fn e_end<I: Input + ?Sized>(ctx: &mut Context<I, Nested>) {
+    ctx.state_label_end(Label::E);
+}
+
+fn parse() -> GenParseMatch<Nested> {
+    match Context::run(b"abc".as_slice(), &Nested, &ParseOptions::new()).unwrap() {
+        ParseResult::Matched(result) => GenParseMatch::new(result),
+        ParseResult::Unmatched { .. } => panic!("the input should match"),
+    }
+}
+
+/// Nodes overlap when they share a symbol, so nested nodes overlap but adjacent nodes don't, and
+/// an empty node overlaps nothing, not even the node it is inside of or itself
+#[test]
+fn overlaps() {
+    let result = parse();
+    let root = result.root();
+
+    let top = root.search(|_| true).collect::<Vec<_>>();
+    let [a, b] = top.as_slice() else {
+        panic!("expected two top level nodes");
+    };
+
+    let inner = b.search(|_| true).collect::<Vec<_>>();
+    let [c, e] = inner.as_slice() else {
+        panic!("expected two nodes inside B");
+    };
+
+    assert_eq!(
+        [a, b, c, e].map(|node| (node.label(), node.position(), node.length())),
+        [
+            (Some(Label::A), 0, 1),
+            (Some(Label::B), 1, 2),
+            (Some(Label::C), 1, 1),
+            (Some(Label::E), 2, 0),
+        ]
+    );
+
+    // Nested
+    assert!(root.overlaps(a));
+    assert!(b.overlaps(c));
+    assert!(c.overlaps(b));
+    assert!(b.overlaps(b));
+
+    // Adjacent
+    assert!(!a.overlaps(b));
+    assert!(!b.overlaps(a));
+    assert!(!a.overlaps(c));
+    assert!(!c.overlaps(e));
+
+    // Empty
+    assert!(!b.overlaps(e));
+    assert!(!e.overlaps(b));
+    assert!(!root.overlaps(e));
+    assert!(!e.overlaps(e));
+}
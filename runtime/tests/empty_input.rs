@@ -0,0 +1,76 @@
+use peg_pack_runtime::{
+    Context, GenParseMatch, Grammar, Input, LineColumn, LineMap, LineOptions, ParseOptions,
+    ParseResult, SkipBom,
+};
+
+use common::{a, empty, grammar, Expected, Label};
+
+mod common;
+
+/// Empty input is a single empty line, which every position resolves to the start of
+#[test]
+fn line_map_of_empty_input() {
+    for map in [
+        LineMap::new(b""),
+        LineMap::with_options(b"", LineOptions::new().newlines_only().tab_width(4)),
+    ] {
+        assert_eq!(map.line_count(), 1);
+        assert_eq!(map.line_start(1), Some(0));
+        assert_eq!(map.line_start(2), None);
+        assert_eq!(map.line_column(0), LineColumn { line: 1, column: 1 });
+        assert_eq!(map.line_column(10), LineColumn { line: 1, column: 1 });
+        assert_eq!(map.visual_column(0, 8), 1);
+    }
+}
+
+/// Empty input has no symbols to read, with or without a byte order mark to skip
+#[test]
+fn empty_input_has_no_symbols() {
+    let input: &[u8] = b"";
+    assert_eq!(Input::len(input), 0);
+    assert_eq!(Input::get(input, 0), None);
+
+    let input = SkipBom::new(b"\xEF\xBB\xBF".as_slice());
+    assert_eq!(input.offset(), 3);
+    assert_eq!(Input::len(&input), 0);
+    assert_eq!(Input::get(&input, 0), None);
+}
+
+grammar! {
+    /// A grammar matching a single `a`
+    Letter { start: a, expected: Expected::A }
+}
+
+grammar! {
+    /// A grammar matching an optional `a`
+    OptionalLetter { start: optional, expected: Expected::A }
+}
+
+unsafe fn optional<I: Input + ?Sized>(ctx: &mut Context<I, OptionalLetter>) {
+    ctx.state_first_choice_start(a, optional_middle);
+}
+
+unsafe fn optional_middle<I: Input + ?Sized>(ctx: &mut Context<I, OptionalLetter>) {
+    ctx.state_first_choice_middle(empty);
+}
+
+fn parse<G: Grammar<Symbol = u8, Label = Label>>(grammar: &G, input: &[u8]) -> Option<String> {
+    match Context::run(input, grammar, &ParseOptions::new()).unwrap() {
+        ParseResult::Matched(result) => Some(GenParseMatch::new(result).to_sexp()),
+        ParseResult::Unmatched { .. } => None,
+    }
+}
+
+/// A grammar requiring a symbol fails cleanly on empty input
+#[test]
+fn empty_input_is_unmatched_when_required() {
+    assert_eq!(parse(&Letter, b""), None);
+    assert_eq!(parse(&Letter, b"a").as_deref(), Some("(root 0 1)"));
+}
+
+/// A grammar accepting empty input matches it with an empty root
+#[test]
+fn empty_input_is_matched_when_accepted() {
+    assert_eq!(parse(&OptionalLetter, b"").as_deref(), Some("(root 0 0)"));
+    assert_eq!(parse(&OptionalLetter, b"a").as_deref(), Some("(root 0 1)"));
+}
@@ -0,0 +1,63 @@
+use peg_pack_runtime::{Abort, Context, Input, ParseOptions, ParseResult};
+
+use common::{a, grammar, Expected};
+
+mod common;
+
+grammar! {
+    /// A grammar matching a single `a`
+    Letter { start: a, expected: Expected::A }
+}
+
+/// Input of `a`s with a given length, which is never actually stored
+struct Repeated(usize);
+
+unsafe impl Input for Repeated {
+    fn get(&self, position: u32) -> Option<u8> {
+        ((position as usize) < self.0).then_some(b'a')
+    }
+
+    fn len(&self) -> usize {
+        self.0
+    }
+}
+
+/// The length matched, or the reason the parse was abandoned, from each of `Context::run`,
+/// `Context::run_with_progress` and `Context::run_profiled` in turn
+fn parse(input: &Repeated) -> [Result<Option<u32>, Abort>; 3] {
+    let options = ParseOptions::new();
+
+    let summarize = |result: Result<ParseResult<Letter>, Abort>| {
+        result.map(|result| match result {
+            ParseResult::Matched(result) => Some(result.distance()),
+            ParseResult::Unmatched { .. } => None,
+        })
+    };
+
+    [
+        summarize(Context::run(input, &Letter, &options)),
+        summarize(Context::run_with_progress(
+            input,
+            &Letter,
+            &options,
+            &mut |_| {},
+        )),
+        summarize(Context::run_profiled(input, &Letter, &options).0),
+    ]
+}
+
+/// Positions must be able to reach one past the end of the input, so the longest input accepted is
+/// one symbol shorter than `u32::MAX`
+#[test]
+fn longest_input_is_parsed() {
+    let length = u32::MAX as usize - 1;
+    assert_eq!(parse(&Repeated(length)), [Ok(Some(1)); 3]);
+}
+
+/// Input of `u32::MAX` symbols or more is rejected before parsing begins
+#[test]
+fn longer_input_is_rejected() {
+    for length in [u32::MAX as usize, u32::MAX as usize + 1, usize::MAX] {
+        assert_eq!(parse(&Repeated(length)), [Err(Abort::InputTooLong); 3]);
+    }
+}
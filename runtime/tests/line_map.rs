@@ -0,0 +1,74 @@
+use peg_pack_runtime::{LineColumn, LineMap, LineOptions};
+
+fn at(line: u32, column: u32) -> LineColumn {
+    LineColumn { line, column }
+}
+
+#[test]
+fn line_terminators() {
+    let lines = LineMap::new(b"a\nb\r\nc\rd");
+
+    assert_eq!(lines.line_count(), 4);
+    assert_eq!(lines.line_column(0), at(1, 1));
+    assert_eq!(lines.line_column(2), at(2, 1));
+    // The `\r` of a `\r\n` stays on the line it ends
+    assert_eq!(lines.line_column(3), at(2, 2));
+    assert_eq!(lines.line_column(5), at(3, 1));
+    assert_eq!(lines.line_column(7), at(4, 1));
+}
+
+#[test]
+fn carriage_returns_can_be_columns() {
+    let lines = LineMap::with_options(b"a\r\nb\rc", LineOptions::new().newlines_only());
+
+    assert_eq!(lines.line_count(), 2);
+    assert_eq!(lines.line_column(3), at(2, 1));
+    assert_eq!(lines.line_column(5), at(2, 3));
+}
+
+#[test]
+fn tabs_count_as_a_fixed_width() {
+    let lines = LineMap::with_options(b"\ta\tb", LineOptions::new().tab_width(4));
+
+    assert_eq!(lines.line_column(1), at(1, 5));
+    assert_eq!(lines.line_column(3), at(1, 10));
+
+    // Multi-byte characters are a single column
+    let lines = LineMap::new("é\tb".as_bytes());
+    assert_eq!(lines.line_column(3), at(1, 3));
+}
+
+#[test]
+fn visual_columns_advance_to_tab_stops() {
+    let lines = LineMap::with_options(b"\ta\tb\nab\tc", LineOptions::new().tab_width(2));
+
+    // The options' tab width isn't used
+    assert_eq!(lines.visual_column(1, 4), 5);
+    assert_eq!(lines.visual_column(3, 4), 9);
+    assert_eq!(lines.visual_column(8, 4), 5);
+    assert_eq!(lines.visual_column(8, 8), 9);
+
+    // A tab width of zero is treated as one
+    assert_eq!(lines.visual_column(3, 0), 4);
+}
+
+#[test]
+fn positions_past_the_end() {
+    let lines = LineMap::new(b"ab\ncd");
+
+    assert_eq!(lines.line_column(5), at(2, 3));
+    assert_eq!(lines.line_column(100), at(2, 3));
+    assert_eq!(lines.visual_column(100, 4), 3);
+}
+
+#[test]
+fn empty_input() {
+    let lines = LineMap::new(b"");
+
+    assert_eq!(lines.line_count(), 1);
+    assert_eq!(lines.line_start(1), Some(0));
+    assert_eq!(lines.line_start(2), None);
+    assert_eq!(lines.line_column(0), at(1, 1));
+    assert_eq!(lines.line_column(10), at(1, 1));
+    assert_eq!(lines.visual_column(0, 4), 1);
+}
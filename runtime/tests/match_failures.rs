@@ -0,0 +1,68 @@
+use peg_pack_runtime::{Context, GenParseMatch, Input, ParseOptions, ParseResult};
+
+use common::{a, b, grammar, seq_end, Expected, Label};
+
+mod common;
+
+grammar! {
+    /// A grammar matching a labelled `a` followed by `b`
+    LetterThenB { start: start, expected: Expected::Letter }
+}
+
+unsafe fn start<I: Input + ?Sized>(ctx: &mut Context<I, LetterThenB>) {
+    ctx.state_seq_start(letter, seq_middle);
+}
+
+unsafe fn seq_middle<I: Input + ?Sized>(ctx: &mut Context<I, LetterThenB>) {
+    ctx.state_seq_middle(b, seq_end);
+}
+
+unsafe fn letter<I: Input + ?Sized>(ctx: &mut Context<I, LetterThenB>) {
+    ctx.state_label_start(a, letter_end);
+}
+
+unsafe fn letter_end<I: Input + ?Sized>(ctx: &mut Context<I, LetterThenB>) {
+    ctx.state_label_end(Label::Letter);
+}
+
+fn parse(input: &[u8], options: &ParseOptions) -> Option<String> {
+    match Context::run(input, &LetterThenB, options).unwrap() {
+        ParseResult::Matched(result) => Some(GenParseMatch::new(result).to_sexp()),
+        ParseResult::Unmatched { .. } => None,
+    }
+}
+
+/// A failed parse keeps the labels matched before the point of failure, and marks the rest of the
+/// input as an error
+#[test]
+fn failures_keep_the_furthest_partial_match() {
+    let expected = "(root 0 3\n  (label letter 0 1)\n  (error (expected letter) 1 2))";
+
+    assert_eq!(parse(b"acc", &ParseOptions::new()), None);
+    assert_eq!(
+        parse(b"acc", &ParseOptions::new().match_failures()).as_deref(),
+        Some(expected)
+    );
+    assert_eq!(
+        parse(b"acc", &ParseOptions::new().lenient()).as_deref(),
+        Some(expected)
+    );
+}
+
+/// Input failing before anything matched is a single error spanning all of it
+#[test]
+fn failures_without_a_partial_match() {
+    assert_eq!(
+        parse(b"cc", &ParseOptions::new().match_failures()).as_deref(),
+        Some("(root 0 2\n  (error (expected letter) 0 2))")
+    );
+}
+
+/// Input that matches is unaffected by reporting failures
+#[test]
+fn matches_are_unchanged() {
+    assert_eq!(
+        parse(b"ab", &ParseOptions::new().match_failures()),
+        parse(b"ab", &ParseOptions::new())
+    );
+}
@@ -0,0 +1,93 @@
+use peg_pack_runtime::{Abort, Context, Grammar, Input, ParseOptions, ParseResult};
+
+use common::{a, empty, grammar, seq_end, Expected};
+
+mod common;
+
+grammar! {
+    /// A grammar matching any number of `a`s by recursing once per `a`
+    Letters { start: start, expected: Expected::A }
+}
+
+grammar! {
+    /// A grammar matching a single `a` with its start state alone
+    Letter { start: a, expected: Expected::A }
+}
+
+unsafe fn start<I: Input + ?Sized>(ctx: &mut Context<I, Letters>) {
+    ctx.state_first_choice_start(nested, choice_middle);
+}
+
+unsafe fn choice_middle<I: Input + ?Sized>(ctx: &mut Context<I, Letters>) {
+    ctx.state_first_choice_middle(empty);
+}
+
+unsafe fn nested<I: Input + ?Sized>(ctx: &mut Context<I, Letters>) {
+    ctx.state_seq_start(a, seq_middle);
+}
+
+unsafe fn seq_middle<I: Input + ?Sized>(ctx: &mut Context<I, Letters>) {
+    ctx.state_seq_middle(start, seq_end);
+}
+
+/// The length matched, or the reason the parse was abandoned
+fn parse(input: &[u8], options: &ParseOptions) -> Result<Option<u32>, Abort> {
+    summarize(Context::run(input, &Letters, options))
+}
+
+fn summarize<G: Grammar>(result: Result<ParseResult<G>, Abort>) -> Result<Option<u32>, Abort> {
+    result.map(|result| match result {
+        ParseResult::Matched(result) => Some(result.distance()),
+        ParseResult::Unmatched { .. } => None,
+    })
+}
+
+/// Parses nesting deeper than the maximum depth are abandoned, which generated parsers report as
+/// `Parse::DepthExceeded`
+#[test]
+fn deep_parses_are_abandoned() {
+    let input = [b'a'; 100];
+
+    assert_eq!(parse(&input, &ParseOptions::new()), Ok(Some(100)));
+    assert_eq!(
+        parse(&input, &ParseOptions::new().max_depth(50)),
+        Err(Abort::DepthExceeded)
+    );
+}
+
+/// Parses nesting within the maximum depth are unaffected by it
+#[test]
+fn shallow_parses_are_unaffected() {
+    let options = ParseOptions::new().max_depth(50);
+
+    assert_eq!(parse(b"", &options), Ok(Some(0)));
+    assert_eq!(parse(b"aaaa", &options), Ok(Some(4)));
+    assert_eq!(
+        parse(&[b'a'; 100], &ParseOptions::new().max_depth(1000)),
+        Ok(Some(100))
+    );
+}
+
+/// Depth is counted from the start state, so trying an `a` on empty input reaches a depth of two,
+/// and each `a` matched nests two states deeper
+#[test]
+fn depth_is_counted_from_the_start_state() {
+    for (input, depth) in [(b"".as_slice(), 2), (b"a", 4), (b"aaaa", 10)] {
+        let length = Some(input.len() as u32);
+
+        assert_eq!(
+            parse(input, &ParseOptions::new().max_depth(depth)),
+            Ok(length)
+        );
+        assert_eq!(
+            parse(input, &ParseOptions::new().max_depth(depth - 1)),
+            Err(Abort::DepthExceeded)
+        );
+    }
+
+    let options = ParseOptions::new().max_depth(0);
+    assert_eq!(
+        summarize(Context::run(b"a".as_slice(), &Letter, &options)),
+        Ok(Some(1))
+    );
+}
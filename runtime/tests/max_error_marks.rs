@@ -0,0 +1,158 @@
+use peg_pack_runtime::{Context, GenParseMatch, Grammar, Input, ParseOptions, ParseResult};
+
+use common::{a, b, grammar, seq_end, Expected, Label};
+
+mod common;
+
+grammar! {
+    /// A grammar matching any number of `a`s, recovering from each `b` among them by marking it as
+    /// an error
+    Recovering { start: items, expected: Expected::A }
+}
+
+grammar! {
+    /// A grammar matching a run of `b`s marked as an error, first followed by an `x` and then on its
+    /// own, with the run cached so the second attempt reuses the first
+    Cached { start: attempts, expected: Expected::A, cache_slots: 1 }
+}
+
+unsafe fn items<I: Input + ?Sized>(ctx: &mut Context<I, Recovering>) {
+    ctx.state_repeat_start(item, items_middle);
+}
+
+unsafe fn items_middle<I: Input + ?Sized>(ctx: &mut Context<I, Recovering>) {
+    ctx.state_repeat_middle(item);
+}
+
+unsafe fn item<I: Input + ?Sized>(ctx: &mut Context<I, Recovering>) {
+    ctx.state_first_choice_start(a, item_middle);
+}
+
+unsafe fn item_middle<I: Input + ?Sized>(ctx: &mut Context<I, Recovering>) {
+    ctx.state_first_choice_middle(recover);
+}
+
+unsafe fn recover<I: Input + ?Sized>(ctx: &mut Context<I, Recovering>) {
+    ctx.state_error_start(b, recover_end);
+}
+
+unsafe fn recover_end<I: Input + ?Sized>(ctx: &mut Context<I, Recovering>) {
+    ctx.state_error_end(Expected::A);
+}
+
+unsafe fn attempts<I: Input + ?Sized>(ctx: &mut Context<I, Cached>) {
+    ctx.state_first_choice_start(followed, attempts_middle);
+}
+
+unsafe fn attempts_middle<I: Input + ?Sized>(ctx: &mut Context<I, Cached>) {
+    ctx.state_first_choice_middle(run);
+}
+
+unsafe fn followed<I: Input + ?Sized>(ctx: &mut Context<I, Cached>) {
+    ctx.state_seq_start(run, followed_middle);
+}
+
+unsafe fn followed_middle<I: Input + ?Sized>(ctx: &mut Context<I, Cached>) {
+    ctx.state_seq_middle(x, seq_end);
+}
+
+unsafe fn x<I: Input + ?Sized>(ctx: &mut Context<I, Cached>) {
+    ctx.state_series(|input, position| input.matches_at(position, b"x"));
+}
+
+unsafe fn run<I: Input + ?Sized>(ctx: &mut Context<I, Cached>) {
+    ctx.state_cache_start(0, run_error, run_end);
+}
+
+unsafe fn run_end<I: Input + ?Sized>(ctx: &mut Context<I, Cached>) {
+    ctx.state_cache_end(0);
+}
+
+unsafe fn run_error<I: Input + ?Sized>(ctx: &mut Context<I, Cached>) {
+    ctx.state_error_start(bs, run_error_end);
+}
+
+unsafe fn run_error_end<I: Input + ?Sized>(ctx: &mut Context<I, Cached>) {
+    ctx.state_error_end(Expected::A);
+}
+
+unsafe fn bs<I: Input + ?Sized>(ctx: &mut Context<I, Cached>) {
+    ctx.state_repeat_start(b, bs_middle);
+}
+
+unsafe fn bs_middle<I: Input + ?Sized>(ctx: &mut Context<I, Cached>) {
+    ctx.state_repeat_middle(b);
+}
+
+fn parse<G: Grammar<Symbol = u8, Label = Label>>(
+    grammar: &G,
+    input: &[u8],
+    options: &ParseOptions,
+) -> Option<String> {
+    match Context::run(input, grammar, options).unwrap() {
+        ParseResult::Matched(result) => Some(GenParseMatch::new(result).to_sexp()),
+        ParseResult::Unmatched { .. } => None,
+    }
+}
+
+/// Every error is recovered from when there are no more of them than the limit
+#[test]
+fn errors_up_to_the_limit_are_marked() {
+    let expected = concat!(
+        "(root 0 5\n",
+        "  (error (expected \"a\") 1 1)\n",
+        "  (error (expected \"a\") 2 1)\n",
+        "  (error (expected \"a\") 4 1))"
+    );
+
+    for options in [
+        ParseOptions::new(),
+        ParseOptions::new().max_error_marks(3),
+        ParseOptions::new().max_error_marks(4),
+    ] {
+        assert_eq!(
+            parse(&Recovering, b"abbab", &options).as_deref(),
+            Some(expected)
+        );
+    }
+}
+
+/// Recovery stops at the first error past the limit, which fails to match instead
+#[test]
+fn errors_past_the_limit_fail() {
+    assert_eq!(
+        parse(
+            &Recovering,
+            b"abbab",
+            &ParseOptions::new().max_error_marks(2)
+        )
+        .as_deref(),
+        Some("(root 0 4\n  (error (expected \"a\") 1 1)\n  (error (expected \"a\") 2 1))")
+    );
+    assert_eq!(
+        parse(
+            &Recovering,
+            b"abbab",
+            &ParseOptions::new().max_error_marks(0)
+        )
+        .as_deref(),
+        Some("(root 0 1)")
+    );
+}
+
+/// An error counts towards the limit when it is marked, so a cached result containing it is still
+/// reused once the limit has been reached
+#[test]
+fn cached_errors_are_reused_past_the_limit() {
+    let input = [b'b'; 200];
+    let expected = "(root 0 200\n  (error (expected \"a\") 0 200))";
+
+    assert_eq!(
+        parse(&Cached, &input, &ParseOptions::new().max_error_marks(1)).as_deref(),
+        Some(expected)
+    );
+    assert_eq!(
+        parse(&Cached, &input, &ParseOptions::new().max_error_marks(0)),
+        None
+    );
+}
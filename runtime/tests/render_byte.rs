@@ -0,0 +1,24 @@
+use peg_pack_runtime::{render_byte, SymbolType};
+
+/// Bytes are escaped the way a Rust string literal would escape them
+#[test]
+fn render_byte_escapes() {
+    let rendered = [
+        b'a', b' ', b'\n', b'\t', b'\r', 0, b'\\', b'"', b'\'', 0x01, 0x7f, 0xff,
+    ]
+    .map(render_byte);
+
+    assert_eq!(
+        rendered,
+        ["a", " ", r"\n", r"\t", r"\r", r"\0", r"\\", r#"\""#, "'", r"\x01", r"\x7f", r"\xff"]
+    );
+}
+
+/// Literals render ASCII through `render_byte`, keep other characters readable and escape bytes
+/// that aren't valid UTF-8
+#[test]
+fn describe_matches_render_byte() {
+    assert_eq!(u8::describe(b"a\"b\x01\n"), r#""a\"b\x01\n""#);
+    assert_eq!(u8::describe("é".as_bytes()), "\"é\"");
+    assert_eq!(u8::describe(b"\xffa"), r#""\xffa""#);
+}
@@ -0,0 +1,53 @@
+use std::thread;
+
+use peg_pack_runtime::{Context, Input, LineMap, ParseOptions, ParseResult};
+
+use common::{grammar, Expected};
+
+mod common;
+
+grammar! {
+    /// A grammar matching the literal `ab`
+    Ab { start: start, expected: Expected::Ab }
+}
+
+unsafe fn start<I: Input + ?Sized>(ctx: &mut Context<I, Ab>) {
+    ctx.state_series(|input, position| input.matches_at(position, b"ab"));
+}
+
+fn assert_thread_safe<T: Send + Sync + ?Sized>() {}
+
+/// The values a server would share between threads parsing requests can be shared
+#[test]
+fn shared_values_are_thread_safe() {
+    assert_thread_safe::<ParseOptions>();
+    assert_thread_safe::<LineMap>();
+    assert_thread_safe::<Ab>();
+}
+
+/// Parses keep all of their state to themselves, so running many at once gives the same results
+/// as running them one at a time
+#[test]
+fn concurrent_parses_are_independent() {
+    let options = ParseOptions::new().max_error_marks(1);
+    let inputs: [&[u8]; 4] = [b"ab", b"abc", b"a", b""];
+
+    thread::scope(|scope| {
+        for _ in 0..8 {
+            scope.spawn(|| {
+                for _ in 0..1000 {
+                    for (input, expected) in inputs.iter().zip([Some(2), Some(2), None, None]) {
+                        let result = Context::run(*input, &Ab, &options).unwrap();
+
+                        let distance = match result {
+                            ParseResult::Matched(result) => Some(result.distance()),
+                            ParseResult::Unmatched { .. } => None,
+                        };
+
+                        assert_eq!(distance, expected);
+                    }
+                }
+            });
+        }
+    });
+}
@@ -0,0 +1,11 @@
+use peg_pack_runtime::{runtime_version_is, RUNTIME_VERSION};
+
+/// The runtime's version is written out by hand, since copies of the runtime
+/// are compiled as part of other crates
+#[test]
+fn runtime_version_matches_crate() {
+    assert_eq!(RUNTIME_VERSION, env!("CARGO_PKG_VERSION"));
+    assert!(runtime_version_is(env!("CARGO_PKG_VERSION")));
+    assert!(!runtime_version_is("0.0.0"));
+    assert!(!runtime_version_is(""));
+}
@@ -1,51 +1,41 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
 use std::fs::File;
-use std::io::{ErrorKind, Write};
-use std::panic::PanicInfo;
+use std::hash::{Hash, Hasher};
+use std::io::{ErrorKind, Read, Write};
+use std::panic::PanicHookInfo;
 use std::path::{Path, PathBuf};
 use std::process::{exit, Command, Output};
-use std::time::Instant;
-use std::{fs, io, panic};
+use std::time::{Duration, Instant};
+use std::{fs, io, iter, panic};
 
 use atty::Stream;
 use clap::CommandFactory;
 use clap::FromArgMatches;
 use clap::Parser as CliParser;
+use clap::ValueEnum;
 use regex::bytes::Regex;
+use serde::{Deserialize, Serialize};
 use termcolor::{Color, ColorSpec, StandardStream, WriteColor};
 
-use crate::core::{CompilerSettings, Error, Parser};
+use crate::core::{
+    describe_symbol, Class, CompilerSettings, Error, GenerationSettings, ModuleConfig, Parser,
+    ParserSize, SymbolWidth, Warning,
+};
+
+/// IR files larger than this many bytes are streamed rather than read into memory
+const STREAMING_IR_THRESHOLD: u64 = 16 * 1024 * 1024;
+
+/// The status peg-pack exits with when it fails, which is kept apart from the
+/// statuses the interactive parser exits with so that scripts can tell them apart
+const EXIT_TOOL_ERROR: i32 = 3;
+
+/// The files written into the build directory to compile the parser from,
+/// which are replaced on each run unlike the compiled parsers beside them
+const BUILD_SOURCES: &[&str] = &["harness.rs", "loader.js", "grammar.js", "ir.json"];
 
 /// A list of paths and contents to copy into the build directory
 const OUT_DIR_FILES: &[(&str, &[u8])] = &[
-    ("build/runtime/mod.rs", include_bytes!("runtime/mod.rs")),
-    (
-        "build/runtime/context.rs",
-        include_bytes!("runtime/context.rs"),
-    ),
-    (
-        "build/runtime/grammar.rs",
-        include_bytes!("runtime/grammar.rs"),
-    ),
-    ("build/runtime/input.rs", include_bytes!("runtime/input.rs")),
-    (
-        "build/runtime/result.rs",
-        include_bytes!("runtime/result.rs"),
-    ),
-    (
-        "build/runtime/buffered_iter.rs",
-        include_bytes!("runtime/buffered_iter.rs"),
-    ),
-    (
-        "build/runtime/array_vec.rs",
-        include_bytes!("runtime/array_vec.rs"),
-    ),
-    (
-        "build/runtime/small_vec.rs",
-        include_bytes!("runtime/small_vec.rs"),
-    ),
-    ("build/runtime/stack.rs", include_bytes!("runtime/stack.rs")),
-    ("build/runtime/cache.rs", include_bytes!("runtime/cache.rs")),
-    ("build/runtime/refc.rs", include_bytes!("runtime/refc.rs")),
     ("build/harness.rs", include_bytes!("include/harness.rs")),
     ("build/loader.js", include_bytes!("include/loader.js")),
     ("loader.d.ts", include_bytes!("include/loader.d.ts")),
@@ -54,7 +44,13 @@ const OUT_DIR_FILES: &[(&str, &[u8])] = &[
 
 pub fn parse_args() -> Cli {
     let command = (Cli::command() as clap::Command).color(clap::ColorChoice::Auto);
-    Cli::from_arg_matches(&command.get_matches()).unwrap()
+    let matches = command.try_get_matches().unwrap_or_else(|err| {
+        let code = if err.use_stderr() { EXIT_TOOL_ERROR } else { 0 };
+        let _ = err.print();
+        exit(code)
+    });
+
+    Cli::from_arg_matches(&matches).unwrap()
 }
 
 pub fn run(args: Cli) {
@@ -69,7 +65,7 @@ pub fn setup_panic_hook() {
     panic::set_hook(Box::new(move |info| panic_hook(info, &default_hook)));
 }
 
-fn panic_hook(info: &PanicInfo, default_hook: &dyn Fn(&PanicInfo)) {
+fn panic_hook(info: &PanicHookInfo, default_hook: &dyn Fn(&PanicHookInfo)) {
     let color = if atty::is(Stream::Stderr) {
         termcolor::ColorChoice::Auto
     } else {
@@ -96,8 +92,13 @@ fn panic_hook(info: &PanicInfo, default_hook: &dyn Fn(&PanicInfo)) {
 #[derive(CliParser)]
 #[clap(author, version, about)]
 pub struct Cli {
-    /// The grammar file to generate from
-    pub grammar: PathBuf,
+    /// The grammar file to generate from, or `-` to read the grammar script from stdin
+    #[clap(required_unless_present = "ir")]
+    pub grammar: Option<PathBuf>,
+
+    /// Generate from an existing IR file rather than running a grammar, so NodeJS isn't needed
+    #[clap(long, conflicts_with = "grammar")]
+    pub ir: Option<PathBuf>,
 
     /// The output directory for build artifacts
     #[clap(short, long)]
@@ -110,6 +111,245 @@ pub struct Cli {
     /// Enable slow state analysis optimizations
     #[clap(long)]
     pub state_opt: bool,
+
+    /// Choose between alternatives that can't start alike by the next symbol
+    #[clap(long)]
+    pub choice_dispatch: bool,
+
+    /// Disable every optional optimization except caching, useful when debugging the optimizer
+    #[clap(long)]
+    pub no_opt: bool,
+
+    /// The most optimization passes to run before giving up on reaching a fixed point
+    #[clap(long)]
+    pub max_passes: Option<usize>,
+
+    /// Start parsing at the named rule rather than the grammar's start rule, so one grammar can
+    /// produce parsers for each of its entry points
+    #[clap(long, value_name = "RULE")]
+    pub start: Option<String>,
+
+    /// Fail to parse unless the start rule matches the whole input, rather than just a prefix of it
+    #[clap(long)]
+    pub require_eof: bool,
+
+    /// A JSON file mapping rule names to messages, which replace what errors in those rules
+    /// report they expected
+    #[clap(long, value_name = "FILE")]
+    pub messages: Option<PathBuf>,
+
+    /// Only match ASCII input, so character classes can ignore every other byte
+    #[clap(long)]
+    pub ascii: bool,
+
+    /// Accept left recursive rules, such as `expr = expr "+" term / term`, matching them by
+    /// growing a seed parse at each position they are entered
+    #[clap(long)]
+    pub left_recursion: bool,
+
+    /// Label every named rule with its name, so the parse tree has a node for each rule matched.
+    /// Useful for debugging, but produces larger trees and can defeat some optimizations
+    #[clap(long)]
+    pub label_all_rules: bool,
+
+    /// The type of symbol the parser reads. Wider symbols suit parsing the tokens produced by a
+    /// separate lexer, and are read a byte at a time when parsing interactively
+    #[clap(long, value_enum, value_name = "TYPE", default_value = "u8")]
+    pub symbols: Symbols,
+
+    /// The most cache slots the parser may use, dropping the cache points estimated to save the
+    /// least work beyond it
+    #[clap(long, value_name = "N")]
+    pub max_cache_slots: Option<usize>,
+
+    /// Exit with an error if the grammar has any warnings, after reporting them
+    #[clap(long)]
+    pub deny_warnings: bool,
+
+    /// How to report warnings about the grammar. JSON prints one object per line to stdout, with
+    /// the warning's `code`, `subject` and `message`
+    #[clap(long, value_enum, value_name = "FORMAT", default_value = "human")]
+    pub message_format: MessageFormat,
+
+    /// Report statistics about the optimizer
+    #[clap(long)]
+    pub opt_stats: bool,
+
+    /// Report the size and a fingerprint of the parser after each pass of the optimizer, and write
+    /// the parser after each to `build/passes` in the output directory, for finding the pass
+    /// responsible for a miscompilation
+    #[clap(long)]
+    pub trace_passes: bool,
+
+    /// Report how many functions each rule generates, largest first, to find the rules that
+    /// contribute most to the parser's size
+    #[clap(long)]
+    pub profile_size: bool,
+
+    /// Write how long each phase of the build took and how large the parser is to a JSON file, for
+    /// tracking builds over time
+    #[clap(long, value_name = "PATH")]
+    pub metrics: Option<PathBuf>,
+
+    /// Parse every file in a directory with the generated parser, reporting the total work it took
+    /// and the errors it found
+    #[clap(long, value_name = "DIR", conflicts_with = "interactive")]
+    pub corpus: Option<PathBuf>,
+
+    /// Fail if parsing the corpus took more work than a baseline file allows, or found more errors
+    #[clap(long, value_name = "PATH", requires = "corpus")]
+    pub work_baseline: Option<PathBuf>,
+
+    /// Record the corpus's totals in the baseline file rather than checking against it
+    #[clap(long, requires = "work_baseline")]
+    pub update_work_baseline: bool,
+
+    /// How many percent over the baseline's work parsing the corpus may take
+    #[clap(long, value_name = "PERCENT", default_value_t = 5)]
+    pub work_tolerance: u64,
+
+    /// Report pairs of alternatives in a choice that can both match the same input, where the
+    /// earlier one always wins
+    #[clap(long)]
+    pub report_overlaps: bool,
+
+    /// Report named rules that are never used, because the start rule can't reach them or
+    /// because an earlier alternative always matches first
+    #[clap(long)]
+    pub report_unused: bool,
+
+    /// List every literal string the grammar matches, such as keywords and punctuation
+    #[clap(long)]
+    pub list_literals: bool,
+
+    /// Report how the optimized parser differs from one compiled from a copy of an earlier build's
+    /// `build/ir.json`
+    #[clap(long, value_name = "OLD_IR")]
+    pub diff: Option<PathBuf>,
+
+    /// Generate a parser that traces the rules it runs to stderr, and reports the positions it
+    /// backtracked to most when interactive
+    #[clap(long)]
+    pub trace: bool,
+
+    /// Generate a parser that counts how often each rule runs and reports it when interactive
+    #[clap(long)]
+    pub coverage: bool,
+
+    /// Spread the generated parser across this many modules in a `parser` directory, which is
+    /// faster to compile and easier on editors for large grammars
+    #[clap(long, value_name = "N")]
+    pub split: Option<usize>,
+
+    /// Compile the interactive parser for the smallest binary rather than the fastest
+    #[clap(long)]
+    pub min_size: bool,
+
+    /// The file name of the compiled parser in the build directory, which is `parser` by default.
+    /// Compiled parsers are kept between runs, so parsers of several grammars can share a directory
+    #[clap(long, value_name = "NAME")]
+    pub executable_name: Option<String>,
+
+    /// The name the generated parser declares its runtime module under
+    #[clap(long)]
+    pub module_name: Option<String>,
+
+    /// The path to the runtime module, relative to the generated parser, for when it will be moved
+    /// out of the output directory
+    #[clap(long, conflicts_with = "interactive")]
+    pub runtime_path: Option<String>,
+
+    /// Where the generated parser gets its runtime from. The runtime crate lets parsers share one
+    /// version of the runtime, but can't be used when parsing interactively
+    #[clap(long, value_enum, value_name = "SOURCE", default_value = "copy")]
+    pub runtime: Runtime,
+
+    /// Report how effective each cache slot was when parsing interactively
+    #[clap(long)]
+    pub cache_stats: bool,
+
+    /// Skip a leading UTF-8 byte order mark when parsing interactively
+    #[clap(long)]
+    pub skip_bom: bool,
+
+    /// How to print the parse tree when parsing interactively
+    #[clap(long, value_enum, value_name = "FORMAT", default_value = "debug")]
+    pub emit: Emit,
+
+    /// Also write the parser's state machine as JSON to `states.json` in the output directory, for
+    /// simulating or interpreting the parser outside of Rust
+    #[clap(long)]
+    pub emit_states: bool,
+
+    /// Also write which labels may appear inside each label, and whether errors may, as JSON to
+    /// `tree-schema.json` in the output directory, for generating typed bindings to parse trees
+    #[clap(long)]
+    pub emit_tree_schema: bool,
+
+    /// Check that the optimized parser is internally consistent before generating it, which is
+    /// always done in debug builds of peg-pack
+    #[clap(long)]
+    pub verify: bool,
+
+    /// Only check that the environment and grammar file are usable, without building anything
+    #[clap(long, alias = "dry-run")]
+    pub check: bool,
+}
+
+/// A type of symbol the parser can read
+#[derive(ValueEnum, Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Symbols {
+    /// Bytes
+    U8,
+    /// 32-bit symbols, such as token kinds
+    U32,
+}
+
+impl Symbols {
+    fn width(self) -> SymbolWidth {
+        match self {
+            Symbols::U8 => SymbolWidth::U8,
+            Symbols::U32 => SymbolWidth::U32,
+        }
+    }
+}
+
+/// Where a generated parser gets its runtime from
+#[derive(ValueEnum, Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Runtime {
+    /// A copy of the runtime written to `build/runtime` in the output directory
+    Copy,
+    /// The `peg-pack-runtime` crate, which the project must depend on
+    Crate,
+}
+
+/// A format warnings about the grammar can be reported in
+#[derive(ValueEnum, Debug, Copy, Clone, Eq, PartialEq)]
+pub enum MessageFormat {
+    /// Colored messages on stderr
+    Human,
+    /// JSON objects on stdout, one per line
+    Json,
+}
+
+/// The totals from parsing every file of a corpus, which are stored as a baseline for later
+/// parses of the corpus to be checked against
+#[derive(Serialize, Deserialize, Default, Debug)]
+struct CorpusTotals {
+    files: u64,
+    work: u64,
+    errors: u64,
+    /// How many files the parser failed to match at all
+    failures: u64,
+}
+
+/// A format the interactive parser can print parse trees in
+#[derive(ValueEnum, Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Emit {
+    /// The parse tree's debug representation
+    Debug,
+    /// Indented S-expressions, suitable for snapshot testing
+    Sexp,
 }
 
 struct Context {
@@ -119,6 +359,10 @@ struct Context {
     active_indicator: bool,
     /// The time peg-pack started
     start: Instant,
+    /// How long each phase of the build took, in the order they ran
+    phases: Vec<(&'static str, Duration)>,
+    /// The size of the parser, and of its generated code in bytes
+    size: Option<(ParserSize, usize)>,
 }
 
 impl Context {
@@ -137,59 +381,388 @@ impl Context {
             opts: cli,
             active_indicator: false,
             start: Instant::now(),
+            phases: Vec::new(),
+            size: None,
         }
     }
 
     fn run(mut self) {
+        if self.opts.check {
+            self.run_check();
+            return;
+        }
+
+        if let Some(name) = &self.opts.executable_name {
+            if !is_file_name(name) {
+                self.exit_with_error(format!("Invalid executable name: {}", name));
+            }
+        }
+
+        if self.opts.runtime == Runtime::Crate {
+            if self.opts.interactive {
+                self.exit_with_error("The runtime crate cannot be used when parsing interactively");
+            }
+
+            if self.opts.corpus.is_some() {
+                self.exit_with_error("The runtime crate cannot be used when parsing a corpus");
+            }
+
+            if self.opts.runtime_path.is_some() {
+                self.exit_with_error("A runtime path cannot be given with the runtime crate");
+            }
+        }
+
+        if self.grammar_from_stdin() && self.opts.interactive {
+            self.exit_with_error(
+                "The grammar cannot be read from stdin when parsing interactively, since the \
+                 input to parse is read from there",
+            );
+        }
+
         self.set_indicator("Checking environment");
-        self.check_node();
-        self.check_grammar();
 
-        if self.opts.interactive {
+        if self.opts.ir.is_none() {
+            self.check_node();
+        }
+
+        self.check_source();
+
+        if self.opts.interactive || self.opts.corpus.is_some() {
             self.check_rust();
         }
 
+        // Existing IR is loaded before the output directory is replaced, since it may be inside
+        let loaded = self.opts.ir.clone().map(|ir_file| {
+            self.set_indicator("Generating parser");
+            self.timed("load", |ctx| ctx.load_parser(&ir_file))
+        });
+
         self.set_indicator("Setting up output");
         self.create_out_dir();
         self.populate_out_dir();
 
-        self.clear_indicator();
-        self.execute_grammar();
+        let parser = match loaded {
+            Some(parser) => {
+                self.set_indicator("Generating parser");
+                parser
+            }
+            None => {
+                self.clear_indicator();
+                self.timed("grammar", Self::execute_grammar);
+
+                self.set_indicator("Generating parser");
+                self.timed("load", |ctx| ctx.load_parser(&ctx.ir_file()))
+            }
+        };
 
-        self.set_indicator("Generating parser");
-        let parser = self.load_parser();
-        self.generate_code(parser);
+        if self.opts.verify {
+            self.verify(&parser);
+        }
 
-        if self.opts.interactive {
+        self.print_warnings(&parser);
+
+        if self.opts.report_overlaps {
+            self.print_overlapping_alternatives(&parser);
+        }
+
+        if self.opts.opt_stats {
+            self.print_opt_stats(&parser);
+        }
+
+        if self.opts.trace_passes {
+            self.print_pass_trace(&parser);
+        }
+
+        if self.opts.profile_size {
+            self.print_size_profile(&parser);
+        }
+
+        if self.opts.list_literals {
+            self.print_literals(&parser);
+        }
+
+        if let Some(old_ir_file) = self.opts.diff.clone() {
+            let old_parser = self.load_parser(&old_ir_file);
+            self.print_diff(&old_parser, &parser);
+        }
+
+        self.timed("generate", |ctx| ctx.generate_code(parser));
+
+        if self.opts.interactive || self.opts.corpus.is_some() {
             self.set_indicator("Compiling");
-            self.compile();
+            self.timed("compile", Self::compile);
+        }
+
+        self.print_ready();
+        self.write_metrics();
+
+        if let Some(corpus) = self.opts.corpus.clone() {
+            self.check_corpus(&corpus);
+        }
 
-            self.print_ready();
+        if self.opts.interactive {
             self.execute();
-        } else {
-            self.print_ready();
         }
     }
 
+    /// Run a phase of the build, recording how long it took
+    fn timed<T>(&mut self, phase: &'static str, run: impl FnOnce(&mut Self) -> T) -> T {
+        let start = Instant::now();
+        let result = run(self);
+        self.phases.push((phase, start.elapsed()));
+        result
+    }
+
+    /// Run the environment checks and report the tool versions found
+    fn run_check(&mut self) {
+        self.set_indicator("Checking environment");
+        let node_version = self.opts.ir.is_none().then(|| self.check_node());
+        let rust_version = self.check_rust();
+        self.check_source();
+
+        if let Some(node_version) = node_version {
+            self.println(format!("Found NodeJS {}", node_version));
+        }
+
+        self.println(format!("Found Rust {}", rust_version));
+        self.println("Environment ready");
+    }
+
     fn print_ready(&mut self) {
         self.println(format!("Parser built in {:.1?}", self.start.elapsed()));
     }
 
-    /// Load the generated IR file into a parser
-    fn load_parser(&mut self) -> Parser {
-        let ir = match fs::read(self.ir_file()) {
-            Ok(ir) => ir,
-            Err(err) => {
-                self.exit_with_error(format!("Could not read IR: {}", err));
+    /// Write the durations of the build's phases and the size of the parser to the metrics file,
+    /// if one was given. Durations are in seconds
+    fn write_metrics(&mut self) {
+        let path = match &self.opts.metrics {
+            Some(path) => path.clone(),
+            None => return,
+        };
+
+        let durations = self
+            .phases
+            .iter()
+            .map(|(phase, duration)| (phase.to_string(), duration.as_secs_f64().into()))
+            .chain(iter::once((
+                String::from("total"),
+                self.start.elapsed().as_secs_f64().into(),
+            )))
+            .collect::<serde_json::Map<_, _>>();
+
+        let (size, generated_bytes) = self.size.expect("parser was not generated");
+
+        let metrics = serde_json::json!({
+            "durations": durations,
+            "instructions": size.instructions,
+            "series": size.series,
+            "labels": size.labels,
+            "expecteds": size.expecteds,
+            "states": size.states,
+            "cacheSlots": size.cache_slots,
+            "generatedBytes": generated_bytes,
+        });
+
+        if let Err(err) = fs::write(path, metrics.to_string() + "\n") {
+            self.exit_with_error(format!("Could not write metrics: {}", err));
+        }
+    }
+
+    /// Parse each file of the corpus with the compiled parser, then report the totals or check them
+    /// against the baseline
+    fn check_corpus(&mut self, corpus: &Path) {
+        let mut paths = match fs::read_dir(corpus) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.is_file())
+                .collect::<Vec<_>>(),
+            Err(err) => self.exit_with_error(format!("Could not read corpus: {}", err)),
+        };
+
+        paths.sort();
+
+        let mut totals = CorpusTotals::default();
+
+        self.set_indicator("Parsing corpus");
+
+        for path in &paths {
+            let input = match File::open(path) {
+                Ok(input) => input,
+                Err(err) => self.exit_with_error(format!(
+                    "Could not open corpus file ({}): {}",
+                    path.display(),
+                    err
+                )),
+            };
+
+            let mut command = Command::new(self.executable_file());
+            command.arg("--work").stdin(input);
+
+            if self.opts.skip_bom {
+                command.arg("--skip-bom");
+            }
+
+            let output = match command.output() {
+                Ok(output) => output,
+                Err(err) => self.exit_with_error(format!("Could not launch parser: {}", err)),
+            };
+
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let words = stdout.split_whitespace().collect::<Vec<_>>();
+
+            match words[..] {
+                ["work", work, "errors", errors] => {
+                    totals.work += work.parse::<u64>().unwrap();
+                    totals.errors += errors.parse::<u64>().unwrap();
+                }
+                ["failed"] => totals.failures += 1,
+                _ => self.exit_with_error_and_output("Parser exited unexpectedly", &output),
             }
+
+            totals.files += 1;
+        }
+
+        self.println(format!(
+            "Parsed {} file(s) with {} work, {} error(s) and {} failure(s)",
+            totals.files, totals.work, totals.errors, totals.failures
+        ));
+
+        let path = match self.opts.work_baseline.clone() {
+            Some(path) => path,
+            None => return,
+        };
+
+        if self.opts.update_work_baseline {
+            let baseline = serde_json::to_string_pretty(&totals).unwrap();
+
+            if let Err(err) = fs::write(path, baseline + "\n") {
+                self.exit_with_error(format!("Could not write work baseline: {}", err));
+            }
+
+            return;
+        }
+
+        let baseline = match fs::read(&path) {
+            Ok(contents) => contents,
+            Err(err) => self.exit_with_error(format!("Could not read work baseline: {}", err)),
+        };
+
+        let baseline = match serde_json::from_slice::<CorpusTotals>(&baseline) {
+            Ok(baseline) => baseline,
+            Err(err) => self.exit_with_error(format!("Invalid work baseline: {}", err)),
+        };
+
+        let limit = baseline.work + baseline.work * self.opts.work_tolerance / 100;
+
+        if totals.work > limit {
+            self.exit_with_error(format!(
+                "Parsing the corpus took {} work, more than {}% over the baseline of {}",
+                totals.work, self.opts.work_tolerance, baseline.work
+            ));
+        }
+
+        if totals.errors > baseline.errors || totals.failures > baseline.failures {
+            self.exit_with_error(format!(
+                "Parsing the corpus found {} error(s) and {} failure(s), more than the baseline's \
+                 {} and {}",
+                totals.errors, totals.failures, baseline.errors, baseline.failures
+            ));
+        }
+
+        self.println(format!(
+            "Within {}% of the baseline's {} work",
+            self.opts.work_tolerance, baseline.work
+        ));
+    }
+
+    /// Read a file of messages for errors, keyed by the name of their rule
+    fn load_messages(&mut self, path: &Path) -> BTreeMap<String, String> {
+        let contents = match fs::read(path) {
+            Ok(contents) => contents,
+            Err(err) => self.exit_with_error(format!("Could not read messages: {}", err)),
+        };
+
+        match serde_json::from_slice(&contents) {
+            Ok(messages) => messages,
+            Err(err) => self.exit_with_error(format!("Invalid messages file: {}", err)),
+        }
+    }
+
+    /// Load an IR file into a parser
+    fn load_parser(&mut self, ir_file: &Path) -> Parser {
+        let mut settings = if self.opts.no_opt {
+            CompilerSettings::none()
+        } else {
+            CompilerSettings::normal()
         };
 
-        let mut settings = CompilerSettings::normal();
         settings.state_optimization = self.opts.state_opt;
+        settings.choice_dispatch = self.opts.choice_dispatch;
+
+        if let Some(max_passes) = self.opts.max_passes {
+            settings.max_passes = max_passes;
+        }
+
+        settings.max_cache_slots = self.opts.max_cache_slots;
+        settings.symbols = self.opts.symbols.width();
+        settings.left_recursion = self.opts.left_recursion;
+        settings.label_all_rules = self.opts.label_all_rules;
+        settings.trace_passes = self.opts.trace_passes;
+        settings.report_unused_rules = self.opts.report_unused;
+
+        if self.opts.ascii {
+            settings.alphabet = Some(Class::of(&[(0, 127)]));
+        }
+
+        let loaded = match fs::metadata(ir_file) {
+            Ok(metadata) if metadata.len() > STREAMING_IR_THRESHOLD => match File::open(ir_file) {
+                Ok(file) => Parser::load_streaming_unprepared(file),
+                Err(err) => self.exit_with_error(format!("Could not read IR: {}", err)),
+            },
+            _ => match fs::read(ir_file) {
+                Ok(ir) => Parser::load_unprepared(&ir),
+                Err(err) => self.exit_with_error(format!("Could not read IR: {}", err)),
+            },
+        };
+
+        let messages = self
+            .opts
+            .messages
+            .clone()
+            .map(|path| self.load_messages(&path));
+        let start = self.opts.start.clone();
+        let require_eof = self.opts.require_eof;
+        let result = loaded.and_then(|mut parser| {
+            if let Some(messages) = messages {
+                parser.set_error_messages(messages)?;
+            }
+
+            if let Some(start) = start {
+                parser.set_start(&start)?;
+            }
 
-        match Parser::load(&ir, settings) {
+            if require_eof {
+                parser.require_eof();
+            }
+
+            parser.prepare(settings)
+        });
+
+        match result {
             Ok(parser) => parser,
             Err(Error::Load(message)) => self.exit_with_error(message),
+            Err(Error::UnknownRule(rule)) => {
+                self.exit_with_error(format!("Unknown rule: {}", rule))
+            }
+            Err(Error::SymbolOutOfRange(symbol)) => self.exit_with_error(format!(
+                "Symbol {} is too large for {} symbols, try --symbols u32",
+                symbol,
+                self.opts.symbols.width().rust_type()
+            )),
+            Err(Error::AssertionFailed(message)) => {
+                self.exit_with_error(format!("Assertion failed, {}", message))
+            }
             Err(Error::LeftRecursive(left_recursive)) => {
                 self.print_error_heading();
 
@@ -215,29 +788,363 @@ impl Context {
                     self.println("");
                 }
 
-                exit(1);
+                exit(EXIT_TOOL_ERROR);
+            }
+        }
+    }
+
+    /// Exit if the optimized parser is inconsistent, listing each problem found
+    fn verify(&mut self, parser: &Parser) {
+        let violations = parser.check_invariants();
+
+        if violations.is_empty() {
+            return;
+        }
+
+        self.print_error_heading();
+        self.println("Optimized parser is inconsistent, this is a bug in peg-pack");
+
+        for violation in violations {
+            self.print("  ");
+            self.println(&violation);
+        }
+
+        exit(EXIT_TOOL_ERROR);
+    }
+
+    /// Report the warnings about the grammar, exiting afterwards if they are denied
+    fn print_warnings(&mut self, parser: &Parser) {
+        let warnings = parser.warnings();
+
+        for warning in &warnings {
+            match self.opts.message_format {
+                MessageFormat::Human => self.print_warning(warning),
+                MessageFormat::Json => {
+                    let message = serde_json::json!({
+                        "level": "warning",
+                        "code": warning.code(),
+                        "subject": warning.subject(),
+                        "message": warning.to_string(),
+                    });
+
+                    println!("{}", message);
+                }
+            }
+        }
+
+        if self.opts.deny_warnings && !warnings.is_empty() {
+            let plural = if warnings.len() == 1 { "" } else { "s" };
+            self.exit_with_error(format!(
+                "Grammar has {} warning{}, which --deny-warnings forbids",
+                warnings.len(),
+                plural
+            ));
+        }
+    }
+
+    fn print_warning(&mut self, warning: &Warning) {
+        let (before, after) = warning.surrounding_text();
+
+        self.print_warn_heading();
+        self.print(before);
+
+        if let Some(subject) = warning.subject() {
+            self.print_color(Color::Yellow, false);
+            self.print(subject);
+            self.print_reset();
+        }
+
+        self.println(after);
+    }
+
+    /// Point out alternatives that can both match the same input, which is legal but easy to miss
+    fn print_overlapping_alternatives(&mut self, parser: &Parser) {
+        for overlap in parser.overlapping_alternatives() {
+            self.print_info_heading();
+            self.print(format!(
+                "Alternatives {} and {} of rule ",
+                overlap.first, overlap.second
+            ));
+            self.print_color(Color::Yellow, false);
+            self.print(&overlap.rule);
+            self.print_reset();
+
+            match overlap.symbol {
+                Some(symbol) => {
+                    self.println(format!(" can both start with {}", describe_symbol(symbol)))
+                }
+                None => self.println(" can both match empty input"),
+            }
+        }
+    }
+
+    /// Report every difference between an earlier parser and the current one
+    fn print_diff(&mut self, old: &Parser, new: &Parser) {
+        let differences = old.diff(new);
+
+        if differences.is_empty() {
+            self.println("Parser is unchanged");
+            return;
+        }
+
+        for difference in differences {
+            let color = match (&difference.old, &difference.new) {
+                (None, _) => Color::Green,
+                (_, None) => Color::Red,
+                _ => Color::Yellow,
+            };
+
+            self.print_color(color, false);
+            self.println(difference.to_string());
+            self.print_reset();
+        }
+    }
+
+    /// Report how much work the optimizer did
+    fn print_opt_stats(&mut self, parser: &Parser) {
+        let passes = parser.optimization_passes();
+        let plural = if passes == 1 { "" } else { "es" };
+        self.println(format!("Ran {} optimization pass{}", passes, plural));
+
+        let caches = parser.eliminated_caches();
+        let plural = if caches == 1 { "" } else { "s" };
+        self.println(format!(
+            "Removed {} redundant cache point{}",
+            caches, plural
+        ));
+
+        let dropped = parser.dropped_caches();
+        if dropped > 0 {
+            let plural = if dropped == 1 { "" } else { "s" };
+            self.println(format!(
+                "Dropped {} cache point{} over the slot limit",
+                dropped, plural
+            ));
+        }
+
+        let slots = parser.cache_slots();
+        let plural = if slots == 1 { "" } else { "s" };
+        self.println(format!("Using {} cache slot{}", slots, plural));
+
+        match parser.max_lookahead() {
+            Some(lookahead) => self.println(format!(
+                "Parses examine at most {} symbol(s) past the end of a match",
+                lookahead
+            )),
+            None => self.println("Parses may examine unboundedly far past the end of a match"),
+        }
+
+        match parser.max_tree_depth() {
+            Some(depth) => self.println(format!(
+                "Parse trees nest at most {} grouping(s) deep",
+                depth
+            )),
+            None => self.println("Parse trees may nest groupings unboundedly deep"),
+        }
+
+        if parser.is_regular() {
+            self.println("Grammar has no recursion or negative lookahead, so it describes a regular language");
+        }
+    }
+
+    /// Report each pass the optimizer ran, with a fingerprint of the parser it left behind so that
+    /// the passes that changed it stand out, and write the parser after each pass to a numbered
+    /// file
+    fn print_pass_trace(&mut self, parser: &Parser) {
+        let passes_dir = self.out_dir().join("build/passes");
+
+        if let Err(err) = fs::create_dir_all(&passes_dir) {
+            self.exit_with_error(format!("Could not create pass directory: {}", err));
+        }
+
+        let mut previous = None;
+
+        for (index, snapshot) in parser.pass_trace().iter().enumerate() {
+            let mut hasher = DefaultHasher::new();
+            snapshot.ir.hash(&mut hasher);
+            let fingerprint = hasher.finish();
+
+            let round = match snapshot.round {
+                Some(round) => round.to_string(),
+                None => String::from("-"),
+            };
+
+            let unchanged = if previous == Some(fingerprint) {
+                "  (unchanged)"
+            } else {
+                ""
+            };
+
+            self.println(format!(
+                "{:>3}  {:>2}  {:<26}  {:>6} instructions  {:016x}{}",
+                index, round, snapshot.pass, snapshot.instructions, fingerprint, unchanged
+            ));
+
+            let path = passes_dir.join(format!("{:03}-{}.json", index, snapshot.pass));
+            if let Err(err) = fs::write(path, &snapshot.ir) {
+                self.exit_with_error(format!("Could not write pass trace: {}", err));
             }
+
+            previous = Some(fingerprint);
+        }
+    }
+
+    /// List the literals of the grammar one per line, escaping anything unprintable and listing
+    /// the symbols of literals that aren't made of bytes
+    fn print_literals(&mut self, parser: &Parser) {
+        for literal in parser.literals() {
+            let bytes = literal.iter().map(|symbol| u8::try_from(*symbol).ok());
+
+            let line = match bytes.collect::<Option<Vec<_>>>() {
+                Some(bytes) => format!("\"{}\"", bytes.escape_ascii()),
+                None => {
+                    let symbols = literal.iter().map(|symbol| describe_symbol(*symbol));
+                    format!("[{}]", symbols.collect::<Vec<_>>().join(", "))
+                }
+            };
+
+            self.println(line);
+        }
+    }
+
+    /// Report the functions generated for each rule as a table
+    fn print_size_profile(&mut self, parser: &Parser) {
+        let profile = parser.size_profile();
+
+        let width = profile
+            .iter()
+            .map(|size| size.rule.len())
+            .chain(iter::once("Rule".len()))
+            .max()
+            .unwrap();
+
+        self.println(format!(
+            "{:<width$}  {:>6}  {:>6}  {:>7}  {:>5}",
+            "Rule",
+            "States",
+            "Series",
+            "Classes",
+            "Total",
+            width = width
+        ));
+
+        for size in profile {
+            self.println(format!(
+                "{:<width$}  {:>6}  {:>6}  {:>7}  {:>5}",
+                size.rule,
+                size.states,
+                size.series_functions,
+                size.class_functions,
+                size.functions(),
+                width = width
+            ));
         }
     }
 
     /// Generate the Rust code for the parser
     fn generate_code(&mut self, parser: Parser) {
-        let code = parser.generate();
+        let mut settings = GenerationSettings::normal();
+        settings.trace = self.opts.trace;
+        settings.coverage = self.opts.coverage;
+
+        if let Some(split) = self.opts.split {
+            if split == 0 {
+                self.exit_with_error("Cannot split the parser into zero modules");
+            }
 
-        if let Err(err) = fs::write(self.parser_file(), code) {
+            settings.split = Some(split);
+        }
+
+        let mut module = ModuleConfig::normal();
+
+        if let Some(module_name) = &self.opts.module_name {
+            if !is_identifier(module_name) {
+                self.exit_with_error(format!("Invalid module name: {}", module_name));
+            }
+
+            module.module_name = module_name.clone();
+        }
+
+        if let Some(runtime_path) = &self.opts.runtime_path {
+            module.runtime_path = runtime_path.clone();
+        }
+
+        module.runtime_crate = self.opts.runtime == Runtime::Crate;
+
+        if self.opts.emit_states {
+            if let Err(err) = fs::write(self.states_file(), parser.dump_states_json()) {
+                self.exit_with_error(format!("Could not write states: {}", err));
+            }
+        }
+
+        if self.opts.emit_tree_schema {
+            if let Err(err) = fs::write(self.tree_schema_file(), parser.dump_tree_schema_json()) {
+                self.exit_with_error(format!("Could not write tree schema: {}", err));
+            }
+        }
+
+        let size = parser.size();
+        let code = parser.generate(settings, &module);
+
+        let generated_bytes = code.root.len()
+            + code
+                .modules
+                .iter()
+                .map(|(_, contents)| contents.len())
+                .sum::<usize>();
+
+        self.size = Some((size, generated_bytes));
+
+        if let Err(err) = fs::write(self.parser_file(), code.root) {
             self.exit_with_error(format!("Could not write generated code: {}", err));
         }
+
+        for (path, contents) in code.modules {
+            let path = self.out_dir().join(path);
+
+            if let Some(parent) = path.parent() {
+                if let Err(err) = fs::create_dir_all(parent) {
+                    self.exit_with_error(format!("Could not create module directory: {}", err));
+                }
+            }
+
+            if let Err(err) = fs::write(path, contents) {
+                self.exit_with_error(format!("Could not write generated code: {}", err));
+            }
+        }
     }
 
     /// Compile the parser into an executable
     fn compile(&mut self) {
-        let result = Command::new("rustc")
-            .args(["--edition", "2021"])
-            .args(["-C", "opt-level=3"])
+        let mut command = Command::new("rustc");
+
+        command.args(["--edition", "2021"]);
+
+        // The runtime barely panics, so unwinding support is dead weight
+        if self.opts.min_size {
+            command
+                .args(["-C", "opt-level=z"])
+                .args(["-C", "panic=abort"])
+                .args(["-C", "lto=fat"])
+                .args(["-C", "codegen-units=1"]);
+        } else {
+            command.args(["-C", "opt-level=3"]);
+        }
+
+        command
             .arg("-o")
             .arg(self.executable_file())
-            .arg(self.harness_file())
-            .output();
+            .arg(self.harness_file());
+
+        if self.opts.coverage {
+            command.args(["--cfg", "coverage"]);
+        }
+
+        if self.opts.trace {
+            command.args(["--cfg", "trace"]);
+        }
+
+        let result = command.output();
 
         let result = match result {
             Ok(result) => result,
@@ -253,7 +1160,28 @@ impl Context {
 
     /// Run the parser executable
     fn execute(&mut self) {
-        let result = Command::new(self.executable_file()).status();
+        let mut command = Command::new(self.executable_file());
+
+        if self.opts.skip_bom {
+            command.arg("--skip-bom");
+        }
+
+        if self.opts.cache_stats {
+            command.arg("--cache-stats");
+        }
+
+        if self.opts.emit == Emit::Sexp {
+            command.arg("--sexp");
+        }
+
+        // The parser reads until the end of stdin, which would look like a
+        // hang when nothing is piped in
+        if atty::is(Stream::Stdin) {
+            self.print_info_heading();
+            self.println("Reading input from stdin until end of file, press Ctrl-D to finish");
+        }
+
+        let result = command.status();
 
         let status = match result {
             Ok(result) => result,
@@ -262,12 +1190,13 @@ impl Context {
             }
         };
 
-        if !status.success() {
-            if let Some(status) = status.code() {
-                self.exit_with_error(format!("Parser exited with status {}", status));
-            } else {
-                self.exit_with_error("Parser exited with unknown status");
-            }
+        match status.code() {
+            Some(0) => {}
+            // The parser reports errors in and failures to parse the input with
+            // these, which are passed on so that scripts can check them
+            Some(code @ (1 | 2)) => exit(code),
+            Some(code) => self.exit_with_error(format!("Parser exited with status {}", code)),
+            None => self.exit_with_error("Parser exited with unknown status"),
         }
     }
 
@@ -279,15 +1208,34 @@ impl Context {
     }
 
     fn execute_grammar_unhandled(&mut self) -> io::Result<()> {
-        let grammar_path = self.opts.grammar.canonicalize()?;
+        // Node needs a real path to load the grammar from, so a grammar piped in is written to a
+        // temporary file in the build directory first, and removed once the script has run
+        let grammar_path = if self.grammar_from_stdin() {
+            let mut script = Vec::new();
+            io::stdin().read_to_end(&mut script)?;
+
+            let path = self.stdin_grammar_file();
+            fs::write(&path, script)?;
+            path.canonicalize()?
+        } else {
+            self.opts.grammar.as_ref().unwrap().canonicalize()?
+        };
+
         let loader_path = self.loader_file();
         let ir_path = self.ir_file();
 
         let status = Command::new("node")
-            .env("PEG_PACK_GRAMMAR", grammar_path)
+            .env("PEG_PACK_GRAMMAR", &grammar_path)
             .env("PEG_PACK_IR", ir_path)
+            .env("PEG_PACK_SYMBOLS", self.opts.symbols.width().rust_type())
             .arg(loader_path)
-            .status()?;
+            .status();
+
+        if self.grammar_from_stdin() {
+            fs::remove_file(&grammar_path)?;
+        }
+
+        let status = status?;
 
         if !status.success() {
             if let Some(status) = status.code() {
@@ -300,33 +1248,45 @@ impl Context {
         Ok(())
     }
 
-    /// Check that the grammar script is an accessible file
-    fn check_grammar(&mut self) {
-        let grammar = &self.opts.grammar;
-        let display = grammar.display();
+    /// Check that the IR file or grammar script the parser is generated from is accessible
+    fn check_source(&mut self) {
+        match (self.opts.ir.clone(), self.opts.grammar.clone()) {
+            (Some(ir_file), _) => self.check_input_file(&ir_file, "IR"),
+            (None, Some(_)) if self.grammar_from_stdin() => {}
+            (None, Some(grammar)) => self.check_input_file(&grammar, "Grammar"),
+            (None, None) => unreachable!("clap requires a grammar or IR file"),
+        }
+    }
+
+    /// Check that an input file, such as the grammar script, is an accessible file
+    fn check_input_file(&mut self, path: &Path, kind: &str) {
+        let display = path.display();
 
-        if let Err(err) = File::open(grammar) {
+        if let Err(err) = File::open(path) {
             if err.kind() == ErrorKind::NotFound {
-                self.exit_with_error(format!("Grammar file does not exist ({})", display));
+                self.exit_with_error(format!("{} file does not exist ({})", kind, display));
             } else if err.kind() == ErrorKind::PermissionDenied {
                 self.exit_with_error(format!(
-                    "Insufficient permissions to access grammar file ({})",
+                    "Insufficient permissions to access {} file ({})",
+                    kind.to_lowercase(),
                     display
                 ));
             } else {
                 self.exit_with_error(format!(
-                    "Could not open grammar file ({}): {}",
-                    display, err
+                    "Could not open {} file ({}): {}",
+                    kind.to_lowercase(),
+                    display,
+                    err
                 ));
             }
         }
 
-        if !grammar.is_file() {
-            self.exit_with_error(format!("Grammar was not a file ({})", display));
+        if !path.is_file() {
+            self.exit_with_error(format!("{} was not a file ({})", kind, display));
         }
     }
 
-    /// Remove the old output directory and create a new one
+    /// Clear out the old output directory, or create a new one
     fn create_out_dir(&mut self) {
         let out_dir = self.out_dir();
         let display = out_dir.display();
@@ -336,9 +1296,11 @@ impl Context {
         }
 
         if out_dir.exists() {
-            if let Err(err) = fs::remove_dir_all(out_dir) {
+            if let Err(err) = clear_out_dir(out_dir) {
                 self.exit_with_error(format!("Could not remove old output directory: {}", err));
             }
+
+            return;
         }
 
         if let Err(err) = fs::create_dir(out_dir) {
@@ -381,11 +1343,14 @@ impl Context {
             fs::write(path, data)?;
         }
 
-        Ok(())
+        match self.opts.runtime {
+            Runtime::Copy => crate::write_runtime(&out_dir.join("build")),
+            Runtime::Crate => Ok(()),
+        }
     }
 
     /// Check that a recent version of NodeJS is installed
-    fn check_node(&mut self) {
+    fn check_node(&mut self) -> String {
         let command = Command::new("node").arg("--version").output();
         let version_regex = Regex::new(r"^v(\d+)\.").unwrap();
 
@@ -396,11 +1361,11 @@ impl Context {
             version_regex,
             16,
             ">=16.0.0",
-        );
+        )
     }
 
     /// Check that a recent version of Rust is installed
-    fn check_rust(&mut self) {
+    fn check_rust(&mut self) -> String {
         let command = Command::new("rustc")
             .args(["+stable", "--version"])
             .output();
@@ -413,10 +1378,11 @@ impl Context {
             version_regex,
             61,
             "^1.61.0",
-        );
+        )
     }
 
-    /// Run version command and use a regex to check its output
+    /// Run version command and use a regex to check its output, returning the
+    /// reported version
     fn check_command_installation(
         &mut self,
         result: io::Result<Output>,
@@ -425,7 +1391,7 @@ impl Context {
         version_regex: Regex,
         expected_version: u32,
         expected_version_spec: &str,
-    ) {
+    ) -> String {
         let result = match result {
             Ok(result) => result,
             Err(err) => {
@@ -452,6 +1418,8 @@ impl Context {
             );
         }
 
+        let reported_version = String::from_utf8_lossy(&result.stdout).trim().to_string();
+
         let version = version_regex.captures(&result.stdout).and_then(|captures| {
             let version_match = captures.get(1).unwrap();
             let version_str = String::from_utf8_lossy(version_match.as_bytes());
@@ -466,22 +1434,24 @@ impl Context {
                 ));
             }
             None => {
-                let version = String::from_utf8_lossy(&result.stdout);
                 self.print_warn(format!(
                     "Could not parse {} version ({})",
-                    name,
-                    version.trim()
+                    name, reported_version
                 ));
             }
             Some(_) => {}
         }
+
+        reported_version
     }
 
     fn executable_file(&self) -> PathBuf {
+        let name = self.opts.executable_name.as_deref().unwrap_or("parser");
+
         if cfg!(windows) {
-            self.out_dir().join("build/parser.exe")
+            self.out_dir().join("build").join(format!("{}.exe", name))
         } else {
-            self.out_dir().join("build/parser")
+            self.out_dir().join("build").join(name)
         }
     }
 
@@ -497,6 +1467,24 @@ impl Context {
         self.out_dir().join("build/loader.js")
     }
 
+    /// Where a grammar script read from stdin is written while it runs
+    fn stdin_grammar_file(&self) -> PathBuf {
+        self.out_dir().join("build/grammar.js")
+    }
+
+    /// Whether the grammar script is read from stdin rather than a file
+    fn grammar_from_stdin(&self) -> bool {
+        self.opts.grammar.as_deref() == Some(Path::new("-"))
+    }
+
+    fn states_file(&self) -> PathBuf {
+        self.out_dir().join("states.json")
+    }
+
+    fn tree_schema_file(&self) -> PathBuf {
+        self.out_dir().join("tree-schema.json")
+    }
+
     fn ir_file(&self) -> PathBuf {
         self.out_dir().join("build/ir.json")
     }
@@ -561,14 +1549,14 @@ impl Context {
     /// Print an error message and exit
     fn exit_with_error(&mut self, message: impl AsRef<str>) -> ! {
         self.print_error(message);
-        exit(1);
+        exit(EXIT_TOOL_ERROR);
     }
 
     /// Print an error message and command output, then exit
     fn exit_with_error_and_output(&mut self, message: impl AsRef<str>, output: &Output) -> ! {
         self.print_error(message);
         self.print_output(output);
-        exit(1);
+        exit(EXIT_TOOL_ERROR);
     }
 
     fn print_error(&mut self, message: impl AsRef<str>) {
@@ -587,6 +1575,12 @@ impl Context {
         self.print_reset();
     }
 
+    fn print_info_heading(&mut self) {
+        self.print_color(Color::Cyan, true);
+        self.print("info: ");
+        self.print_reset();
+    }
+
     fn print_warn_heading(&mut self) {
         self.print_color(Color::Yellow, true);
         self.print("warn: ");
@@ -618,3 +1612,52 @@ impl Context {
         let _ = self.stderr.set_color(&reset_color);
     }
 }
+
+/// Removes everything from an old output directory except the parsers compiled
+/// into its build directory, so that parsers compiled under different names
+/// with `--executable-name` survive each other's builds
+fn clear_out_dir(out_dir: &Path) -> io::Result<()> {
+    let build_dir = out_dir.join("build");
+
+    for entry in fs::read_dir(out_dir)? {
+        let path = entry?.path();
+
+        if path == build_dir && path.is_dir() {
+            for entry in fs::read_dir(&path)? {
+                let path = entry?.path();
+
+                if path.is_dir() {
+                    fs::remove_dir_all(path)?;
+                } else if path
+                    .file_name()
+                    .is_some_and(|name| BUILD_SOURCES.iter().any(|source| name == *source))
+                {
+                    fs::remove_file(path)?;
+                }
+            }
+        } else if path.is_dir() {
+            fs::remove_dir_all(path)?;
+        } else {
+            fs::remove_file(path)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether a string names a file directly inside a directory, rather than a path to elsewhere
+fn is_file_name(string: &str) -> bool {
+    !string.is_empty() && string != "." && string != ".." && !string.contains(['/', '\\'])
+}
+
+/// Whether a string can be used as a Rust identifier
+fn is_identifier(string: &str) -> bool {
+    let mut chars = string.chars();
+
+    match chars.next() {
+        Some(char) if char.is_ascii_alphabetic() || char == '_' => {}
+        _ => return false,
+    }
+
+    string != "_" && chars.all(|char| char.is_ascii_alphanumeric() || char == '_')
+}
@@ -0,0 +1,104 @@
+use std::fmt::{self, Display, Formatter};
+
+use serde::Deserialize;
+
+use crate::core::first_set::FirstSet;
+use crate::core::series::{describe_symbol, Class};
+use crate::core::{Error, InstructionId, Parser};
+
+/// A relation between the first sets of two rules, which the grammar asserts
+/// holds and is checked at build time
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Assertion {
+    pub first: InstructionId,
+    pub relation: Relation,
+    pub second: InstructionId,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Relation {
+    /// No match of either rule can begin like a match of the other
+    Disjoint,
+    /// Every match of the first rule begins like some match of the second
+    Subset,
+    /// Both rules are subsets of each other
+    Equal,
+}
+
+impl Parser {
+    /// Checks every assertion made by the grammar, failing with a description
+    /// of the first that does not hold
+    pub(super) fn check_assertions(&self) -> Result<(), Error> {
+        if self.assertions.is_empty() {
+            return Ok(());
+        }
+
+        let first_sets = self.first_sets();
+
+        for assertion in &self.assertions {
+            let first = &first_sets[&assertion.first];
+            let second = &first_sets[&assertion.second];
+
+            let first_name = self.rule_name(assertion.first);
+            let second_name = self.rule_name(assertion.second);
+
+            let violation = match assertion.relation {
+                Relation::Disjoint => Self::disjoint_violation(first, second),
+                Relation::Subset => Self::subset_violation(first, &first_name, second),
+                Relation::Equal => Self::subset_violation(first, &first_name, second)
+                    .or_else(|| Self::subset_violation(second, &second_name, first)),
+            };
+
+            if let Some(violation) = violation {
+                return Err(Error::AssertionFailed(format!(
+                    "{} is not {} {}, {}",
+                    first_name, assertion.relation, second_name, violation
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn disjoint_violation(first: &FirstSet, second: &FirstSet) -> Option<String> {
+        if first.empty && second.empty {
+            return Some(String::from("both can match empty input"));
+        }
+
+        Class::intersection(&first.class, &second.class)
+            .first_symbol()
+            .map(|symbol| format!("both can start with {}", describe_symbol(symbol)))
+    }
+
+    fn subset_violation(first: &FirstSet, name: &str, second: &FirstSet) -> Option<String> {
+        if first.empty && !second.empty {
+            return Some(format!("{} can match empty input", name));
+        }
+
+        Class::difference(&first.class, &second.class)
+            .first_symbol()
+            .map(|symbol| format!("{} can start with {}", name, describe_symbol(symbol)))
+    }
+
+    /// Names the rules an instruction belongs to for error messages
+    fn rule_name(&self, id: InstructionId) -> String {
+        let names = &self.debug_symbols[&id].names;
+
+        if names.is_empty() {
+            format!("instruction {}", id.0)
+        } else {
+            names.iter().cloned().collect::<Vec<_>>().join("/")
+        }
+    }
+}
+
+impl Display for Relation {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Relation::Disjoint => write!(f, "disjoint from"),
+            Relation::Subset => write!(f, "a subset of"),
+            Relation::Equal => write!(f, "equal to"),
+        }
+    }
+}
@@ -7,7 +7,7 @@ use crate::core::{Instruction, Parser};
 
 impl Parser {
     /// Computes the instruction characters of the parser
-    pub(super) fn characterize(&self) -> HashMap<InstructionId, Character> {
+    pub fn characterize(&self) -> HashMap<InstructionId, Character> {
         self.patch_characters(HashMap::new(), self.instructions().map(|(id, _)| id))
     }
 
@@ -42,7 +42,14 @@ impl Parser {
                 Instruction::Cache(target, _) | Instruction::Delegate(target) => {
                     self.characterize_delegate_like(target, states)
                 }
+                Instruction::Grow(target, _) => self.characterize_grow(target, states),
+                Instruction::Repeat(target) => self.characterize_repeat(target, states),
+                Instruction::SepBy { item, sep, min, .. } => {
+                    self.characterize_sep_by(item, sep, min, states)
+                }
                 Instruction::Series(series) => self.characterize_series(series),
+                Instruction::ConsumeUntil(_) => Self::characterize_consume_until(),
+                Instruction::Balanced(open, close) => self.characterize_balanced(open, close),
             },
         )
     }
@@ -155,6 +162,20 @@ impl Parser {
         }
     }
 
+    /// Growth points match whatever their target last matched, but the first
+    /// time around the recursive call fails, so they can fail even if their
+    /// target appears not to
+    fn characterize_grow(
+        &self,
+        target: InstructionId,
+        states: &FixedPointStates<Character>,
+    ) -> Character {
+        Character {
+            fallible: true,
+            ..self.characterize_delegate_like(target, states)
+        }
+    }
+
     fn characterize_delegate_like(
         &self,
         target: InstructionId,
@@ -171,6 +192,45 @@ impl Parser {
         }
     }
 
+    fn characterize_repeat(
+        &self,
+        target: InstructionId,
+        states: &FixedPointStates<Character>,
+    ) -> Character {
+        let target = states[target];
+
+        Character {
+            transparent: target.fallible || target.transparent,
+            antitransparent: target.antitransparent,
+            fallible: false,
+            label_prone: target.label_prone,
+            error_prone: target.error_prone,
+        }
+    }
+
+    /// Only the first item can match without consuming input, since later
+    /// iterations that consume nothing end the list
+    fn characterize_sep_by(
+        &self,
+        item: InstructionId,
+        sep: InstructionId,
+        min: u32,
+        states: &FixedPointStates<Character>,
+    ) -> Character {
+        let item = states[item];
+        let sep = states[sep];
+
+        let continues = item.possible() && sep.possible();
+
+        Character {
+            transparent: (min == 0 && item.fallible) || (min <= 1 && item.transparent),
+            antitransparent: item.antitransparent || (continues && sep.antitransparent),
+            fallible: min > 1 || (min == 1 && item.fallible),
+            label_prone: item.label_prone || (continues && sep.label_prone),
+            error_prone: item.error_prone || (continues && sep.error_prone),
+        }
+    }
+
     fn characterize_series(&self, series: SeriesId) -> Character {
         let series = &self.series[series];
 
@@ -178,6 +238,36 @@ impl Parser {
             transparent: series.is_empty(),
             antitransparent: !series.is_empty() && !series.is_never(),
             fallible: !series.is_empty(),
+            // Series with a kind produce a token, which must be kept like a label
+            label_prone: series.kind().is_some(),
+            error_prone: false,
+        }
+    }
+
+    /// Scanning stops either immediately or after consuming some input, and
+    /// reaching the end of input counts as a match
+    fn characterize_consume_until() -> Character {
+        Character {
+            transparent: true,
+            antitransparent: true,
+            fallible: false,
+            label_prone: false,
+            error_prone: false,
+        }
+    }
+
+    /// A balanced match always consumes at least the closing series, and can
+    /// only match at all if both series can consume input
+    fn characterize_balanced(&self, open: SeriesId, close: SeriesId) -> Character {
+        let consumes = |series: SeriesId| {
+            let series = &self.series[series];
+            !series.is_empty() && !series.is_never()
+        };
+
+        Character {
+            transparent: false,
+            antitransparent: consumes(open) && consumes(close),
+            fallible: true,
             label_prone: false,
             error_prone: false,
         }
@@ -197,6 +287,7 @@ pub struct Character {
     /// An instruction is fallible if it can fail to match
     pub fallible: bool,
     /// An instruction is label prone if it can successfully match with a label
+    /// or token
     pub label_prone: bool,
     /// An instruction is label prone if it can successfully match with an
     /// error
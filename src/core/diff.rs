@@ -0,0 +1,186 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display, Formatter};
+
+use serde::Serialize;
+
+use crate::core::{Instruction, Parser};
+use crate::store::{Store, StoreKey};
+
+/// A part of a parser that can differ from another parser
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub enum DiffItem {
+    Start,
+    Instruction(usize),
+    Series(usize),
+    Label(usize),
+    Expected(usize),
+}
+
+/// A single way in which two parsers differ. An item missing its old
+/// description was added and one missing its new description was removed
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub struct Difference {
+    pub item: DiffItem,
+    /// The names of the rules the item belongs to, on either side
+    pub names: BTreeSet<String>,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+impl Parser {
+    /// Compares two prepared parsers item by item. Preparation sorts every
+    /// store into a canonical order, so identical grammars compiled with the
+    /// same settings never differ
+    pub fn diff(&self, other: &Parser) -> Vec<Difference> {
+        let mut differences = Vec::new();
+
+        let old_start = BTreeMap::from([(0, describe(&self.start))]);
+        let new_start = BTreeMap::from([(0, describe(&other.start))]);
+        compare(&old_start, &new_start, &mut differences, |_| {
+            DiffItem::Start
+        });
+
+        compare(
+            &describe_store(&self.instructions),
+            &describe_store(&other.instructions),
+            &mut differences,
+            DiffItem::Instruction,
+        );
+
+        compare(
+            &describe_store(&self.series),
+            &describe_store(&other.series),
+            &mut differences,
+            DiffItem::Series,
+        );
+
+        compare(
+            &describe_store(&self.labels),
+            &describe_store(&other.labels),
+            &mut differences,
+            DiffItem::Label,
+        );
+
+        compare(
+            &describe_store(&self.expecteds),
+            &describe_store(&other.expecteds),
+            &mut differences,
+            DiffItem::Expected,
+        );
+
+        let old_names = self.item_names();
+        let new_names = other.item_names();
+
+        for difference in &mut differences {
+            for names in [&old_names, &new_names] {
+                if let Some(item_names) = names.get(&difference.item) {
+                    difference.names.extend(item_names.iter().cloned());
+                }
+            }
+        }
+
+        differences
+    }
+
+    /// The rule names of every item, where resources take the names of the
+    /// instructions that use them
+    fn item_names(&self) -> BTreeMap<DiffItem, BTreeSet<String>> {
+        let mut result = BTreeMap::<_, BTreeSet<_>>::new();
+
+        for (id, instruction) in self.instructions() {
+            let names = self.debug_symbols[&id].names.iter().cloned();
+
+            let resource = match instruction {
+                Instruction::Label(_, label) => Some(DiffItem::Label(label.into_usize())),
+                Instruction::Error(_, expected) => Some(DiffItem::Expected(expected.into_usize())),
+                _ => None,
+            };
+
+            let series = instruction
+                .series()
+                .map(|series| DiffItem::Series(series.into_usize()));
+
+            for resource in resource.into_iter().chain(series) {
+                result.entry(resource).or_default().extend(names.clone());
+            }
+
+            if id == self.start {
+                result
+                    .entry(DiffItem::Start)
+                    .or_default()
+                    .extend(names.clone());
+            }
+
+            result
+                .entry(DiffItem::Instruction(id.into_usize()))
+                .or_default()
+                .extend(names);
+        }
+
+        result
+    }
+}
+
+fn describe(value: &impl Serialize) -> String {
+    serde_json::to_string(value).unwrap()
+}
+
+fn describe_store<K: StoreKey, V: Serialize>(store: &Store<K, V>) -> BTreeMap<usize, String> {
+    store
+        .iter()
+        .map(|(id, value)| (id.into_usize(), describe(value)))
+        .collect()
+}
+
+fn compare(
+    old: &BTreeMap<usize, String>,
+    new: &BTreeMap<usize, String>,
+    differences: &mut Vec<Difference>,
+    item: impl Fn(usize) -> DiffItem,
+) {
+    let keys = old.keys().chain(new.keys()).collect::<BTreeSet<_>>();
+
+    for key in keys {
+        let old = old.get(key);
+        let new = new.get(key);
+
+        if old != new {
+            differences.push(Difference {
+                item: item(*key),
+                names: BTreeSet::new(),
+                old: old.cloned(),
+                new: new.cloned(),
+            });
+        }
+    }
+}
+
+impl Display for DiffItem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DiffItem::Start => write!(f, "start"),
+            DiffItem::Instruction(id) => write!(f, "instruction {}", id),
+            DiffItem::Series(id) => write!(f, "series {}", id),
+            DiffItem::Label(id) => write!(f, "label {}", id),
+            DiffItem::Expected(id) => write!(f, "expected {}", id),
+        }
+    }
+}
+
+impl Display for Difference {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match (&self.old, &self.new) {
+            (None, Some(new)) => write!(f, "+ {}: {}", self.item, new)?,
+            (Some(old), None) => write!(f, "- {}: {}", self.item, old)?,
+            (Some(old), Some(new)) => write!(f, "~ {}: {} => {}", self.item, old, new)?,
+            (None, None) => write!(f, "  {}", self.item)?,
+        }
+
+        if !self.names.is_empty() {
+            let names = self.names.iter().cloned().collect::<Vec<_>>();
+            write!(f, " ({})", names.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
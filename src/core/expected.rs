@@ -7,6 +7,10 @@ use crate::core::series::Series;
 use crate::core::{Instruction, InstructionId, Parser};
 use crate::store::StoreKey;
 
+/// Classes matching more symbols than this are not listed as expected
+/// literals, which would otherwise be impractical for wide symbols
+const MAX_EXPECTED_SYMBOLS: u64 = 256;
+
 /// Before expecteds are computer for all error rules, these actually point to
 /// instructions
 #[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize)]
@@ -22,56 +26,73 @@ impl StoreKey for ExpectedId {
     }
 }
 
-#[derive(Debug, Eq, PartialEq, Hash, Serialize)]
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize)]
 pub struct Expected {
     labels: BTreeSet<String>,
-    literals: BTreeSet<Vec<u8>>,
+    literals: BTreeSet<Vec<u32>>,
 }
 
 impl Expected {
     fn append_series(&mut self, series: &Series) {
-        let literal = self.linear_series_prefix(series);
+        let literal = series.literal_prefix();
         if !literal.is_empty() {
             self.literals.insert(literal);
             return;
         }
 
-        if let Some(class) = series.classes().get(0) {
-            if !class.negated() {
-                for (lower, upper) in class.ranges() {
-                    for char in *lower..=*upper {
-                        self.literals.insert(vec![char]);
-                    }
+        if let Some(class) = series.classes().first() {
+            if !class.negated() && class.symbol_count() <= MAX_EXPECTED_SYMBOLS {
+                for symbol in class.symbols() {
+                    self.literals.insert(vec![symbol]);
                 }
             }
         }
     }
 
-    fn linear_series_prefix(&self, series: &Series) -> Vec<u8> {
-        let mut buffer = Vec::new();
+    pub fn labels(&self) -> impl Iterator<Item = &str> + '_ {
+        self.labels.iter().map(|string| string.as_str())
+    }
 
-        for class in series.classes() {
-            if class.negated() || class.ranges().len() != 1 {
-                return buffer;
-            }
+    pub fn literals(&self) -> impl Iterator<Item = &[u32]> + '_ {
+        self.literals.iter().map(|buffer| buffer.as_slice())
+    }
 
-            let (lower, upper) = class.ranges()[0];
-            if lower != upper {
-                return buffer;
+    /// A name derived only from the contents of the expected, so that it is the
+    /// same across builds regardless of how expecteds were numbered
+    pub fn stable_name(&self) -> String {
+        // FNV-1a, which unlike the standard library's hasher is guaranteed to
+        // be stable across releases
+        let mut hash = 0xcbf29ce484222325u64;
+
+        let mut write = |bytes: &[u8]| {
+            for byte in (bytes.len() as u64).to_le_bytes().iter().chain(bytes) {
+                hash ^= *byte as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
             }
+        };
 
-            buffer.push(lower);
+        write(&self.labels.len().to_le_bytes());
+        for label in &self.labels {
+            write(label.as_bytes());
         }
 
-        buffer
-    }
-
-    pub fn labels(&self) -> impl Iterator<Item = &str> + '_ {
-        self.labels.iter().map(|string| string.as_str())
-    }
+        write(&self.literals.len().to_le_bytes());
+        for literal in &self.literals {
+            // Literals that fit in bytes are hashed as bytes, so that names
+            // don't depend on the symbol width
+            let bytes: Vec<u8> = match literal.iter().map(|symbol| u8::try_from(*symbol)).collect()
+            {
+                Ok(bytes) => bytes,
+                Err(_) => literal
+                    .iter()
+                    .flat_map(|symbol| symbol.to_le_bytes())
+                    .collect(),
+            };
+
+            write(&bytes);
+        }
 
-    pub fn literals(&self) -> impl Iterator<Item = &[u8]> + '_ {
-        self.literals.iter().map(|buffer| buffer.as_slice())
+        format!("E{:016x}", hash)
     }
 }
 
@@ -107,7 +128,12 @@ impl Parser {
         let instruction = self.instructions[id];
 
         match instruction {
-            Instruction::Seq(first, second) => {
+            Instruction::Seq(first, second)
+            | Instruction::SepBy {
+                item: first,
+                sep: second,
+                ..
+            } => {
                 self.expected_at(first, result, characters, visited);
 
                 if characters[&first].transparent {
@@ -131,20 +157,58 @@ impl Parser {
             }
             Instruction::Error(target, _)
             | Instruction::Cache(target, _)
-            | Instruction::Delegate(target) => {
+            | Instruction::Grow(target, _)
+            | Instruction::Delegate(target)
+            | Instruction::Repeat(target) => {
                 self.expected_at(target, result, characters, visited);
             }
             Instruction::Label(_, label) => {
                 let label = self.labels[label].clone();
                 result.labels.insert(label);
             }
-            Instruction::Series(series) => {
+            Instruction::Series(series) | Instruction::Balanced(series, _) => {
                 let series = &self.series[series];
                 result.append_series(series);
             }
-            Instruction::NotAhead(_) => {}
+            Instruction::NotAhead(_) | Instruction::ConsumeUntil(_) => {}
         }
 
         visited.remove(&id);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::Expected;
+
+    fn expected(labels: &[&str], literals: &[&[u8]]) -> Expected {
+        Expected {
+            labels: labels.iter().map(|label| label.to_string()).collect(),
+            literals: literals
+                .iter()
+                .map(|literal| literal.iter().map(|byte| *byte as u32).collect())
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn stable_name_is_fixed() {
+        let expected = expected(&["number"], &[b"null"]);
+        assert_eq!(expected.stable_name(), "Ea2f6bb4a7ab3164b");
+    }
+
+    #[test]
+    fn stable_name_distinguishes_labels_from_literals() {
+        let label = expected(&["ab"], &[]);
+        let literal = expected(&[], &[b"ab"]);
+        assert_ne!(label.stable_name(), literal.stable_name());
+
+        let empty = Expected {
+            labels: BTreeSet::new(),
+            literals: BTreeSet::new(),
+        };
+        assert_ne!(empty.stable_name(), label.stable_name());
+    }
+}
@@ -0,0 +1,175 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::core::fixed_point::FixedPointStates;
+use crate::core::series::{Class, SeriesId};
+use crate::core::{Instruction, InstructionId, Parser};
+
+impl Parser {
+    /// Computes the first set of every instruction in the parser
+    pub(super) fn first_sets(&self) -> HashMap<InstructionId, FirstSet> {
+        let default = FirstSet {
+            class: self.symbol_class(false),
+            empty: false,
+        };
+
+        self.solve_fixed_point(
+            HashMap::new(),
+            self.instructions().map(|(id, _)| id),
+            default,
+            |_, instruction, states| match instruction {
+                Instruction::Seq(first, second) => Self::first_set_seq(first, second, states),
+                Instruction::Choice(first, second) | Instruction::FirstChoice(first, second) => {
+                    Self::first_set_choice(first, second, states)
+                }
+                Instruction::NotAhead(_) => FirstSet {
+                    class: self.symbol_class(false),
+                    empty: true,
+                },
+                Instruction::Error(target, _)
+                | Instruction::Label(target, _)
+                | Instruction::Cache(target, _)
+                | Instruction::Grow(target, _)
+                | Instruction::Delegate(target) => states[target].clone(),
+                Instruction::Repeat(target) => FirstSet {
+                    class: states[target].class.clone(),
+                    empty: true,
+                },
+                Instruction::SepBy { item, sep, min, .. } => {
+                    let list = Self::first_set_seq(item, sep, states);
+
+                    FirstSet {
+                        class: list.class,
+                        empty: min == 0 || states[item].empty,
+                    }
+                }
+                Instruction::Series(series) => self.first_set_series(series),
+                Instruction::Balanced(open, _) => FirstSet {
+                    empty: false,
+                    ..self.first_set_series(open)
+                },
+                Instruction::ConsumeUntil(_) => FirstSet {
+                    class: self.symbol_class(true),
+                    empty: true,
+                },
+            },
+        )
+    }
+
+    fn first_set_seq(
+        first: InstructionId,
+        second: InstructionId,
+        states: &FixedPointStates<FirstSet>,
+    ) -> FirstSet {
+        let first = &states[first];
+        let second = &states[second];
+
+        let class = if first.empty {
+            Class::union(&first.class, &second.class)
+        } else {
+            first.class.clone()
+        };
+
+        FirstSet {
+            class,
+            empty: first.empty && second.empty,
+        }
+    }
+
+    fn first_set_choice(
+        first: InstructionId,
+        second: InstructionId,
+        states: &FixedPointStates<FirstSet>,
+    ) -> FirstSet {
+        let first = &states[first];
+        let second = &states[second];
+
+        FirstSet {
+            class: Class::union(&first.class, &second.class),
+            empty: first.empty || second.empty,
+        }
+    }
+
+    fn first_set_series(&self, series: SeriesId) -> FirstSet {
+        let series = &self.series[series];
+
+        match series.classes().first() {
+            _ if series.is_never() => FirstSet {
+                class: self.symbol_class(false),
+                empty: false,
+            },
+            Some(class) => FirstSet {
+                class: class.clone(),
+                empty: false,
+            },
+            None => FirstSet {
+                class: self.symbol_class(false),
+                empty: true,
+            },
+        }
+    }
+
+    /// Finds the alternatives of nested first choices in the order they are
+    /// tried, if each of them must consume input and no two can start with
+    /// the same symbol. At most one such alternative can match at any
+    /// position, so the order they are tried in doesn't matter and the next
+    /// symbol decides which to try
+    pub(super) fn disjoint_alternatives(
+        &self,
+        id: InstructionId,
+        first_sets: &HashMap<InstructionId, FirstSet>,
+    ) -> Option<Vec<InstructionId>> {
+        let mut alternatives = Vec::new();
+        let mut visited = HashSet::new();
+        let mut pending = vec![id];
+
+        while let Some(current) = pending.pop() {
+            match self.instructions[current] {
+                Instruction::FirstChoice(first, second) => {
+                    if !visited.insert(current) {
+                        return None;
+                    }
+
+                    pending.push(second);
+                    pending.push(first);
+                }
+                _ => alternatives.push(current),
+            }
+        }
+
+        let mut seen = self.symbol_class(false);
+
+        for alternative in &alternatives {
+            let first_set = &first_sets[alternative];
+
+            if first_set.empty {
+                return None;
+            }
+
+            if Class::intersection(&seen, &first_set.class)
+                .first_symbol()
+                .is_some()
+            {
+                return None;
+            }
+
+            seen = Class::union(&seen, &first_set.class);
+        }
+
+        Some(alternatives)
+    }
+
+    /// An empty or negated empty class over the symbols of the parser
+    pub(super) fn symbol_class(&self, negated: bool) -> Class {
+        Class::new(negated).with_max(self.symbols.max()).unwrap()
+    }
+}
+
+/// The first set of an instruction conservatively approximates how its
+/// matches can begin
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct FirstSet {
+    /// The symbols a match consuming input can start with
+    pub class: Class,
+    /// Whether the instruction can match without consuming input
+    pub empty: bool,
+}
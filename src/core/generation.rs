@@ -1,14 +1,22 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::mem;
 
+use crate::core::expected::ExpectedId;
 use crate::core::series::{Class, Series};
-use crate::core::{Instruction, InstructionId, Parser};
+use crate::core::{
+    GenerationSettings, Instruction, InstructionId, ModuleConfig, Parser, SymbolWidth,
+};
 use crate::output::{Codegen, Statements};
+use peg_pack_runtime::{GENERATE_MACRO, RUNTIME_VERSION};
+
+/// Classes whose comparison trees would cost more than this are matched with
+/// a lookup table instead
+const MAX_CLASS_TREE_COST: u32 = 4;
 
 #[derive(Copy, Clone)]
-struct State {
-    id: InstructionId,
-    stage: usize,
+pub(super) struct State {
+    pub id: InstructionId,
+    pub stage: usize,
 }
 
 impl State {
@@ -17,12 +25,59 @@ impl State {
     }
 }
 
+/// The number of functions generated on behalf of a rule
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct RuleSize {
+    /// The name of the rule, or `<anonymous>` for instructions that do not
+    /// belong to a named rule
+    pub rule: String,
+    pub states: usize,
+    /// Functions matching and scanning for series
+    pub series_functions: usize,
+    /// Functions matching the classes within series
+    pub class_functions: usize,
+}
+
+impl RuleSize {
+    pub fn functions(&self) -> usize {
+        self.states + self.series_functions + self.class_functions
+    }
+}
+
+/// How large an optimized parser is, counting what the generated code is
+/// made of
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ParserSize {
+    pub instructions: usize,
+    pub series: usize,
+    pub labels: usize,
+    pub expecteds: usize,
+    pub states: usize,
+    pub cache_slots: usize,
+}
+
+/// The source files making up a generated parser
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct GeneratedCode {
+    /// The root of the parser, which declares every other file as a module
+    pub root: String,
+    /// The paths of the other files, relative to the root, and their contents
+    pub modules: Vec<(String, String)>,
+}
+
 impl Parser {
-    pub fn generate(self) -> String {
+    pub fn generate(
+        mut self,
+        settings: GenerationSettings,
+        module: &ModuleConfig,
+    ) -> GeneratedCode {
+        let start_expected = self.insert_start_expected();
         let mut codegen = Codegen::new();
 
-        codegen.line("//! A generated parser. See [`parse`] for more information.");
-        codegen.newline();
+        if module.inner_docs {
+            codegen.line("//! A generated parser. See [`parse`] for more information.");
+            codegen.newline();
+        }
 
         codegen.line("// Generated by peg-pack, do not modify");
         codegen.line(&format!(
@@ -34,25 +89,155 @@ impl Parser {
 
         codegen.newline();
 
-        codegen.line("#[path = \"build/runtime/mod.rs\"]");
-        codegen.line("mod runtime;");
-        codegen.line("use runtime::*;");
+        if module.runtime_crate {
+            codegen.line(&format!(
+                "// Requires peg-pack-runtime {}",
+                env!("CARGO_PKG_VERSION")
+            ));
+            codegen.line(&format!("use peg_pack_runtime as {};", module.module_name));
+        } else {
+            codegen.line(&format!("#[path = {:?}]", module.runtime_path));
+            codegen.line(&format!("mod {};", module.module_name));
+        }
+
+        codegen.line(&format!("use {}::*;", module.module_name));
+        codegen.newline();
+
+        codegen.line("/// The version of peg-pack the parser was generated by.");
+        codegen.line(&format!(
+            "pub const GENERATED_VERSION: &str = {:?};",
+            RUNTIME_VERSION
+        ));
+        codegen.line(&format!(
+            "const _: () = assert!(runtime_version_is(GENERATED_VERSION), {:?});",
+            "The parser was generated by a different version of peg-pack than its runtime, \
+             regenerate the parser and runtime together"
+        ));
+        codegen.newline();
+
+        // The runtime crate can't export the macro, since exporting it from a copy of the runtime
+        // would clash between parsers in the same project, so it is embedded instead
+        if module.runtime_crate {
+            for line in GENERATE_MACRO.lines() {
+                codegen.line(line);
+            }
+
+            codegen.newline();
+        }
+
+        codegen.line("/// The type of symbol the parser reads from its input.");
+        codegen.line(&format!("pub type Symbol = {};", self.symbols.rust_type()));
         codegen.newline();
 
         self.generate_labels(&mut codegen);
         self.generate_expecteds(&mut codegen);
         self.generate_visualization_comment(&mut codegen);
-        self.generate_state_functions(&mut codegen);
-        self.generate_series_functions(&mut codegen);
-        self.generate_macro(&mut codegen);
 
-        codegen.finish()
+        let mut modules = Vec::new();
+        let states = self.states().collect::<Vec<_>>();
+        let dispatches = self.choice_dispatches();
+
+        match settings.split {
+            Some(parts) => {
+                for (i, states) in self.split_states(&states, parts).iter().enumerate() {
+                    let name = format!("states_{}", i);
+                    let mut part = Self::module_codegen(&mut codegen, &name);
+                    self.generate_state_functions(
+                        &mut part,
+                        states,
+                        &dispatches,
+                        settings,
+                        "pub(super) ",
+                    );
+                    modules.push((format!("parser/{}.rs", name), part.finish()));
+                }
+            }
+            None => self.generate_state_functions(&mut codegen, &states, &dispatches, settings, ""),
+        }
+
+        if settings.trace {
+            self.generate_trace_functions(&mut codegen);
+        }
+
+        if settings.coverage {
+            self.generate_coverage_functions(&mut codegen, settings);
+        }
+
+        Self::generate_backtrack_functions(&mut codegen, settings);
+
+        if settings.split.is_some() {
+            let mut part = Self::module_codegen(&mut codegen, "series");
+            self.generate_series_functions(&mut part, "pub(super) ");
+            modules.push((String::from("parser/series.rs"), part.finish()));
+        } else {
+            self.generate_series_functions(&mut codegen, "");
+        }
+
+        self.generate_cache_slots(&mut codegen);
+        self.generate_macro(&mut codegen, settings, module, start_expected);
+
+        GeneratedCode {
+            root: codegen.finish(),
+            modules,
+        }
+    }
+
+    /// Declares a module holding part of the parser in the root, and starts
+    /// generating its contents. Everything in the root, including the other
+    /// modules, is in scope within it
+    fn module_codegen(root: &mut Codegen, name: &str) -> Codegen {
+        root.line(&format!("#[path = \"parser/{}.rs\"]", name));
+        root.line(&format!("mod {};", name));
+        root.line(&format!("use {}::*;", name));
+        root.newline();
+
+        let mut codegen = Codegen::new();
+        codegen.line("// Generated by peg-pack, do not modify");
+        codegen.line("use super::*;");
+        codegen.newline();
+        codegen
+    }
+
+    /// Divides states into at most the given number of parts of roughly equal
+    /// size, keeping every stage of an instruction together
+    fn split_states(&self, states: &[State], parts: usize) -> Vec<Vec<State>> {
+        let per_part = self.instructions.len().div_ceil(parts.max(1)).max(1);
+        let mut result = Vec::<Vec<State>>::new();
+        let mut instructions = 0;
+
+        for state in states {
+            if state.stage == 0 {
+                if instructions % per_part == 0 {
+                    result.push(Vec::new());
+                }
+
+                instructions += 1;
+            }
+
+            result.last_mut().unwrap().push(*state);
+        }
+
+        result
+    }
+
+    /// Adds what the start instruction expects to the expecteds, unless it is already present,
+    /// so that failed parses can be reported as errors
+    fn insert_start_expected(&mut self) -> ExpectedId {
+        let characters = self.characterize();
+        let expected = self.compute_expected(self.start(), &characters);
+
+        let existing = self
+            .expecteds()
+            .find(|(_, other)| **other == expected)
+            .map(|(id, _)| id);
+
+        existing.unwrap_or_else(|| self.expecteds.insert(expected))
     }
 
     fn generate_visualization_comment(&self, codegen: &mut Codegen) {
         codegen.line("/*");
         for line in self.visualize().lines() {
-            codegen.line(&format!("{}", line));
+            codegen.line(line);
         }
         codegen.line("*/");
         codegen.newline();
@@ -67,9 +252,14 @@ impl Parser {
         let labels = self
             .labels()
             .map(|(_, label)| label)
-            .collect::<HashSet<_>>();
+            .collect::<BTreeSet<_>>();
 
         for label in labels {
+            if let Some(docs) = self.label_docs.get(label) {
+                let docs = docs.iter().cloned().collect::<Vec<_>>();
+                enumeration.doc(&docs.join("\n\n"));
+            }
+
             let label = self.pascal_case(label);
             enumeration.variant(&label);
         }
@@ -77,41 +267,202 @@ impl Parser {
         mem::drop(enumeration);
 
         codegen.trait_impl("LabelType", "Label");
+
+        {
+            let mut label_impl = codegen.inherent_impl("Label");
+            label_impl.doc("The name of the label as written in the grammar.");
+            let mut function = label_impl.function("pub fn name(&self) -> &'static str");
+            let mut name_match = function.match_statement("*self");
+
+            for label in self.all_labels() {
+                let pattern = format!("Label::{}", self.pascal_case(&label));
+                name_match.case_line(&pattern, &format!("{:?}", label));
+            }
+
+            mem::drop(name_match);
+            mem::drop(function);
+
+            label_impl.doc(
+                "A small number identifying the label, for code that can't use this enum such as \
+                 consumers across an FFI boundary.\n\n\
+                 Labels are numbered in order of their names, so an ID only changes when labels are \
+                 added to or removed from the grammar. The label with an ID is found at that index \
+                 of [`LABELS`], and its name at that index of [`LABEL_NAMES`].",
+            );
+
+            let mut function = label_impl.function("pub fn label_id(&self) -> u16");
+            let mut id_match = function.match_statement("*self");
+
+            for (id, label) in self.all_labels().iter().enumerate() {
+                let id = u16::try_from(id).expect("grammar has too many labels");
+                let pattern = format!("Label::{}", self.pascal_case(label));
+                id_match.case_line(&pattern, &id.to_string());
+            }
+
+            mem::drop(id_match);
+            mem::drop(function);
+
+            label_impl.doc(
+                "Whether the grammar marks the label as trivia, such as whitespace or comments. \
+                 See [`Cursor::significant_children`].",
+            );
+
+            let mut function = label_impl.function("pub fn is_trivia(&self) -> bool");
+
+            let trivia = self
+                .all_labels()
+                .iter()
+                .filter(|label| self.trivia_labels.contains(*label))
+                .map(|label| format!("Label::{}", self.pascal_case(label)))
+                .collect::<Vec<_>>();
+
+            if trivia.is_empty() {
+                function.line("false");
+            } else {
+                function.line(&format!("matches!(*self, {})", trivia.join(" | ")));
+            }
+        }
+
+        {
+            let mut display_impl = codegen.trait_impl("std::fmt::Display", "Label");
+            let mut function = display_impl
+                .function("fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result");
+            function.line("f.write_str(self.name())");
+        }
+
+        let all_labels = self
+            .all_labels()
+            .iter()
+            .map(|label| format!("Label::{}", self.pascal_case(label)))
+            .collect::<Vec<_>>();
+
+        codegen.line("/// Every label the grammar can produce, indexed by [`Label::label_id`].");
+        codegen.line("#[allow(unused)]");
+        codegen.line(&format!(
+            "pub const LABELS: &[Label] = &[{}];",
+            all_labels.join(", ")
+        ));
+        codegen.newline();
+
+        let names = self
+            .all_labels()
+            .iter()
+            .map(|label| format!("{:?}", label))
+            .collect::<Vec<_>>();
+
+        codegen.line("/// The name of every label, indexed by [`Label::label_id`].");
+        codegen.line("#[allow(unused)]");
+        codegen.line(&format!(
+            "pub const LABEL_NAMES: &[&str] = &[{}];",
+            names.join(", ")
+        ));
+        codegen.newline();
+
+        codegen.line("/// Lists every label the grammar can produce. See [`LABELS`].");
+        let mut function = codegen.function("pub fn all_labels() -> &'static [Label]");
+        function.line("LABELS");
     }
 
     fn generate_expecteds(&self, codegen: &mut Codegen) {
+        let messages = self.error_messages_used();
+
         codegen.line("#[derive(Copy, Clone, Eq, PartialEq, Hash)]");
         let mut enumeration = codegen.enumeration("Expected", false);
 
-        for (id, _) in self.expecteds() {
-            enumeration.variant(&format!("E{}", id.0));
+        for (_, expected) in self.expecteds() {
+            enumeration.variant(&expected.stable_name());
+        }
+
+        for index in 0..messages.len() {
+            enumeration.variant(&format!("Message{}", index));
         }
 
         mem::drop(enumeration);
 
+        let literals = self.generate_literal_constants(codegen);
         let mut trait_impl = codegen.trait_impl("ExpectedType<Label>", "Expected");
 
+        trait_impl.associated_type("Symbol", "Symbol");
+
         {
             let mut literals_function =
-                trait_impl.function("fn literals(&self) -> &'static [&'static [u8]]");
-            self.generate_expected_literals(&mut literals_function);
+                trait_impl.function("fn literals(&self) -> &'static [&'static [Symbol]]");
+            self.generate_expected_literals(&mut literals_function, &literals);
         }
 
         {
             let mut labels_function = trait_impl.function("fn labels(&self) -> &'static [Label]");
             self.generate_expected_labels(&mut labels_function);
         }
+
+        if !messages.is_empty() {
+            let mut message_function =
+                trait_impl.function("fn message(&self) -> Option<&'static str>");
+            let mut match_statement = message_function.match_statement("self");
+
+            for (index, message) in messages.iter().enumerate() {
+                let case = format!("Self::Message{}", index);
+                match_statement.case_line(&case, &format!("Some({:?})", message));
+            }
+
+            match_statement.case_line("_", "None");
+        }
+    }
+
+    /// The message errors of an instruction are generated with in place of
+    /// what they expected, taken from the first of its rules given one
+    fn error_message(&self, id: InstructionId) -> Option<&str> {
+        self.debug_symbols[&id]
+            .names
+            .iter()
+            .find_map(|name| self.error_messages.get(name))
+            .map(|message| message.as_str())
+    }
+
+    /// Every distinct message an error is generated with, in order. Each is
+    /// given a variant of the expected type, numbered by its index
+    fn error_messages_used(&self) -> Vec<&str> {
+        let messages = self
+            .instructions()
+            .filter(|(_, instruction)| matches!(instruction, Instruction::Error(_, _)))
+            .filter_map(|(id, _)| self.error_message(id))
+            .collect::<BTreeSet<_>>();
+
+        messages.into_iter().collect()
     }
 
-    fn generate_expected_literals(&self, block: &mut Statements) {
+    /// Declares each distinct literal expected anywhere as a constant, so that
+    /// literals shared between expecteds are only stored once. Returns the
+    /// name of the constant for each literal
+    fn generate_literal_constants<'a>(
+        &'a self,
+        codegen: &mut Codegen,
+    ) -> HashMap<&'a [u32], String> {
+        let literals = self
+            .expecteds()
+            .flat_map(|(_, expected)| expected.literals())
+            .collect::<BTreeSet<_>>();
+
+        let mut names = HashMap::new();
+
+        for (index, literal) in literals.into_iter().enumerate() {
+            let name = format!("LITERAL_{}", index);
+            codegen.line(&format!("const {}: &[Symbol] = &{:?};", name, literal));
+            names.insert(literal, name);
+        }
+
+        names
+    }
+
+    fn generate_expected_literals(&self, block: &mut Statements, names: &HashMap<&[u32], String>) {
         let mut match_statement = block.match_statement("self");
 
-        for (id, expected) in self.expecteds() {
-            let case = format!("Self::E{}", id.0);
+        for (_, expected) in self.expecteds() {
+            let case = format!("Self::{}", expected.stable_name());
 
             let literals = expected
                 .literals()
-                .map(|literal| format!("&{:?}", literal))
+                .map(|literal| names[literal].as_str())
                 .collect::<Vec<_>>();
 
             let line = format!("&[{}]", literals.join(", "));
@@ -119,7 +470,9 @@ impl Parser {
             match_statement.case_line(&case, &line);
         }
 
-        if self.expecteds().count() == 0 {
+        if !self.error_messages_used().is_empty() {
+            match_statement.case_line("_", "&[]");
+        } else if self.expecteds().count() == 0 {
             match_statement.case_line("_", "unsafe { std::hint::unreachable_unchecked() }");
         }
     }
@@ -127,8 +480,8 @@ impl Parser {
     fn generate_expected_labels(&self, block: &mut Statements) {
         let mut match_statement = block.match_statement("self");
 
-        for (id, expected) in self.expecteds() {
-            let case = format!("Self::E{}", id.0);
+        for (_, expected) in self.expecteds() {
+            let case = format!("Self::{}", expected.stable_name());
 
             let labels = expected
                 .labels()
@@ -140,7 +493,9 @@ impl Parser {
             match_statement.case_line(&case, &line);
         }
 
-        if self.expecteds().count() == 0 {
+        if !self.error_messages_used().is_empty() {
+            match_statement.case_line("_", "&[]");
+        } else if self.expecteds().count() == 0 {
             match_statement.case_line("_", "unsafe { std::hint::unreachable_unchecked() }");
         }
     }
@@ -161,19 +516,83 @@ impl Parser {
         result
     }
 
-    fn generate_state_functions(&self, codegen: &mut Codegen) {
-        for state in self.states() {
-            self.generate_state_function(codegen, state);
+    fn generate_state_functions(
+        &self,
+        codegen: &mut Codegen,
+        states: &[State],
+        dispatches: &HashMap<InstructionId, Vec<(InstructionId, Class)>>,
+        settings: GenerationSettings,
+        visibility: &str,
+    ) {
+        let rule_entries = self.rule_entries();
+        let messages = self.error_messages_used();
+
+        for state in states {
+            self.generate_state_comment(codegen, *state, &rule_entries);
+            self.generate_state_function(
+                codegen, *state, dispatches, &messages, settings, visibility,
+            );
         }
     }
 
-    fn generate_state_function(&self, codegen: &mut Codegen, state: State) {
-        self.generate_state_comment(codegen, state);
+    /// The alternatives of each first choice that can be chosen between by
+    /// the next symbol, along with the symbols each of them can start with.
+    /// Alternatives that can't start with any symbol are left out
+    pub(super) fn choice_dispatches(&self) -> HashMap<InstructionId, Vec<(InstructionId, Class)>> {
+        let mut dispatches = HashMap::new();
+
+        if !self.choice_dispatch {
+            return dispatches;
+        }
+
+        let first_sets = self.first_sets();
 
+        for (id, instruction) in self.instructions() {
+            if !matches!(instruction, Instruction::FirstChoice(_, _)) {
+                continue;
+            }
+
+            if let Some(alternatives) = self.disjoint_alternatives(id, &first_sets) {
+                let alternatives = alternatives
+                    .into_iter()
+                    .map(|alternative| (alternative, first_sets[&alternative].class.clone()))
+                    .filter(|(_, class)| !class.positive_ranges().is_empty())
+                    .collect();
+
+                dispatches.insert(id, alternatives);
+            }
+        }
+
+        dispatches
+    }
+
+    /// The first instruction belonging to each rule, which is where its
+    /// documentation is placed
+    fn rule_entries(&self) -> HashMap<&str, InstructionId> {
+        let mut entries = HashMap::new();
+
+        for (id, _) in self.instructions() {
+            for name in self.debug_symbols[&id].names.iter() {
+                entries.entry(name.as_str()).or_insert(id);
+            }
+        }
+
+        entries
+    }
+
+    fn generate_state_function(
+        &self,
+        codegen: &mut Codegen,
+        state: State,
+        dispatches: &HashMap<InstructionId, Vec<(InstructionId, Class)>>,
+        messages: &[&str],
+        settings: GenerationSettings,
+        visibility: &str,
+    ) {
         let function_name = state.function_name();
         let function_signature = format!(
-            "unsafe fn {}<I: Input + ?Sized>(ctx: &mut Context<I, Impl>)",
-            function_name
+            "{}unsafe fn {}<I: Input<Symbol> + ?Sized>(ctx: &mut Context<I, Impl>)",
+            visibility, function_name
         );
 
         let mut function = codegen.function(&function_signature);
@@ -186,6 +605,7 @@ impl Parser {
                         "state_seq_start",
                         state,
                         first,
+                        settings,
                     );
                 }
                 1 => {
@@ -194,6 +614,7 @@ impl Parser {
                         "state_seq_middle",
                         state,
                         second,
+                        settings,
                     );
                 }
                 2 => {
@@ -208,6 +629,7 @@ impl Parser {
                         "state_choice_start",
                         state,
                         first,
+                        settings,
                     );
                 }
                 1 => {
@@ -216,6 +638,7 @@ impl Parser {
                         "state_choice_middle",
                         state,
                         second,
+                        settings,
                     );
                 }
                 2 => {
@@ -224,12 +647,16 @@ impl Parser {
                 _ => unreachable!(),
             },
             Instruction::FirstChoice(first, second) => match state.stage {
+                0 if dispatches.contains_key(&state.id) => {
+                    self.generate_choice_dispatch(&mut function, &dispatches[&state.id], settings);
+                }
                 0 => {
                     self.generate_unary_continuing_dispatch(
                         &mut function,
                         "state_first_choice_start",
                         state,
                         first,
+                        settings,
                     );
                 }
                 1 => {
@@ -237,17 +664,22 @@ impl Parser {
                         &mut function,
                         "state_first_choice_middle",
                         second,
+                        settings,
                     );
                 }
                 _ => unreachable!(),
             },
             Instruction::NotAhead(id) => match state.stage {
+                0 if self.is_end_of_input_check(id, settings) => {
+                    function.line("ctx.state_end_of_input();");
+                }
                 0 => {
                     self.generate_unary_continuing_dispatch(
                         &mut function,
                         "state_not_ahead_start",
                         state,
                         id,
+                        settings,
                     );
                 }
                 1 => {
@@ -262,10 +694,19 @@ impl Parser {
                         "state_error_start",
                         state,
                         id,
+                        settings,
                     );
                 }
                 1 => {
-                    function.line(&format!("ctx.state_error_end(Expected::E{});", expected.0));
+                    let variant = match self.error_message(state.id) {
+                        Some(message) => {
+                            let index = messages.binary_search(&message).unwrap();
+                            format!("Message{}", index)
+                        }
+                        None => self.expecteds[expected].stable_name(),
+                    };
+
+                    function.line(&format!("ctx.state_error_end(Expected::{});", variant));
                 }
                 _ => unreachable!(),
             },
@@ -276,6 +717,7 @@ impl Parser {
                         "state_label_start",
                         state,
                         target,
+                        settings,
                     );
                 }
                 1 => {
@@ -290,7 +732,7 @@ impl Parser {
 
                 match state.stage {
                     0 => {
-                        let target_name = format!("state_{}_0", target.0);
+                        let target_name = self.entry_state_name(target, settings);
                         let continuation_name = format!("state_{}_{}", state.id.0, state.stage + 1);
                         function.line(&format!(
                             "ctx.state_cache_start(id, {}, {});",
@@ -303,18 +745,126 @@ impl Parser {
                     _ => unreachable!(),
                 }
             }
+            Instruction::Grow(target, id) => {
+                function.line(&format!("let id = {};", id.unwrap()));
+                let target_name = self.entry_state_name(target, settings);
+
+                match state.stage {
+                    0 => {
+                        let continuation_name = format!("state_{}_{}", state.id.0, state.stage + 1);
+                        function.line(&format!(
+                            "ctx.state_grow_start(id, {}, {});",
+                            target_name, continuation_name
+                        ));
+                    }
+                    1 => {
+                        function.line(&format!("ctx.state_grow_middle(id, {});", target_name));
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            Instruction::Repeat(target) => match state.stage {
+                0 => {
+                    self.generate_unary_continuing_dispatch(
+                        &mut function,
+                        "state_repeat_start",
+                        state,
+                        target,
+                        settings,
+                    );
+                }
+                1 => {
+                    self.generate_unary_consuming_dispatch(
+                        &mut function,
+                        "state_repeat_middle",
+                        target,
+                        settings,
+                    );
+                }
+                _ => unreachable!(),
+            },
+            Instruction::SepBy {
+                item,
+                sep,
+                allow_trailing,
+                min,
+            } => {
+                let item_name = self.entry_state_name(item, settings);
+                let sep_name = self.entry_state_name(sep, settings);
+                let state_name = |stage: usize| format!("state_{}_{}", state.id.0, stage);
+
+                // Stages after the first alternate between separators and items
+                let line = match state.stage {
+                    0 => format!("ctx.state_sep_by_start({}, {});", item_name, state_name(1)),
+                    1 => format!(
+                        "ctx.state_sep_by_first_item({}, {}, {});",
+                        sep_name,
+                        state_name(2),
+                        min
+                    ),
+                    2 => format!(
+                        "ctx.state_sep_by_separator({}, {}, {});",
+                        item_name,
+                        state_name(3),
+                        min
+                    ),
+                    3 => format!(
+                        "ctx.state_sep_by_item({}, {}, {}, {});",
+                        sep_name,
+                        state_name(2),
+                        allow_trailing,
+                        min
+                    ),
+                    _ => unreachable!(),
+                };
+
+                function.line(&line);
+            }
             Instruction::Delegate(id) => {
                 assert_eq!(state.stage, 0);
-                self.generate_unary_consuming_dispatch(&mut function, "state_delegate", id);
+                self.generate_unary_consuming_dispatch(
+                    &mut function,
+                    "state_delegate",
+                    id,
+                    settings,
+                );
             }
             Instruction::Series(series_id) => {
                 assert_eq!(state.stage, 0);
-                function.line(&format!("ctx.state_series(series_{});", series_id.0));
+
+                match self.series[series_id].kind() {
+                    Some(kind) => {
+                        let kind = self.pascal_case(&self.labels[kind]);
+                        function.line(&format!(
+                            "ctx.state_token(series_{}, Label::{});",
+                            series_id.0, kind
+                        ));
+                    }
+                    None => {
+                        function.line(&format!("ctx.state_series(series_{});", series_id.0));
+                    }
+                }
+            }
+            Instruction::ConsumeUntil(series_id) => {
+                assert_eq!(state.stage, 0);
+                function.line(&format!("ctx.state_consume_until(scan_{});", series_id.0));
+            }
+            Instruction::Balanced(open, close) => {
+                assert_eq!(state.stage, 0);
+                function.line(&format!(
+                    "ctx.state_balanced(series_{}, series_{});",
+                    open.0, close.0
+                ));
             }
         }
     }
 
-    fn generate_state_comment(&self, codegen: &mut Codegen, state: State) {
+    fn generate_state_comment(
+        &self,
+        codegen: &mut Codegen,
+        state: State,
+        rule_entries: &HashMap<&str, InstructionId>,
+    ) {
         let instruction = self.instructions[state.id];
         let symbol = &self.debug_symbols[&state.id];
 
@@ -325,6 +875,18 @@ impl Parser {
 
             codegen.line(&format!("// Rule {}: {:?}", names.join(", "), instruction));
         }
+
+        if state.stage == 0 {
+            for name in symbol.names.iter() {
+                if rule_entries[name.as_str()] != state.id {
+                    continue;
+                }
+
+                if let Some(doc) = self.rule_docs.get(name) {
+                    codegen.doc(doc);
+                }
+            }
+        }
     }
 
     fn generate_unary_continuing_dispatch(
@@ -333,8 +895,9 @@ impl Parser {
         name: &str,
         state: State,
         target: InstructionId,
+        settings: GenerationSettings,
     ) {
-        let target_name = format!("state_{}_0", target.0);
+        let target_name = self.entry_state_name(target, settings);
         let continuation_name = format!("state_{}_{}", state.id.0, state.stage + 1);
         block.line(&format!(
             "ctx.{}({}, {});",
@@ -342,25 +905,469 @@ impl Parser {
         ));
     }
 
+    /// Jumps straight to the alternative that can start with the next symbol,
+    /// or fails if there is none
+    fn generate_choice_dispatch(
+        &self,
+        block: &mut Statements,
+        alternatives: &[(InstructionId, Class)],
+        settings: GenerationSettings,
+    ) {
+        block.line("ctx.state_dispatch(|symbol| match symbol {");
+
+        for (alternative, class) in alternatives {
+            let ranges = class
+                .positive_ranges()
+                .iter()
+                .map(|(start, end)| {
+                    if start == end {
+                        start.to_string()
+                    } else {
+                        format!("{}..={}", start, end)
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            block.line(&format!(
+                "    Some({}) => Some({}),",
+                ranges.join(" | "),
+                self.entry_state_name(*alternative, settings)
+            ));
+        }
+
+        block.line("    _ => None,");
+        block.line("});");
+    }
+
     fn generate_unary_consuming_dispatch(
         &self,
         block: &mut Statements,
         name: &str,
         target: InstructionId,
+        settings: GenerationSettings,
     ) {
-        block.line(&format!("ctx.{}(state_{}_0);", name, target.0));
+        let target_name = self.entry_state_name(target, settings);
+        block.line(&format!("ctx.{}({});", name, target_name));
+    }
+
+    /// Whether an instruction matches exactly one symbol of any kind, so that
+    /// a negative lookahead for it only matches at the end of input
+    fn is_any_symbol(&self, id: InstructionId) -> bool {
+        match self.instructions[id] {
+            Instruction::Series(series) => {
+                matches!(self.series[series].classes(), [class] if class.is_any())
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether a negative lookahead of an instruction only checks for the end
+    /// of input, so it can be run as such rather than as a lookahead
+    pub(super) fn is_end_of_input_check(
+        &self,
+        target: InstructionId,
+        settings: GenerationSettings,
+    ) -> bool {
+        self.is_any_symbol(target) && !self.is_observed(target, settings)
+    }
+
+    /// Whether running an instruction is traced or counted, so it can't be
+    /// skipped in favor of a shortcut
+    fn is_observed(&self, id: InstructionId, settings: GenerationSettings) -> bool {
+        settings.trace || settings.coverage && !self.debug_symbols[&id].names.is_empty()
+    }
+
+    /// The name of the state that should be dispatched to in order to run an instruction
+    fn entry_state_name(&self, id: InstructionId, settings: GenerationSettings) -> String {
+        if settings.coverage && !self.debug_symbols[&id].names.is_empty() {
+            format!("coverage_{}_enter", id.0)
+        } else {
+            self.traced_state_name(id, settings)
+        }
+    }
+
+    /// The name of the state that runs an instruction, bypassing coverage counting
+    fn traced_state_name(&self, id: InstructionId, settings: GenerationSettings) -> String {
+        if settings.trace {
+            format!("trace_{}_enter", id.0)
+        } else {
+            format!("state_{}_0", id.0)
+        }
+    }
+
+    /// Generates a pair of states for each instruction that wrap its execution with tracing
+    fn generate_trace_functions(&self, codegen: &mut Codegen) {
+        for (id, instruction) in self.instructions() {
+            let rule = self.rule_description(id);
+
+            let description = format!("{:?}", format!("{} #{}: {:?}", rule, id.0, instruction));
+
+            let enter_signature = format!(
+                "unsafe fn trace_{}_enter<I: Input<Symbol> + ?Sized>(ctx: &mut Context<I, Impl>)",
+                id.0
+            );
+
+            let mut enter = codegen.function(&enter_signature);
+            enter.line(&format!(
+                "ctx.state_trace_enter({}, state_{}_0, trace_{}_exit);",
+                description, id.0, id.0
+            ));
+            mem::drop(enter);
+
+            let exit_signature = format!(
+                "unsafe fn trace_{}_exit<I: Input<Symbol> + ?Sized>(ctx: &mut Context<I, Impl>)",
+                id.0
+            );
+
+            let mut exit = codegen.function(&exit_signature);
+            exit.line(&format!("ctx.state_trace_exit({});", description));
+        }
+    }
+
+    /// Generates a counter for each rule along with states that bump the counters of the rules an
+    /// instruction belongs to before running it
+    fn generate_coverage_functions(&self, codegen: &mut Codegen, settings: GenerationSettings) {
+        let rules = self
+            .debug_symbols
+            .values()
+            .flat_map(|symbol| symbol.names.iter())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        let names = rules
+            .iter()
+            .map(|rule| format!("{:?}", rule))
+            .collect::<Vec<_>>();
+
+        codegen.line("#[allow(clippy::declare_interior_mutable_const)]");
+        codegen.line("const COVERAGE_ZERO: std::sync::atomic::AtomicUsize =");
+        codegen.line("    std::sync::atomic::AtomicUsize::new(0);");
+        codegen.line(&format!(
+            "static COVERAGE_COUNTS: [std::sync::atomic::AtomicUsize; {}] = [COVERAGE_ZERO; {}];",
+            rules.len(),
+            rules.len()
+        ));
+        codegen.line(&format!(
+            "static COVERAGE_RULES: [&str; {}] = [{}];",
+            rules.len(),
+            names.join(", ")
+        ));
+        codegen.newline();
+
+        codegen.line("/// Lists every rule in the grammar along with the number of times instructions belonging");
+        codegen.line("/// to it have run since the program started.");
+        codegen.line("#[allow(unused)]");
+        let mut report = codegen.function("pub fn coverage() -> Vec<(&'static str, usize)>");
+        report.line("COVERAGE_RULES");
+        report.line("    .iter()");
+        report.line("    .zip(&COVERAGE_COUNTS)");
+        report.line(
+            "    .map(|(rule, count)| (*rule, count.load(std::sync::atomic::Ordering::Relaxed)))",
+        );
+        report.line("    .collect()");
+        mem::drop(report);
+
+        for (id, _) in self.instructions() {
+            let symbol = &self.debug_symbols[&id];
+
+            if symbol.names.is_empty() {
+                continue;
+            }
+
+            let signature = format!(
+                "unsafe fn coverage_{}_enter<I: Input<Symbol> + ?Sized>(ctx: &mut Context<I, Impl>)",
+                id.0
+            );
+
+            let mut function = codegen.function(&signature);
+
+            for name in symbol.names.iter() {
+                let index = rules.binary_search(&name).unwrap();
+                function.line(&format!(
+                    "COVERAGE_COUNTS[{}].fetch_add(1, std::sync::atomic::Ordering::Relaxed);",
+                    index
+                ));
+            }
+
+            function.line(&format!(
+                "ctx.state_delegate({});",
+                self.traced_state_name(id, settings)
+            ));
+        }
+    }
+
+    /// Generates the function the runtime reports backtracking to, which counts how often the
+    /// parser backtracks to each position when tracing and otherwise does nothing
+    fn generate_backtrack_functions(codegen: &mut Codegen, settings: GenerationSettings) {
+        if !settings.trace {
+            codegen.line("#[inline(always)]");
+            codegen.function("fn record_backtrack(_position: u32)");
+            return;
+        }
+
+        codegen.line(
+            "static BACKTRACK_COUNTS: std::sync::Mutex<Vec<usize>> = std::sync::Mutex::new(Vec::new());",
+        );
+        codegen.newline();
+
+        codegen.line("/// Counts the number of times the parser has moved back over input it had matched to each");
+        codegen.line("/// position since the program started, indexed by position. Positions past the last one");
+        codegen.line("/// backtracked to are left out.");
+        codegen.line("#[allow(unused)]");
+        let mut report = codegen.function("pub fn backtracking() -> Vec<usize>");
+        report.line("BACKTRACK_COUNTS.lock().unwrap().clone()");
+        mem::drop(report);
+
+        let mut record = codegen.function("fn record_backtrack(position: u32)");
+        record.line("let mut counts = BACKTRACK_COUNTS.lock().unwrap();");
+        record.line("let position = position as usize;");
+        record.newline();
+
+        let mut grow = record.if_statement("counts.len() <= position");
+        grow.line("counts.resize(position + 1, 0);");
+        mem::drop(grow);
+
+        record.newline();
+        record.line("counts[position] += 1;");
+    }
+
+    /// Generates the names of the rules each cache slot belongs to, for profiling
+    fn generate_cache_slots(&self, codegen: &mut Codegen) {
+        let slots = self
+            .instructions()
+            .filter_map(|(id, instruction)| match instruction {
+                Instruction::Cache(_, slot) | Instruction::Grow(_, slot) => {
+                    Some((slot.unwrap(), id))
+                }
+                _ => None,
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        let names = slots
+            .values()
+            .map(|id| format!("{:?}", self.rule_description(*id)))
+            .collect::<Vec<_>>();
+
+        codegen.line(
+            "/// The rules each cache slot belongs to, indexed by slot. See [`parse_profiled`].",
+        );
+        codegen.line("#[allow(unused)]");
+        codegen.line(&format!(
+            "pub const CACHE_SLOTS: &[&str] = &[{}];",
+            names.join(", ")
+        ));
+        codegen.newline();
+    }
+
+    /// Counts the parts of the parser, for tracking its size over time
+    pub fn size(&self) -> ParserSize {
+        ParserSize {
+            instructions: self.instructions.len(),
+            series: self.series.len(),
+            labels: self.labels.len(),
+            expecteds: self.expecteds.len(),
+            states: self.states().count(),
+            cache_slots: self.cache_slots(),
+        }
+    }
+
+    /// Counts the functions that would be generated for each rule, ranked with
+    /// the largest first. Series are shared between instructions, so a series
+    /// is counted once for every rule using it
+    pub fn size_profile(&self) -> Vec<RuleSize> {
+        let mut states = HashMap::<&str, usize>::new();
+        let mut series = HashMap::<&str, BTreeSet<_>>::new();
+        let mut scanned = HashMap::<&str, BTreeSet<_>>::new();
+
+        for state in self.states() {
+            for rule in self.owning_rules(state.id) {
+                *states.entry(rule).or_default() += 1;
+
+                match self.instructions[state.id] {
+                    Instruction::Series(id) => {
+                        series.entry(rule).or_default().insert(id);
+                    }
+                    Instruction::ConsumeUntil(id) => {
+                        series.entry(rule).or_default().insert(id);
+                        scanned.entry(rule).or_default().insert(id);
+                    }
+                    Instruction::Balanced(open, close) => {
+                        series.entry(rule).or_default().extend([open, close]);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let mut result = states
+            .into_iter()
+            .map(|(rule, states)| {
+                let mut size = RuleSize {
+                    rule: String::from(rule),
+                    states,
+                    series_functions: 0,
+                    class_functions: 0,
+                };
+
+                for id in series.get(rule).into_iter().flatten() {
+                    size.series_functions += 1;
+                    size.class_functions += self.series_class_functions(&self.series[*id]);
+                }
+
+                for id in scanned.get(rule).into_iter().flatten() {
+                    size.series_functions += 1;
+
+                    if let [_] = self.series[*id].classes() {
+                        size.class_functions += 1;
+                    }
+                }
+
+                size
+            })
+            .collect::<Vec<_>>();
+
+        result.sort_by(|a, b| {
+            b.functions()
+                .cmp(&a.functions())
+                .then_with(|| a.rule.cmp(&b.rule))
+        });
+
+        result
+    }
+
+    /// The names of the rules an instruction belongs to, or `<anonymous>`
+    fn owning_rules(&self, id: InstructionId) -> Vec<&str> {
+        let names = &self.debug_symbols[&id].names;
+
+        if names.is_empty() {
+            vec!["<anonymous>"]
+        } else {
+            names.iter().map(String::as_str).collect()
+        }
     }
 
-    fn generate_series_functions(&self, codegen: &mut Codegen) {
+    /// The number of class functions generated alongside a series function
+    fn series_class_functions(&self, series: &Series) -> usize {
+        if series.is_never() || Self::series_literal(series).is_some() {
+            0
+        } else {
+            series.classes().len()
+        }
+    }
+
+    /// The literal a series matches, if every class in it is a single symbol
+    fn series_literal(series: &Series) -> Option<Vec<u32>> {
+        let literal = series.literal_prefix();
+
+        if !literal.is_empty() && literal.len() == series.classes().len() {
+            Some(literal)
+        } else {
+            None
+        }
+    }
+
+    /// The names of the rules an instruction belongs to, for diagnostics
+    fn rule_description(&self, id: InstructionId) -> String {
+        let symbol = &self.debug_symbols[&id];
+
+        if symbol.names.is_empty() {
+            String::from("<anonymous>")
+        } else {
+            symbol.names.iter().cloned().collect::<Vec<_>>().join(", ")
+        }
+    }
+
+    fn generate_series_functions(&self, codegen: &mut Codegen, visibility: &str) {
+        let scanned = self
+            .instructions()
+            .filter_map(|(_, instruction)| match instruction {
+                Instruction::ConsumeUntil(series) => Some(series),
+                _ => None,
+            })
+            .collect::<BTreeSet<_>>();
+
         for (id, series) in self.series() {
-            self.generate_series_function(codegen, id.0, series);
+            self.generate_series_function(codegen, id.0, series, visibility);
+
+            if scanned.contains(&id) {
+                self.generate_scan_function(codegen, id.0, series, visibility);
+            }
         }
     }
 
-    fn generate_series_function(&self, codegen: &mut Codegen, id: usize, series: &Series) {
+    /// Generates a function finding the distance to the next position where a
+    /// series matches, or to the end of input. Stops consisting of a single
+    /// class are tested a byte at a time without calling the series function
+    fn generate_scan_function(
+        &self,
+        codegen: &mut Codegen,
+        id: usize,
+        series: &Series,
+        visibility: &str,
+    ) {
         let signature = format!(
-            "fn series_{}<I: Input + ?Sized>(input: &I, position: u32) -> (bool, u32)",
-            id
+            "{}fn scan_{}<I: Input<Symbol> + ?Sized>(input: &I, position: u32) -> (u32, u32)",
+            visibility, id
+        );
+
+        let mut function = codegen.function(&signature);
+        function.line("let mut length = 0;");
+
+        if let [class] = series.classes() {
+            function.newline();
+
+            {
+                let mut scan =
+                    function.while_statement("let Some(char) = input.get(position + length)");
+                let mut stop = scan.if_statement(&format!("stop_{}(char)", id));
+                stop.line("break;");
+                mem::drop(stop);
+                scan.line("length += 1;");
+            }
+
+            function.newline();
+            function.line("(length, length + 1)");
+
+            mem::drop(function);
+            self.generate_class_function(codegen, &format!("stop_{}", id), class);
+            return;
+        }
+
+        function.line("let mut scan_distance = 0;");
+        function.newline();
+
+        {
+            let mut scan = function.while_statement("((position + length) as usize) < input.len()");
+            scan.line(&format!(
+                "let (matched, distance) = series_{}(input, position + length);",
+                id
+            ));
+            scan.line("scan_distance = scan_distance.max(length + distance);");
+            scan.newline();
+
+            let mut stop = scan.if_statement("matched");
+            stop.line("return (length, scan_distance);");
+            mem::drop(stop);
+
+            scan.newline();
+            scan.line("length += 1;");
+        }
+
+        function.newline();
+        function.line("(length, scan_distance.max(length + 1))");
+    }
+
+    fn generate_series_function(
+        &self,
+        codegen: &mut Codegen,
+        id: usize,
+        series: &Series,
+        visibility: &str,
+    ) {
+        let signature = format!(
+            "{}fn series_{}<I: Input<Symbol> + ?Sized>(input: &I, position: u32) -> (bool, u32)",
+            visibility, id
         );
 
         let mut function = codegen.function(&signature);
@@ -370,6 +1377,35 @@ impl Parser {
             return;
         }
 
+        // Literals are compared in one go rather than a byte at a time
+        if let Some(literal) = Self::series_literal(series) {
+            function.line(&format!("input.matches_at(position, &{:?})", literal));
+            return;
+        }
+
+        if series.classes().is_empty() {
+            function.line("(true, 0)");
+            return;
+        }
+
+        // Contiguous input is matched through a slice, where each symbol is
+        // read without a call to the input
+        let mut slice_match = function.match_statement(&format!(
+            "input.slice(position, {})",
+            series.classes().len()
+        ));
+        slice_match.case_line("Some(slice)", &format!("match_series_{}(slice, 0)", id));
+        slice_match.case_line("None", &format!("match_series_{}(input, position)", id));
+        mem::drop(slice_match);
+        mem::drop(function);
+
+        let signature = format!(
+            "fn match_series_{}<I: Input<Symbol> + ?Sized>(input: &I, position: u32) -> (bool, u32)",
+            id
+        );
+
+        let mut function = codegen.function(&signature);
+
         function.line("let mut length = 0;");
         function.newline();
 
@@ -388,26 +1424,44 @@ impl Parser {
 
         mem::drop(function);
         for (i, class) in series.classes().iter().enumerate() {
-            self.generate_class_function(codegen, id, i, class);
+            self.generate_class_function(codegen, &format!("class_{}_{}", id, i), class);
         }
     }
 
-    fn generate_class_function(
-        &self,
-        codegen: &mut Codegen,
-        series: usize,
-        index: usize,
-        class: &Class,
-    ) {
-        let signature = format!("fn class_{}_{}(char: u8) -> bool", series, index);
+    fn generate_class_function(&self, codegen: &mut Codegen, name: &str, class: &Class) {
+        let signature = format!("fn {}(char: Symbol) -> bool", name);
         let mut function = codegen.function(&signature);
 
+        // Tables are indexed by symbol, so they are only practical for bytes
+        if self.symbols == SymbolWidth::U8 && class.match_cost() > MAX_CLASS_TREE_COST {
+            self.generate_class_table(&mut function, class);
+            return;
+        }
+
         self.generate_class_ranges(&mut function, class.ranges(), class.negated());
 
         function.line(&format!("{}", class.negated()));
     }
 
-    fn generate_class_ranges(&self, block: &mut Statements, ranges: &[(u8, u8)], negated: bool) {
+    /// Matches a class with a bitset of the bytes it contains, which takes the
+    /// same time however many ranges the class has
+    fn generate_class_table(&self, block: &mut Statements, class: &Class) {
+        let mut words = [0u64; 4];
+
+        for byte in class.bytes() {
+            words[byte as usize / 64] |= 1 << (byte % 64);
+        }
+
+        let words = words
+            .iter()
+            .map(|word| format!("{:#018x}", word))
+            .collect::<Vec<_>>();
+
+        block.line(&format!("const TABLE: [u64; 4] = [{}];", words.join(", ")));
+        block.line("TABLE[(char / 64) as usize] & (1 << (char % 64)) != 0");
+    }
+
+    fn generate_class_ranges(&self, block: &mut Statements, ranges: &[(u32, u32)], negated: bool) {
         if ranges.len() <= 3 {
             for range in ranges {
                 block.line("#[allow(unused_comparisons)]");
@@ -434,37 +1488,47 @@ impl Parser {
         }
     }
 
-    fn generate_macro(&self, codegen: &mut Codegen) {
-        let max_cache_id = self
-            .instructions()
-            .flat_map(|instruction| match instruction.1 {
-                Instruction::Cache(_, id) => Some(id.unwrap()),
-                _ => None,
-            })
-            .max()
-            .unwrap_or(0);
+    fn generate_macro(
+        &self,
+        codegen: &mut Codegen,
+        settings: GenerationSettings,
+        module: &ModuleConfig,
+        start_expected: ExpectedId,
+    ) {
+        let cache_slots = self.cache_slots();
 
-        let cache_slots = max_cache_id + 1;
+        // Parsers that never look a result up are spared the cache entirely
+        let cache = if cache_slots == 0 { "NoCache" } else { "Cache" };
 
         codegen.line(&format!(
-            "generate!(state_{}_0, {});",
-            self.start().0,
-            cache_slots
+            "generate!({}, {}, {}, {}, Expected::{});",
+            module.module_name,
+            self.entry_state_name(self.start(), settings),
+            cache,
+            cache_slots,
+            self.expecteds[start_expected].stable_name()
         ));
     }
 
-    fn states(&self) -> impl Iterator<Item = State> {
+    /// Every state of the generated parser, in the order they are generated
+    pub(super) fn states(&self) -> impl Iterator<Item = State> {
         let mut states = Vec::new();
 
         for (id, instruction) in self.instructions() {
             let stages = match instruction {
+                Instruction::SepBy { .. } => 4,
                 Instruction::Seq(_, _) | Instruction::Choice(_, _) => 3,
                 Instruction::FirstChoice(_, _)
                 | Instruction::NotAhead(_)
                 | Instruction::Error(_, _)
                 | Instruction::Label(_, _)
-                | Instruction::Cache(_, _) => 2,
-                Instruction::Delegate(_) | Instruction::Series(_) => 1,
+                | Instruction::Cache(_, _)
+                | Instruction::Grow(_, _)
+                | Instruction::Repeat(_) => 2,
+                Instruction::Delegate(_)
+                | Instruction::Series(_)
+                | Instruction::ConsumeUntil(_)
+                | Instruction::Balanced(_, _) => 1,
             };
 
             for stage in 0..stages {
@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::core::first_set::FirstSet;
+use crate::core::series::Class;
+use crate::core::{Instruction, InstructionId, Parser};
+use crate::ordered_set::OrderedSet;
+
+/// The classic nullable, first and follow sets of a rule. Like the first
+/// sets used for optimization, these are conservative, so they may contain
+/// symbols that can't actually start or follow the rule
+#[derive(Serialize, Debug, Clone, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleSets {
+    /// Whether the rule can match without consuming input
+    pub nullable: bool,
+    /// The symbols a match of the rule consuming input can start with
+    pub first: Class,
+    /// The symbols that can come straight after a match of the rule
+    pub follow: Class,
+    /// Whether a match of the rule can be followed by the end of the input
+    pub followed_by_end: bool,
+}
+
+/// What can come after an instruction
+#[derive(Clone, Eq, PartialEq)]
+struct FollowSet {
+    class: Class,
+    end: bool,
+}
+
+impl FollowSet {
+    /// Adds another follow set to this one, returning whether it grew
+    fn extend(&mut self, other: &FollowSet) -> bool {
+        let class = Class::union(&self.class, &other.class);
+        let end = self.end || other.end;
+        let changed = class != self.class || end != self.end;
+
+        self.class = class;
+        self.end = end;
+        changed
+    }
+}
+
+impl Parser {
+    /// Computes the nullable, first and follow sets of every named rule. A
+    /// rule starts at the first delegate carrying its name. Whatever follows
+    /// the inside of a negative lookahead is unconstrained, so it may be
+    /// followed by any symbol
+    pub(super) fn compute_grammar_sets(&self) -> HashMap<String, RuleSets> {
+        let first_sets = self.first_sets();
+        let follow_sets = self.follow_sets(&first_sets);
+
+        let mut rules = HashMap::new();
+
+        for (id, instruction) in self.instructions() {
+            if !matches!(instruction, Instruction::Delegate(_)) {
+                continue;
+            }
+
+            for name in self.debug_symbols[&id].names.iter() {
+                if rules.contains_key(name) {
+                    continue;
+                }
+
+                let first_set = &first_sets[&id];
+                let follow_set = follow_sets.get(&id);
+
+                let sets = RuleSets {
+                    nullable: first_set.empty,
+                    first: first_set.class.clone(),
+                    follow: follow_set
+                        .map(|follow_set| follow_set.class.clone())
+                        .unwrap_or_else(|| self.symbol_class(false)),
+                    followed_by_end: follow_set.is_some_and(|follow_set| follow_set.end),
+                };
+
+                rules.insert(name.clone(), sets);
+            }
+        }
+
+        rules
+    }
+
+    /// Computes what can follow each instruction reachable from the start,
+    /// by pushing the follow set of each instruction down to its successors
+    /// until nothing changes. The start is followed by the end of the input
+    fn follow_sets(
+        &self,
+        first_sets: &HashMap<InstructionId, FirstSet>,
+    ) -> HashMap<InstructionId, FollowSet> {
+        let nothing = FollowSet {
+            class: self.symbol_class(false),
+            end: false,
+        };
+
+        let anything = FollowSet {
+            class: self.symbol_class(true),
+            end: true,
+        };
+
+        let mut follow_sets = HashMap::new();
+        follow_sets.insert(
+            self.start,
+            FollowSet {
+                end: true,
+                ..nothing.clone()
+            },
+        );
+
+        let mut queue = OrderedSet::new();
+        queue.push(self.start);
+
+        while let Some(id) = queue.pop() {
+            let follow = follow_sets[&id].clone();
+
+            // What could start a match of an instruction, followed by whatever follows it if it can
+            // match without consuming input
+            let first_then = |target: InstructionId, then: &FollowSet| {
+                let first_set = &first_sets[&target];
+                let mut result = FollowSet {
+                    class: first_set.class.clone(),
+                    end: false,
+                };
+
+                if first_set.empty {
+                    result.extend(then);
+                }
+
+                result
+            };
+
+            let mut pushes = Vec::new();
+
+            match self.instructions[id] {
+                Instruction::Seq(first, second) => {
+                    pushes.push((first, first_then(second, &follow)));
+                    pushes.push((second, follow));
+                }
+                Instruction::NotAhead(target) => pushes.push((target, anything.clone())),
+                Instruction::Repeat(target) => {
+                    let mut after = follow.clone();
+                    after.extend(&first_then(target, &nothing));
+                    pushes.push((target, after));
+                }
+                Instruction::SepBy {
+                    item,
+                    sep,
+                    allow_trailing,
+                    ..
+                } => {
+                    let mut after_item = follow.clone();
+                    after_item.extend(&first_then(sep, &first_then(item, &follow)));
+
+                    let mut after_sep = first_then(item, &follow);
+
+                    if allow_trailing {
+                        after_sep.extend(&follow);
+                    }
+
+                    pushes.push((item, after_item));
+                    pushes.push((sep, after_sep));
+                }
+                instruction => {
+                    for successor in instruction.successors() {
+                        pushes.push((successor, follow.clone()));
+                    }
+                }
+            }
+
+            for (target, addition) in pushes {
+                let grew = match follow_sets.get_mut(&target) {
+                    Some(existing) => existing.extend(&addition),
+                    None => {
+                        follow_sets.insert(target, addition);
+                        true
+                    }
+                };
+
+                if grew {
+                    queue.push(target);
+                }
+            }
+        }
+
+        follow_sets
+    }
+}
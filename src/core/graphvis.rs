@@ -2,7 +2,7 @@ use crate::core::character::Character;
 use crate::core::expected::Expected;
 use crate::core::series::{Class, Series};
 use crate::core::{Instruction, Parser};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap};
 
 impl Parser {
     pub fn visualize(&self) -> String {
@@ -11,7 +11,7 @@ impl Parser {
         self.visualize_instructions(&mut result);
         self.visualize_debug_symbols(&mut result);
 
-        result.push_str("}");
+        result.push('}');
         result
     }
 
@@ -32,7 +32,12 @@ impl Parser {
             match instruction {
                 Instruction::Seq(first, second)
                 | Instruction::Choice(first, second)
-                | Instruction::FirstChoice(first, second) => {
+                | Instruction::FirstChoice(first, second)
+                | Instruction::SepBy {
+                    item: first,
+                    sep: second,
+                    ..
+                } => {
                     result.push_str(&format!("    i{}:w -> i{};\n", id.0, first.0));
                     result.push_str(&format!("    i{}:e -> i{};\n", id.0, second.0));
                 }
@@ -40,10 +45,14 @@ impl Parser {
                 | Instruction::Error(target, _)
                 | Instruction::Label(target, _)
                 | Instruction::Cache(target, _)
-                | Instruction::Delegate(target) => {
+                | Instruction::Grow(target, _)
+                | Instruction::Delegate(target)
+                | Instruction::Repeat(target) => {
                     result.push_str(&format!("    i{} -> i{};\n", id.0, target.0));
                 }
-                Instruction::Series(_) => {}
+                Instruction::Series(_)
+                | Instruction::ConsumeUntil(_)
+                | Instruction::Balanced(_, _) => {}
             };
         }
 
@@ -59,8 +68,13 @@ impl Parser {
             | Instruction::Error(_, _)
             | Instruction::Label(_, _)
             | Instruction::Cache(_, _)
-            | Instruction::Delegate(_) => "oval",
-            Instruction::Series(_) => "box",
+            | Instruction::Grow(_, _)
+            | Instruction::Delegate(_)
+            | Instruction::Repeat(_)
+            | Instruction::SepBy { .. } => "oval",
+            Instruction::Series(_) | Instruction::ConsumeUntil(_) | Instruction::Balanced(_, _) => {
+                "box"
+            }
         }
     }
 
@@ -78,15 +92,45 @@ impl Parser {
                 Some(id) => format!("Cache[{}]", id),
                 None => String::from("Cache[?]"),
             },
+            Instruction::Grow(_, id) => match id {
+                Some(id) => format!("Grow[{}]", id),
+                None => String::from("Grow[?]"),
+            },
             Instruction::Delegate(_) => String::from("Delegate"),
+            Instruction::Repeat(_) => String::from("Repeat"),
+            Instruction::SepBy {
+                allow_trailing,
+                min,
+                ..
+            } => {
+                let trailing = if allow_trailing { ", trailing" } else { "" };
+                format!("Separated[{}+{}]", min, trailing)
+            }
             Instruction::Label(_, label) => {
                 let label = &self.labels[label];
                 format!("Label[{}]", label)
             }
             Instruction::Series(series) => {
                 let series = &self.series[series];
-                format!("Series[{}]", self.series_specifier(series))
+
+                match series.kind() {
+                    Some(kind) => format!(
+                        "Token[{}, {}]",
+                        self.series_specifier(series),
+                        self.labels[kind]
+                    ),
+                    None => format!("Series[{}]", self.series_specifier(series)),
+                }
+            }
+            Instruction::ConsumeUntil(series) => {
+                let series = &self.series[series];
+                format!("Consume until[{}]", self.series_specifier(series))
             }
+            Instruction::Balanced(open, close) => format!(
+                "Balanced[{}, {}]",
+                self.series_specifier(&self.series[open]),
+                self.series_specifier(&self.series[close])
+            ),
         };
 
         if character.antitransparent {
@@ -104,66 +148,12 @@ impl Parser {
         name
     }
 
+    /// The specifier of a series, escaped for use in a label
     fn series_specifier(&self, series: &Series) -> String {
-        let mut specifier = String::new();
-
-        for (i, class) in series.classes().iter().enumerate() {
-            if i != 0 {
-                specifier.push_str(", ");
-            }
-
-            specifier.push_str(&self.class_specifier(class));
-        }
-
-        specifier
-    }
-
-    fn class_specifier(&self, class: &Class) -> String {
-        let mut specifier = String::new();
-        let brackets = class.negated() || class.ranges().len() != 1;
-
-        if brackets {
-            specifier.push_str("[");
-        }
-
-        if class.negated() {
-            specifier.push_str("^");
-        }
-
-        for (i, (start, end)) in class.ranges().iter().enumerate() {
-            if i != 0 {
-                specifier.push_str(", ");
-            }
-
-            if start == end {
-                specifier.push_str(&self.format_class_bound(*start));
-            } else {
-                specifier.push_str(&format!(
-                    "{}-{}",
-                    self.format_class_bound(*start),
-                    self.format_class_bound(*end)
-                ));
-            }
-        }
-
-        if brackets {
-            specifier.push_str("]");
-        }
-
-        specifier
-    }
-
-    fn format_class_bound(&self, bound: u8) -> String {
-        let format_char =
-            bound == 0 || bound == 9 || bound == 10 || bound == 13 || bound >= 32 && bound <= 126;
-
-        if format_char {
-            format!("{:?}", bound as char)
-                .replace('\\', "\\\\")
-                .replace('"', "\\\"")
-        } else {
-            format!("0x{:x}", bound)
-        }
+        series
+            .specifier()
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
     }
 
     fn expected_specifier(&self, expected: &Expected) -> String {
@@ -188,16 +178,20 @@ impl Parser {
     }
 
     fn visualize_debug_symbols(&self, result: &mut String) {
-        let mut groups = HashMap::<_, HashSet<_>>::new();
+        let mut groups = HashMap::<_, BTreeSet<_>>::new();
 
         for (instruction, symbol) in &self.debug_symbols {
             if let Some(set) = groups.get_mut(symbol) {
                 set.insert(*instruction);
             } else {
-                groups.insert(symbol.clone(), HashSet::from([*instruction]));
+                groups.insert(symbol.clone(), BTreeSet::from([*instruction]));
             }
         }
 
+        // Clusters are ordered by their first instruction so the output is the same every run
+        let mut groups = groups.into_iter().collect::<Vec<_>>();
+        groups.sort_by_key(|(_, instructions)| instructions.first().copied());
+
         for (i, (symbol, instructions)) in groups.into_iter().enumerate() {
             let names = if symbol.names.is_empty() {
                 String::from("<anonymous>")
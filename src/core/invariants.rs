@@ -0,0 +1,167 @@
+use std::collections::HashSet;
+
+use crate::core::{Instruction, InstructionId, Parser};
+
+impl Parser {
+    /// Checks that a prepared parser is internally consistent, returning a
+    /// description of each violation found. Any violation is a bug in a
+    /// transformation pass, which would otherwise surface as a panic while
+    /// generating code or as a subtly wrong parser
+    pub fn check_invariants(&self) -> Vec<String> {
+        let mut violations = self.structural_violations();
+
+        if !violations.is_empty() {
+            return violations;
+        }
+
+        let mut reachable = HashSet::new();
+        let mut queue = vec![self.start];
+
+        while let Some(id) = queue.pop() {
+            if reachable.insert(id) {
+                queue.extend(self.instructions[id].successors());
+            }
+        }
+
+        let mut used_series = HashSet::new();
+        let mut used_expecteds = HashSet::new();
+
+        for (id, instruction) in self.instructions() {
+            if !reachable.contains(&id) {
+                violations.push(format!(
+                    "{} is unreachable from the start",
+                    self.describe(id)
+                ));
+            }
+
+            used_series.extend(instruction.series());
+
+            match instruction {
+                Instruction::Error(_, expected) => {
+                    used_expecteds.insert(expected);
+                }
+                // Delegates are resolved by every optimization pass, but the
+                // state optimizer introduces them again if the passes ran out
+                Instruction::Delegate(_) if self.converged => {
+                    violations.push(format!("{} is a delegate", self.describe(id)));
+                }
+                _ => {}
+            }
+        }
+
+        for (id, _) in self.series() {
+            if !used_series.contains(&id) {
+                violations.push(format!("Series {} is unused", id.0));
+            }
+        }
+
+        for (id, _) in self.expecteds() {
+            if !used_expecteds.contains(&id) {
+                violations.push(format!("Expected {} is unused", id.0));
+            }
+        }
+
+        violations
+    }
+
+    /// Checks the invariants that hold between every transformation pass: that
+    /// everything referenced exists and every instruction has a debug symbol
+    fn structural_violations(&self) -> Vec<String> {
+        let mut violations = Vec::new();
+
+        if !self.instructions.contains_key(self.start) {
+            violations.push(format!(
+                "Start refers to missing instruction {}",
+                self.start.0
+            ));
+        }
+
+        for (id, instruction) in self.instructions() {
+            if !self.debug_symbols.contains_key(&id) {
+                violations.push(format!("Instruction {} has no debug symbol", id.0));
+            }
+
+            for successor in instruction.successors() {
+                if !self.instructions.contains_key(successor) {
+                    violations.push(format!(
+                        "{} refers to missing instruction {}",
+                        self.describe(id),
+                        successor.0
+                    ));
+                }
+            }
+
+            let missing_series = instruction
+                .series()
+                .find(|series| !self.series.contains_key(*series))
+                .map(|series| format!("series {}", series.0));
+
+            let missing = missing_series.or(match instruction {
+                Instruction::Label(_, label) if !self.labels.contains_key(label) => {
+                    Some(format!("label {}", label.0))
+                }
+                Instruction::Error(_, expected) if !self.expecteds.contains_key(expected) => {
+                    Some(format!("expected {}", expected.0))
+                }
+                _ => None,
+            });
+
+            if let Some(missing) = missing {
+                violations.push(format!(
+                    "{} refers to missing {}",
+                    self.describe(id),
+                    missing
+                ));
+            }
+        }
+
+        for (id, series) in self.series() {
+            if let Some(kind) = series.kind() {
+                if !self.labels.contains_key(kind) {
+                    violations.push(format!("Series {} has missing kind {}", id.0, kind.0));
+                }
+            }
+        }
+
+        violations
+    }
+
+    /// Names an instruction along with the rules it belongs to, if any
+    fn describe(&self, id: InstructionId) -> String {
+        let names = match self.debug_symbols.get(&id) {
+            Some(symbol) => symbol.names.iter().cloned().collect::<Vec<_>>(),
+            None => Vec::new(),
+        };
+
+        if names.is_empty() {
+            format!("Instruction {}", id.0)
+        } else {
+            format!("Instruction {} ({})", id.0, names.join(", "))
+        }
+    }
+
+    /// Panics if the structural invariants are violated, so that a pass which
+    /// corrupts the parser fails immediately rather than producing a subtly
+    /// wrong grammar. Only checked in debug builds
+    pub(super) fn debug_assert_consistent(&self) {
+        if cfg!(debug_assertions) {
+            Self::assert_no_violations(self.structural_violations());
+        }
+    }
+
+    /// Like [`debug_assert_consistent`](Self::debug_assert_consistent), but
+    /// checks every invariant of a prepared parser
+    pub(super) fn debug_assert_invariants(&self) {
+        if cfg!(debug_assertions) {
+            Self::assert_no_violations(self.check_invariants());
+        }
+    }
+
+    fn assert_no_violations(violations: Vec<String>) {
+        assert!(
+            violations.is_empty(),
+            "Parser is inconsistent:\n{}",
+            violations.join("\n")
+        );
+    }
+}
@@ -1,10 +1,16 @@
+use std::collections::HashMap;
+use std::fmt::{self, Formatter};
+use std::io::{BufReader, Read};
+
 use regex::Regex;
-use serde::de::Error;
+use serde::de::{DeserializeSeed, Error, IgnoredAny, MapAccess, SeqAccess, Visitor};
 use serde::{Deserialize, Deserializer};
 
+use crate::core::assertion::{Assertion, Relation};
 use crate::core::expected::ExpectedId;
 use crate::core::series::{Class, Series};
-use crate::core::{DebugSymbol, Instruction, InstructionId, Parser};
+use crate::core::{DebugSymbol, GrammarSpan, Instruction, InstructionId, Parser};
+use crate::store::Store;
 
 /// Required IR file version
 const VERSION: u32 = 0;
@@ -17,125 +23,424 @@ impl Parser {
             Err(err) => return Err(format!("Malformed internal representation ({})", err)),
         };
 
-        let mut loader = Loader {
-            parser: Parser::new(),
-            instruction_count: 0,
+        let mut loader = Loader::new();
+        loader.load_ir(ir)?;
+        loader.finish()
+    }
+
+    /// Load some IR from a reader, deserializing one instruction at a time so
+    /// that the whole IR never needs to be resident in memory at once
+    pub(super) fn load_ir_streaming(reader: impl Read) -> Result<Self, String> {
+        let mut loader = Loader::new();
+
+        let reader = BufReader::new(reader);
+        let mut deserializer = serde_json::Deserializer::from_reader(reader);
+
+        let result = StreamingIr {
+            loader: &mut loader,
+        }
+        .deserialize(&mut deserializer)
+        .and_then(|result| deserializer.end().map(|_| result));
+
+        let start = match result {
+            Ok(Ok(start)) => start,
+            Ok(Err(message)) => return Err(message),
+            Err(err) => return Err(format!("Malformed internal representation ({})", err)),
         };
 
-        loader.load_ir(ir)?;
+        let start = loader.load_reference(start);
+        *loader.parser.start_mut() = start;
 
-        Ok(loader.parser)
+        loader.finish()
     }
 }
 
 struct Loader {
     parser: Parser,
-    instruction_count: usize,
+    /// The largest instruction ID referenced so far. References are checked
+    /// once every instruction has been loaded, since a streamed IR does not
+    /// reveal its length up front
+    max_reference: Option<usize>,
+    /// Where each rule loaded so far was defined. A rule's delegate always
+    /// precedes the instructions making up its body
+    rule_spans: HashMap<String, GrammarSpan>,
 }
 
 impl Loader {
+    pub fn new() -> Self {
+        Self {
+            parser: Parser::new(),
+            max_reference: None,
+            rule_spans: HashMap::new(),
+        }
+    }
+
     pub fn load_ir(&mut self, ir: Ir) -> Result<(), String> {
-        let (start, instructions) = match ir {
+        let (start, skip, instructions, assertions, trivia) = match ir {
             Ir::Success {
                 start,
+                skip,
                 instructions,
+                assertions,
+                trivia,
                 ..
-            } => (start, instructions),
+            } => (start, skip, instructions, assertions, trivia),
             Ir::Error { message: error, .. } => return Err(error),
         };
 
-        self.instruction_count = instructions.len();
-
-        let start = self.load_reference(start)?;
+        let start = self.load_reference(start);
         *self.parser.start_mut() = start;
+        self.load_skip(skip);
+        self.load_assertions(assertions);
+        self.parser.trivia_labels.extend(trivia);
+
+        self.parser.instructions = Store::with_capacity(instructions.len());
+        self.parser.debug_symbols.reserve(instructions.len());
 
         for instruction in instructions {
-            self.load_instruction(instruction)?;
+            self.load_instruction(instruction);
         }
 
         Ok(())
     }
 
-    fn load_instruction(&mut self, ir: InstructionIr) -> Result<(), String> {
+    /// Checks the grammar isn't empty and every reference made by the loaded
+    /// instructions, including the start instruction, is valid
+    pub fn finish(self) -> Result<Parser, String> {
+        if self.parser.instructions.len() == 0 {
+            return Err(String::from("Invalid IR: Grammar is empty"));
+        }
+
+        let start = self.parser.start;
+        if !self.parser.instructions.contains_key(start) {
+            return Err(format!(
+                "Invalid IR: Start instruction ID {} is out of range",
+                start.0
+            ));
+        }
+
+        if let Some(id) = self.max_reference {
+            if !self.parser.instructions.contains_key(InstructionId(id)) {
+                return Err(format!("Invalid IR: Illegal instruction ID: {}", id));
+            }
+        }
+
+        if self.parser.skip.is_none() && !self.parser.spaced_seqs.is_empty() {
+            return Err(String::from(
+                "Invalid IR: Spaced sequence without a skip rule",
+            ));
+        }
+
+        Ok(self.parser)
+    }
+
+    fn load_instruction(&mut self, ir: InstructionIr) {
         let rule_name = match &ir {
             InstructionIr::Seq { rule_name, .. }
             | InstructionIr::Choice { rule_name, .. }
+            | InstructionIr::SepBy { rule_name, .. }
             | InstructionIr::NotAhead { rule_name, .. }
             | InstructionIr::Error { rule_name, .. }
             | InstructionIr::Label { rule_name, .. }
             | InstructionIr::Delegate { rule_name, .. }
-            | InstructionIr::Series { rule_name, .. } => rule_name,
+            | InstructionIr::Series { rule_name, .. }
+            | InstructionIr::Bytes { rule_name, .. }
+            | InstructionIr::ConsumeUntil { rule_name, .. }
+            | InstructionIr::Balanced { rule_name, .. } => rule_name,
         };
 
+        if let InstructionIr::Delegate {
+            rule_name: Some(name),
+            grammar_span: Some(span),
+            ..
+        } = &ir
+        {
+            let span = GrammarSpan {
+                file: span.file.clone(),
+                start: span.start,
+                end: span.end,
+            };
+
+            self.rule_spans.insert(name.clone(), span);
+        }
+
         let symbol = match rule_name {
-            Some(name) => DebugSymbol::named(name.clone()),
+            Some(name) => {
+                let span = self.rule_spans.get(name).cloned();
+                DebugSymbol::named(name.clone()).with_spans(span)
+            }
             None => DebugSymbol::anonymous(),
         };
 
         match &ir {
-            InstructionIr::Seq { first, second, .. } => {
-                let first = self.load_reference(*first)?;
-                let second = self.load_reference(*second)?;
-                self.parser.insert(Instruction::Seq(first, second), symbol);
+            InstructionIr::Seq {
+                first,
+                second,
+                spaced,
+                ..
+            } => {
+                let first = self.load_reference(*first);
+                let second = self.load_reference(*second);
+                let id = self.parser.insert(Instruction::Seq(first, second), symbol);
+
+                if *spaced {
+                    self.parser.spaced_seqs.insert(id);
+                }
             }
             InstructionIr::Choice { first, second, .. } => {
-                let first = self.load_reference(*first)?;
-                let second = self.load_reference(*second)?;
+                let first = self.load_reference(*first);
+                let second = self.load_reference(*second);
                 self.parser
                     .insert(Instruction::Choice(first, second), symbol);
             }
+            InstructionIr::SepBy {
+                item,
+                sep,
+                allow_trailing,
+                min,
+                ..
+            } => {
+                let item = self.load_reference(*item);
+                let sep = self.load_reference(*sep);
+
+                let instruction = Instruction::SepBy {
+                    item,
+                    sep,
+                    allow_trailing: *allow_trailing,
+                    min: *min,
+                };
+
+                self.parser.insert(instruction, symbol);
+            }
             InstructionIr::NotAhead { target, .. } => {
-                let target = self.load_reference(*target)?;
+                let target = self.load_reference(*target);
                 self.parser.insert(Instruction::NotAhead(target), symbol);
             }
             InstructionIr::Error {
                 target, expected, ..
             } => {
-                let target = self.load_reference(*target)?;
-                let expected = self.load_reference(*expected)?;
+                let target = self.load_reference(*target);
+                let expected = self.load_reference(*expected);
                 self.parser
                     .insert(Instruction::Error(target, ExpectedId(expected.0)), symbol);
             }
             InstructionIr::Label { target, label, .. } => {
-                let label = self.parser.insert_label(label.clone());
-                let target = self.load_reference(*target)?;
+                let label = self.parser.insert_label(label.0.clone());
+                let target = self.load_reference(*target);
                 self.parser
                     .insert(Instruction::Label(target, label), symbol);
             }
-            InstructionIr::Delegate { target, .. } => {
-                let target = self.load_reference(*target)?;
+            InstructionIr::Delegate {
+                target,
+                rule_name,
+                rule_doc,
+                no_cache,
+                ..
+            } => {
+                let target = self.load_reference(*target);
                 self.parser.insert(Instruction::Delegate(target), symbol);
-            }
-            InstructionIr::Series { classes, .. } => {
-                let mut series = Series::empty();
-
-                for class_ir in classes {
-                    let mut class = Class::new(class_ir.negated);
 
-                    for (lower, upper) in &class_ir.ranges {
-                        class.insert(*lower, *upper);
-                    }
+                if let (Some(name), Some(doc)) = (rule_name, rule_doc) {
+                    self.parser.rule_docs.insert(name.clone(), doc.clone());
+                }
 
-                    series.append(class);
+                if let (Some(name), true) = (rule_name, no_cache) {
+                    self.parser.uncached_rules.insert(name.clone());
                 }
+            }
+            InstructionIr::Series { classes, kind, .. } => {
+                let mut series = Self::load_series(classes);
+
+                let kind = kind
+                    .as_ref()
+                    .map(|kind| self.parser.insert_label(kind.0.clone()));
+                series.set_kind(kind);
 
                 let series = self.parser.insert_series(series);
                 self.parser.insert(Instruction::Series(series), symbol);
             }
+            InstructionIr::Bytes { hex, .. } => {
+                let series = self.parser.insert_series(Series::literal(&hex.0));
+                self.parser.insert(Instruction::Series(series), symbol);
+            }
+            InstructionIr::ConsumeUntil { classes, .. } => {
+                let series = self.parser.insert_series(Self::load_series(classes));
+                self.parser
+                    .insert(Instruction::ConsumeUntil(series), symbol);
+            }
+            InstructionIr::Balanced { open, close, .. } => {
+                let open = self.parser.insert_series(Self::load_series(open));
+                let close = self.parser.insert_series(Self::load_series(close));
+                self.parser
+                    .insert(Instruction::Balanced(open, close), symbol);
+            }
         }
+    }
 
-        Ok(())
+    fn load_series(classes: &[ClassIr]) -> Series {
+        let mut series = Series::empty();
+
+        for class_ir in classes {
+            // Classes are only bounded once the symbol width is known, since
+            // until then it is unclear which classes cover every symbol
+            let mut class = Class::unbounded(class_ir.negated);
+
+            for (lower, upper) in &class_ir.ranges {
+                class.insert(*lower, *upper);
+            }
+
+            series.append(class);
+        }
+
+        series
     }
 
-    fn load_reference(&self, id: usize) -> Result<InstructionId, String> {
-        if id < self.instruction_count {
-            Ok(InstructionId(id))
-        } else {
-            Err(format!("Invalid IR: Illegal instruction ID: {}", id))
+    fn load_skip(&mut self, skip: Option<usize>) {
+        self.parser.skip = skip.map(|skip| self.load_reference(skip));
+    }
+
+    fn load_assertions(&mut self, assertions: Vec<AssertionIr>) {
+        for assertion in assertions {
+            let assertion = Assertion {
+                first: self.load_reference(assertion.first),
+                relation: assertion.relation,
+                second: self.load_reference(assertion.second),
+            };
+
+            self.parser.assertions.push(assertion);
+        }
+    }
+
+    fn load_reference(&mut self, id: usize) -> InstructionId {
+        self.max_reference = Some(self.max_reference.map_or(id, |max| max.max(id)));
+        InstructionId(id)
+    }
+}
+
+/// Deserializes an [`Ir`] document, feeding each instruction into the loader
+/// as soon as it is read. Produces either the start instruction ID or the
+/// error message of a failed IR
+struct StreamingIr<'a> {
+    loader: &'a mut Loader,
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for StreamingIr<'a> {
+    type Value = Result<usize, String>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for StreamingIr<'a> {
+    type Value = Result<usize, String>;
+
+    fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+        formatter.write_str("an IR object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut version = None;
+        let mut status = None;
+        let mut message = None;
+        let mut start = None;
+        let mut skip = None;
+        let mut assertions = Vec::new();
+        let mut trivia = Vec::new();
+        let mut instructions = false;
+
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "version" => version = Some(map.next_value::<VersionCheck>()?),
+                "status" => status = Some(map.next_value::<Status>()?),
+                "message" => message = Some(map.next_value::<String>()?),
+                "start" => start = Some(map.next_value::<usize>()?),
+                "skip" => skip = map.next_value::<Option<usize>>()?,
+                "assertions" => assertions = map.next_value::<Vec<AssertionIr>>()?,
+                "trivia" => trivia = map.next_value::<Vec<String>>()?,
+                "instructions" => {
+                    map.next_value_seed(StreamingInstructions {
+                        loader: &mut *self.loader,
+                    })?;
+
+                    instructions = true;
+                }
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        if version.is_none() {
+            return Err(A::Error::missing_field("version"));
+        }
+
+        match status {
+            Some(Status::Success) => {
+                if !instructions {
+                    return Err(A::Error::missing_field("instructions"));
+                }
+
+                self.loader.load_skip(skip);
+                self.loader.load_assertions(assertions);
+                self.loader.parser.trivia_labels.extend(trivia);
+
+                match start {
+                    Some(start) => Ok(Ok(start)),
+                    None => Err(A::Error::missing_field("start")),
+                }
+            }
+            Some(Status::Error) => match message {
+                Some(message) => Ok(Err(message)),
+                None => Err(A::Error::missing_field("message")),
+            },
+            None => Err(A::Error::missing_field("status")),
         }
     }
 }
 
+/// Deserializes the instruction array of an IR document into the loader
+struct StreamingInstructions<'a> {
+    loader: &'a mut Loader,
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for StreamingInstructions<'a> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for StreamingInstructions<'a> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+        formatter.write_str("an array of instructions")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        // Instructions are only inserted here, so the store is still empty
+        if let Some(len) = seq.size_hint() {
+            self.loader.parser.instructions = Store::with_capacity(len);
+            self.loader.parser.debug_symbols.reserve(len);
+        }
+
+        while let Some(instruction) = seq.next_element::<InstructionIr>()? {
+            self.loader.load_instruction(instruction);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum Status {
+    Success,
+    Error,
+}
+
 #[derive(Deserialize)]
 #[serde(tag = "status", rename_all = "camelCase")]
 enum Ir {
@@ -148,10 +453,22 @@ enum Ir {
         #[serde(rename = "version")]
         _version: VersionCheck,
         start: usize,
+        skip: Option<usize>,
         instructions: Vec<InstructionIr>,
+        #[serde(default)]
+        assertions: Vec<AssertionIr>,
+        #[serde(default)]
+        trivia: Vec<String>,
     },
 }
 
+#[derive(Deserialize)]
+struct AssertionIr {
+    first: usize,
+    relation: Relation,
+    second: usize,
+}
+
 #[derive(Deserialize)]
 #[serde(tag = "name", rename_all = "camelCase")]
 enum InstructionIr {
@@ -159,6 +476,8 @@ enum InstructionIr {
     Seq {
         first: usize,
         second: usize,
+        #[serde(default)]
+        spaced: bool,
         rule_name: Option<String>,
     },
     #[serde(rename_all = "camelCase")]
@@ -167,6 +486,17 @@ enum InstructionIr {
         second: usize,
         rule_name: Option<String>,
     },
+    /// Matches items with separators between them, see `Instruction::SepBy`
+    #[serde(rename_all = "camelCase")]
+    SepBy {
+        item: usize,
+        sep: usize,
+        #[serde(default)]
+        allow_trailing: bool,
+        #[serde(default)]
+        min: u32,
+        rule_name: Option<String>,
+    },
     #[serde(rename_all = "camelCase")]
     NotAhead {
         target: usize,
@@ -181,25 +511,58 @@ enum InstructionIr {
     #[serde(rename_all = "camelCase")]
     Label {
         target: usize,
-        label: String,
+        label: Label,
         rule_name: Option<String>,
     },
     #[serde(rename_all = "camelCase")]
     Delegate {
         target: usize,
         rule_name: Option<String>,
+        rule_doc: Option<String>,
+        grammar_span: Option<SpanIr>,
+        /// Whether the rule should never be cached, whatever the heuristics
+        /// estimate it would save
+        #[serde(default)]
+        no_cache: bool,
     },
     #[serde(rename_all = "camelCase")]
     Series {
         classes: Vec<ClassIr>,
+        kind: Option<Label>,
+        rule_name: Option<String>,
+    },
+    /// An exact sequence of bytes, which is far more readable than the
+    /// equivalent series when writing IR for binary formats by hand
+    #[serde(rename_all = "camelCase")]
+    Bytes { hex: Hex, rule_name: Option<String> },
+    /// Consumes input up to the next position where the series of classes
+    /// matches, or the end of input
+    #[serde(rename_all = "camelCase")]
+    ConsumeUntil {
+        classes: Vec<ClassIr>,
+        rule_name: Option<String>,
+    },
+    /// Matches the opening series of classes, then input up to the closing
+    /// series that balances it
+    #[serde(rename_all = "camelCase")]
+    Balanced {
+        open: Vec<ClassIr>,
+        close: Vec<ClassIr>,
         rule_name: Option<String>,
     },
 }
 
+#[derive(Deserialize)]
+struct SpanIr {
+    file: String,
+    start: usize,
+    end: usize,
+}
+
 #[derive(Deserialize)]
 struct ClassIr {
     negated: bool,
-    ranges: Vec<(u8, u8)>,
+    ranges: Vec<(u32, u32)>,
 }
 
 struct VersionCheck;
@@ -230,3 +593,30 @@ impl<'a> Deserialize<'a> for Label {
         Ok(Label(value))
     }
 }
+
+/// Bytes written as pairs of hexadecimal digits, optionally separated by
+/// whitespace
+struct Hex(Vec<u8>);
+
+impl<'a> Deserialize<'a> for Hex {
+    fn deserialize<D: Deserializer<'a>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        let digits = value
+            .chars()
+            .filter(|char| !char.is_whitespace())
+            .map(|char| char.to_digit(16))
+            .collect::<Option<Vec<_>>>();
+
+        match digits {
+            Some(digits) if digits.len() % 2 == 0 => {
+                let bytes = digits
+                    .chunks(2)
+                    .map(|pair| (pair[0] * 16 + pair[1]) as u8)
+                    .collect();
+
+                Ok(Hex(bytes))
+            }
+            _ => Err(D::Error::custom("invalid hex bytes")),
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::core::character::Character;
+use crate::core::fixed_point::FixedPointStates;
+use crate::core::{Instruction, InstructionId, Parser};
+
+/// A distance in symbols, where `None` means there is no bound
+type Bound = Option<u32>;
+
+impl Parser {
+    /// Bounds how many symbols past the end of a successful parse the parser
+    /// may have examined, which is how much input must be buffered beyond a
+    /// match when parsing a stream. Returns `None` if there is no bound, such
+    /// as when a lookahead can scan over a repetition
+    pub fn max_lookahead(&self) -> Option<u32> {
+        let characters = self.characterize();
+        let recursive = self.recursive_instructions();
+        let lengths = self.max_lengths(&recursive);
+
+        let scans = self.solve_fixed_point(
+            HashMap::new(),
+            self.instructions().map(|(id, _)| id),
+            Scans {
+                failure: Some(0),
+                overshoot: Some(0),
+            },
+            |id, instruction, states| {
+                let scans = Scans {
+                    failure: self.max_failure_scan(instruction, &lengths, states),
+                    overshoot: self.max_overshoot(instruction, &lengths, &characters, states),
+                };
+
+                // Failures can scan into each level of recursion without bound
+                if recursive.contains(&id) {
+                    Scans {
+                        failure: None,
+                        ..scans
+                    }
+                } else {
+                    scans
+                }
+            },
+        );
+
+        scans[&self.start].overshoot
+    }
+
+    /// How far past where it starts an instruction may have examined when it
+    /// fails to match
+    fn max_failure_scan(
+        &self,
+        instruction: Instruction,
+        lengths: &HashMap<InstructionId, Bound>,
+        states: &FixedPointStates<Scans>,
+    ) -> Bound {
+        match instruction {
+            Instruction::Seq(first, second) => {
+                let after = lengths[&first]?
+                    .checked_add(max(states[first].overshoot, states[second].failure)?)?;
+                max(states[first].failure, Some(after))
+            }
+            Instruction::Choice(first, second) => {
+                max(states[first].failure, states[second].failure)
+            }
+            // The first alternative's scan distance is discarded when it fails
+            Instruction::FirstChoice(_, second) => states[second].failure,
+            Instruction::NotAhead(target) => states[target].any(lengths[&target]),
+            Instruction::Error(target, _)
+            | Instruction::Label(target, _)
+            | Instruction::Cache(target, _)
+            | Instruction::Delegate(target) => states[target].failure,
+            Instruction::Grow(target, _) => states[target].any(lengths[&target]),
+            Instruction::Repeat(_) | Instruction::ConsumeUntil(_) => Some(0),
+            Instruction::SepBy { item, min, .. } => match min {
+                0 => Some(0),
+                1 => states[item].failure,
+                _ => None,
+            },
+            Instruction::Series(series) => Some(self.series[series].classes().len() as u32),
+            Instruction::Balanced(_, _) => None,
+        }
+    }
+
+    /// How far past the end of its match an instruction may have examined
+    /// when it succeeds. Alternatives and iterations that were tried and
+    /// discarded still count, since the parser looked at their input
+    fn max_overshoot(
+        &self,
+        instruction: Instruction,
+        lengths: &HashMap<InstructionId, Bound>,
+        characters: &HashMap<InstructionId, Character>,
+        states: &FixedPointStates<Scans>,
+    ) -> Bound {
+        match instruction {
+            Instruction::Seq(first, second) => {
+                max(states[first].overshoot, states[second].overshoot)
+            }
+            // The second alternative only runs once the first has failed,
+            // which it may have scanned ahead to do
+            Instruction::FirstChoice(first, second) => {
+                let result = max(states[first].overshoot, states[second].overshoot);
+
+                if characters[&first].fallible {
+                    max(result, states[first].failure)
+                } else {
+                    result
+                }
+            }
+            // The second alternative runs unless the first matches without
+            // errors, and the scan distance of whichever is discarded is kept
+            Instruction::Choice(first, second) => {
+                let first_character = characters[&first];
+                let first_scan = if first_character.error_prone {
+                    states[first].any(lengths[&first])
+                } else {
+                    states[first].failure
+                };
+
+                let mut result = max(states[first].overshoot, states[second].overshoot);
+
+                if first_character.fallible || first_character.error_prone {
+                    result = max(result, first_scan);
+                }
+
+                if first_character.error_prone {
+                    result = max(result, states[second].any(lengths[&second]));
+                }
+
+                result
+            }
+            Instruction::Error(target, _)
+            | Instruction::Label(target, _)
+            | Instruction::Cache(target, _)
+            | Instruction::Delegate(target) => states[target].overshoot,
+            Instruction::NotAhead(target) => states[target].failure,
+            Instruction::Grow(target, _) => states[target].any(lengths[&target]),
+            // Repetitions end with an iteration that failed or matched nothing
+            Instruction::Repeat(target) => max(states[target].failure, states[target].overshoot),
+            Instruction::SepBy { item, sep, .. } => {
+                let item_scan = max(states[item].failure, states[item].overshoot);
+                let after_sep = lengths[&sep]?.checked_add(item_scan?)?;
+
+                [
+                    item_scan,
+                    states[sep].failure,
+                    states[sep].overshoot,
+                    Some(after_sep),
+                ]
+                .into_iter()
+                .try_fold(0, |result, bound| Some(result.max(bound?)))
+            }
+            Instruction::Series(_) => Some(0),
+            Instruction::ConsumeUntil(series) => {
+                Some(self.series[series].classes().len().max(1) as u32)
+            }
+            Instruction::Balanced(open, _) => Some(self.series[open].classes().len() as u32),
+        }
+    }
+
+    /// The longest match of each instruction
+    fn max_lengths(&self, recursive: &HashSet<InstructionId>) -> HashMap<InstructionId, Bound> {
+        self.solve_fixed_point(
+            HashMap::new(),
+            self.instructions().map(|(id, _)| id),
+            Some(0),
+            |id, instruction, states| {
+                if recursive.contains(&id) {
+                    return None;
+                }
+
+                match instruction {
+                    Instruction::Seq(first, second) => states[first]?.checked_add(states[second]?),
+                    Instruction::Choice(first, second)
+                    | Instruction::FirstChoice(first, second) => max(states[first], states[second]),
+                    Instruction::NotAhead(_) => Some(0),
+                    Instruction::Error(target, _)
+                    | Instruction::Label(target, _)
+                    | Instruction::Cache(target, _)
+                    | Instruction::Grow(target, _)
+                    | Instruction::Delegate(target) => states[target],
+                    // Iterations only continue while they consume input
+                    Instruction::Repeat(target) if states[target] == Some(0) => Some(0),
+                    Instruction::SepBy { item, sep, .. }
+                        if states[item] == Some(0) && states[sep] == Some(0) =>
+                    {
+                        Some(0)
+                    }
+                    Instruction::Repeat(_) | Instruction::SepBy { .. } => None,
+                    Instruction::Series(series) => {
+                        let series = &self.series[series];
+
+                        if series.is_never() {
+                            Some(0)
+                        } else {
+                            Some(series.classes().len() as u32)
+                        }
+                    }
+                    Instruction::ConsumeUntil(_) | Instruction::Balanced(_, _) => None,
+                }
+            },
+        )
+    }
+
+    /// Finds the instructions that can run themselves again, whose lengths
+    /// and failure scans are treated as unbounded rather than solved for
+    fn recursive_instructions(&self) -> HashSet<InstructionId> {
+        let components = self.separate_components();
+        let mut recursive = HashSet::new();
+
+        for (_, component) in components.components.iter() {
+            if component.instructions.len() > 1 {
+                recursive.extend(component.instructions.iter().copied());
+            }
+        }
+
+        for (id, instruction) in self.instructions() {
+            if instruction.successors().any(|successor| successor == id) {
+                recursive.insert(id);
+            }
+        }
+
+        recursive
+    }
+}
+
+/// How far an instruction may examine past where it starts when it fails,
+/// and past where its match ends when it succeeds
+#[derive(Copy, Clone, Eq, PartialEq)]
+struct Scans {
+    failure: Bound,
+    overshoot: Bound,
+}
+
+impl Scans {
+    /// How far past where it starts the instruction may examine either way
+    fn any(&self, length: Bound) -> Bound {
+        let matched = length?.checked_add(self.overshoot?)?;
+        max(self.failure, Some(matched))
+    }
+}
+
+/// The larger of two bounds, where no bound is larger than any other
+fn max(first: Bound, second: Bound) -> Bound {
+    Some(first?.max(second?))
+}
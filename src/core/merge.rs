@@ -0,0 +1,142 @@
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+
+use crate::core::expected::ExpectedId;
+use crate::core::series::{Class, Series};
+use crate::core::{DebugSymbol, Error, Instruction, InstructionId, Parser};
+
+impl Parser {
+    /// Imports every instruction of another grammar into this one, renaming
+    /// each of its rules with the provided function. The start rule is left
+    /// unchanged, see [`set_start`](Self::set_start) and
+    /// [`override_rule`](Self::override_rule) for tying the grammars together.
+    /// Both parsers must be unprepared
+    pub fn merge(&mut self, mut other: Parser, rename: impl Fn(&str) -> String) {
+        // Each grammar's spaced sequences must use that grammar's skip rule
+        self.desugar();
+        other.desugar();
+
+        let offset = self.instructions.reserve().0;
+
+        let mut label_mappings = HashMap::new();
+        for (id, label) in other.labels.drain() {
+            label_mappings.insert(id, self.labels.insert(label));
+        }
+
+        let mut series_mappings = HashMap::new();
+        for (id, mut series) in other.series.drain() {
+            series.set_kind(series.kind().map(|kind| label_mappings[&kind]));
+            series_mappings.insert(id, self.series.insert(series));
+        }
+
+        for (id, instruction) in other.instructions.drain() {
+            let instruction = match instruction.remapped(|id| InstructionId(id.0 + offset)) {
+                // Expected IDs refer to instructions until the parser is prepared
+                Instruction::Error(target, expected) => {
+                    Instruction::Error(target, ExpectedId(expected.0 + offset))
+                }
+                Instruction::Label(target, label) => {
+                    Instruction::Label(target, label_mappings[&label])
+                }
+                Instruction::Series(series) => Instruction::Series(series_mappings[&series]),
+                Instruction::ConsumeUntil(series) => {
+                    Instruction::ConsumeUntil(series_mappings[&series])
+                }
+                Instruction::Balanced(open, close) => {
+                    Instruction::Balanced(series_mappings[&open], series_mappings[&close])
+                }
+                instruction => instruction,
+            };
+
+            let new_id = InstructionId(id.0 + offset);
+            self.instructions.set(new_id, instruction);
+
+            let names = other.debug_symbols[&id]
+                .names
+                .iter()
+                .map(|name| rename(name));
+            let symbol = DebugSymbol {
+                names: Rc::new(names.collect()),
+                spans: other.debug_symbols[&id].spans.clone(),
+            };
+
+            self.debug_symbols.insert(new_id, symbol);
+        }
+
+        for mut assertion in other.assertions {
+            assertion.first = InstructionId(assertion.first.0 + offset);
+            assertion.second = InstructionId(assertion.second.0 + offset);
+            self.assertions.push(assertion);
+        }
+
+        for (name, doc) in other.rule_docs {
+            self.rule_docs.insert(rename(&name), doc);
+        }
+
+        for name in other.uncached_rules {
+            self.uncached_rules.insert(rename(&name));
+        }
+
+        for (name, message) in other.error_messages {
+            self.error_messages.insert(rename(&name), message);
+        }
+
+        // Labels aren't renamed, so trivia in either grammar stays trivia
+        self.trivia_labels.extend(other.trivia_labels);
+    }
+
+    /// Makes a named rule the start rule
+    pub fn set_start(&mut self, rule: &str) -> Result<(), Error> {
+        self.start = self.rule_entry(rule)?;
+        Ok(())
+    }
+
+    /// Makes the start rule fail unless it matches the whole input, by
+    /// following it with a check for the end of input. The parser must be
+    /// unprepared
+    pub fn require_eof(&mut self) {
+        let mut any = Series::empty();
+        any.append(Class::unbounded(true));
+        let any = self.series.insert(any);
+
+        let any = self.insert(Instruction::Series(any), DebugSymbol::anonymous());
+        let eof = self.insert(Instruction::NotAhead(any), DebugSymbol::anonymous());
+        let start = Instruction::Seq(self.start, eof);
+        self.start = self.insert(start, DebugSymbol::anonymous());
+    }
+
+    /// Makes a named rule match another named rule instead of its own
+    /// definition
+    pub fn override_rule(&mut self, rule: &str, replacement: &str) -> Result<(), Error> {
+        let entry = self.rule_entry(rule)?;
+        let replacement = self.rule_entry(replacement)?;
+        self.instructions[entry] = Instruction::Delegate(replacement);
+        Ok(())
+    }
+
+    /// Replaces what errors in each named rule expected with a message when
+    /// the parser is generated, leaving optimization unaffected. An error
+    /// shared between several rules takes the message of the first of them
+    /// by name. The parser must be unprepared
+    pub fn set_error_messages(&mut self, messages: BTreeMap<String, String>) -> Result<(), Error> {
+        for rule in messages.keys() {
+            self.rule_entry(rule)?;
+        }
+
+        self.error_messages = messages;
+        Ok(())
+    }
+
+    /// Finds the instruction a named rule starts at. Rules are loaded as a
+    /// delegate to their definition, which is inserted before any of the
+    /// instructions in the definition
+    fn rule_entry(&self, rule: &str) -> Result<InstructionId, Error> {
+        self.instructions()
+            .find(|(id, instruction)| {
+                matches!(instruction, Instruction::Delegate(_))
+                    && self.debug_symbols[id].names.contains(rule)
+            })
+            .map(|(id, _)| id)
+            .ok_or_else(|| Error::UnknownRule(rule.to_string()))
+    }
+}
@@ -1,23 +1,50 @@
-use std::collections::{BTreeSet, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::{self, Debug, Formatter};
+use std::hash::{Hash, Hasher};
+use std::io::Read;
 use std::rc::Rc;
 
-use crate::core::expected::{Expected, ExpectedId};
+use crate::core::assertion::Assertion;
+use crate::core::expected::Expected;
 use serde::Serialize;
 
-use crate::core::series::{Series, SeriesId};
+pub use crate::core::character::Character;
+pub use crate::core::diff::{DiffItem, Difference};
+pub use crate::core::expected::ExpectedId;
+pub use crate::core::generation::{GeneratedCode, ParserSize, RuleSize};
+pub use crate::core::grammar_sets::RuleSets;
+use crate::core::series::Series;
+pub use crate::core::series::SeriesId;
+pub use crate::core::series::{describe_symbol, Class};
+pub use crate::core::specifier::SpecifierError;
+pub use crate::core::transformation::PassSnapshot;
+pub use crate::core::tree_schema::{NodeSchema, TreeSchema};
+pub use crate::core::validation::Overlap;
+pub use crate::core::warning::Warning;
 use crate::store::{Store, StoreKey};
 
+mod assertion;
 mod character;
+mod diff;
 mod expected;
+mod first_set;
 mod fixed_point;
 mod generation;
+mod grammar_sets;
 mod graphvis;
+mod invariants;
 mod load;
+mod lookahead;
+mod merge;
 mod series;
+mod specifier;
+mod state_table;
 mod structure;
 mod transformation;
+mod tree_schema;
 mod validation;
 mod walk;
+mod warning;
 
 #[derive(Debug, Eq, PartialEq)]
 pub struct Parser {
@@ -27,16 +54,105 @@ pub struct Parser {
     labels: Store<LabelId, String>,
     expecteds: Store<ExpectedId, Expected>,
     debug_symbols: HashMap<InstructionId, DebugSymbol>,
+    unused_rules: BTreeSet<String>,
+    unmatchable_errors: BTreeSet<String>,
+    /// The names of reachable rules that can never match
+    never_rules: BTreeSet<String>,
+    /// The names of rules containing a choice between an alternative and itself
+    duplicate_alternatives: BTreeSet<String>,
+    /// Labels that are only produced inside a negative lookahead, which
+    /// discards them
+    lookahead_labels: BTreeSet<String>,
+    /// Pairs of alternatives in a choice that can both match some input
+    overlapping_alternatives: BTreeSet<Overlap>,
+    /// Every complete literal string matched by a series of the grammar
+    literals: BTreeSet<Vec<u32>>,
+    /// The nullable, first and follow sets of each rule, by rule name
+    grammar_sets: HashMap<String, RuleSets>,
+    optimization_passes: usize,
+    /// Whether the optimization passes reached a fixed point before running
+    /// out of passes
+    converged: bool,
+    /// Whether normalization stopped reordering junctions because the grammar
+    /// grew too large
+    reordering_limited: bool,
+    /// The type of symbol the generated parser reads from its input
+    symbols: SymbolWidth,
+    /// Whether to dispatch between the alternatives of first choices that
+    /// can't start alike by the next symbol
+    choice_dispatch: bool,
+    eliminated_caches: usize,
+    dropped_caches: usize,
+    /// Documentation written for rules in the grammar, by rule name
+    rule_docs: BTreeMap<String, String>,
+    /// Rules the grammar asks never to be cached, by rule name
+    uncached_rules: BTreeSet<String>,
+    /// Documentation for labels taken from the rules that directly produce them,
+    /// since rule boundaries are lost during optimization
+    label_docs: BTreeMap<String, BTreeSet<String>>,
+    /// Labels the grammar marks as trivia, such as whitespace and comments
+    trivia_labels: BTreeSet<String>,
+    /// The rule matched between the elements of spaced sequences
+    skip: Option<InstructionId>,
+    /// Sequences that match the skip rule between their elements, until the
+    /// skip rule is inserted into them
+    spaced_seqs: BTreeSet<InstructionId>,
+    /// Relations between the first sets of rules that the grammar asserts
+    assertions: Vec<Assertion>,
+    /// The parser as it was after each pass of transformation, when traced
+    pass_trace: Vec<PassSnapshot>,
+    /// Messages that replace what errors expected, by the name of the rule
+    /// the errors are in
+    error_messages: BTreeMap<String, String>,
 }
 
 impl Parser {
     pub fn load(ir: &[u8], settings: CompilerSettings) -> Result<Parser, Error> {
-        let mut parser = match Self::load_ir(ir) {
-            Ok(result) => result,
-            Err(err) => return Err(Error::Load(err)),
-        };
+        match Self::load_ir(ir) {
+            Ok(parser) => parser.prepare(settings),
+            Err(err) => Err(Error::Load(err)),
+        }
+    }
+
+    /// Loads IR from a reader without holding the entire IR in memory, which
+    /// is preferable for very large machine generated grammars
+    pub fn load_streaming(ir: impl Read, settings: CompilerSettings) -> Result<Parser, Error> {
+        match Self::load_ir_streaming(ir) {
+            Ok(parser) => parser.prepare(settings),
+            Err(err) => Err(Error::Load(err)),
+        }
+    }
+
+    /// Loads IR without validating or transforming it, so that it can be
+    /// combined with other grammars using [`merge`](Self::merge). The parser
+    /// must be [prepared](Self::prepare) before code can be generated
+    pub fn load_unprepared(ir: &[u8]) -> Result<Parser, Error> {
+        Self::load_ir(ir).map_err(Error::Load)
+    }
+
+    /// Like [`load_unprepared`](Self::load_unprepared), but streams the IR
+    /// like [`load_streaming`](Self::load_streaming)
+    pub fn load_streaming_unprepared(ir: impl Read) -> Result<Parser, Error> {
+        Self::load_ir_streaming(ir).map_err(Error::Load)
+    }
+
+    /// Validates and transforms a freshly loaded parser
+    pub fn prepare(mut self, settings: CompilerSettings) -> Result<Parser, Error> {
+        self.set_symbols(settings.symbols)?;
+        self.choice_dispatch = settings.choice_dispatch;
 
-        let errors = parser.validate();
+        if settings.label_all_rules {
+            self.label_all_rules();
+        }
+
+        self.label_docs = self.collect_label_docs();
+        self.desugar();
+
+        if settings.left_recursion {
+            self.accept_left_recursion();
+        }
+
+        let errors = self.validate();
 
         if !errors.is_empty() {
             let mut left_recursive = BTreeSet::new();
@@ -44,7 +160,7 @@ impl Parser {
             for error in errors {
                 match error {
                     ValidationError::LeftRecursion(id) => {
-                        let symbol = parser.debug_symbols[&id].clone();
+                        let symbol = self.debug_symbols[&id].clone();
                         for name in symbol.names.iter() {
                             left_recursive.insert(name.clone());
                         }
@@ -55,9 +171,31 @@ impl Parser {
             return Err(Error::LeftRecursive(left_recursive));
         }
 
-        parser.transform(settings);
+        self.check_assertions()?;
+
+        self.unmatchable_errors = self.find_unmatchable_errors();
+        self.never_rules = self.find_never_rules();
+        self.duplicate_alternatives = self.find_duplicate_alternatives();
+        self.lookahead_labels = self.find_lookahead_labels();
+        self.overlapping_alternatives = self.find_overlapping_alternatives();
+        self.literals = self.collect_literals();
+        self.grammar_sets = self.compute_grammar_sets();
+        self.transform(&settings);
 
-        Ok(parser)
+        Ok(self)
+    }
+
+    /// Bounds every class by the largest symbol of a symbol width, failing if
+    /// any class contains a larger symbol
+    fn set_symbols(&mut self, symbols: SymbolWidth) -> Result<(), Error> {
+        for (_, series) in self.series.iter_mut() {
+            *series = series
+                .with_max(symbols.max())
+                .map_err(Error::SymbolOutOfRange)?;
+        }
+
+        self.symbols = symbols;
+        Ok(())
     }
 
     pub fn dump_json(&self) -> String {
@@ -81,6 +219,227 @@ impl Parser {
         serde_json::to_string(&proxy).unwrap()
     }
 
+    /// Finds every series instruction made up entirely of classes matching
+    /// one symbol each
+    fn collect_literals(&self) -> BTreeSet<Vec<u32>> {
+        let mut result = BTreeSet::new();
+
+        for (_, instruction) in self.instructions() {
+            let series = match instruction {
+                Instruction::Series(series) => &self.series[series],
+                _ => continue,
+            };
+
+            let literal = series.literal_prefix();
+
+            if literal.is_empty() || literal.len() != series.classes().len() {
+                continue;
+            }
+
+            result.insert(literal);
+        }
+
+        result
+    }
+
+    /// Associates the documentation of each rule with the label it produces,
+    /// if the rule is just a label around some other rule
+    fn collect_label_docs(&self) -> BTreeMap<String, BTreeSet<String>> {
+        let mut result = BTreeMap::<_, BTreeSet<_>>::new();
+
+        for (id, instruction) in self.instructions() {
+            let mut target = match instruction {
+                Instruction::Delegate(target) => target,
+                _ => continue,
+            };
+
+            let doc = match self.debug_symbols[&id].names.iter().next() {
+                Some(name) if self.debug_symbols[&id].names.len() == 1 => {
+                    match self.rule_docs.get(name) {
+                        Some(doc) => doc,
+                        None => continue,
+                    }
+                }
+                _ => continue,
+            };
+
+            // Delegates may be chained, but never endlessly since that would
+            // be left recursive
+            for _ in 0..self.instructions.len() {
+                match self.instructions[target] {
+                    Instruction::Delegate(next) => target = next,
+                    Instruction::Label(_, label) => {
+                        let label = self.labels[label].clone();
+                        result.entry(label).or_default().insert(doc.clone());
+                        break;
+                    }
+                    _ => break,
+                }
+            }
+        }
+
+        result
+    }
+
+    /// The names of rules that were defined but are never used, either because they can't be
+    /// reached from the start rule or because an earlier alternative always matches before them.
+    /// Only found when [`CompilerSettings::report_unused_rules`] is set
+    pub fn unused_rules(&self) -> impl Iterator<Item = &str> + '_ {
+        self.unused_rules.iter().map(|name| name.as_str())
+    }
+
+    /// The names of rules that mark an error around something that can never match, so the error
+    /// never appears
+    pub fn unmatchable_errors(&self) -> impl Iterator<Item = &str> + '_ {
+        self.unmatchable_errors.iter().map(|name| name.as_str())
+    }
+
+    /// The names of rules reachable from the start rule that can never match, because a class in
+    /// one of their series has no symbols left in it
+    pub fn never_rules(&self) -> impl Iterator<Item = &str> + '_ {
+        self.never_rules.iter().map(|name| name.as_str())
+    }
+
+    /// The names of rules with a choice that lists the same alternative twice, which the second
+    /// time can never match
+    pub fn duplicate_alternatives(&self) -> impl Iterator<Item = &str> + '_ {
+        self.duplicate_alternatives.iter().map(|name| name.as_str())
+    }
+
+    /// The labels that are only ever produced inside a negative lookahead, which throws away what
+    /// it matched, so they never appear in a parse tree
+    pub fn lookahead_labels(&self) -> impl Iterator<Item = &str> + '_ {
+        self.lookahead_labels.iter().map(|name| name.as_str())
+    }
+
+    /// The pairs of alternatives in choices that can both match the start of some input, of which
+    /// the earlier always wins. Overlaps are often intended, so these are informational
+    pub fn overlapping_alternatives(&self) -> impl Iterator<Item = &Overlap> + '_ {
+        self.overlapping_alternatives.iter()
+    }
+
+    /// Every literal string the grammar matches in full, such as keywords and punctuation. These
+    /// are taken from the grammar before optimization merges literals together, and unlike the
+    /// literals of an expected they are never cut short. Grammars reading wider symbols than
+    /// bytes can have literals of symbols past `0xFF`
+    pub fn literals(&self) -> &BTreeSet<Vec<u32>> {
+        &self.literals
+    }
+
+    /// The nullable, first and follow sets of each named rule, by rule name, for grammar tooling
+    /// and documentation. These are taken from the grammar before optimization, and are
+    /// conservative, so they may include symbols that can't actually start or follow a rule
+    pub fn grammar_sets(&self) -> &HashMap<String, RuleSets> {
+        &self.grammar_sets
+    }
+
+    /// The number of optimization passes run before the grammar stopped changing or the pass
+    /// limit was reached
+    pub fn optimization_passes(&self) -> usize {
+        self.optimization_passes
+    }
+
+    /// Whether sequences and choices were left partially unordered because reordering them grew
+    /// the grammar too much, which may leave the parser less optimized
+    pub fn reordering_limited(&self) -> bool {
+        self.reordering_limited
+    }
+
+    /// The parser as it was after each pass of transformation, in the order they ran. Empty unless
+    /// [`CompilerSettings::trace_passes`] was set
+    pub fn pass_trace(&self) -> &[PassSnapshot] {
+        &self.pass_trace
+    }
+
+    /// The number of cache points removed because they could never be hit
+    pub fn eliminated_caches(&self) -> usize {
+        self.eliminated_caches
+    }
+
+    /// The number of cache points removed to keep within
+    /// [`CompilerSettings::max_cache_slots`]
+    pub fn dropped_caches(&self) -> usize {
+        self.dropped_caches
+    }
+
+    /// The number of cache slots the generated parser allocates, one for each
+    /// cache point and each target of a growth point
+    pub fn cache_slots(&self) -> usize {
+        self.instructions()
+            .filter_map(|(_, instruction)| match instruction {
+                Instruction::Cache(_, id) | Instruction::Grow(_, id) => id,
+                _ => None,
+            })
+            .collect::<BTreeSet<_>>()
+            .len()
+    }
+
+    /// Whether the grammar is free of recursion and negative lookahead, in which case it
+    /// describes a regular language
+    pub fn is_regular(&self) -> bool {
+        let components = self.separate_components();
+
+        for (_, component) in components.components.iter() {
+            if component.instructions.len() != 1 {
+                return false;
+            }
+        }
+
+        for (id, instruction) in self.instructions() {
+            if let Instruction::NotAhead(_) = instruction {
+                return false;
+            }
+
+            if instruction.successors().any(|successor| successor == id) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Where the rules producing each label were defined in the grammar, so
+    /// that nodes in a parse tree can be traced back to the grammar. Rules
+    /// whose definitions could not be located are left out
+    pub fn label_spans(&self) -> BTreeMap<String, BTreeSet<GrammarSpan>> {
+        let mut result = BTreeMap::<_, BTreeSet<_>>::new();
+
+        for (id, instruction) in self.instructions() {
+            if let Instruction::Label(_, label) = instruction {
+                let spans = self.debug_symbols[&id].spans.iter().cloned();
+
+                result
+                    .entry(self.labels[label].clone())
+                    .or_default()
+                    .extend(spans);
+            }
+        }
+
+        result.retain(|_, spans| !spans.is_empty());
+        result
+    }
+
+    /// The names of every label the grammar can produce, sorted and without duplicates
+    pub fn all_labels(&self) -> Vec<String> {
+        let labels = self
+            .labels()
+            .map(|(_, label)| String::from(label))
+            .collect::<BTreeSet<_>>();
+
+        labels.into_iter().collect()
+    }
+
+    /// Every literal that can appear in an error's expected set, sorted and without duplicates
+    pub fn all_expected_literals(&self) -> Vec<Vec<u32>> {
+        let literals = self
+            .expecteds()
+            .flat_map(|(_, expected)| expected.literals())
+            .map(|literal| literal.to_vec())
+            .collect::<BTreeSet<_>>();
+
+        literals.into_iter().collect()
+    }
+
     fn new() -> Self {
         Self {
             start: InstructionId(0),
@@ -89,21 +448,68 @@ impl Parser {
             labels: Store::new(),
             expecteds: Store::new(),
             debug_symbols: HashMap::new(),
+            unused_rules: BTreeSet::new(),
+            unmatchable_errors: BTreeSet::new(),
+            never_rules: BTreeSet::new(),
+            duplicate_alternatives: BTreeSet::new(),
+            lookahead_labels: BTreeSet::new(),
+            overlapping_alternatives: BTreeSet::new(),
+            literals: BTreeSet::new(),
+            grammar_sets: HashMap::new(),
+            optimization_passes: 0,
+            converged: false,
+            reordering_limited: false,
+            symbols: SymbolWidth::U8,
+            choice_dispatch: false,
+            eliminated_caches: 0,
+            dropped_caches: 0,
+            rule_docs: BTreeMap::new(),
+            uncached_rules: BTreeSet::new(),
+            label_docs: BTreeMap::new(),
+            trivia_labels: BTreeSet::new(),
+            skip: None,
+            spaced_seqs: BTreeSet::new(),
+            assertions: Vec::new(),
+            pass_trace: Vec::new(),
+            error_messages: BTreeMap::new(),
         }
     }
 
     fn insert(&mut self, instruction: Instruction, symbol: DebugSymbol) -> InstructionId {
         let id = self.instructions.reserve();
         self.instructions.set(id, instruction);
-        self.debug_symbols.insert(id, symbol);
+
+        let previous = self.debug_symbols.insert(id, symbol);
+        debug_assert!(previous.is_none(), "Instruction {} inserted twice", id.0);
+
         id
     }
 
-    fn instructions(&self) -> impl DoubleEndedIterator<Item = (InstructionId, Instruction)> + '_ {
+    /// Adds an anonymous instruction to the parser, which must be made
+    /// reachable from the start to survive trimming
+    pub fn add_instruction(&mut self, instruction: Instruction) -> InstructionId {
+        self.insert(instruction, DebugSymbol::anonymous())
+    }
+
+    /// Iterates over every instruction in the parser, including any that are
+    /// unreachable from the start
+    pub fn instructions(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = (InstructionId, Instruction)> + '_ {
         self.instructions.iter_copied()
     }
 
-    fn start(&self) -> InstructionId {
+    pub fn instruction(&self, id: InstructionId) -> Instruction {
+        self.instructions[id]
+    }
+
+    /// Replaces an instruction, which every instruction referring to it then
+    /// runs instead
+    pub fn set_instruction(&mut self, id: InstructionId, instruction: Instruction) {
+        self.instructions[id] = instruction;
+    }
+
+    pub fn start(&self) -> InstructionId {
         self.start
     }
 
@@ -135,6 +541,13 @@ impl Parser {
         for (id, _) in self.instructions.iter() {
             let new_id = mapper(id);
 
+            debug_assert!(
+                self.instructions.contains_key(new_id),
+                "Instruction {} remapped to missing instruction {}",
+                id.0,
+                new_id.0
+            );
+
             let source_symbol = &self.debug_symbols[&id];
             let dest_symbol = &self.debug_symbols[&new_id];
             let new_symbol = DebugSymbol::merge(source_symbol, dest_symbol);
@@ -147,16 +560,54 @@ impl Parser {
         }
 
         self.start = mapper(self.start);
+        self.debug_assert_consistent();
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct CompilerSettings {
     pub merge_series: bool,
     pub character_replacement: bool,
     pub cache_insertion: bool,
+    pub cache_elimination: bool,
     pub redundant_junction_elimination: bool,
     pub state_optimization: bool,
+    pub tail_recursion_elimination: bool,
+    /// Factors a shared suffix out of the alternatives of a choice, when the
+    /// suffix can never fail or produce an error
+    pub suffix_factoring: bool,
+    /// Sorts the alternatives of first choices that can't start with the
+    /// same symbol, and generates code choosing between them by the next
+    /// symbol rather than trying each in turn
+    pub choice_dispatch: bool,
+    /// The most optimization passes to run before giving up on reaching a
+    /// fixed point
+    pub max_passes: usize,
+    /// The only bytes that can be matched, if the input is known to be
+    /// restricted to them. Every class is narrowed to this alphabet
+    pub alphabet: Option<Class>,
+    /// The most cache slots the parser may use. Cache points over the limit
+    /// are removed, starting with those estimated to save the least work
+    pub max_cache_slots: Option<usize>,
+    /// The type of symbol the parser reads, which is bytes unless the grammar
+    /// matches a stream of tokens
+    pub symbols: SymbolWidth,
+    /// Accepts left recursive rules instead of rejecting the grammar, matching
+    /// them by growing a seed parse at each position they are entered
+    pub left_recursion: bool,
+    /// Labels every named rule that does not already produce a label with
+    /// the rule's name, so the parse tree mirrors the grammar
+    pub label_all_rules: bool,
+    /// Extra passes run in order during each round of optimization, after
+    /// normalization and before deduplication
+    pub passes: Vec<Pass>,
+    /// Records the parser after each pass of transformation, for finding the
+    /// pass responsible for a miscompilation
+    pub trace_passes: bool,
+    /// Finds the named rules that are never used, either because the start
+    /// rule can't reach them or because optimization showed they are never
+    /// tried, so they can be reported as warnings
+    pub report_unused_rules: bool,
 }
 
 impl CompilerSettings {
@@ -165,14 +616,171 @@ impl CompilerSettings {
             merge_series: true,
             character_replacement: true,
             cache_insertion: true,
+            cache_elimination: true,
             redundant_junction_elimination: true,
             state_optimization: false,
+            tail_recursion_elimination: true,
+            suffix_factoring: true,
+            choice_dispatch: false,
+            max_passes: 8,
+            alphabet: None,
+            max_cache_slots: None,
+            symbols: SymbolWidth::U8,
+            left_recursion: false,
+            label_all_rules: false,
+            passes: Vec::new(),
+            trace_passes: false,
+            report_unused_rules: false,
+        }
+    }
+
+    /// Disables every optional pass except cache insertion. The mandatory
+    /// parts of compilation still run, so the resulting parser is correct but
+    /// larger and slower. Cache insertion stays on because without it parsers
+    /// that backtrack, such as those recovering from errors, can take time
+    /// exponential in the length of malformed input
+    pub fn none() -> Self {
+        Self {
+            merge_series: false,
+            character_replacement: false,
+            cache_insertion: true,
+            cache_elimination: false,
+            redundant_junction_elimination: false,
+            state_optimization: false,
+            tail_recursion_elimination: false,
+            suffix_factoring: false,
+            choice_dispatch: false,
+            max_passes: 8,
+            alphabet: None,
+            max_cache_slots: None,
+            symbols: SymbolWidth::U8,
+            left_recursion: false,
+            label_all_rules: false,
+            passes: Vec::new(),
+            trace_passes: false,
+            report_unused_rules: false,
+        }
+    }
+}
+
+/// A transformation supplied by a user of the library, for prototyping
+/// optimizations against real grammars. A pass must leave the parser
+/// consistent, which is checked in debug builds, and must preserve what the
+/// grammar matches
+#[derive(Clone)]
+pub struct Pass {
+    name: String,
+    run: Rc<dyn Fn(&mut Parser)>,
+}
+
+impl Pass {
+    pub fn new(name: impl Into<String>, run: impl Fn(&mut Parser) + 'static) -> Self {
+        Self {
+            name: name.into(),
+            run: Rc::new(run),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Debug for Pass {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_tuple("Pass").field(&self.name).finish()
+    }
+}
+
+/// Passes are only equal to clones of themselves
+impl PartialEq for Pass {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.run, &other.run)
+    }
+}
+
+impl Eq for Pass {}
+
+impl Hash for Pass {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+    }
+}
+
+/// The type of symbol a generated parser reads from its input
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum SymbolWidth {
+    /// Bytes, which is the fastest and supports string and byte slice input
+    U8,
+    /// 32-bit symbols, such as token kinds produced by a separate lexer
+    U32,
+}
+
+impl SymbolWidth {
+    /// The largest symbol that can appear in the input
+    pub fn max(self) -> u32 {
+        match self {
+            SymbolWidth::U8 => u8::MAX as u32,
+            SymbolWidth::U32 => u32::MAX,
+        }
+    }
+
+    /// The Rust type symbols are represented by in the generated parser
+    pub fn rust_type(self) -> &'static str {
+        match self {
+            SymbolWidth::U8 => "u8",
+            SymbolWidth::U32 => "u32",
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct GenerationSettings {
+    pub trace: bool,
+    pub coverage: bool,
+    /// The number of modules to spread the state functions across, rather
+    /// than generating a single file. Series are then given their own module
+    pub split: Option<usize>,
+}
+
+impl GenerationSettings {
+    pub fn normal() -> Self {
+        Self {
+            trace: false,
+            coverage: false,
+            split: None,
+        }
+    }
+}
+
+/// Controls how the generated parser fits into the module it is compiled as
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ModuleConfig {
+    /// The path to the runtime's `mod.rs`, relative to the generated parser
+    pub runtime_path: String,
+    /// The name of the module the runtime is declared as within the generated parser
+    pub module_name: String,
+    /// Whether the parser starts with an inner doc comment, which is not allowed in code pulled
+    /// into a module with `include!`
+    pub inner_docs: bool,
+    /// Whether the parser depends on the `peg-pack-runtime` crate instead of declaring the
+    /// runtime as a module, in which case the runtime path is unused
+    pub runtime_crate: bool,
+}
+
+impl ModuleConfig {
+    pub fn normal() -> Self {
+        Self {
+            runtime_path: String::from("build/runtime/mod.rs"),
+            module_name: String::from("runtime"),
+            inner_docs: true,
+            runtime_crate: false,
         }
     }
 }
 
 #[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize)]
-struct InstructionId(pub usize);
+pub struct InstructionId(pub usize);
 
 impl StoreKey for InstructionId {
     fn from_usize(value: usize) -> Self {
@@ -185,7 +793,7 @@ impl StoreKey for InstructionId {
 }
 
 #[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize)]
-struct LabelId(pub usize);
+pub struct LabelId(pub usize);
 
 impl StoreKey for LabelId {
     fn from_usize(value: usize) -> Self {
@@ -199,7 +807,7 @@ impl StoreKey for LabelId {
 
 #[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize)]
 #[serde(rename_all = "camelCase")]
-enum Instruction {
+pub enum Instruction {
     Seq(InstructionId, InstructionId),
     Choice(InstructionId, InstructionId),
     FirstChoice(InstructionId, InstructionId),
@@ -207,12 +815,37 @@ enum Instruction {
     Error(InstructionId, ExpectedId),
     Label(InstructionId, LabelId),
     Cache(InstructionId, Option<usize>),
+    /// Matches a left recursive rule by growing a seed: the target is matched
+    /// repeatedly at the same position, with each recursive call reading the
+    /// previous match back out of a cache slot, until the match stops getting
+    /// longer
+    Grow(InstructionId, Option<usize>),
     Delegate(InstructionId),
+    Repeat(InstructionId),
+    /// Matches a list of items with separators between them. Separator and
+    /// item pairs are matched until one fails or consumes no input, and the
+    /// list fails if fewer than `min` items matched. A separator left over
+    /// after the last item is kept when trailing separators are allowed
+    #[serde(rename_all = "camelCase")]
+    SepBy {
+        item: InstructionId,
+        sep: InstructionId,
+        allow_trailing: bool,
+        min: u32,
+    },
     Series(SeriesId),
+    /// Consumes input up to, but not including, the next position the series
+    /// matches at, or the end of the input. Always succeeds
+    ConsumeUntil(SeriesId),
+    /// Matches the first series, then consumes input until it is balanced by
+    /// a match of the second, counting nested matches of the first. Both must
+    /// consume input. Fails if the input ends while unbalanced
+    Balanced(SeriesId, SeriesId),
 }
 
 impl Instruction {
-    fn successors(&self) -> impl DoubleEndedIterator<Item = InstructionId> {
+    /// The instructions this one may run, in the order it would try them
+    pub fn successors(&self) -> impl DoubleEndedIterator<Item = InstructionId> {
         let (first, second) = match *self {
             Instruction::Seq(first, second)
             | Instruction::Choice(first, second)
@@ -221,8 +854,24 @@ impl Instruction {
             | Instruction::Error(target, _)
             | Instruction::Label(target, _)
             | Instruction::Cache(target, _)
-            | Instruction::Delegate(target) => (Some(target), None),
-            Instruction::Series(_) => (None, None),
+            | Instruction::Grow(target, _)
+            | Instruction::Delegate(target)
+            | Instruction::Repeat(target) => (Some(target), None),
+            Instruction::SepBy { item, sep, .. } => (Some(item), Some(sep)),
+            Instruction::Series(_) | Instruction::ConsumeUntil(_) | Instruction::Balanced(_, _) => {
+                (None, None)
+            }
+        };
+
+        first.into_iter().chain(second)
+    }
+
+    /// The series this instruction matches
+    pub fn series(&self) -> impl DoubleEndedIterator<Item = SeriesId> {
+        let (first, second) = match *self {
+            Instruction::Series(series) | Instruction::ConsumeUntil(series) => (Some(series), None),
+            Instruction::Balanced(open, close) => (Some(open), Some(close)),
+            _ => (None, None),
         };
 
         first.into_iter().chain(second)
@@ -242,29 +891,61 @@ impl Instruction {
             Instruction::Label(target, label) => Instruction::Label(mapper(target), label),
             Instruction::Delegate(target) => Instruction::Delegate(mapper(target)),
             Instruction::Cache(target, id) => Instruction::Cache(mapper(target), id),
-            Instruction::Series(_) => *self,
+            Instruction::Grow(target, id) => Instruction::Grow(mapper(target), id),
+            Instruction::Repeat(target) => Instruction::Repeat(mapper(target)),
+            Instruction::SepBy {
+                item,
+                sep,
+                allow_trailing,
+                min,
+            } => Instruction::SepBy {
+                item: mapper(item),
+                sep: mapper(sep),
+                allow_trailing,
+                min,
+            },
+            Instruction::Series(_) | Instruction::ConsumeUntil(_) | Instruction::Balanced(_, _) => {
+                *self
+            }
         }
     }
 }
 
+/// Where a rule was defined within a grammar file, as byte offsets
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct GrammarSpan {
+    pub file: String,
+    pub start: usize,
+    pub end: usize,
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 struct DebugSymbol {
     names: Rc<BTreeSet<String>>,
+    /// Where the named rules were defined, for those that could be located
+    spans: Rc<BTreeSet<GrammarSpan>>,
 }
 
 impl DebugSymbol {
     pub fn named(name: String) -> Self {
         Self {
             names: Rc::new(BTreeSet::from([name])),
+            spans: Rc::new(BTreeSet::new()),
         }
     }
 
     pub fn anonymous() -> Self {
         Self {
             names: Rc::new(BTreeSet::new()),
+            spans: Rc::new(BTreeSet::new()),
         }
     }
 
+    pub fn with_spans(mut self, spans: impl IntoIterator<Item = GrammarSpan>) -> Self {
+        Rc::make_mut(&mut self.spans).extend(spans);
+        self
+    }
+
     pub fn merge_many<'a>(values: impl IntoIterator<Item = &'a DebugSymbol>) -> Self {
         let mut values = values.into_iter().collect::<Vec<_>>();
 
@@ -282,15 +963,15 @@ impl DebugSymbol {
     }
 
     pub fn merge(first: &DebugSymbol, second: &DebugSymbol) -> Self {
-        if first.names == second.names {
+        if first == second {
             return first.clone();
         }
 
-        if first.names.is_empty() {
+        if first.names.is_empty() && first.spans.is_empty() {
             return second.clone();
         }
 
-        if second.names.is_empty() {
+        if second.names.is_empty() && second.spans.is_empty() {
             return first.clone();
         }
 
@@ -298,8 +979,13 @@ impl DebugSymbol {
         new_names.extend(first.names.iter().cloned());
         new_names.extend(second.names.iter().cloned());
 
+        let mut new_spans = BTreeSet::new();
+        new_spans.extend(first.spans.iter().cloned());
+        new_spans.extend(second.spans.iter().cloned());
+
         Self {
             names: Rc::new(new_names),
+            spans: Rc::new(new_spans),
         }
     }
 }
@@ -308,6 +994,12 @@ impl DebugSymbol {
 pub enum Error {
     LeftRecursive(BTreeSet<String>),
     Load(String),
+    UnknownRule(String),
+    /// A class contains a symbol too large for the configured symbol width
+    SymbolOutOfRange(u32),
+    /// An assertion made by the grammar does not hold, with a description of
+    /// how it fails
+    AssertionFailed(String),
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
@@ -1,5 +1,11 @@
-use serde::Serialize;
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+use std::ops::RangeInclusive;
 
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+use crate::core::LabelId;
 use crate::store::StoreKey;
 
 #[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize)]
@@ -15,25 +21,49 @@ impl StoreKey for SeriesId {
     }
 }
 
-#[derive(Debug, Eq, PartialEq, Hash, Serialize, Clone, Ord, PartialOrd)]
-#[serde(transparent)]
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Ord, PartialOrd)]
 pub struct Series {
     classes: Vec<Class>,
+    /// The token kind reported for input matched by the series, if any
+    kind: Option<LabelId>,
 }
 
 impl Series {
     pub fn empty() -> Self {
         Self {
             classes: Vec::new(),
+            kind: None,
         }
     }
 
     pub fn never() -> Self {
         Self {
             classes: vec![Class::new(false)],
+            kind: None,
+        }
+    }
+
+    /// A series matching exactly the given bytes in order
+    pub fn literal(bytes: &[u8]) -> Self {
+        let mut result = Self::empty();
+
+        for byte in bytes {
+            result.append(Class::of(&[(*byte, *byte)]));
         }
+
+        result
     }
 
+    pub fn kind(&self) -> Option<LabelId> {
+        self.kind
+    }
+
+    pub fn set_kind(&mut self, kind: Option<LabelId>) {
+        self.kind = kind;
+    }
+
+    /// Concatenates the classes of two series. The result has no kind, so
+    /// series with kinds should not be concatenated
     pub fn concatenate(first: &Series, second: &Series) -> Series {
         let mut result = Self::empty();
 
@@ -53,13 +83,18 @@ impl Series {
             return Some(first.clone());
         }
 
+        // Merging would change the kind reported for some input
+        if first.kind != second.kind {
+            return None;
+        }
+
         if first.classes.len() != second.classes.len() {
             return None;
         }
 
         let len = first.classes.len();
         if len == 0 {
-            return Some(Self::empty());
+            return Some(first.clone());
         }
 
         if let Some(result) = Self::union_equivalent(first, second, len) {
@@ -84,12 +119,16 @@ impl Series {
 
         let first_last = first.classes.last().unwrap();
         let second_last = second.classes.last().unwrap();
-        let last = Class::union(first_last, second_last);
+        let mut last = Class::union(first_last, second_last);
+        last.canonicalize();
 
         let mut classes = first.classes[0..len - 1].to_vec();
         classes.push(last);
 
-        Some(Self { classes })
+        Some(Self {
+            classes,
+            kind: first.kind,
+        })
     }
 
     fn union_subset(first: &Series, second: &Series, len: usize) -> Option<Series> {
@@ -102,6 +141,17 @@ impl Series {
         Some(first.clone())
     }
 
+    /// The series that remains after removing a prefix, if this series starts
+    /// with that prefix. The result has no kind
+    pub fn strip_prefix(&self, prefix: &Series) -> Option<Series> {
+        let classes = self.classes.strip_prefix(prefix.classes.as_slice())?;
+
+        Some(Self {
+            classes: classes.to_vec(),
+            kind: None,
+        })
+    }
+
     pub fn is_empty(&self) -> bool {
         self.classes.is_empty()
     }
@@ -110,7 +160,8 @@ impl Series {
         self.classes.iter().any(|class| class.is_never())
     }
 
-    pub fn append(&mut self, class: Class) {
+    pub fn append(&mut self, mut class: Class) {
+        class.canonicalize();
         self.classes.push(class);
 
         if self.is_never() {
@@ -122,12 +173,102 @@ impl Series {
     pub fn classes(&self) -> &[Class] {
         &self.classes
     }
+
+    /// Rewrites every class into its canonical form, so that series matching
+    /// the same input compare equal. See [`Class::canonicalize_negation`]
+    pub fn canonicalize_negation(&mut self) {
+        for class in &mut self.classes {
+            class.canonicalize_negation();
+        }
+    }
+
+    /// Redefines every class over symbols up to a new maximum, failing with
+    /// the first symbol found beyond it. Classes covering every symbol up to
+    /// the maximum are then canonicalized
+    pub fn with_max(&self, max: u32) -> Result<Series, u32> {
+        let mut result = Self::empty();
+
+        for class in &self.classes {
+            result.append(class.with_max(max)?);
+        }
+
+        result.kind = self.kind;
+        Ok(result)
+    }
+
+    /// The longest prefix of the series whose classes each match exactly one
+    /// symbol, as a string of symbols
+    pub fn literal_prefix(&self) -> Vec<u32> {
+        let mut buffer = Vec::new();
+
+        for class in &self.classes {
+            if class.negated() || class.ranges().len() != 1 {
+                return buffer;
+            }
+
+            let (lower, upper) = class.ranges()[0];
+            if lower != upper {
+                return buffer;
+            }
+
+            buffer.push(lower);
+        }
+
+        buffer
+    }
+
+    /// The series matching the same input as this one, except for anything
+    /// containing bytes outside the alphabet. Classes matching every byte are
+    /// only restricted if `any` is set
+    pub fn restricted(&self, alphabet: &Class, any: bool) -> Series {
+        let mut result = Self::empty();
+
+        for class in &self.classes {
+            if class.is_any() && !any {
+                result.append(class.clone());
+            } else {
+                result.append(Class::intersection(class, alphabet));
+            }
+        }
+
+        result.kind = self.kind;
+        result
+    }
 }
 
-#[derive(Debug, Eq, PartialEq, Hash, Serialize, Clone, Ord, PartialOrd)]
+impl Serialize for Series {
+    /// Series without a kind serialize as just their classes
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.kind {
+            Some(kind) => {
+                let mut state = serializer.serialize_struct("Series", 2)?;
+                state.serialize_field("classes", &self.classes)?;
+                state.serialize_field("kind", &kind)?;
+                state.end()
+            }
+            None => self.classes.serialize(serializer),
+        }
+    }
+}
+
+/// Describes a symbol for diagnostics, as a character if it is printable
+/// ASCII and as a number otherwise
+pub fn describe_symbol(symbol: u32) -> String {
+    match char::from_u32(symbol) {
+        Some(char) if char.is_ascii_graphic() => format!("'{}'", char),
+        _ => format!("symbol {}", symbol),
+    }
+}
+
+/// A set of symbols, stored as sorted and disjoint inclusive ranges. Negated
+/// classes match every symbol up to `max` outside of their ranges
+#[derive(Debug, Clone)]
 pub struct Class {
     negated: bool,
-    ranges: Vec<(u8, u8)>,
+    ranges: Vec<(u32, u32)>,
+    /// The largest symbol the input can contain. This is a property of the
+    /// parser rather than of the class, so it is ignored by comparisons
+    max: u32,
 }
 
 impl Class {
@@ -135,9 +276,83 @@ impl Class {
         Self {
             negated,
             ranges: vec![],
+            max: u8::MAX as u32,
         }
     }
 
+    /// The largest symbol the class is defined over
+    pub fn max(&self) -> u32 {
+        self.max
+    }
+
+    /// A class over every 32-bit symbol, for use before the symbol width of
+    /// the parser is known
+    pub fn unbounded(negated: bool) -> Self {
+        Self {
+            negated,
+            ranges: vec![],
+            max: u32::MAX,
+        }
+    }
+
+    /// Redefines the class over symbols up to a new maximum, failing with the
+    /// largest symbol in the class if it lies beyond it. Negated classes keep
+    /// excluding the same ranges, so they grow or shrink with the maximum
+    pub fn with_max(&self, max: u32) -> Result<Self, u32> {
+        if let Some((_, end)) = self.ranges.last() {
+            if *end > max {
+                return Err(*end);
+            }
+        }
+
+        Ok(Self {
+            negated: self.negated,
+            ranges: self.ranges.clone(),
+            max,
+        })
+    }
+
+    /// Builds a non-negated class from a set of inclusive ranges
+    pub fn of(ranges: &[(u8, u8)]) -> Self {
+        let mut class = Self::new(false);
+
+        for (start, end) in ranges {
+            class.insert(*start, *end);
+        }
+
+        class
+    }
+
+    /// Builds a non-negated class matching each of the provided bytes
+    pub fn from_bytes(bytes: impl IntoIterator<Item = u8>) -> Self {
+        bytes.into_iter().map(|byte| byte..=byte).collect()
+    }
+
+    /// The ASCII digits, like the POSIX `[:digit:]` class
+    pub fn digit() -> Self {
+        Self::of(&[(b'0', b'9')])
+    }
+
+    /// The ASCII letters, like the POSIX `[:alpha:]` class
+    pub fn alpha() -> Self {
+        Self::of(&[(b'A', b'Z'), (b'a', b'z')])
+    }
+
+    /// The ASCII letters and digits, like the POSIX `[:alnum:]` class
+    pub fn alnum() -> Self {
+        Self::union(&Self::digit(), &Self::alpha())
+    }
+
+    /// The ASCII whitespace characters, like the POSIX `[:space:]` class
+    pub fn space() -> Self {
+        Self::of(&[(0x09, 0x0D), (b' ', b' ')])
+    }
+
+    /// The hexadecimal digits, like the POSIX `[:xdigit:]` class
+    pub fn xdigit() -> Self {
+        Self::of(&[(b'0', b'9'), (b'A', b'F'), (b'a', b'f')])
+    }
+
     pub fn union(first: &Self, second: &Self) -> Self {
         if first.negated == second.negated {
             let mut result = first.clone();
@@ -164,7 +379,74 @@ impl Class {
         }
     }
 
-    pub fn insert<T: Into<u8>>(&mut self, start: T, end: T) {
+    /// The class matching only symbols matched by both classes. The result is
+    /// never negated unless it matches every symbol
+    pub fn intersection(first: &Self, second: &Self) -> Self {
+        let first_ranges = first.positive_ranges();
+        let second_ranges = second.positive_ranges();
+
+        let mut result = Self::new(false);
+        result.max = first.max;
+
+        for (first_start, first_end) in &first_ranges {
+            for (second_start, second_end) in &second_ranges {
+                let start = u32::max(*first_start, *second_start);
+                let end = u32::min(*first_end, *second_end);
+
+                if start <= end {
+                    result.ranges.push((start, end));
+                }
+            }
+        }
+
+        result.normalize();
+        result.canonicalize();
+        result
+    }
+
+    /// The class matching only symbols matched by the first class and not the
+    /// second. Like an intersection, the result is never negated unless it
+    /// matches every symbol
+    pub fn difference(first: &Self, second: &Self) -> Self {
+        let complement = Self {
+            negated: !second.negated,
+            ranges: second.ranges.clone(),
+            max: second.max,
+        };
+
+        Self::intersection(first, &complement)
+    }
+
+    /// The smallest symbol the class matches, if any
+    pub fn first_symbol(&self) -> Option<u32> {
+        self.positive_ranges().first().map(|(start, _)| *start)
+    }
+
+    /// The ranges of symbols the class matches, with negation resolved
+    pub fn positive_ranges(&self) -> Vec<(u32, u32)> {
+        if !self.negated {
+            return self.ranges.clone();
+        }
+
+        let mut result = Vec::new();
+        let mut next = 0u64;
+
+        for (start, end) in &self.ranges {
+            if next < *start as u64 {
+                result.push((next as u32, start - 1));
+            }
+
+            next = *end as u64 + 1;
+        }
+
+        if next <= self.max as u64 {
+            result.push((next as u32, self.max));
+        }
+
+        result
+    }
+
+    pub fn insert<T: Into<u32>>(&mut self, start: T, end: T) {
         let start = start.into();
         let end = end.into();
         assert!(start <= end);
@@ -173,7 +455,7 @@ impl Class {
         self.normalize();
     }
 
-    pub fn remove<T: Into<u8>>(&mut self, start: T, end: T) {
+    pub fn remove<T: Into<u32>>(&mut self, start: T, end: T) {
         let start = start.into();
         let end = end.into();
         assert!(start <= end);
@@ -207,9 +489,10 @@ impl Class {
             let current = self.ranges[i];
             let next = &mut self.ranges[i + 1];
 
-            if current.1 >= next.0 {
-                next.0 = u8::min(current.0, next.0);
-                next.1 = u8::max(current.1, next.1);
+            // Adjacent ranges are merged as well as overlapping ones
+            if current.1 as u64 + 1 >= next.0 as u64 {
+                next.0 = u32::min(current.0, next.0);
+                next.1 = u32::max(current.1, next.1);
                 self.ranges.remove(i);
             } else {
                 i += 1;
@@ -217,9 +500,52 @@ impl Class {
         }
     }
 
+    /// Rewrites a class covering every symbol into the canonical "any" form of
+    /// a negated empty class, and a negated class covering every symbol into
+    /// the canonical "never" form of an empty class. This changes what
+    /// further insertions mean, so it is only done once a class is complete
+    fn canonicalize(&mut self) {
+        if self.ranges == [(0, self.max)] {
+            self.negated = !self.negated;
+            self.ranges.clear();
+        }
+    }
+
+    /// Rewrites the class into whichever of its negated and non-negated forms
+    /// has fewer ranges, preferring the non-negated form on a tie. Both forms
+    /// match the same symbols, so afterwards classes matching the same symbols
+    /// compare equal. Negated classes mean something else once the maximum
+    /// changes, so this is only done once it is final
+    pub fn canonicalize_negation(&mut self) {
+        // The ranges of the other form are the symbols outside these ranges
+        let outside = Self {
+            negated: true,
+            ranges: self.ranges.clone(),
+            max: self.max,
+        }
+        .positive_ranges();
+
+        let flip = match outside.len().cmp(&self.ranges.len()) {
+            Ordering::Less => true,
+            Ordering::Equal => self.negated,
+            Ordering::Greater => false,
+        };
+
+        if flip {
+            self.negated = !self.negated;
+            self.ranges = outside;
+        }
+
+        self.canonicalize();
+    }
+
+    pub fn is_any(&self) -> bool {
+        self.negated && self.ranges.is_empty()
+    }
+
     pub fn is_never(&self) -> bool {
         if self.negated {
-            self.ranges == [(u8::MIN, u8::MAX)]
+            self.ranges == [(0, self.max)]
         } else {
             self.ranges.is_empty()
         }
@@ -229,7 +555,383 @@ impl Class {
         self.negated
     }
 
-    pub fn ranges(&self) -> &[(u8, u8)] {
+    /// Determines whether the class matches a particular byte
+    pub fn contains_byte(&self, byte: u8) -> bool {
+        self.contains_symbol(byte as u32)
+    }
+
+    /// Determines whether the class matches a particular symbol
+    pub fn contains_symbol(&self, symbol: u32) -> bool {
+        let in_ranges = self
+            .ranges
+            .iter()
+            .any(|(start, end)| (*start..=*end).contains(&symbol));
+
+        in_ranges != self.negated && symbol <= self.max
+    }
+
+    /// Iterates over every byte the class matches in ascending order
+    pub fn bytes(&self) -> impl Iterator<Item = u8> + '_ {
+        (u8::MIN..=u8::MAX).filter(move |byte| self.contains_byte(*byte))
+    }
+
+    /// The number of symbols a non-negated class matches
+    pub fn symbol_count(&self) -> u64 {
+        self.ranges
+            .iter()
+            .map(|(start, end)| (end - start) as u64 + 1)
+            .sum()
+    }
+
+    /// Iterates over every symbol a non-negated class matches in ascending
+    /// order
+    pub fn symbols(&self) -> impl Iterator<Item = u32> + '_ {
+        self.ranges.iter().flat_map(|(start, end)| *start..=*end)
+    }
+
+    pub fn ranges(&self) -> &[(u32, u32)] {
         &self.ranges
     }
+
+    /// The number of disjoint ranges making up the class
+    pub fn range_count(&self) -> usize {
+        self.ranges.len()
+    }
+
+    /// Estimates how many comparisons matching a byte against the class takes
+    /// in the worst case. A few ranges are checked one after another, and any
+    /// more are split in half by a comparison against the middle range
+    pub fn match_cost(&self) -> u32 {
+        fn cost(ranges: usize) -> u32 {
+            if ranges <= 3 {
+                ranges as u32
+            } else {
+                2 + cost(ranges - ranges / 2)
+            }
+        }
+
+        cost(self.range_count())
+    }
+}
+
+/// Builds a non-negated class from inclusive ranges of bytes, such as
+/// `Class::from_iter([b'a'..=b'z', b'A'..=b'Z'])`. Empty ranges are ignored
+impl FromIterator<RangeInclusive<u8>> for Class {
+    fn from_iter<T: IntoIterator<Item = RangeInclusive<u8>>>(iter: T) -> Self {
+        let mut class = Self::new(false);
+
+        for range in iter {
+            if !range.is_empty() {
+                class
+                    .ranges
+                    .push((*range.start() as u32, *range.end() as u32));
+            }
+        }
+
+        class.normalize();
+        class
+    }
+}
+
+impl PartialEq for Class {
+    fn eq(&self, other: &Self) -> bool {
+        self.negated == other.negated && self.ranges == other.ranges
+    }
+}
+
+impl Eq for Class {}
+
+impl Hash for Class {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.negated.hash(state);
+        self.ranges.hash(state);
+    }
+}
+
+impl PartialOrd for Class {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Class {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.negated, &self.ranges).cmp(&(other.negated, &other.ranges))
+    }
+}
+
+impl Serialize for Class {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Class", 2)?;
+        state.serialize_field("negated", &self.negated)?;
+        state.serialize_field("ranges", &self.ranges)?;
+        state.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::RangeInclusive;
+
+    use super::{Class, Series};
+
+    fn class(negated: bool, ranges: &[(u8, u8)]) -> Class {
+        let mut class = Class::new(negated);
+
+        for (start, end) in ranges {
+            class.insert(*start, *end);
+        }
+
+        class
+    }
+
+    #[test]
+    fn contains_byte_respects_negation() {
+        let positive = class(false, &[(b'a', b'z')]);
+        let negative = class(true, &[(b'a', b'z')]);
+
+        assert!(positive.contains_byte(b'm'));
+        assert!(!positive.contains_byte(b'A'));
+        assert!(!negative.contains_byte(b'm'));
+        assert!(negative.contains_byte(b'A'));
+    }
+
+    #[test]
+    fn contains_byte_after_remove_at_boundaries() {
+        let mut full = class(false, &[(0, 255)]);
+        full.remove(0u8, 0u8);
+        full.remove(255u8, 255u8);
+
+        assert!(!full.contains_byte(0));
+        assert!(full.contains_byte(1));
+        assert!(full.contains_byte(254));
+        assert!(!full.contains_byte(255));
+        assert_eq!(full.bytes().count(), 254);
+    }
+
+    #[test]
+    fn contains_byte_after_union_at_boundaries() {
+        let low = class(false, &[(0, 0)]);
+        let high = class(true, &[(0, 0), (255, 255)]);
+        let union = Class::union(&low, &high);
+
+        assert!(union.contains_byte(0));
+        assert!(union.contains_byte(128));
+        assert!(!union.contains_byte(255));
+        assert_eq!(union.bytes().collect::<Vec<_>>().len(), 255);
+    }
+
+    #[test]
+    fn full_coverage_is_canonicalized() {
+        let mut any = Series::empty();
+        any.append(class(false, &[(0, 127), (128, 255)]));
+        assert_eq!(any.classes(), &[Class::new(true)]);
+
+        let mut never = Series::empty();
+        never.append(class(true, &[(0, 99), (100, 255)]));
+        assert!(never.is_never());
+        assert_eq!(never.classes(), &[Class::new(false)]);
+    }
+
+    #[test]
+    fn series_equality_ignores_construction() {
+        let mut first = Series::empty();
+        first.append(class(false, &[(b'x', b'z'), (b'a', b'c')]));
+        first.append(class(false, &[(b'0', b'4'), (b'5', b'9')]));
+
+        let mut second = Series::empty();
+        second.append(class(
+            false,
+            &[(b'b', b'c'), (b'y', b'z'), (b'a', b'a'), (b'x', b'x')],
+        ));
+        second.append(class(false, &[(b'0', b'9'), (b'3', b'6')]));
+
+        assert_eq!(first, second);
+
+        // A series that can never match collapses wherever the never class is
+        let mut first = Series::empty();
+        first.append(class(false, &[(b'a', b'a')]));
+        first.append(Class::new(false));
+
+        let mut second = Series::empty();
+        second.append(Class::new(false));
+        second.append(class(false, &[(b'b', b'b')]));
+
+        assert_eq!(first, second);
+        assert_eq!(first, Series::never());
+
+        // Series match their classes in order, so reordering them matches
+        // different input and can't be canonicalized away
+        assert_ne!(Series::literal(b"ab"), Series::literal(b"ba"));
+    }
+
+    #[test]
+    fn complementary_classes_canonicalize_equal() {
+        let mut negated = class(true, &[(0, 0)]);
+        let mut positive = class(false, &[(1, 255)]);
+        assert_ne!(negated, positive);
+
+        negated.canonicalize_negation();
+        positive.canonicalize_negation();
+        assert_eq!(negated, positive);
+        assert_eq!(positive, class(false, &[(1, 255)]));
+
+        let mut all_but_one = class(false, &[(0, b'a' - 1), (b'a' + 1, 255)]);
+        all_but_one.canonicalize_negation();
+        assert_eq!(all_but_one, class(true, &[(b'a', b'a')]));
+
+        let mut any = Class::new(true);
+        any.canonicalize_negation();
+        assert!(any.is_any());
+
+        let mut never = Class::new(false);
+        never.canonicalize_negation();
+        assert!(never.is_never());
+    }
+
+    #[test]
+    fn merged_full_coverage_is_any() {
+        let mut low = Series::empty();
+        low.append(class(false, &[(0, 127)]));
+
+        let mut high = Series::empty();
+        high.append(class(false, &[(128, 255)]));
+
+        let merged = Series::merge(&low, &high).unwrap();
+        assert_eq!(merged.classes(), &[Class::new(true)]);
+    }
+
+    #[test]
+    fn bytes_of_never_and_any() {
+        assert_eq!(Class::new(false).bytes().count(), 0);
+        assert_eq!(Class::new(true).bytes().count(), 256);
+    }
+
+    #[test]
+    fn match_cost_grows_logarithmically() {
+        let ranges = (0..32).map(|i| (i * 4, i * 4 + 1)).collect::<Vec<_>>();
+
+        assert_eq!(Class::new(true).match_cost(), 0);
+        assert_eq!(class(false, &ranges[..3]).match_cost(), 3);
+        assert_eq!(class(true, &ranges[..4]).match_cost(), 4);
+        assert_eq!(class(false, &ranges[..8]).match_cost(), 6);
+        assert_eq!(class(false, &ranges).match_cost(), 10);
+        assert_eq!(class(false, &ranges).range_count(), 32);
+    }
+
+    #[test]
+    fn intersection_removes_negation() {
+        let ascii = class(false, &[(0, 127)]);
+        let not_a = class(true, &[(b'a', b'a')]);
+
+        let result = Class::intersection(&not_a, &ascii);
+        assert_eq!(result, class(false, &[(0, b'a' - 1), (b'a' + 1, 127)]));
+    }
+
+    #[test]
+    fn difference_of_negated_classes() {
+        let not_a = class(true, &[(b'a', b'a')]);
+        let not_digit = class(true, &[(b'0', b'9')]);
+
+        let result = Class::difference(&not_a, &not_digit);
+        assert_eq!(result, class(false, &[(b'0', b'9')]));
+        assert_eq!(result.first_symbol(), Some(b'0' as u32));
+
+        assert!(Class::difference(&not_a, &Class::new(true)).is_never());
+        assert_eq!(
+            Class::difference(&not_a, &Class::new(true)).first_symbol(),
+            None
+        );
+    }
+
+    #[test]
+    fn restricted_series_outside_alphabet_is_never() {
+        let ascii = class(false, &[(0, 127)]);
+
+        let mut series = Series::empty();
+        series.append(class(false, &[(b'a', b'a')]));
+        series.append(class(false, &[(200, 255)]));
+
+        assert!(series.restricted(&ascii, false).is_never());
+
+        let mut any = Series::empty();
+        any.append(Class::new(true));
+
+        assert_eq!(any.restricted(&ascii, false), any);
+
+        let mut restricted = Series::empty();
+        restricted.append(ascii.clone());
+        assert_eq!(any.restricted(&ascii, true), restricted);
+    }
+
+    #[test]
+    fn full_coverage_depends_on_max() {
+        let mut bytes = Class::unbounded(false);
+        bytes.insert(0u8, 255u8);
+
+        let mut narrow = Series::empty();
+        narrow.append(bytes.with_max(255).unwrap());
+        assert_eq!(narrow.classes(), &[Class::new(true)]);
+
+        let mut wide = Series::empty();
+        wide.append(bytes.with_max(u32::MAX).unwrap());
+        assert_eq!(wide.classes(), &[bytes.clone()]);
+
+        assert_eq!(bytes.with_max(127), Err(255));
+    }
+
+    #[test]
+    fn intersection_of_wide_negated_classes() {
+        let mut not_low = Class::unbounded(true);
+        not_low.insert(0u32, 999);
+
+        let mut not_high = Class::unbounded(true);
+        not_high.insert(2000u32, u32::MAX);
+
+        let mut expected = Class::unbounded(false);
+        expected.insert(1000u32, 1999);
+
+        assert_eq!(Class::intersection(&not_low, &not_high), expected);
+    }
+
+    #[test]
+    fn named_classes_compose() {
+        let word = Class::union(&Class::alnum(), &Class::of(&[(b'_', b'_')]));
+
+        assert!(word.contains_byte(b'_'));
+        assert!(word.contains_byte(b'7'));
+        assert!(word.contains_byte(b'Q'));
+        assert!(!word.contains_byte(b'-'));
+
+        let mut hex_letters = Class::xdigit();
+        hex_letters.remove(b'0', b'9');
+        assert_eq!(hex_letters, class(false, &[(b'A', b'F'), (b'a', b'f')]));
+
+        assert_eq!(Class::space().bytes().count(), 6);
+    }
+
+    #[test]
+    fn from_ranges_merges_adjacent_and_overlapping() {
+        let letters = Class::from_iter([b'a'..=b'm', b'A'..=b'Z', b'n'..=b'z', b'c'..=b'f']);
+        assert_eq!(letters, Class::alpha());
+
+        let overlapping = Class::from_iter([b'0'..=b'5', b'3'..=b'9', b'2'..=b'4']);
+        assert_eq!(overlapping, Class::digit());
+
+        let reversed = Class::from_iter([RangeInclusive::new(b'9', b'0')]);
+        assert!(reversed.is_never());
+
+        assert!(Class::from_iter(Vec::new()).is_never());
+    }
+
+    #[test]
+    fn from_bytes_merges_runs() {
+        let bytes = Class::from_bytes(b"dcba_0".iter().copied());
+        assert_eq!(
+            bytes,
+            class(false, &[(b'0', b'0'), (b'_', b'_'), (b'a', b'd')])
+        );
+
+        assert_eq!(Class::from_bytes(0..=255).ranges(), [(0, 255)]);
+    }
 }
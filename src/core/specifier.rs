@@ -0,0 +1,404 @@
+use std::fmt::{self, Display, Formatter};
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+use crate::core::series::{Class, Series};
+use peg_pack_runtime::render_byte;
+
+impl Series {
+    /// Renders the classes of the series separated by commas, such as
+    /// `'a', [^'b', 'c'-'e']`. The kind of the series is not included
+    pub fn specifier(&self) -> String {
+        let classes = self
+            .classes()
+            .iter()
+            .map(|class| class.specifier())
+            .collect::<Vec<_>>();
+
+        classes.join(", ")
+    }
+
+    /// Parses the format produced by [`specifier`](Self::specifier). The
+    /// classes are defined over every 32-bit symbol, and the series has no
+    /// kind
+    #[cfg(test)]
+    pub fn parse_specifier(text: &str) -> Result<Series, SpecifierError> {
+        let mut parser = SpecifierParser::new(text);
+        let mut result = Series::empty();
+
+        parser.skip_whitespace();
+
+        if !parser.at_end() {
+            result.append(parser.class()?);
+
+            while parser.eat(',') {
+                result.append(parser.class()?);
+            }
+        }
+
+        parser.finish()?;
+        Ok(result)
+    }
+}
+
+impl Class {
+    /// Renders the class as a bracketed list of symbols and ranges, such as
+    /// `[^'a', 'c'-'e']`. Classes of a single range leave out the brackets.
+    /// Bytes are quoted and escaped like error messages show them, and wider
+    /// symbols are written in hexadecimal
+    pub fn specifier(&self) -> String {
+        let ranges = self
+            .ranges()
+            .iter()
+            .map(|(start, end)| {
+                if start == end {
+                    format_bound(*start)
+                } else {
+                    format!("{}-{}", format_bound(*start), format_bound(*end))
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        if self.negated() {
+            format!("[^{}]", ranges)
+        } else if self.ranges().len() == 1 {
+            ranges
+        } else {
+            format!("[{}]", ranges)
+        }
+    }
+
+    /// Parses the format produced by [`specifier`](Self::specifier). The
+    /// class is defined over every 32-bit symbol
+    pub fn parse_specifier(text: &str) -> Result<Class, SpecifierError> {
+        let mut parser = SpecifierParser::new(text);
+        let result = parser.class()?;
+        parser.finish()?;
+        Ok(result)
+    }
+}
+
+fn format_bound(bound: u32) -> String {
+    match u8::try_from(bound) {
+        Ok(b'\'') => String::from(r"'\''"),
+        Ok(byte) => format!("'{}'", render_byte(byte)),
+        Err(_) => format!("0x{:x}", bound),
+    }
+}
+
+/// The reason a specifier could not be parsed
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct SpecifierError {
+    /// The byte offset into the specifier the problem was found at
+    pub position: usize,
+    /// A description of what was expected there
+    pub expected: &'static str,
+}
+
+impl Display for SpecifierError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "expected {} at byte {}", self.expected, self.position)
+    }
+}
+
+struct SpecifierParser<'a> {
+    text: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl<'a> SpecifierParser<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            chars: text.char_indices().peekable(),
+        }
+    }
+
+    fn class(&mut self) -> Result<Class, SpecifierError> {
+        let mut class;
+        self.skip_whitespace();
+
+        if self.eat('[') {
+            class = Class::unbounded(self.eat('^'));
+            self.skip_whitespace();
+
+            if !self.eat(']') {
+                loop {
+                    self.range(&mut class)?;
+
+                    if self.eat(']') {
+                        break;
+                    }
+
+                    self.expect(',', "',' or ']'")?;
+                }
+            }
+        } else {
+            class = Class::unbounded(false);
+            self.range(&mut class)?;
+        }
+
+        Ok(class)
+    }
+
+    fn range(&mut self, class: &mut Class) -> Result<(), SpecifierError> {
+        let position = self.position();
+        let start = self.bound()?;
+
+        let end = if self.eat('-') { self.bound()? } else { start };
+
+        if start > end {
+            return Err(error(
+                position,
+                "a range that does not end before it starts",
+            ));
+        }
+
+        class.insert(start, end);
+        Ok(())
+    }
+
+    fn bound(&mut self) -> Result<u32, SpecifierError> {
+        self.skip_whitespace();
+        let position = self.position();
+
+        if self.eat('\'') {
+            let char = match self.next() {
+                Some('\\') => match self.next() {
+                    Some('0') => '\0',
+                    Some('t') => '\t',
+                    Some('n') => '\n',
+                    Some('r') => '\r',
+                    Some(char @ ('\\' | '\'' | '"')) => char,
+                    Some('x') => return self.escaped_byte(position + 1),
+                    _ => return Err(error(position + 1, "an escape sequence")),
+                },
+                Some(char) if char != '\'' => char,
+                _ => return Err(error(position + 1, "a character")),
+            };
+
+            self.expect('\'', "'''")?;
+            return Ok(char as u32);
+        }
+
+        if self.text[position..].starts_with("0x") {
+            self.next();
+            self.next();
+
+            let digits_start = self.position();
+            while matches!(self.chars.peek(), Some((_, char)) if char.is_ascii_hexdigit()) {
+                self.next();
+            }
+
+            let digits = &self.text[digits_start..self.position()];
+            return u32::from_str_radix(digits, 16)
+                .map_err(|_| error(digits_start, "a 32-bit hexadecimal number"));
+        }
+
+        Err(error(position, "a quoted character or hexadecimal number"))
+    }
+
+    /// Parses the two hexadecimal digits and closing quote following a `\x`
+    fn escaped_byte(&mut self, position: usize) -> Result<u32, SpecifierError> {
+        let digits_start = self.position();
+
+        for _ in 0..2 {
+            match self.chars.peek() {
+                Some((_, char)) if char.is_ascii_hexdigit() => {
+                    self.next();
+                }
+                _ => return Err(error(position, "an escape sequence")),
+            }
+        }
+
+        let byte = u32::from_str_radix(&self.text[digits_start..self.position()], 16).unwrap();
+        self.expect('\'', "'''")?;
+        Ok(byte)
+    }
+
+    fn finish(&mut self) -> Result<(), SpecifierError> {
+        self.skip_whitespace();
+
+        if self.at_end() {
+            Ok(())
+        } else {
+            Err(error(self.position(), "the end of the specifier"))
+        }
+    }
+
+    /// Consumes a character if it comes next, ignoring whitespace before it
+    fn eat(&mut self, expected: char) -> bool {
+        self.skip_whitespace();
+
+        if matches!(self.chars.peek(), Some((_, char)) if *char == expected) {
+            self.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, char: char, description: &'static str) -> Result<(), SpecifierError> {
+        if self.eat(char) {
+            Ok(())
+        } else {
+            Err(error(self.position(), description))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.chars.peek(), Some((_, char)) if char.is_whitespace()) {
+            self.next();
+        }
+    }
+
+    fn next(&mut self) -> Option<char> {
+        self.chars.next().map(|(_, char)| char)
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.chars.peek().is_none()
+    }
+
+    fn position(&mut self) -> usize {
+        match self.chars.peek() {
+            Some((position, _)) => *position,
+            None => self.text.len(),
+        }
+    }
+}
+
+fn error(position: usize, expected: &'static str) -> SpecifierError {
+    SpecifierError { position, expected }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SpecifierError;
+    use crate::core::series::{Class, Series};
+
+    /// A xorshift generator, so that failures are reproducible
+    struct Random(u64);
+
+    impl Random {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, bound: u64) -> u64 {
+            self.next() % bound
+        }
+
+        /// Mostly bytes around the quoted range, with some wide symbols
+        fn symbol(&mut self) -> u32 {
+            match self.below(8) {
+                0 => self.next() as u32,
+                1 => self.below(256) as u32,
+                _ => self.below(140) as u32,
+            }
+        }
+    }
+
+    fn arbitrary_series(random: &mut Random) -> Series {
+        let mut series = Series::empty();
+
+        for _ in 0..random.below(4) {
+            let negated = random.below(3) == 0;
+            let mut class = if random.below(2) == 0 {
+                Class::new(negated)
+            } else {
+                Class::unbounded(negated)
+            };
+
+            for _ in 0..random.below(5) {
+                let first = random.symbol();
+                let second = random.symbol();
+
+                if Class::max(&class) < first.max(second) {
+                    continue;
+                }
+
+                class.insert(first.min(second), first.max(second));
+            }
+
+            series.append(class);
+        }
+
+        series
+    }
+
+    #[test]
+    fn series_round_trip() {
+        let mut random = Random(0x2545f4914f6cdd1d);
+
+        for _ in 0..10_000 {
+            let series = arbitrary_series(&mut random);
+            let specifier = series.specifier();
+
+            assert_eq!(
+                Series::parse_specifier(&specifier),
+                Ok(series),
+                "{}",
+                specifier
+            );
+        }
+    }
+
+    #[test]
+    fn escaped_symbols_round_trip() {
+        let mut series = Series::empty();
+
+        for symbol in [
+            0, b'\t', b'\n', b'\r', b'\\', b'\'', b'"', b',', b'-', b']', 0x01, 0x7f, 0xff,
+        ] {
+            series.append(Class::of(&[(symbol, symbol)]));
+        }
+
+        let specifier = series.specifier();
+        assert_eq!(
+            specifier,
+            r#"'\0', '\t', '\n', '\r', '\\', '\'', '\"', ',', '-', ']', '\x01', '\x7f', '\xff'"#
+        );
+        assert_eq!(Series::parse_specifier(&specifier), Ok(series));
+    }
+
+    #[test]
+    fn hand_written_classes() {
+        let mut expected = Class::new(true);
+        expected.insert(b'a', b'z');
+        expected.insert(0x100u32, 0x100u32);
+
+        assert_eq!(
+            Class::parse_specifier("[^ 'a'-'m','n' - 'z' ,0x100 ]"),
+            Ok(expected)
+        );
+
+        assert_eq!(
+            Class::parse_specifier("'z'-'a'"),
+            Err(SpecifierError {
+                position: 0,
+                expected: "a range that does not end before it starts",
+            })
+        );
+
+        assert_eq!(
+            Class::parse_specifier(r"'\x4'"),
+            Err(SpecifierError {
+                position: 1,
+                expected: "an escape sequence",
+            })
+        );
+
+        assert_eq!(
+            Class::parse_specifier("['a' 'b']"),
+            Err(SpecifierError {
+                position: 5,
+                expected: "',' or ']'",
+            })
+        );
+    }
+}
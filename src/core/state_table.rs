@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::core::expected::{Expected, ExpectedId};
+use crate::core::generation::State;
+use crate::core::series::{Class, Series, SeriesId};
+use crate::core::{GenerationSettings, Instruction, InstructionId, LabelId, Parser};
+use crate::store::Store;
+
+/// A state of the generated parser, described by the context function it
+/// calls and what it passes to it
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StateEntry {
+    /// The context function called, such as `seq-start` for `state_seq_start`
+    kind: &'static str,
+    /// The instruction the state was generated from
+    instruction: InstructionId,
+    /// The indices of the states passed to the context function, in order
+    targets: Vec<usize>,
+    /// For a dispatch, the symbols that choose each of the targets
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    classes: Vec<Class>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    series: Vec<SeriesId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    label: Option<LabelId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expected: Option<ExpectedId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cache_slot: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    allow_trailing: Option<bool>,
+}
+
+impl Parser {
+    /// Serializes the states the generated parser is made of as JSON, so the
+    /// parser can be simulated or interpreted without generating Rust code.
+    /// Each state is identified by its index, and names the context function
+    /// it calls along with the states and ids passed to it. The series,
+    /// labels and expecteds those ids refer to are included too
+    pub fn dump_states_json(&self) -> String {
+        #[derive(Serialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Proxy<'a> {
+            start: usize,
+            cache_slots: usize,
+            states: Vec<StateEntry>,
+            series: &'a Store<SeriesId, Series>,
+            labels: &'a Store<LabelId, String>,
+            expecteds: &'a Store<ExpectedId, Expected>,
+        }
+
+        let states = self.states().collect::<Vec<_>>();
+
+        let indices = states
+            .iter()
+            .enumerate()
+            .map(|(index, state)| ((state.id, state.stage), index))
+            .collect::<HashMap<_, _>>();
+
+        let dispatches = self.choice_dispatches();
+
+        let entries = states
+            .iter()
+            .map(|state| self.state_entry(*state, &indices, &dispatches))
+            .collect();
+
+        let proxy = Proxy {
+            start: indices[&(self.start, 0)],
+            cache_slots: self.cache_slots(),
+            states: entries,
+            series: &self.series,
+            labels: &self.labels,
+            expecteds: &self.expecteds,
+        };
+
+        serde_json::to_string(&proxy).unwrap()
+    }
+
+    fn state_entry(
+        &self,
+        state: State,
+        indices: &HashMap<(InstructionId, usize), usize>,
+        dispatches: &HashMap<InstructionId, Vec<(InstructionId, Class)>>,
+    ) -> StateEntry {
+        let entry = |id: InstructionId| indices[&(id, 0)];
+        let stage = |stage: usize| indices[&(state.id, stage)];
+
+        let mut result = StateEntry {
+            kind: "",
+            instruction: state.id,
+            targets: Vec::new(),
+            classes: Vec::new(),
+            series: Vec::new(),
+            label: None,
+            expected: None,
+            cache_slot: None,
+            min: None,
+            allow_trailing: None,
+        };
+
+        let (kind, targets) = match (self.instructions[state.id], state.stage) {
+            (Instruction::Seq(first, _), 0) => ("seq-start", vec![entry(first), stage(1)]),
+            (Instruction::Seq(_, second), 1) => ("seq-middle", vec![entry(second), stage(2)]),
+            (Instruction::Seq(_, _), _) => ("seq-end", vec![]),
+            (Instruction::Choice(first, _), 0) => ("choice-start", vec![entry(first), stage(1)]),
+            (Instruction::Choice(_, second), 1) => ("choice-middle", vec![entry(second), stage(2)]),
+            (Instruction::Choice(_, _), _) => ("choice-end", vec![]),
+            (Instruction::FirstChoice(_, _), 0) if dispatches.contains_key(&state.id) => {
+                let alternatives = &dispatches[&state.id];
+                result.classes = alternatives
+                    .iter()
+                    .map(|(_, class)| class.clone())
+                    .collect();
+                let targets = alternatives.iter().map(|(id, _)| entry(*id)).collect();
+                ("dispatch", targets)
+            }
+            (Instruction::FirstChoice(first, _), 0) => {
+                ("first-choice-start", vec![entry(first), stage(1)])
+            }
+            (Instruction::FirstChoice(_, second), _) => {
+                ("first-choice-middle", vec![entry(second)])
+            }
+            (Instruction::NotAhead(target), 0)
+                if self.is_end_of_input_check(target, GenerationSettings::normal()) =>
+            {
+                ("end-of-input", vec![])
+            }
+            (Instruction::NotAhead(target), 0) => {
+                ("not-ahead-start", vec![entry(target), stage(1)])
+            }
+            (Instruction::NotAhead(_), _) => ("not-ahead-end", vec![]),
+            (Instruction::Error(target, _), 0) => ("error-start", vec![entry(target), stage(1)]),
+            (Instruction::Error(_, expected), _) => {
+                result.expected = Some(expected);
+                ("error-end", vec![])
+            }
+            (Instruction::Label(target, _), 0) => ("label-start", vec![entry(target), stage(1)]),
+            (Instruction::Label(_, label), _) => {
+                result.label = Some(label);
+                ("label-end", vec![])
+            }
+            (Instruction::Cache(target, slot), 0) => {
+                result.cache_slot = slot;
+                ("cache-start", vec![entry(target), stage(1)])
+            }
+            (Instruction::Cache(_, slot), _) => {
+                result.cache_slot = slot;
+                ("cache-end", vec![])
+            }
+            (Instruction::Grow(target, slot), 0) => {
+                result.cache_slot = slot;
+                ("grow-start", vec![entry(target), stage(1)])
+            }
+            (Instruction::Grow(target, slot), _) => {
+                result.cache_slot = slot;
+                ("grow-middle", vec![entry(target)])
+            }
+            (Instruction::Repeat(target), 0) => ("repeat-start", vec![entry(target), stage(1)]),
+            (Instruction::Repeat(target), _) => ("repeat-middle", vec![entry(target)]),
+            (Instruction::SepBy { item, .. }, 0) => ("sep-by-start", vec![entry(item), stage(1)]),
+            (Instruction::SepBy { sep, min, .. }, 1) => {
+                result.min = Some(min);
+                ("sep-by-first-item", vec![entry(sep), stage(2)])
+            }
+            (Instruction::SepBy { item, min, .. }, 2) => {
+                result.min = Some(min);
+                ("sep-by-separator", vec![entry(item), stage(3)])
+            }
+            (
+                Instruction::SepBy {
+                    sep,
+                    allow_trailing,
+                    min,
+                    ..
+                },
+                _,
+            ) => {
+                result.min = Some(min);
+                result.allow_trailing = Some(allow_trailing);
+                ("sep-by-item", vec![entry(sep), stage(2)])
+            }
+            (Instruction::Delegate(target), _) => ("delegate", vec![entry(target)]),
+            (Instruction::Series(series), _) => {
+                result.series = vec![series];
+
+                match self.series[series].kind() {
+                    Some(kind) => {
+                        result.label = Some(kind);
+                        ("token", vec![])
+                    }
+                    None => ("series", vec![]),
+                }
+            }
+            (Instruction::ConsumeUntil(series), _) => {
+                result.series = vec![series];
+                ("consume-until", vec![])
+            }
+            (Instruction::Balanced(open, close), _) => {
+                result.series = vec![open, close];
+                ("balanced", vec![])
+            }
+        };
+
+        result.kind = kind;
+        result.targets = targets;
+        result
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::hash_map::Entry;
 use std::collections::{BTreeSet, HashMap, HashSet};
 
 use crate::core::InstructionId;
@@ -6,7 +7,7 @@ use crate::store::{Store, StoreKey};
 
 impl Parser {
     /// Computes the predecessors of each instruction
-    pub(super) fn compute_predecessors(&self) -> HashMap<InstructionId, HashSet<InstructionId>> {
+    pub fn compute_predecessors(&self) -> HashMap<InstructionId, HashSet<InstructionId>> {
         let mut results = HashMap::new();
 
         for (id, instruction) in self.instructions() {
@@ -91,20 +92,34 @@ impl Parser {
         roots
     }
 
+    /// Pushes instructions reachable from `id` onto the queue in post-order.
+    /// An explicit stack is used since instruction chains can be arbitrarily
+    /// deep
     fn kosaraju_visit(
         &self,
         id: InstructionId,
         visited: &mut HashSet<InstructionId>,
         queue: &mut Vec<InstructionId>,
     ) {
-        if visited.insert(id) {
-            let instruction = self.instructions[id];
+        if !visited.insert(id) {
+            return;
+        }
 
-            for successor in instruction.successors() {
-                self.kosaraju_visit(successor, visited, queue);
-            }
+        let mut stack = vec![(id, self.instructions[id].successors())];
 
-            queue.push(id);
+        while let Some((id, successors)) = stack.last_mut() {
+            match successors.next() {
+                Some(successor) => {
+                    if visited.insert(successor) {
+                        let successors = self.instructions[successor].successors();
+                        stack.push((successor, successors));
+                    }
+                }
+                None => {
+                    queue.push(*id);
+                    stack.pop();
+                }
+            }
         }
     }
 
@@ -115,11 +130,12 @@ impl Parser {
         predecessors: &HashMap<InstructionId, HashSet<InstructionId>>,
         roots: &mut HashMap<InstructionId, InstructionId>,
     ) {
-        if !roots.contains_key(&id) {
-            roots.insert(id, root);
+        let mut stack = vec![id];
 
-            for predecessor in &predecessors[&id] {
-                self.kosaraju_assign(*predecessor, root, predecessors, roots);
+        while let Some(id) = stack.pop() {
+            if let Entry::Vacant(entry) = roots.entry(id) {
+                entry.insert(root);
+                stack.extend(predecessors[&id].iter().copied());
             }
         }
     }
@@ -0,0 +1,57 @@
+use std::collections::HashSet;
+
+use crate::core::series::Class;
+use crate::core::{Instruction, Parser};
+
+impl Parser {
+    /// Narrows every series to only match bytes in the alphabet, so that
+    /// negated classes no longer need to account for bytes that can't appear.
+    /// Classes matching every byte are left for
+    /// [`restrict_alphabet_any`](Self::restrict_alphabet_any), since checks
+    /// for the end of input can't be told apart from them until optimization
+    /// has simplified the grammar
+    pub(super) fn restrict_alphabet(&mut self, alphabet: &Class) {
+        for (_, series) in self.series.iter_mut() {
+            *series = series.restricted(alphabet, false);
+        }
+    }
+
+    /// Narrows the classes matching every byte to the alphabet too. Checks for
+    /// the end of input are given series of their own which are left alone,
+    /// since otherwise they would succeed before a byte outside the alphabet
+    /// rather than failing
+    pub(super) fn restrict_alphabet_any(&mut self, alphabet: &Class) {
+        let mut end_checks = HashSet::new();
+
+        for (id, instruction) in self.instructions().collect::<Vec<_>>() {
+            let Instruction::NotAhead(mut target) = instruction else {
+                continue;
+            };
+
+            while let Instruction::Delegate(next) = self.instructions[target] {
+                target = next;
+            }
+
+            let Instruction::Series(series) = self.instructions[target] else {
+                continue;
+            };
+
+            if !matches!(self.series[series].classes(), [class] if class.is_any()) {
+                continue;
+            }
+
+            let series = self.series.insert(self.series[series].clone());
+            let symbol = self.debug_symbols[&target].clone();
+            let target = self.insert(Instruction::Series(series), symbol);
+
+            self.instructions[id] = Instruction::NotAhead(target);
+            end_checks.insert(series);
+        }
+
+        for (id, series) in self.series.iter_mut() {
+            if !end_checks.contains(&id) {
+                *series = series.restricted(alphabet, true);
+            }
+        }
+    }
+}
@@ -1,13 +1,31 @@
+use std::collections::HashMap;
+
 use crate::core::{Instruction, Parser};
 
 impl Parser {
     pub(super) fn assign_cache_ids(&mut self) {
         let mut next_id = 0;
 
+        // Optimization may copy a growth point, but every copy must read and
+        // replace the same seed, so growth points share a slot by target
+        let mut growth_ids = HashMap::new();
+
         for (_, instruction) in self.instructions.iter_mut() {
-            if let Instruction::Cache(_, id) = instruction {
-                *id = Some(next_id);
-                next_id += 1;
+            match instruction {
+                Instruction::Cache(_, id) => {
+                    *id = Some(next_id);
+                    next_id += 1;
+                }
+                Instruction::Grow(target, id) => {
+                    let slot = *growth_ids.entry(*target).or_insert(next_id);
+
+                    if slot == next_id {
+                        next_id += 1;
+                    }
+
+                    *id = Some(slot);
+                }
+                _ => {}
             }
         }
     }
@@ -0,0 +1,120 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::core::character::Character;
+use crate::core::{Instruction, InstructionId, Parser};
+
+impl Parser {
+    /// Removes cache points whose targets can never be entered twice at the
+    /// same position, since nothing could ever be read back out of them.
+    /// Returns the number of cache points removed
+    pub(super) fn eliminate_redundant_caches(&mut self) -> usize {
+        let predecessors = self.compute_duplicated_predecessors();
+        let characters = self.characterize();
+        let mut once_per_position = HashMap::new();
+        let mut removed = 0;
+
+        let caches = self
+            .instructions()
+            .filter_map(|(id, instruction)| match instruction {
+                Instruction::Cache(target, _) => Some((id, target)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        for (id, target) in caches {
+            if !self.entered_once_per_position(
+                id,
+                &predecessors,
+                &characters,
+                &mut once_per_position,
+            ) {
+                continue;
+            }
+
+            for pred_id in &predecessors[&id] {
+                let pred = self.instructions[*pred_id];
+
+                self.instructions[*pred_id] =
+                    pred.remapped(|old_id| if old_id == id { target } else { old_id });
+            }
+
+            if self.start == id {
+                self.start = target;
+            }
+
+            removed += 1;
+        }
+
+        self.trim();
+        removed
+    }
+
+    /// Checks whether every parse enters an instruction at most once at any
+    /// given position. This holds when the instruction is the start, or it has
+    /// a single predecessor for which this holds and which enters it at a
+    /// different position each time
+    fn entered_once_per_position(
+        &self,
+        id: InstructionId,
+        predecessors: &HashMap<InstructionId, Vec<InstructionId>>,
+        characters: &HashMap<InstructionId, Character>,
+        memo: &mut HashMap<InstructionId, bool>,
+    ) -> bool {
+        let mut chain = Vec::new();
+        let mut visited = HashSet::new();
+        let mut current = id;
+
+        // Walk up the chain of single predecessors until it reaches the start
+        // or something that disqualifies it, which decides the whole chain
+        let result = loop {
+            if let Some(result) = memo.get(&current) {
+                break *result;
+            }
+
+            if !visited.insert(current) {
+                break false;
+            }
+
+            chain.push(current);
+
+            let preds = &predecessors[&current];
+
+            if current == self.start {
+                break preds.is_empty();
+            }
+
+            let pred = match preds.first() {
+                Some(pred) if preds.iter().all(|other| other == pred) => *pred,
+                _ => break false,
+            };
+
+            // A sequence of an instruction with itself or a repetition enters
+            // it many times, but always at different positions if it can't
+            // match without consuming input. Lists may retry an item where
+            // an empty separator matched, so they are never known to advance
+            let reentered = match self.instructions[pred] {
+                Instruction::Repeat(_) | Instruction::SepBy { .. } => true,
+                _ => preds.len() > 1,
+            };
+
+            let advances = match self.instructions[pred] {
+                Instruction::Seq(_, _) | Instruction::Repeat(_) => {
+                    !characters[&current].transparent
+                }
+                _ => false,
+            };
+
+            if reentered && !advances {
+                break false;
+            }
+
+            current = pred;
+        };
+
+        for id in chain {
+            memo.insert(id, result);
+        }
+
+        result
+    }
+}
@@ -1,9 +1,9 @@
 use std::collections::HashSet;
 
 use crate::core::{Instruction, InstructionId, Parser};
-use crate::runtime::{
+use peg_pack_runtime::{
     CACHE_WORK, CHOICE_WORK, LABEL_WORK, MARK_ERROR_WORK, MAX_UNCACHED_WORK, NOT_AHEAD_WORK,
-    SEQ_WORK, SERIES_WORK,
+    REPEAT_WORK, SEQ_WORK, SERIES_WORK,
 };
 
 impl Parser {
@@ -15,7 +15,8 @@ impl Parser {
         instructions.reverse();
 
         for id in instructions {
-            if let Instruction::Cache(_, _) = self.instructions[id] {
+            // Growth points already remember what they match
+            if let Instruction::Cache(_, _) | Instruction::Grow(_, _) = self.instructions[id] {
                 continue;
             }
 
@@ -23,6 +24,11 @@ impl Parser {
                 continue;
             }
 
+            let names = &self.debug_symbols[&id].names;
+            if names.iter().any(|name| self.uncached_rules.contains(name)) {
+                continue;
+            }
+
             let mut visited = HashSet::new();
             let work = self.work(id, &mut visited);
 
@@ -45,7 +51,11 @@ impl Parser {
         }
     }
 
-    fn work(&self, id: InstructionId, visited: &mut HashSet<InstructionId>) -> Option<u32> {
+    pub(super) fn work(
+        &self,
+        id: InstructionId,
+        visited: &mut HashSet<InstructionId>,
+    ) -> Option<u32> {
         if !visited.insert(id) {
             return None;
         }
@@ -80,6 +90,13 @@ impl Parser {
                 Some(target + inherent_complexity)
             }
             Instruction::Cache(_, _) | Instruction::Series(_) => Some(inherent_complexity),
+            // The number of times the seed grows depends on the input too
+            Instruction::Grow(_, _) => None,
+            // The number of iterations depends on the input, so the work is unbounded
+            Instruction::Repeat(_)
+            | Instruction::SepBy { .. }
+            | Instruction::ConsumeUntil(_)
+            | Instruction::Balanced(_, _) => None,
         }
     }
 
@@ -89,9 +106,13 @@ impl Parser {
             Instruction::Choice(_, _) | Instruction::FirstChoice(_, _) => CHOICE_WORK,
             Instruction::NotAhead(_) => NOT_AHEAD_WORK,
             Instruction::Delegate(_) => 0,
-            Instruction::Cache(_, _) => CACHE_WORK,
+            Instruction::Cache(_, _) | Instruction::Grow(_, _) => CACHE_WORK,
             Instruction::Error(_, _) => MARK_ERROR_WORK,
             Instruction::Label(_, _) => LABEL_WORK,
+            Instruction::Repeat(_)
+            | Instruction::SepBy { .. }
+            | Instruction::ConsumeUntil(_)
+            | Instruction::Balanced(_, _) => REPEAT_WORK,
             Instruction::Series(_) => SERIES_WORK,
         }
     }
@@ -0,0 +1,52 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::core::{Instruction, Parser};
+
+impl Parser {
+    /// Removes cache points until no more than the given number of slots are
+    /// used, starting with those whose targets are estimated to do the least
+    /// work, since they save the least when hit. Growth points need their
+    /// slots, so they are never removed. Returns the number removed
+    pub(super) fn limit_cache_points(&mut self, max: usize) -> usize {
+        let growth_slots = self
+            .instructions()
+            .filter_map(|(_, instruction)| match instruction {
+                Instruction::Grow(target, _) => Some(target),
+                _ => None,
+            })
+            .collect::<HashSet<_>>()
+            .len();
+
+        let max = max.saturating_sub(growth_slots);
+
+        let mut caches = self
+            .instructions()
+            .filter_map(|(id, instruction)| match instruction {
+                Instruction::Cache(target, _) => Some((id, target)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        if caches.len() <= max {
+            return 0;
+        }
+
+        // Unbounded work sorts last, so it is kept the longest
+        caches.sort_by_cached_key(|(id, target)| {
+            let work = self.work(*target, &mut HashSet::new());
+            (work.is_none(), work, *id)
+        });
+
+        let removed = caches.len() - max;
+        let mappings = caches[..removed].iter().copied().collect::<HashMap<_, _>>();
+
+        for (_, instruction) in self.instructions.iter_mut() {
+            *instruction = instruction.remapped(|id| Self::follow_mappings(id, &mappings));
+        }
+
+        self.start = Self::follow_mappings(self.start, &mappings);
+
+        self.trim();
+        removed
+    }
+}
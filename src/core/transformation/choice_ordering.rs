@@ -0,0 +1,52 @@
+use crate::core::{Instruction, Parser};
+
+impl Parser {
+    /// Sorts the alternatives of first choices by the smallest symbol they can
+    /// start with, when no two alternatives can start alike. Only one of them
+    /// can match at any position, so the order doesn't change what matches,
+    /// but it lets equivalent choices be deduplicated and keeps the dispatch
+    /// generated for them in order
+    pub(super) fn order_choices(&mut self) {
+        let first_sets = self.first_sets();
+
+        let choices = self
+            .instructions()
+            .filter(|(_, instruction)| matches!(instruction, Instruction::FirstChoice(_, _)))
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+
+        for id in choices {
+            let mut alternatives = match self.disjoint_alternatives(id, &first_sets) {
+                Some(alternatives) => alternatives,
+                None => continue,
+            };
+
+            let key = |id| first_sets[&id].class.first_symbol();
+
+            if alternatives
+                .windows(2)
+                .all(|pair| key(pair[0]) <= key(pair[1]))
+            {
+                continue;
+            }
+
+            alternatives.sort_by_key(|id| key(*id));
+
+            let symbol = self.debug_symbols[&id].clone();
+            let mut alternatives = alternatives.into_iter().rev();
+            let mut second = alternatives.next().unwrap();
+            let first = alternatives.next_back().unwrap();
+
+            for alternative in alternatives {
+                second = self.insert(
+                    Instruction::FirstChoice(alternative, second),
+                    symbol.clone(),
+                );
+            }
+
+            self.instructions[id] = Instruction::FirstChoice(first, second);
+        }
+
+        self.trim();
+    }
+}
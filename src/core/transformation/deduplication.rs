@@ -1,35 +1,51 @@
+use std::collections::hash_map::Entry;
 use std::collections::{BTreeSet, HashMap, HashSet};
 use std::hash::{Hash, Hasher};
 use std::mem;
 
+use rayon::prelude::*;
 use seahash::SeaHasher;
 
-use crate::core::structure::{Component, ComponentId, Components};
+use crate::core::structure::{Component, Components};
 use crate::core::{Instruction, InstructionId, Parser};
 use crate::store::{Store, StoreKey};
 
 impl Parser {
     pub(super) fn deduplicate(&mut self) {
-        self.deduplicate_series();
+        // Labels go first since series refer to them by their kinds
         self.deduplicate_labels();
+        self.deduplicate_series();
         self.deduplicate_expecteds();
         self.deduplicate_components();
+        self.deduplicate_identical_instructions();
+        self.deduplicate_label_wrappers();
         self.trim();
     }
 
     fn deduplicate_series(&mut self) {
+        // Classes with both a negated and non-negated form would otherwise
+        // keep series matching the same input apart
+        for (_, series) in self.series.iter_mut() {
+            series.canonicalize_negation();
+        }
+
         self.deduplicate_resource(
             |parser| &mut parser.series,
-            |instruction, mappings| {
-                if let Instruction::Series(id) = instruction {
+            |instruction, mappings| match instruction {
+                Instruction::Series(id) | Instruction::ConsumeUntil(id) => {
                     *id = mappings[id];
                 }
+                Instruction::Balanced(open, close) => {
+                    *open = mappings[open];
+                    *close = mappings[close];
+                }
+                _ => {}
             },
         );
     }
 
     fn deduplicate_labels(&mut self) {
-        self.deduplicate_resource(
+        let mappings = self.deduplicate_resource(
             |parser| &mut parser.labels,
             |instruction, mappings| {
                 if let Instruction::Label(_, id) = instruction {
@@ -37,9 +53,17 @@ impl Parser {
                 }
             },
         );
+
+        for (_, series) in self.series.iter_mut() {
+            if let Some(kind) = series.kind() {
+                series.set_kind(Some(mappings[&kind]));
+            }
+        }
     }
 
-    fn deduplicate_expecteds(&mut self) {
+    /// Merges identical expecteds. Generated parsers name expecteds by their
+    /// contents, so this must run even when optimization is off
+    pub(super) fn deduplicate_expecteds(&mut self) {
         self.deduplicate_resource(
             |parser| &mut parser.expecteds,
             |instruction, mappings| {
@@ -54,7 +78,7 @@ impl Parser {
         &mut self,
         resources: impl Fn(&mut Self) -> &mut Store<K, V>,
         fix: impl Fn(&mut Instruction, &HashMap<K, K>),
-    ) {
+    ) -> HashMap<K, K> {
         let mut canonicals = HashMap::new();
         let mut mappings = HashMap::new();
         let mut removals = Vec::new();
@@ -76,6 +100,8 @@ impl Parser {
         for removal in removals {
             resources(self).remove(removal);
         }
+
+        mappings
     }
 
     /// Attempts to remove as much duplication in the graph as possible. This
@@ -85,53 +111,154 @@ impl Parser {
     ///
     /// In order to determine equality between two components, a high quality
     /// hash is used. This hash, however, depends on the starting instruction
-    /// of the component
+    /// of the component.
+    ///
+    /// Components are processed in waves, each containing the components
+    /// whose successors were all processed by earlier waves. Components in a
+    /// wave are independent, so they are deduplicated internally and hashed in
+    /// parallel, before being merged with their duplicates one at a time
     fn deduplicate_components(&mut self) {
         let components = self.separate_components();
 
         let mut mappings = HashMap::new();
         let mut canonicals = HashMap::new();
-        let mut visited = HashSet::new();
 
-        self.deduplicate_component(
-            self.start,
-            &components,
-            &mut mappings,
-            &mut canonicals,
-            &mut visited,
-        );
+        for wave in self.component_waves(&components) {
+            let deduplicator = ComponentDeduplicator {
+                instructions: &self.instructions,
+                components: &components,
+                mappings: &mappings,
+            };
+
+            let mut results = wave
+                .par_iter()
+                .map(|start| deduplicator.deduplicate(*start))
+                .collect::<Vec<_>>();
+
+            // Hashing from every instruction is only needed for components
+            // that may become canonical, which excludes any duplicating a
+            // component from an earlier wave
+            results
+                .par_iter_mut()
+                .zip(&wave)
+                .filter(|(result, _)| !canonicals.contains_key(&result.hash))
+                .for_each(|(result, start)| deduplicator.hash_instructions(*start, result));
+
+            for (start, result) in wave.into_iter().zip(results) {
+                self.merge_component(start, result, &components, &mut mappings, &mut canonicals);
+            }
+        }
 
         self.remap(|id| Self::follow_mappings(id, &mappings));
     }
 
-    /// Performs a depth first search of all components, remapping if a
-    /// duplicate is found. If a component is encountered that is not a
-    /// duplicate, it is added to the canonicals map
-    fn deduplicate_component(
-        &mut self,
+    /// Merges instructions that are identical down to the IDs of their
+    /// successors. Components are only merged with whole components, so an
+    /// instruction within a cycle survives alongside an identical one outside
+    /// of it, such as an error marked both inside a recursive rule and after
+    /// it. Merging can make predecessors identical, so this repeats until
+    /// nothing is merged
+    fn deduplicate_identical_instructions(&mut self) {
+        loop {
+            let mut canonicals = HashMap::new();
+            let mut mappings = HashMap::new();
+
+            for (id, instruction) in self.instructions() {
+                match canonicals.entry(instruction) {
+                    Entry::Occupied(entry) => {
+                        mappings.insert(id, *entry.get());
+                    }
+                    Entry::Vacant(entry) => {
+                        entry.insert(id);
+                    }
+                }
+            }
+
+            if mappings.is_empty() {
+                return;
+            }
+
+            self.remap(|id| Self::follow_mappings(id, &mappings));
+            self.trim();
+        }
+    }
+
+    /// Performs a depth first search of all components reachable from the
+    /// start, grouping the instructions each component is entered through into
+    /// waves. A component's wave is one past the latest wave of its
+    /// successors, and within a wave components are kept in the order the
+    /// search finishes them. An explicit stack is used since chains of
+    /// components can be arbitrarily deep
+    fn component_waves(&self, components: &Components) -> Vec<Vec<InstructionId>> {
+        let mut waves = Vec::<Vec<InstructionId>>::new();
+        let mut component_waves = HashMap::new();
+
+        let component_id = components.instruction_components[&self.start];
+        component_waves.insert(component_id, None);
+
+        let successors = components.components[component_id].successors.iter();
+        let mut stack = vec![(self.start, successors)];
+
+        while let Some((start, successors)) = stack.last_mut() {
+            match successors.next() {
+                Some(successor) => {
+                    let component_id = components.instruction_components[successor];
+
+                    if let Entry::Vacant(entry) = component_waves.entry(component_id) {
+                        entry.insert(None);
+                        let successors = components.components[component_id].successors.iter();
+                        stack.push((*successor, successors));
+                    }
+                }
+                None => {
+                    let start = *start;
+                    stack.pop();
+
+                    let component_id = components.instruction_components[&start];
+                    let component = &components.components[component_id];
+
+                    // The graph of components is acyclic, so every successor
+                    // has already been finished
+                    let wave = component
+                        .successors
+                        .iter()
+                        .map(|successor| {
+                            let successor_id = components.instruction_components[successor];
+                            component_waves[&successor_id].unwrap() + 1
+                        })
+                        .max()
+                        .unwrap_or(0);
+
+                    component_waves.insert(component_id, Some(wave));
+
+                    if wave == waves.len() {
+                        waves.push(Vec::new());
+                    }
+
+                    waves[wave].push(start);
+                }
+            }
+        }
+
+        waves
+    }
+
+    /// Merges a component into a duplicate of it if one has been found, or
+    /// otherwise adds it to the canonicals map
+    fn merge_component(
+        &self,
         start: InstructionId,
+        result: DeduplicatedComponent,
         components: &Components,
         mappings: &mut HashMap<InstructionId, InstructionId>,
         canonicals: &mut HashMap<u64, InstructionId>,
-        visited: &mut HashSet<ComponentId>,
     ) {
         let component_id = components.instruction_components[&start];
-
-        if !visited.insert(component_id) {
-            return;
-        }
-
         let component = &components.components[component_id];
 
-        for successor in &component.successors {
-            self.deduplicate_component(*successor, components, mappings, canonicals, visited);
-        }
-
-        self.deduplicate_instructions(start, component.instructions.clone(), mappings);
-
-        let component_hash = self.create_canonical_hash(start, component, mappings);
+        mappings.extend(result.mappings);
 
-        if let Some(replacement) = canonicals.get(&component_hash) {
+        if let Some(replacement) = canonicals.get(&result.hash) {
             let replacement_component_id = components.instruction_components[replacement];
             let replacement_component = &components.components[replacement_component_id];
 
@@ -143,11 +270,31 @@ impl Parser {
                 mappings,
             );
         } else {
-            for start in &component.instructions {
-                let hash = self.create_canonical_hash(*start, component, mappings);
-                canonicals.insert(hash, *start);
+            canonicals.extend(result.instruction_hashes);
+        }
+    }
+
+    /// Merges label instructions that apply the same label to the same target.
+    /// Component deduplication misses these when only one of the labels is
+    /// part of a cycle, since their components then differ as a whole
+    fn deduplicate_label_wrappers(&mut self) {
+        let mut canonicals = HashMap::new();
+        let mut mappings = HashMap::new();
+
+        for (id, instruction) in self.instructions.iter() {
+            if let Instruction::Label(target, label) = *instruction {
+                match canonicals.entry((target, label)) {
+                    Entry::Occupied(entry) => {
+                        mappings.insert(id, *entry.get());
+                    }
+                    Entry::Vacant(entry) => {
+                        entry.insert(id);
+                    }
+                }
             }
         }
+
+        self.remap(|id| Self::follow_mappings(id, &mappings));
     }
 
     /// Remaps all the instructions in a component the the corresponding
@@ -201,6 +348,70 @@ impl Parser {
             mappings.insert(mapping.0, mapping.1);
         }
     }
+}
+
+/// A component that has been deduplicated internally, but not yet merged with
+/// any duplicates of it
+struct DeduplicatedComponent {
+    /// The mappings between instructions within the component
+    mappings: HashMap<InstructionId, InstructionId>,
+    /// The hash of the component when started from the instruction it was
+    /// entered through
+    hash: u64,
+    /// The hash of the component when started from each of its instructions,
+    /// which is only computed if the component may become canonical
+    instruction_hashes: Vec<(u64, InstructionId)>,
+}
+
+/// Deduplicates components whose successors have already been deduplicated.
+/// Only reads from the parser, so components can be processed in parallel
+struct ComponentDeduplicator<'a> {
+    instructions: &'a Store<InstructionId, Instruction>,
+    components: &'a Components,
+    /// The mappings resulting from every component processed so far
+    mappings: &'a HashMap<InstructionId, InstructionId>,
+}
+
+impl<'a> ComponentDeduplicator<'a> {
+    fn deduplicate(&self, start: InstructionId) -> DeduplicatedComponent {
+        let component_id = self.components.instruction_components[&start];
+        let component = &self.components.components[component_id];
+
+        let mut mappings = HashMap::new();
+        self.deduplicate_instructions(start, component.instructions.clone(), &mut mappings);
+
+        let hash = self.create_canonical_hash(start, component, &mappings);
+
+        DeduplicatedComponent {
+            mappings,
+            hash,
+            instruction_hashes: Vec::new(),
+        }
+    }
+
+    fn hash_instructions(&self, start: InstructionId, result: &mut DeduplicatedComponent) {
+        let component_id = self.components.instruction_components[&start];
+        let component = &self.components.components[component_id];
+
+        for start in &component.instructions {
+            let hash = self.create_canonical_hash(*start, component, &result.mappings);
+            result.instruction_hashes.push((hash, *start));
+        }
+    }
+
+    /// Follows the mappings made within the component, and those of the
+    /// components processed before it
+    fn follow_mappings(
+        &self,
+        mut id: InstructionId,
+        mappings: &HashMap<InstructionId, InstructionId>,
+    ) -> InstructionId {
+        while let Some(new_id) = mappings.get(&id).or_else(|| self.mappings.get(&id)) {
+            id = *new_id;
+        }
+
+        id
+    }
 
     /// Reduces a component to a hash for deduplication purposes, these hashes
     /// must never collide for non-equal components
@@ -217,7 +428,7 @@ impl Parser {
         let mut hasher = SeaHasher::new();
         let mut backreferences = HashMap::new();
 
-        let mut queue = vec![Self::follow_mappings(start, mappings)];
+        let mut queue = vec![self.follow_mappings(start, mappings)];
 
         while let Some(id) = queue.pop() {
             if let Some(internal) = backreferences.get(&id) {
@@ -230,10 +441,10 @@ impl Parser {
 
             let instruction = self.instructions[id];
             hasher.write(INSTRUCTION_HASH);
-            self.intrinsic_instruction_hash(instruction, &mut hasher);
+            Self::intrinsic_instruction_hash(instruction, &mut hasher);
 
             for successor in instruction.successors() {
-                let successor = Self::follow_mappings(successor, mappings);
+                let successor = self.follow_mappings(successor, mappings);
 
                 if component.instructions.contains(&successor) {
                     queue.push(successor);
@@ -247,7 +458,7 @@ impl Parser {
         hasher.finish()
     }
 
-    fn intrinsic_instruction_hash(&self, instruction: Instruction, hasher: &mut impl Hasher) {
+    fn intrinsic_instruction_hash(instruction: Instruction, hasher: &mut impl Hasher) {
         let discriminant = mem::discriminant(&instruction);
         discriminant.hash(hasher);
 
@@ -258,17 +469,30 @@ impl Parser {
             Instruction::Label(_, label) => {
                 label.0.hash(hasher);
             }
-            Instruction::Cache(_, id) => {
+            Instruction::Cache(_, id) | Instruction::Grow(_, id) => {
                 id.hash(hasher);
             }
-            Instruction::Series(series) => {
+            Instruction::Series(series) | Instruction::ConsumeUntil(series) => {
                 series.0.hash(hasher);
             }
+            Instruction::Balanced(open, close) => {
+                open.0.hash(hasher);
+                close.0.hash(hasher);
+            }
+            Instruction::SepBy {
+                allow_trailing,
+                min,
+                ..
+            } => {
+                allow_trailing.hash(hasher);
+                min.hash(hasher);
+            }
             Instruction::Seq(_, _)
             | Instruction::Choice(_, _)
             | Instruction::FirstChoice(_, _)
             | Instruction::NotAhead(_)
-            | Instruction::Delegate(_) => {}
+            | Instruction::Delegate(_)
+            | Instruction::Repeat(_) => {}
         }
     }
 
@@ -276,7 +500,7 @@ impl Parser {
     /// algorithm to component deduplication. Cycles are ignored when
     /// performing the depth first search
     fn deduplicate_instructions(
-        &mut self,
+        &self,
         start: InstructionId,
         mut unvisited: BTreeSet<InstructionId>,
         mappings: &mut HashMap<InstructionId, InstructionId>,
@@ -287,7 +511,7 @@ impl Parser {
     }
 
     fn canonicalize_instruction(
-        &mut self,
+        &self,
         id: InstructionId,
         mappings: &mut HashMap<InstructionId, InstructionId>,
         canonicals: &mut HashMap<Instruction, InstructionId>,
@@ -302,7 +526,7 @@ impl Parser {
             self.canonicalize_instruction(successor, mappings, canonicals, unvisited);
         }
 
-        let canonical = instruction.remapped(|id| Self::follow_mappings(id, mappings));
+        let canonical = instruction.remapped(|id| self.follow_mappings(id, mappings));
 
         if let Some(replacement) = canonicals.get(&canonical) {
             mappings.insert(id, *replacement);
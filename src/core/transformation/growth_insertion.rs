@@ -0,0 +1,40 @@
+use std::collections::HashSet;
+
+use crate::core::{Instruction, Parser};
+
+impl Parser {
+    /// Wraps the instructions that left recursion enters through in growth
+    /// points, so that every cycle which consumes no input passes through one.
+    /// Instructions are visited from the start, so the growth point usually
+    /// wraps the rule that is recursed into rather than some part of it
+    pub(super) fn insert_growth_points(&mut self) {
+        let mut characters = self.characterize();
+
+        let order = self
+            .walk()
+            .map(|(id, _)| id)
+            .chain(self.instructions().map(|(id, _)| id))
+            .collect::<Vec<_>>();
+
+        for id in order {
+            if !self.can_reach(id, id, &mut HashSet::new(), &characters) {
+                continue;
+            }
+
+            let symbol = self.debug_symbols[&id].clone();
+            let grow_id = self.insert(Instruction::Grow(id, None), symbol);
+            characters.insert(grow_id, characters[&id]);
+
+            for (other_id, instruction) in self.instructions.iter_mut() {
+                if other_id != grow_id {
+                    *instruction =
+                        instruction.remapped(|old_id| if old_id == id { grow_id } else { old_id });
+                }
+            }
+
+            if self.start == id {
+                self.start = grow_id;
+            }
+        }
+    }
+}
@@ -3,45 +3,180 @@ use std::collections::HashMap;
 use crate::core::Parser;
 use crate::core::{CompilerSettings, InstructionId};
 
+mod alphabet;
 mod cache_assignment;
+mod cache_elimination;
 mod cache_insertion;
+mod cache_limit;
+mod choice_ordering;
 mod debug_symbol_inference;
 mod deduplication;
 mod expected_inference;
+mod growth_insertion;
 mod normalize;
+mod rule_labelling;
+mod skip_insertion;
 mod sort;
 mod state_optimize;
+mod tail_recursion;
 mod trim;
 
-const OPT_PASSES: usize = 2;
+/// The parser as it was after a pass of transformation, recorded when
+/// [`CompilerSettings::trace_passes`] is set
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PassSnapshot {
+    /// The name of the pass
+    pub pass: String,
+    /// The round of optimization the pass ran in, counting from one, or
+    /// `None` for passes run before or after optimization
+    pub round: Option<usize>,
+    /// The number of instructions in the parser
+    pub instructions: usize,
+    /// The parser as [`Parser::dump_json`] renders it
+    pub ir: String,
+}
 
 impl Parser {
+    /// Rewrite grammar conveniences into plain instructions. Must be run
+    /// before validation, since the rewritten grammar is what gets validated
+    pub(super) fn desugar(&mut self) {
+        self.insert_skips();
+    }
+
+    /// Label every named rule with its name, so that the parse tree mirrors
+    /// the rules of the grammar. Must be run before label docs are collected
+    pub(super) fn label_all_rules(&mut self) {
+        self.insert_rule_labels();
+    }
+
+    /// Rewrite left recursion into growth points, so that it passes
+    /// validation. Must be run after desugaring
+    pub(super) fn accept_left_recursion(&mut self) {
+        self.insert_growth_points();
+    }
+
     /// Transform and optimize the parser, cannot be run on an ill-formed grammar
-    pub(super) fn transform(&mut self, settings: CompilerSettings) {
+    pub(super) fn transform(&mut self, settings: &CompilerSettings) {
         // Must be first since all ExpectedIds start out invalid
         self.infer_expecteds();
+        self.trace_pass(settings, "infer_expecteds", None);
+        self.deduplicate_expecteds();
+        self.trace_pass(settings, "deduplicate_expecteds", None);
+
+        if let Some(alphabet) = &settings.alphabet {
+            self.restrict_alphabet(alphabet);
+            self.trace_pass(settings, "restrict_alphabet", None);
+        }
+
+        if settings.report_unused_rules {
+            self.unused_rules = self.trim_reporting();
+        } else {
+            self.trim();
+        }
 
-        self.trim();
+        self.trace_pass(settings, "trim", None);
         self.sort();
+        self.trace_pass(settings, "sort", None);
 
-        for _ in 0..OPT_PASSES {
-            self.normalize(settings);
-            self.deduplicate();
+        // Passes are repeated until one leaves the grammar unchanged
+        let mut fingerprint = self.dump_json();
+
+        for pass in 1..=settings.max_passes {
+            self.optimization_passes = pass;
+            self.optimize(settings, pass);
+            self.debug_assert_consistent();
 
-            if settings.state_optimization {
-                self.state_optimize();
-                self.deduplicate();
+            let new_fingerprint = self.dump_json();
+            if new_fingerprint == fingerprint {
+                self.converged = true;
+                break;
             }
+
+            fingerprint = new_fingerprint;
+        }
+
+        if let Some(alphabet) = &settings.alphabet {
+            self.restrict_alphabet_any(alphabet);
+            self.trace_pass(settings, "restrict_alphabet_any", None);
         }
 
         if settings.cache_insertion {
             self.insert_cache_points();
+            self.trace_pass(settings, "insert_cache_points", None);
+        }
+
+        if settings.cache_elimination {
+            self.eliminated_caches = self.eliminate_redundant_caches();
+            self.trace_pass(settings, "eliminate_redundant_caches", None);
+        }
+
+        if let Some(max) = settings.max_cache_slots {
+            self.dropped_caches = self.limit_cache_points(max);
+            self.trace_pass(settings, "limit_cache_points", None);
         }
 
         self.assign_cache_ids();
+        self.trace_pass(settings, "assign_cache_ids", None);
 
         self.infer_debug_symbols();
+        self.trace_pass(settings, "infer_debug_symbols", None);
         self.sort();
+        self.trace_pass(settings, "sort", None);
+
+        if settings.report_unused_rules {
+            // Rules optimization found are never tried in one place may still be used elsewhere
+            let used = self.rule_names();
+            self.unused_rules.retain(|name| !used.contains(name));
+        }
+
+        self.debug_assert_invariants();
+    }
+
+    fn optimize(&mut self, settings: &CompilerSettings, round: usize) {
+        self.normalize(settings);
+        self.trace_pass(settings, "normalize", Some(round));
+
+        for pass in &settings.passes {
+            (pass.run)(self);
+            self.debug_assert_consistent();
+            self.trace_pass(settings, pass.name(), Some(round));
+        }
+
+        if settings.tail_recursion_elimination {
+            self.eliminate_tail_recursion();
+            self.trace_pass(settings, "eliminate_tail_recursion", Some(round));
+        }
+
+        if settings.choice_dispatch {
+            self.order_choices();
+            self.trace_pass(settings, "order_choices", Some(round));
+        }
+
+        self.deduplicate();
+        self.trace_pass(settings, "deduplicate", Some(round));
+
+        if settings.state_optimization {
+            self.state_optimize();
+            self.trace_pass(settings, "state_optimize", Some(round));
+            self.deduplicate();
+            self.trace_pass(settings, "deduplicate", Some(round));
+        }
+    }
+
+    /// Records the parser as it is after a pass, if passes are being traced
+    fn trace_pass(&mut self, settings: &CompilerSettings, pass: &str, round: Option<usize>) {
+        if !settings.trace_passes {
+            return;
+        }
+
+        let snapshot = PassSnapshot {
+            pass: pass.to_string(),
+            round,
+            instructions: self.instructions.len(),
+            ir: self.dump_json(),
+        };
+
+        self.pass_trace.push(snapshot);
     }
 
     /// Look up the mapped ID of an instruction, potentially following multiple
@@ -1,6 +1,6 @@
 use crate::core::character::Character;
 use crate::core::series::{Series, SeriesId};
-use crate::core::{CompilerSettings, DebugSymbol, Instruction, InstructionId, Parser};
+use crate::core::{CompilerSettings, DebugSymbol, Instruction, InstructionId, LabelId, Parser};
 use crate::ordered_set::OrderedSet;
 use std::collections::{HashMap, HashSet};
 use std::mem;
@@ -19,11 +19,18 @@ macro_rules! passes {
     };
 }
 
+/// Reordering stops once a round of normalization has grown the grammar to
+/// this many times its size, since some shapes of grammar make it explode
+const MAX_REORDER_GROWTH: usize = 8;
+
 const STAGES: &[&[Pass]] = &[
     passes!(
         resolve_delegate,
+        collapse_nested_labels,
+        eliminate_duplicate_alternatives,
         lower_to_first_choice,
-        lower_to_first_choice_without_seq
+        lower_to_first_choice_without_seq,
+        collapse_nested_optionals,
     ),
     passes!(
         replace_by_character,
@@ -31,8 +38,11 @@ const STAGES: &[&[Pass]] = &[
         eliminate_redundant_choices,
         translate_unnecessary_non_first_choice,
         eliminate_double_not_aheads,
+        fold_series_chain,
         concatenate_series,
         merge_series,
+        factor_optional_series_suffix,
+        factor_common_suffix,
     ),
     passes!(
         normalize_seq_order,
@@ -43,17 +53,21 @@ const STAGES: &[&[Pass]] = &[
 
 struct State<'a> {
     parser: &'a mut Parser,
-    settings: CompilerSettings,
+    settings: &'a CompilerSettings,
     queue: OrderedSet<InstructionId>,
     predecessors: HashMap<InstructionId, HashSet<InstructionId>>,
     characters: HashMap<InstructionId, Character>,
+    /// The number of instructions beyond which junctions are not reordered
+    reorder_budget: usize,
 }
 
 impl Parser {
-    pub(super) fn normalize(&mut self, settings: CompilerSettings) {
+    pub(super) fn normalize(&mut self, settings: &CompilerSettings) {
+        let reorder_budget = self.instructions.len() * MAX_REORDER_GROWTH;
+
         'normalize: loop {
             for stage in STAGES {
-                if self.run_passes(settings, stage) {
+                if self.run_passes(settings, stage, reorder_budget) {
                     continue 'normalize;
                 }
             }
@@ -62,7 +76,12 @@ impl Parser {
         }
     }
 
-    fn run_passes(&mut self, settings: CompilerSettings, passes: &[Pass]) -> bool {
+    fn run_passes(
+        &mut self,
+        settings: &CompilerSettings,
+        passes: &[Pass],
+        reorder_budget: usize,
+    ) -> bool {
         let mut modified = false;
 
         let mut queue = self.walk().map(|(id, _)| id).collect::<OrderedSet<_>>();
@@ -76,6 +95,7 @@ impl Parser {
             queue,
             predecessors,
             characters,
+            reorder_budget,
             parser: self,
         };
 
@@ -136,7 +156,7 @@ impl<'a> State<'a> {
             self.predecessors.get_mut(&successor).unwrap().insert(id);
         }
 
-        let characters = mem::replace(&mut self.characters, HashMap::new());
+        let characters = mem::take(&mut self.characters);
 
         self.characters = self.parser.patch_characters(characters, [id]);
 
@@ -167,6 +187,45 @@ impl<'a> State<'a> {
         Some(target)
     }
 
+    /// Replaces a label directly around another of the same label with the
+    /// inner label. The outer node would always hold exactly one child, an
+    /// identical copy of itself, so it carries no information
+    fn collapse_nested_labels(
+        &mut self,
+        _id: InstructionId,
+        instruction: Instruction,
+    ) -> Option<Instruction> {
+        let (label, _, target) = self.as_label(instruction)?;
+        let (inner_label, _, _) = self.as_label(target)?;
+
+        if label == inner_label {
+            Some(target)
+        } else {
+            None
+        }
+    }
+
+    /// Replaces a choice between an alternative and itself with just the
+    /// alternative, since the second attempt can never succeed where the first
+    /// failed
+    fn eliminate_duplicate_alternatives(
+        &mut self,
+        _id: InstructionId,
+        instruction: Instruction,
+    ) -> Option<Instruction> {
+        if !self.settings.redundant_junction_elimination {
+            return None;
+        }
+
+        let (left_id, left, right_id, _) = self.as_choice_like(instruction)?;
+
+        if left_id != right_id {
+            return None;
+        }
+
+        Some(left)
+    }
+
     fn lower_to_first_choice(
         &mut self,
         _id: InstructionId,
@@ -202,6 +261,35 @@ impl<'a> State<'a> {
         None
     }
 
+    /// Collapses an optional of an optional, as produced by lowering `(e?)?`,
+    /// into a single optional. The inner optional never fails, so the second
+    /// branch of the outer choice is never reached
+    fn collapse_nested_optionals(
+        &mut self,
+        _id: InstructionId,
+        instruction: Instruction,
+    ) -> Option<Instruction> {
+        if !self.settings.redundant_junction_elimination {
+            return None;
+        }
+
+        let (_, first, _, second) = self.as_first_choice(instruction)?;
+        let (_, empty) = self.as_series(second)?;
+
+        if !empty.is_empty() {
+            return None;
+        }
+
+        let (_, _, _, first_second) = self.as_first_choice(first)?;
+        let (_, first_empty) = self.as_series(first_second)?;
+
+        if !first_empty.is_empty() {
+            return None;
+        }
+
+        Some(first)
+    }
+
     fn concatenate_series(
         &mut self,
         _id: InstructionId,
@@ -215,11 +303,72 @@ impl<'a> State<'a> {
         let (_, first) = self.as_series(first)?;
         let (_, second) = self.as_series(second)?;
 
+        // Each series with a kind must remain a distinct token
+        if first.kind().is_some() || second.kind().is_some() {
+            return None;
+        }
+
         let new_series = Series::concatenate(first, second);
         let new_series_id = self.parser.series.insert(new_series);
         Some(Instruction::Series(new_series_id))
     }
 
+    /// Folds every series at the start of a right leaning chain of sequences
+    /// into one at once, rather than relying on repeated concatenation to
+    /// work its way up the chain
+    fn fold_series_chain(
+        &mut self,
+        id: InstructionId,
+        _instruction: Instruction,
+    ) -> Option<Instruction> {
+        if !self.settings.merge_series {
+            return None;
+        }
+
+        let mut folded = Series::empty();
+        let mut count = 0;
+        let mut visited = HashSet::new();
+        let mut current = id;
+
+        // The rest of the chain once its leading series have been folded
+        let rest = loop {
+            let (first, second) = match self.parser.instructions[current] {
+                Instruction::Seq(first, second) if visited.insert(current) => (first, second),
+                _ => break Some(current),
+            };
+
+            match self.as_foldable_series(first) {
+                Some(series) => folded = Series::concatenate(&folded, series),
+                None => break Some(current),
+            }
+
+            count += 1;
+
+            if let Some(series) = self.as_foldable_series(second) {
+                folded = Series::concatenate(&folded, series);
+                count += 1;
+                break None;
+            }
+
+            current = second;
+        };
+
+        if count < 2 {
+            return None;
+        }
+
+        let folded = self.parser.series.insert(folded);
+
+        match rest {
+            None => Some(Instruction::Series(folded)),
+            Some(rest) => {
+                let debug_symbol = self.parser.debug_symbols[&id].clone();
+                let folded = self.insert(Instruction::Series(folded), debug_symbol, [id]);
+                Some(Instruction::Seq(folded, rest))
+            }
+        }
+    }
+
     fn merge_series(
         &mut self,
         _id: InstructionId,
@@ -238,6 +387,89 @@ impl<'a> State<'a> {
         Some(Instruction::Series(new_series_id))
     }
 
+    /// Rewrites a choice between a series and one of its prefixes so that the
+    /// prefix is only scanned once, turning `ab / a` into `a b?`
+    fn factor_optional_series_suffix(
+        &mut self,
+        id: InstructionId,
+        instruction: Instruction,
+    ) -> Option<Instruction> {
+        if !self.settings.merge_series {
+            return None;
+        }
+
+        let (_, first, second_id, second) = self.as_first_choice(instruction)?;
+        let (_, first) = self.as_series(first)?;
+        let (_, second) = self.as_series(second)?;
+
+        if first.kind().is_some() || second.kind().is_some() {
+            return None;
+        }
+
+        let suffix = first.strip_prefix(second)?;
+
+        if second.is_empty() || second.is_never() || suffix.is_empty() {
+            return None;
+        }
+
+        let suffix_id = self.parser.series.insert(suffix);
+        let empty_id = self.parser.series.insert(Series::empty());
+
+        let debug_symbol = self.parser.debug_symbols[&id].clone();
+        let suffix_id = self.insert(Instruction::Series(suffix_id), debug_symbol.clone(), []);
+        let empty_id = self.insert(Instruction::Series(empty_id), debug_symbol.clone(), []);
+
+        let optional_suffix = self.insert(
+            Instruction::FirstChoice(suffix_id, empty_id),
+            debug_symbol,
+            [id],
+        );
+
+        Some(Instruction::Seq(second_id, optional_suffix))
+    }
+
+    /// Rewrites a choice between two sequences ending in the same instruction
+    /// so that the shared suffix is only matched once, turning `a x / b x`
+    /// into `(a / b) x`. Once the first branch has matched, the original
+    /// would backtrack into the second if the suffix failed or produced an
+    /// error, so this only applies to suffixes that can do neither
+    fn factor_common_suffix(
+        &mut self,
+        id: InstructionId,
+        instruction: Instruction,
+    ) -> Option<Instruction> {
+        if !self.settings.suffix_factoring {
+            return None;
+        }
+
+        let (_, first, _, second) = self.as_choice_like(instruction)?;
+        let (first_prefix, _, first_suffix, _) = self.as_seq(first)?;
+        let (second_prefix, _, second_suffix, _) = self.as_seq(second)?;
+
+        if first_suffix != second_suffix {
+            return None;
+        }
+
+        let suffix = self.characters[&first_suffix];
+        if suffix.fallible || suffix.error_prone {
+            return None;
+        }
+
+        let junction = match instruction {
+            Instruction::Choice(_, _) => Instruction::Choice(first_prefix, second_prefix),
+            _ => Instruction::FirstChoice(first_prefix, second_prefix),
+        };
+
+        let debug_symbol = self.parser.debug_symbols[&id].clone();
+        let junction = self.insert(junction, debug_symbol, [id]);
+
+        Some(Instruction::Seq(junction, first_suffix))
+    }
+
+    /// Replaces an instruction that always matches empty input with an empty
+    /// series, and one that never matches with a never series. This is what
+    /// reduces a not ahead of something infallible to never and a not ahead
+    /// of something unmatchable to empty, even when its target has to stay
     fn replace_by_character(
         &mut self,
         id: InstructionId,
@@ -323,6 +555,7 @@ impl<'a> State<'a> {
         };
 
         if !right_reachable {
+            self.mark_unused(right_id);
             return Some(left);
         }
 
@@ -337,6 +570,16 @@ impl<'a> State<'a> {
         None
     }
 
+    /// Records the rules an instruction is named after as unused, since it is
+    /// about to be dropped without ever being tried. They are only reported if
+    /// no other use of them survives optimization
+    fn mark_unused(&mut self, id: InstructionId) {
+        if self.settings.report_unused_rules {
+            let names = &self.parser.debug_symbols[&id].names;
+            self.parser.unused_rules.extend(names.iter().cloned());
+        }
+    }
+
     fn translate_unnecessary_non_first_choice(
         &mut self,
         _id: InstructionId,
@@ -373,6 +616,10 @@ impl<'a> State<'a> {
         id: InstructionId,
         instruction: Instruction,
     ) -> Option<Instruction> {
+        if !self.within_reorder_budget() {
+            return None;
+        }
+
         let (old_junction, old_junction_instruction, third, _) = self.as_seq(instruction)?;
         let (first, first_instruction, second, second_instruction) =
             self.as_seq(old_junction_instruction)?;
@@ -401,6 +648,10 @@ impl<'a> State<'a> {
         id: InstructionId,
         instruction: Instruction,
     ) -> Option<Instruction> {
+        if !self.within_reorder_budget() {
+            return None;
+        }
+
         let (_, old_junction_instruction, third, _) = self.as_choice(instruction)?;
         let (first, first_instruction, second, second_instruction) =
             self.as_choice(old_junction_instruction)?;
@@ -424,6 +675,10 @@ impl<'a> State<'a> {
         id: InstructionId,
         instruction: Instruction,
     ) -> Option<Instruction> {
+        if !self.within_reorder_budget() {
+            return None;
+        }
+
         let (_, old_junction_instruction, third, _) = self.as_first_choice(instruction)?;
         let (first, first_instruction, second, second_instruction) =
             self.as_first_choice(old_junction_instruction)?;
@@ -442,6 +697,18 @@ impl<'a> State<'a> {
         Some(Instruction::FirstChoice(first, new_junction))
     }
 
+    /// Whether junctions can still be reordered without the grammar growing
+    /// too large. Reordering only helps other passes, so once the budget is
+    /// exhausted it stops and the parser is flagged so the user can be warned
+    fn within_reorder_budget(&mut self) -> bool {
+        if self.parser.instructions.len() <= self.reorder_budget {
+            return true;
+        }
+
+        self.parser.reordering_limited = true;
+        false
+    }
+
     fn as_seq(
         &self,
         instruction: Instruction,
@@ -509,6 +776,15 @@ impl<'a> State<'a> {
         }
     }
 
+    /// Resolves an instruction to a series that can be concatenated with
+    /// others, since it isn't a distinct token
+    fn as_foldable_series(&self, id: InstructionId) -> Option<&Series> {
+        match self.as_series(self.parser.instructions[id]) {
+            Some((_, series)) if series.kind().is_none() => Some(series),
+            _ => None,
+        }
+    }
+
     fn as_not_ahead(&self, instruction: Instruction) -> Option<(InstructionId, Instruction)> {
         match instruction {
             Instruction::NotAhead(target) => Some((target, self.parser.instructions[target])),
@@ -516,6 +792,15 @@ impl<'a> State<'a> {
         }
     }
 
+    fn as_label(&self, instruction: Instruction) -> Option<(LabelId, InstructionId, Instruction)> {
+        match instruction {
+            Instruction::Label(target, label) => {
+                Some((label, target, self.parser.instructions[target]))
+            }
+            _ => None,
+        }
+    }
+
     fn as_delegate(&self, instruction: Instruction) -> Option<(InstructionId, Instruction)> {
         match instruction {
             Instruction::Delegate(target) => Some((target, self.parser.instructions[target])),
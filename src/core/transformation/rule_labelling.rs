@@ -0,0 +1,69 @@
+use std::collections::HashSet;
+
+use crate::core::{Instruction, Parser};
+
+impl Parser {
+    /// Rewrites the delegate at the entry of every named rule, `Delegate(body)`,
+    /// into `Delegate(Label(body))` with a label named after the rule. Rules
+    /// that already match a label are left alone, as are rules whose label
+    /// would clash with another in the generated label enum
+    pub(super) fn insert_rule_labels(&mut self) {
+        let mut identifiers = self
+            .labels()
+            .map(|(_, label)| Self::label_identifier(label))
+            .collect::<HashSet<_>>();
+
+        let mut seen = HashSet::new();
+        let mut entries = Vec::new();
+
+        for (id, instruction) in self.instructions() {
+            let body = match instruction {
+                Instruction::Delegate(body) => body,
+                _ => continue,
+            };
+
+            let names = &self.debug_symbols[&id].names;
+            if names.len() != 1 {
+                continue;
+            }
+
+            // A rule's entry is inserted before anything else in its body,
+            // which may contain more delegates carrying the rule's name
+            let name = names.iter().next().unwrap();
+            if !seen.insert(name.clone()) {
+                continue;
+            }
+
+            // Delegates may be chained, but never endlessly since that would
+            // be left recursive
+            let mut target = body;
+            for _ in 0..self.instructions.len() {
+                match self.instructions[target] {
+                    Instruction::Delegate(next) => target = next,
+                    _ => break,
+                }
+            }
+
+            if matches!(self.instructions[target], Instruction::Label(_, _)) {
+                continue;
+            }
+
+            if identifiers.insert(Self::label_identifier(name)) {
+                entries.push((id, body, name.clone()));
+            }
+        }
+
+        for (id, body, name) in entries {
+            let label = self.insert_label(name);
+            let symbol = self.debug_symbols[&id].clone();
+            let labelled = self.insert(Instruction::Label(body, label), symbol);
+            self.instructions[id] = Instruction::Delegate(labelled);
+        }
+    }
+
+    /// Approximates the enum variant generated for a label, so that labels
+    /// which may share a variant compare equal
+    fn label_identifier(label: &str) -> String {
+        label.replace('_', "").to_ascii_lowercase()
+    }
+}
@@ -0,0 +1,24 @@
+use std::mem;
+
+use crate::core::{Instruction, Parser};
+
+impl Parser {
+    /// Rewrites every spaced `Seq(a, b)` into `Seq(a, Seq(skip, b))` so that
+    /// the skip rule is matched between the elements of the sequence
+    pub(super) fn insert_skips(&mut self) {
+        let spaced_seqs = mem::take(&mut self.spaced_seqs);
+
+        let skip = match self.skip {
+            Some(skip) => skip,
+            None => return,
+        };
+
+        for id in spaced_seqs {
+            if let Instruction::Seq(first, second) = self.instructions[id] {
+                let symbol = self.debug_symbols[&id].clone();
+                let skipped = self.insert(Instruction::Seq(skip, second), symbol);
+                self.instructions[id] = Instruction::Seq(first, skipped);
+            }
+        }
+    }
+}
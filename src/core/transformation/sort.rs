@@ -15,11 +15,12 @@ impl Parser {
     }
 
     fn sort_instructions(&mut self) {
-        let mut new_instructions = Store::new();
+        let walk = self.walk().collect::<Vec<_>>();
+
+        let mut new_instructions = Store::with_capacity(walk.len());
         let mut new_debug_symbols = HashMap::new();
         let mut instruction_mappings = HashMap::new();
 
-        let walk = self.walk().collect::<Vec<_>>();
         for (id, instruction) in walk {
             let new_id = new_instructions.insert(instruction);
             instruction_mappings.insert(id, new_id);
@@ -40,20 +41,22 @@ impl Parser {
     fn sort_series(&mut self) {
         self.sort_resource(
             |parser| &mut parser.series,
-            |instruction| match instruction {
-                Instruction::Series(id) => Some(id),
-                _ => None,
-            },
-            |instruction, mappings| {
-                if let Instruction::Series(id) = instruction {
-                    *id = mappings[&id];
+            |instruction| instruction.series(),
+            |instruction, mappings| match instruction {
+                Instruction::Series(id) | Instruction::ConsumeUntil(id) => {
+                    *id = mappings[id];
+                }
+                Instruction::Balanced(open, close) => {
+                    *open = mappings[open];
+                    *close = mappings[close];
                 }
+                _ => {}
             },
         );
     }
 
     fn sort_labels(&mut self) {
-        self.sort_resource(
+        let mappings = self.sort_resource(
             |parser| &mut parser.labels,
             |instruction| match instruction {
                 Instruction::Label(_, id) => Some(id),
@@ -61,50 +64,59 @@ impl Parser {
             },
             |instruction, mappings| {
                 if let Instruction::Label(_, id) = instruction {
-                    *id = mappings[&id];
+                    *id = mappings[id];
                 }
             },
         );
+
+        for (_, series) in self.series.iter_mut() {
+            if let Some(kind) = series.kind() {
+                series.set_kind(Some(mappings[&kind]));
+            }
+        }
     }
 
+    /// Expecteds are ordered by their contents rather than by where they are first used, since
+    /// optimization reshapes the grammar and would otherwise shuffle them in generated code
     fn sort_expecteds(&mut self) {
-        self.sort_resource(
-            |parser| &mut parser.expecteds,
-            |instruction| match instruction {
-                Instruction::Error(_, id) => Some(id),
-                _ => None,
-            },
-            |instruction, mappings| {
-                if let Instruction::Error(_, id) = instruction {
-                    *id = mappings[&id];
-                }
-            },
-        );
+        let mut expecteds = self.expecteds.drain().collect::<Vec<_>>();
+        expecteds.sort_by(|(_, first), (_, second)| first.cmp(second));
+
+        let mut mappings = HashMap::new();
+        for (id, expected) in expecteds {
+            mappings.insert(id, self.expecteds.insert(expected));
+        }
+
+        for (_, instruction) in self.instructions.iter_mut() {
+            if let Instruction::Error(_, id) = instruction {
+                *id = mappings[id];
+            }
+        }
     }
 
-    fn sort_resource<K: StoreKey, V: Eq + Hash>(
+    fn sort_resource<K: StoreKey, V: Eq + Hash, E: IntoIterator<Item = K>>(
         &mut self,
         store: impl Fn(&mut Self) -> &mut Store<K, V>,
-        extract: impl Fn(Instruction) -> Option<K>,
+        extract: impl Fn(Instruction) -> E,
         fix: impl Fn(&mut Instruction, &HashMap<K, K>),
-    ) {
-        let mut new_store = Store::new();
+    ) -> HashMap<K, K> {
+        let mut new_store = Store::with_capacity(store(self).len());
         let mut mappings = HashMap::new();
 
         let walk = self.walk().collect::<Vec<_>>();
         for (_, instruction) in walk {
-            if let Some(id) = extract(instruction) {
-                if !mappings.contains_key(&id) {
+            for id in extract(instruction) {
+                mappings.entry(id).or_insert_with(|| {
                     let value = store(self).remove(id).unwrap();
-                    let new_id = new_store.insert(value);
-                    mappings.insert(id, new_id);
-                }
+
+                    new_store.insert(value)
+                });
             }
         }
 
         // We aren't allowed to remove resources, for example unused labels must not be trimmed
-        for (_k, v) in store(self).drain() {
-            new_store.insert(v);
+        for (k, v) in store(self).drain() {
+            mappings.insert(k, new_store.insert(v));
         }
 
         for (_, instruction) in self.instructions.iter_mut() {
@@ -112,5 +124,6 @@ impl Parser {
         }
 
         *store(self) = new_store;
+        mappings
     }
 }
@@ -434,7 +434,12 @@ impl Parser {
                     map.entry(target).or_default().match_implies_match(id);
                     map.entry(target).or_default().fail_implies_fail(id);
                 }
-                Instruction::Series(_) => {}
+                Instruction::Grow(_, _)
+                | Instruction::Repeat(_)
+                | Instruction::SepBy { .. }
+                | Instruction::Series(_)
+                | Instruction::ConsumeUntil(_)
+                | Instruction::Balanced(_, _) => {}
             }
         }
 
@@ -491,11 +496,11 @@ impl Parser {
         HashMap<InstructionId, Postconditions>,
     ) {
         let predecessors = self.compute_predecessors();
-        let implications = Rc::new(self.compute_implications(&characters));
+        let implications = Rc::new(self.compute_implications(characters));
 
         let mut stack = Stack::new(self.start);
 
-        let base = self.derive_base(&characters, implications.clone());
+        let base = self.derive_base(characters, implications.clone());
         let total = self.derive_total(implications.clone());
 
         let mut preconditions = HashMap::new();
@@ -514,7 +519,7 @@ impl Parser {
 
         while !stack.resolve.is_empty() {
             while !stack.propagate.is_empty() {
-                self.propagate_next(&mut stack, &mut preconditions, &postconditions, &characters);
+                self.propagate_next(&mut stack, &mut preconditions, &postconditions, characters);
             }
 
             self.resolve_next(
@@ -522,7 +527,7 @@ impl Parser {
                 &preconditions,
                 &mut postconditions,
                 &predecessors,
-                &characters,
+                characters,
                 &base,
                 &total,
             );
@@ -536,6 +541,7 @@ impl Parser {
         (preconditions, postconditions)
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn resolve_next(
         &self,
         stack: &mut Stack,
@@ -555,10 +561,10 @@ impl Parser {
             instruction,
             &instruction_preconditions,
             ResolveContext {
-                base: &base,
-                total: &total,
-                postconditions: &postconditions,
-                characters: &characters,
+                base,
+                total,
+                postconditions,
+                characters,
             },
         );
 
@@ -566,7 +572,7 @@ impl Parser {
             id,
             &characters[&id],
             &instruction_preconditions,
-            &total,
+            total,
             &mut new_postconditions,
         );
 
@@ -600,7 +606,7 @@ impl Parser {
                 preconditions,
                 postconditions,
                 stack,
-                characters: &characters,
+                characters,
             },
         );
     }
@@ -677,10 +683,32 @@ impl Parser {
             | Instruction::Error(target, _)
             | Instruction::Label(target, _)
             | Instruction::Cache(target, _)
+            | Instruction::Grow(target, _)
             | Instruction::Delegate(target) => {
                 ctx.update(target, preconditions.clone());
             }
-            Instruction::Series(_) => {}
+            Instruction::Repeat(target) => {
+                // Every iteration after the first begins where the previous one matched
+                let target_preconditions = if ctx.character(target).antitransparent {
+                    State::intersection(preconditions, &ctx.postconditions(target).positive)
+                } else {
+                    preconditions.clone()
+                };
+
+                ctx.update(target, target_preconditions);
+            }
+            Instruction::SepBy { item, sep, .. } => {
+                // Items after the first begin where a separator matched, and
+                // separators always begin where an item matched
+                let item_preconditions =
+                    State::intersection(preconditions, &ctx.postconditions(sep).positive);
+                let sep_preconditions = ctx.postconditions(item).positive.clone();
+
+                ctx.update(item, item_preconditions);
+                ctx.update(sep, sep_preconditions);
+            }
+            Instruction::Series(_) | Instruction::ConsumeUntil(_) | Instruction::Balanced(_, _) => {
+            }
         }
     }
 
@@ -702,7 +730,12 @@ impl Parser {
             | Instruction::Delegate(target) => {
                 self.resolve_delegate_like(target, preconditions, ctx)
             }
-            Instruction::Series(_) => Postconditions {
+            Instruction::Grow(_, _)
+            | Instruction::Repeat(_)
+            | Instruction::SepBy { .. }
+            | Instruction::Series(_)
+            | Instruction::ConsumeUntil(_)
+            | Instruction::Balanced(_, _) => Postconditions {
                 positive: ctx.base(),
                 negative: ctx.base(),
             },
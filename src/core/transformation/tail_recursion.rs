@@ -0,0 +1,85 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::core::character::Character;
+use crate::core::{Instruction, InstructionId, Parser};
+
+impl Parser {
+    /// Rewrites tail recursive optionals into repetitions so that the runtime
+    /// loops instead of recursing. An instruction of the form
+    /// `X = (a b ... X)?` is equivalent to `X = (a b ...)*`, and rules such as
+    /// `list = item (sep list)?` take this form once their sequences have
+    /// been normalized
+    pub(super) fn eliminate_tail_recursion(&mut self) {
+        let components = self.separate_components();
+        let characters = self.characterize();
+
+        for (_, component) in components.components.iter() {
+            for &id in &component.instructions {
+                let body = match self.tail_recursive_body(id, &component.instructions, &characters)
+                {
+                    Some(body) => body,
+                    None => continue,
+                };
+
+                let symbol = self.debug_symbols[&id].clone();
+                let mut body = body.into_iter().rev();
+                let mut target = body.next().unwrap();
+
+                for element in body {
+                    target = self.insert(Instruction::Seq(element, target), symbol.clone());
+                }
+
+                self.instructions[id] = Instruction::Repeat(target);
+            }
+        }
+
+        self.trim();
+    }
+
+    /// Finds the elements of the sequence repeated by a tail recursive
+    /// optional, if the instruction is one
+    fn tail_recursive_body(
+        &self,
+        id: InstructionId,
+        component: &BTreeSet<InstructionId>,
+        characters: &HashMap<InstructionId, Character>,
+    ) -> Option<Vec<InstructionId>> {
+        let (first, second) = match self.instructions[id] {
+            Instruction::FirstChoice(first, second) => (first, second),
+            _ => return None,
+        };
+
+        match self.instructions[second] {
+            Instruction::Series(series) if self.series[series].is_empty() => {}
+            _ => return None,
+        }
+
+        let mut body = Vec::new();
+        let mut visited = HashSet::new();
+        let mut current = first;
+
+        // Follow the right spine of the sequence, which must lead back to the
+        // optional without leaving its component
+        while current != id {
+            if !component.contains(&current) || !visited.insert(current) {
+                return None;
+            }
+
+            match self.instructions[current] {
+                Instruction::Seq(first, second) => {
+                    body.push(first);
+                    current = second;
+                }
+                _ => return None,
+            }
+        }
+
+        // A body that can match without consuming input would loop forever,
+        // although such a grammar would have been rejected as left recursive
+        if body.iter().all(|element| characters[element].transparent) {
+            return None;
+        }
+
+        Some(body)
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 
 use crate::core::{Instruction, Parser};
 use crate::store::{Store, StoreKey};
@@ -11,6 +11,25 @@ impl Parser {
         self.trim_expecteds();
     }
 
+    /// Like [`trim`](Self::trim), but also returns the names of any rules that were removed
+    /// entirely. Anonymous instructions are never reported, and neither are rules that still
+    /// name a reachable instruction
+    pub(super) fn trim_reporting(&mut self) -> BTreeSet<String> {
+        let names_before = self.rule_names();
+        self.trim();
+        let names_after = self.rule_names();
+
+        names_before.difference(&names_after).cloned().collect()
+    }
+
+    /// The names of every rule with an instruction remaining in the parser
+    pub(super) fn rule_names(&self) -> BTreeSet<String> {
+        self.debug_symbols
+            .values()
+            .flat_map(|symbol| symbol.names.iter().cloned())
+            .collect()
+    }
+
     fn trim_instructions(&mut self) {
         let mut reachable = HashSet::new();
 
@@ -47,10 +66,7 @@ impl Parser {
     fn trim_series(&mut self) {
         self.trim_resource(
             |parser| &mut parser.series,
-            |instruction| match instruction {
-                Instruction::Series(id) => Some(id),
-                _ => None,
-            },
+            |instruction| instruction.series(),
         );
     }
 
@@ -64,17 +80,15 @@ impl Parser {
         );
     }
 
-    fn trim_resource<K: StoreKey, V>(
+    fn trim_resource<K: StoreKey, V, E: IntoIterator<Item = K>>(
         &mut self,
         store: impl FnOnce(&mut Self) -> &mut Store<K, V>,
-        extract: impl Fn(Instruction) -> Option<K>,
+        extract: impl Fn(Instruction) -> E,
     ) {
         let mut reachable = HashSet::new();
 
         for (_, instruction) in self.instructions() {
-            if let Some(id) = extract(instruction) {
-                reachable.insert(id);
-            }
+            reachable.extend(extract(instruction));
         }
 
         let store = store(self);
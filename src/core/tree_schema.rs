@@ -0,0 +1,194 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use serde::Serialize;
+
+use crate::core::{Instruction, InstructionId, LabelId, Parser};
+
+/// The shapes of parse tree the grammar can produce
+#[derive(Serialize, Debug, Clone, Eq, PartialEq)]
+pub struct TreeSchema {
+    /// What may appear at the top of the tree, outside of any label
+    pub root: NodeSchema,
+    /// What may appear inside each label, including token kinds, by name
+    pub labels: BTreeMap<String, NodeSchema>,
+}
+
+/// What may appear directly inside a node of the parse tree
+#[derive(Serialize, Default, Debug, Clone, Eq, PartialEq)]
+pub struct NodeSchema {
+    /// The labels of nodes that may be children of the node. Errors are
+    /// looked through, so the labels inside an error count too
+    pub children: BTreeSet<String>,
+    /// Whether an error may be marked inside the node
+    pub errors: bool,
+}
+
+/// The labels and errors an instruction may produce before reaching the
+/// next label, ids rather than names
+#[derive(Default, Clone, Eq, PartialEq)]
+struct Reach {
+    labels: BTreeSet<LabelId>,
+    errors: bool,
+}
+
+impl Reach {
+    fn union(&mut self, other: &Reach) {
+        self.labels.extend(other.labels.iter().copied());
+        self.errors |= other.errors;
+    }
+}
+
+impl Parser {
+    /// Works out which labels may appear as children of each label, and
+    /// whether errors may appear inside it. Anything inside a negative
+    /// lookahead is thrown away, so it doesn't count
+    pub fn tree_schema(&self) -> TreeSchema {
+        let reaches = self.solve_fixed_point(
+            HashMap::new(),
+            self.instructions().map(|(id, _)| id),
+            Reach::default(),
+            |_, instruction, reaches| match instruction {
+                Instruction::Label(_, label) => Reach {
+                    labels: BTreeSet::from([label]),
+                    errors: false,
+                },
+                Instruction::Series(series) => Reach {
+                    labels: self.series[series].kind().into_iter().collect(),
+                    errors: false,
+                },
+                Instruction::NotAhead(_) => Reach::default(),
+                Instruction::Error(target, _) => Reach {
+                    labels: reaches[target].labels.clone(),
+                    errors: true,
+                },
+                instruction => {
+                    let mut reach = Reach::default();
+
+                    for successor in instruction.successors() {
+                        reach.union(&reaches[successor]);
+                    }
+
+                    reach
+                }
+            },
+        );
+
+        let mut labels = BTreeMap::<_, Reach>::new();
+        let mut visited = HashSet::new();
+        let mut stack = vec![self.start];
+
+        while let Some(id) = stack.pop() {
+            if !visited.insert(id) {
+                continue;
+            }
+
+            let instruction = self.instructions[id];
+
+            if !matches!(instruction, Instruction::NotAhead(_)) {
+                stack.extend(instruction.successors());
+            }
+
+            match instruction {
+                Instruction::Label(target, label) => {
+                    labels.entry(label).or_default().union(&reaches[&target]);
+                }
+                Instruction::Series(series) => {
+                    if let Some(kind) = self.series[series].kind() {
+                        labels.entry(kind).or_default();
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        TreeSchema {
+            root: self.node_schema(&reaches[&self.start]),
+            labels: labels
+                .iter()
+                .map(|(label, reach)| (self.labels[*label].clone(), self.node_schema(reach)))
+                .collect(),
+        }
+    }
+
+    /// Bounds how deeply groupings can nest in a parse tree, counting each
+    /// label, error and token as one level below the root. Returns `None` if
+    /// there is no bound, which is when recursion passes through a grouping,
+    /// such as a labelled rule that can contain itself
+    pub fn max_tree_depth(&self) -> Option<u32> {
+        let unbounded = self.recursive_groupings();
+
+        let depths = self.solve_fixed_point::<Option<u32>>(
+            HashMap::new(),
+            self.instructions().map(|(id, _)| id),
+            Some(0),
+            |id, instruction, depths| {
+                if unbounded.contains(&id) {
+                    return None;
+                }
+
+                match instruction {
+                    Instruction::Label(target, _) | Instruction::Error(target, _) => {
+                        depths[target]?.checked_add(1)
+                    }
+                    Instruction::Series(series) => {
+                        Some(self.series[series].kind().is_some() as u32)
+                    }
+                    // Whatever a lookahead matched is thrown away
+                    Instruction::NotAhead(_) => Some(0),
+                    instruction => instruction
+                        .successors()
+                        .try_fold(0, |depth, successor| Some(depth.max(depths[successor]?))),
+                }
+            },
+        );
+
+        depths[&self.start]
+    }
+
+    /// Finds the instructions that can run themselves again by way of a
+    /// grouping, so each time around nests another level into the tree
+    fn recursive_groupings(&self) -> HashSet<InstructionId> {
+        let components = self.separate_components();
+        let mut recursive = HashSet::new();
+
+        for (_, component) in components.components.iter() {
+            let cyclic = component.instructions.len() > 1
+                || component.instructions.iter().any(|id| {
+                    self.instructions[*id]
+                        .successors()
+                        .any(|successor| successor == *id)
+                });
+
+            let grouping = component
+                .instructions
+                .iter()
+                .any(|id| match self.instructions[*id] {
+                    Instruction::Label(_, _) | Instruction::Error(_, _) => true,
+                    Instruction::Series(series) => self.series[series].kind().is_some(),
+                    _ => false,
+                });
+
+            if cyclic && grouping {
+                recursive.extend(component.instructions.iter().copied());
+            }
+        }
+
+        recursive
+    }
+
+    /// Serializes the [`tree_schema`](Self::tree_schema) as JSON
+    pub fn dump_tree_schema_json(&self) -> String {
+        serde_json::to_string(&self.tree_schema()).unwrap()
+    }
+
+    fn node_schema(&self, reach: &Reach) -> NodeSchema {
+        NodeSchema {
+            children: reach
+                .labels
+                .iter()
+                .map(|label| self.labels[*label].clone())
+                .collect(),
+            errors: reach.errors,
+        }
+    }
+}
@@ -1,6 +1,8 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
 use crate::core::character::Character;
+use crate::core::first_set::FirstSet;
+use crate::core::series::Class;
 use crate::core::{Instruction, Parser};
 use crate::core::{InstructionId, ValidationError};
 
@@ -21,8 +23,366 @@ impl Parser {
         errors
     }
 
-    /// Determines if an instruction can be reached from another
-    fn can_reach(
+    /// Finds the rules that mark errors over something that can never match.
+    /// An error only appears in the parse tree when its target matches, so
+    /// these errors are dead, which usually means the target is mistaken
+    pub(super) fn find_unmatchable_errors(&self) -> BTreeSet<String> {
+        let characters = self.characterize();
+        let mut rules = BTreeSet::new();
+
+        for (id, instruction) in self.instructions() {
+            if let Instruction::Error(target, _) = instruction {
+                if !characters[&target].possible() {
+                    rules.extend(self.debug_symbols[&id].names.iter().cloned());
+                }
+            }
+        }
+
+        rules
+    }
+
+    /// Finds the rules that can never match because one of their own series
+    /// contains a class with no symbols in it, such as a class with every
+    /// symbol subtracted from it. Only rules reachable from the start rule
+    /// are reported. Rules that merely refer to such a rule are left out, as
+    /// are the built in `never` rule and rules already reported for marking
+    /// an unmatchable error
+    pub(super) fn find_never_rules(&self) -> BTreeSet<String> {
+        let characters = self.characterize();
+        let mut rules = BTreeSet::new();
+
+        for (id, instruction) in self.walk() {
+            let series = match instruction {
+                Instruction::Series(series) => series,
+                _ => continue,
+            };
+
+            if !self.series[series].is_never() {
+                continue;
+            }
+
+            for name in self.debug_symbols[&id].names.iter() {
+                if name == "never" || self.unmatchable_errors.contains(name) {
+                    continue;
+                }
+
+                let entry = self.instructions().find(|(id, instruction)| {
+                    matches!(instruction, Instruction::Delegate(_))
+                        && self.debug_symbols[id].names.contains(name)
+                });
+
+                if entry.is_none_or(|(entry, _)| !characters[&entry].possible()) {
+                    rules.insert(name.clone());
+                }
+            }
+        }
+
+        rules
+    }
+
+    /// Finds the rules with a choice that lists the same alternative more
+    /// than once, either by referring to the same rule twice or by spelling
+    /// out two alternatives that match identically. The later copy can never
+    /// match where the earlier one failed, so this is almost always a mistake.
+    /// Must be run before expecteds are inferred
+    pub(super) fn find_duplicate_alternatives(&self) -> BTreeSet<String> {
+        let nested = self
+            .instructions()
+            .filter_map(|(_, instruction)| match instruction {
+                Instruction::Choice(first, _) => Some(first),
+                _ => None,
+            })
+            .collect::<HashSet<_>>();
+
+        let mut rules = BTreeSet::new();
+
+        for (id, instruction) in self.instructions() {
+            if !matches!(instruction, Instruction::Choice(_, _)) || nested.contains(&id) {
+                continue;
+            }
+
+            let alternatives = self.choice_alternatives(id);
+
+            let duplicated = alternatives.iter().enumerate().any(|(i, first)| {
+                alternatives[i + 1..]
+                    .iter()
+                    .any(|second| self.equivalent(*first, *second))
+            });
+
+            if duplicated {
+                rules.extend(self.debug_symbols[&id].names.iter().cloned());
+            }
+        }
+
+        rules
+    }
+
+    /// Finds the labels that are only produced inside a negative lookahead.
+    /// A lookahead throws away whatever it matched, so these labels can never
+    /// appear in a parse tree, even though they are part of the generated
+    /// label type. Labels that can be produced outside a lookahead anywhere
+    /// in the grammar are not reported
+    pub(super) fn find_lookahead_labels(&self) -> BTreeSet<String> {
+        let characters = self.characterize();
+
+        let mut visible = HashSet::new();
+        let mut discarded = HashSet::new();
+
+        let mut visited = HashSet::new();
+        let mut stack = vec![(self.start, false)];
+
+        while let Some((id, in_lookahead)) = stack.pop() {
+            if !visited.insert((id, in_lookahead)) {
+                continue;
+            }
+
+            match self.instructions[id] {
+                Instruction::Label(target, label) => {
+                    if in_lookahead {
+                        discarded.insert(label);
+                    } else {
+                        visible.insert(label);
+                    }
+
+                    stack.push((target, in_lookahead));
+                }
+                Instruction::NotAhead(target) => stack.push((target, true)),
+                // The second half of a sequence only runs after the first matches
+                Instruction::Seq(first, second) => {
+                    stack.push((first, in_lookahead));
+
+                    if characters[&first].possible() {
+                        stack.push((second, in_lookahead));
+                    }
+                }
+                instruction => {
+                    for successor in instruction.successors() {
+                        stack.push((successor, in_lookahead));
+                    }
+                }
+            }
+        }
+
+        discarded
+            .difference(&visible)
+            .map(|label| self.labels[*label].clone())
+            .collect()
+    }
+
+    /// Finds the pairs of alternatives in a choice that can both match some
+    /// input, judging by their first sets. The earlier alternative always
+    /// wins, which is often intended but hides the ambiguity. Alternatives
+    /// starting with a negative lookahead are assumed to be guarded against
+    /// those before them, as in a strict choice, and those starting with an
+    /// error are recovering rather than ambiguous. Each pair is reported once
+    /// per rule
+    pub(super) fn find_overlapping_alternatives(&self) -> BTreeSet<Overlap> {
+        let nested = self
+            .instructions()
+            .filter_map(|(_, instruction)| match instruction {
+                Instruction::Choice(first, _) | Instruction::FirstChoice(first, _) => Some(first),
+                _ => None,
+            })
+            .collect::<HashSet<_>>();
+
+        let first_sets = self.first_sets();
+        let mut overlaps = BTreeMap::new();
+
+        for (id, instruction) in self.instructions() {
+            let is_choice = matches!(
+                instruction,
+                Instruction::Choice(_, _) | Instruction::FirstChoice(_, _)
+            );
+
+            let rule = match self.debug_symbols[&id].names.iter().next() {
+                Some(rule) if is_choice && !nested.contains(&id) => rule,
+                _ => continue,
+            };
+
+            let mut alternatives = self.choice_alternatives(id);
+            alternatives.reverse();
+
+            for (i, first) in alternatives.iter().enumerate() {
+                for (j, second) in alternatives.iter().enumerate().skip(i + 1) {
+                    if self.starts_guarded(*second, &first_sets) {
+                        continue;
+                    }
+
+                    let first_set = &first_sets[first];
+                    let second_set = &first_sets[second];
+
+                    let symbol =
+                        Class::intersection(&first_set.class, &second_set.class).first_symbol();
+
+                    if symbol.is_some() || first_set.empty && second_set.empty {
+                        overlaps
+                            .entry((rule.clone(), i + 1, j + 1))
+                            .or_insert(symbol);
+                    }
+                }
+            }
+        }
+
+        overlaps
+            .into_iter()
+            .map(|((rule, first, second), symbol)| Overlap {
+                rule,
+                first,
+                second,
+                symbol,
+            })
+            .collect()
+    }
+
+    /// Determines whether an instruction begins by checking that something
+    /// is not ahead or by marking an error, possibly after something that can
+    /// match empty input such as whitespace
+    fn starts_guarded(
+        &self,
+        id: InstructionId,
+        first_sets: &HashMap<InstructionId, FirstSet>,
+    ) -> bool {
+        let mut stack = vec![id];
+
+        while let Some(id) = stack.pop() {
+            match self.instructions[self.resolve_delegates(id)] {
+                Instruction::Seq(first, second) => {
+                    if first_sets[&first].empty {
+                        stack.push(second);
+                    }
+
+                    stack.push(first);
+                }
+                Instruction::NotAhead(_) | Instruction::Error(_, _) => return true,
+                _ => {}
+            }
+        }
+
+        false
+    }
+
+    /// Lists the alternatives of a choice, in reverse. Choices with several
+    /// alternatives are loaded as a chain of choices nested in their first
+    /// alternative, starting from a series that never matches. Alternatives
+    /// that never match are left out, since grammar conveniences use them
+    fn choice_alternatives(&self, mut id: InstructionId) -> Vec<InstructionId> {
+        let mut alternatives = Vec::new();
+
+        while let Instruction::Choice(first, second) | Instruction::FirstChoice(first, second) =
+            self.instructions[id]
+        {
+            alternatives.push(self.resolve_delegates(second));
+            id = first;
+        }
+
+        alternatives.push(self.resolve_delegates(id));
+
+        alternatives.retain(|id| match self.instructions[*id] {
+            Instruction::Series(series) => !self.series[series].is_never(),
+            _ => true,
+        });
+
+        alternatives
+    }
+
+    /// Determines whether two instructions match identically, by checking that
+    /// they are the same kind of instruction with equivalent successors.
+    /// Instructions reached again are assumed equivalent, which handles cycles
+    fn equivalent(&self, first: InstructionId, second: InstructionId) -> bool {
+        let mut assumed = HashSet::new();
+        let mut stack = vec![(first, second)];
+
+        while let Some((first, second)) = stack.pop() {
+            let first = self.resolve_delegates(first);
+            let second = self.resolve_delegates(second);
+
+            if first == second || !assumed.insert((first, second)) {
+                continue;
+            }
+
+            let equal = match (self.instructions[first], self.instructions[second]) {
+                (Instruction::Seq(a1, a2), Instruction::Seq(b1, b2))
+                | (Instruction::Choice(a1, a2), Instruction::Choice(b1, b2))
+                | (Instruction::FirstChoice(a1, a2), Instruction::FirstChoice(b1, b2)) => {
+                    stack.push((a1, b1));
+                    stack.push((a2, b2));
+                    true
+                }
+                (
+                    Instruction::SepBy {
+                        item: a_item,
+                        sep: a_sep,
+                        allow_trailing: a_trailing,
+                        min: a_min,
+                    },
+                    Instruction::SepBy {
+                        item: b_item,
+                        sep: b_sep,
+                        allow_trailing: b_trailing,
+                        min: b_min,
+                    },
+                ) => {
+                    stack.push((a_item, b_item));
+                    stack.push((a_sep, b_sep));
+                    a_trailing == b_trailing && a_min == b_min
+                }
+                (Instruction::NotAhead(a), Instruction::NotAhead(b))
+                | (Instruction::Repeat(a), Instruction::Repeat(b))
+                | (Instruction::Cache(a, _), Instruction::Cache(b, _))
+                | (Instruction::Grow(a, _), Instruction::Grow(b, _)) => {
+                    stack.push((a, b));
+                    true
+                }
+                // Expecteds refer to instructions until they are inferred
+                (Instruction::Error(a, a_expected), Instruction::Error(b, b_expected)) => {
+                    stack.push((a, b));
+                    stack.push((InstructionId(a_expected.0), InstructionId(b_expected.0)));
+                    true
+                }
+                (Instruction::Label(a, a_label), Instruction::Label(b, b_label)) => {
+                    stack.push((a, b));
+                    self.labels[a_label] == self.labels[b_label]
+                }
+                (Instruction::Series(a), Instruction::Series(b))
+                | (Instruction::ConsumeUntil(a), Instruction::ConsumeUntil(b)) => {
+                    let a = &self.series[a];
+                    let b = &self.series[b];
+                    let a_kind = a.kind().map(|kind| &self.labels[kind]);
+                    let b_kind = b.kind().map(|kind| &self.labels[kind]);
+                    a.classes() == b.classes() && a_kind == b_kind
+                }
+                (
+                    Instruction::Balanced(a_open, a_close),
+                    Instruction::Balanced(b_open, b_close),
+                ) => {
+                    self.series[a_open].classes() == self.series[b_open].classes()
+                        && self.series[a_close].classes() == self.series[b_close].classes()
+                }
+                _ => false,
+            };
+
+            if !equal {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Follows delegates to the instruction they match. Delegates cannot form
+    /// a cycle, since that would be left recursive
+    fn resolve_delegates(&self, mut id: InstructionId) -> InstructionId {
+        while let Instruction::Delegate(target) = self.instructions[id] {
+            id = target;
+        }
+
+        id
+    }
+
+    /// Determines if an instruction can be reached from another without
+    /// consuming input. Growth points are never passed through, since they
+    /// match left recursion without looping
+    pub(super) fn can_reach(
         &self,
         base: InstructionId,
         id: InstructionId,
@@ -62,8 +422,21 @@ impl Parser {
             | Instruction::Error(target, _)
             | Instruction::Label(target, _)
             | Instruction::Cache(target, _)
-            | Instruction::Delegate(target) => self.can_reach(base, target, visited, characters),
-            Instruction::Series(_) => false,
+            | Instruction::Delegate(target)
+            | Instruction::Repeat(target) => self.can_reach(base, target, visited, characters),
+            // Iterations that consume nothing end the list, so only the first
+            // item and separator can be reached without consuming input
+            Instruction::SepBy { item, sep, .. } => {
+                let item_transparent = characters[&item].transparent;
+                let item = self.can_reach(base, item, visited, characters);
+                let sep = item_transparent && self.can_reach(base, sep, visited, characters);
+
+                item || sep
+            }
+            Instruction::Grow(_, _)
+            | Instruction::Series(_)
+            | Instruction::ConsumeUntil(_)
+            | Instruction::Balanced(_, _) => false,
         };
 
         visited.remove(&id);
@@ -71,3 +444,17 @@ impl Parser {
         result
     }
 }
+
+/// A pair of alternatives in a choice that can both match some input
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Overlap {
+    /// The rule containing the choice
+    pub rule: String,
+    /// The position of the earlier alternative, counting from one
+    pub first: usize,
+    /// The position of the later alternative, counting from one
+    pub second: usize,
+    /// The smallest symbol both alternatives can start with, or none if the
+    /// alternatives only overlap by both matching empty input
+    pub symbol: Option<u32>,
+}
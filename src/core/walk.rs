@@ -4,7 +4,11 @@ use std::iter::FusedIterator;
 use crate::core::{Instruction, InstructionId, Parser};
 
 impl Parser {
-    pub(super) fn walk(&self) -> impl Iterator<Item = (InstructionId, Instruction)> + '_ {
+    /// Iterates over the instructions reachable from the start, depth first
+    /// with each instruction's successors visited in order. Every reachable
+    /// instruction is visited exactly once, and the order only depends on the
+    /// structure of the grammar, so it is stable between runs
+    pub fn walk(&self) -> impl Iterator<Item = (InstructionId, Instruction)> + '_ {
         Walk {
             parser: self,
             queue: vec![self.start],
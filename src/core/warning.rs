@@ -0,0 +1,117 @@
+use std::fmt::{self, Display, Formatter};
+
+use crate::core::Parser;
+
+/// A likely mistake in a grammar, which doesn't stop a parser from being
+/// generated
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub enum Warning {
+    /// A rule that can never be reached from the start rule
+    UnusedRule(String),
+    /// A rule marking an error around something that never matches
+    UnmatchableError(String),
+    /// A rule with a series that can never match, such as an emptied class
+    NeverMatches(String),
+    /// A rule with a choice between an alternative and itself
+    DuplicateAlternative(String),
+    /// A label only produced inside a lookahead, so it never appears in the tree
+    LookaheadLabel(String),
+    /// The optimizer stopped reordering the grammar to keep its size in check
+    ReorderingLimited,
+}
+
+impl Warning {
+    /// A stable name for the kind of warning, for tools reading warnings
+    pub fn code(&self) -> &'static str {
+        match self {
+            Warning::UnusedRule(_) => "unused-rule",
+            Warning::UnmatchableError(_) => "unmatchable-error",
+            Warning::NeverMatches(_) => "never-matches",
+            Warning::DuplicateAlternative(_) => "duplicate-alternative",
+            Warning::LookaheadLabel(_) => "lookahead-label",
+            Warning::ReorderingLimited => "reordering-limited",
+        }
+    }
+
+    /// The rule or label the warning is about, if any
+    pub fn subject(&self) -> Option<&str> {
+        match self {
+            Warning::UnusedRule(subject)
+            | Warning::UnmatchableError(subject)
+            | Warning::NeverMatches(subject)
+            | Warning::DuplicateAlternative(subject)
+            | Warning::LookaheadLabel(subject) => Some(subject),
+            Warning::ReorderingLimited => None,
+        }
+    }
+
+    /// The text of the message before and after the subject, so that it can
+    /// be highlighted
+    pub fn surrounding_text(&self) -> (&'static str, &'static str) {
+        match self {
+            Warning::UnusedRule(_) => ("Rule ", " is never used"),
+            Warning::UnmatchableError(_) => (
+                "Rule ",
+                " marks an error around something that never matches",
+            ),
+            Warning::NeverMatches(_) => ("Rule ", " can never match"),
+            Warning::DuplicateAlternative(_) => {
+                ("Rule ", " has a choice between an alternative and itself")
+            }
+            Warning::LookaheadLabel(_) => (
+                "Label ",
+                " is only produced inside a lookahead, so it never appears in the tree",
+            ),
+            Warning::ReorderingLimited => (
+                "Grammar grew too large to fully normalize, so it may be less optimized",
+                "",
+            ),
+        }
+    }
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let (before, after) = self.surrounding_text();
+        let subject = self.subject().unwrap_or("");
+        write!(f, "{}{}{}", before, subject, after)
+    }
+}
+
+impl Parser {
+    /// Every warning about the grammar, grouped by kind
+    pub fn warnings(&self) -> Vec<Warning> {
+        let mut warnings = Vec::new();
+
+        warnings.extend(
+            self.unused_rules()
+                .map(|rule| Warning::UnusedRule(rule.to_string())),
+        );
+
+        warnings.extend(
+            self.unmatchable_errors()
+                .map(|rule| Warning::UnmatchableError(rule.to_string())),
+        );
+
+        warnings.extend(
+            self.never_rules()
+                .map(|rule| Warning::NeverMatches(rule.to_string())),
+        );
+
+        warnings.extend(
+            self.duplicate_alternatives()
+                .map(|rule| Warning::DuplicateAlternative(rule.to_string())),
+        );
+
+        warnings.extend(
+            self.lookahead_labels()
+                .map(|label| Warning::LookaheadLabel(label.to_string())),
+        );
+
+        if self.reordering_limited() {
+            warnings.push(Warning::ReorderingLimited);
+        }
+
+        warnings
+    }
+}
@@ -1,4 +1,6 @@
+use std::env;
 use std::io::{Read, stdin};
+use std::process::exit;
 use std::time::Instant;
 
 #[path = "../parser.rs"]
@@ -6,21 +8,153 @@ mod parser;
 
 use parser::*;
 
+/// The input matched without any errors
+const EXIT_CLEAN: i32 = 0;
+/// The input matched, but the parse contains errors
+const EXIT_ERRORS: i32 = 1;
+/// The input failed to match, or the parse was abandoned
+const EXIT_FAILED: i32 = 2;
+
 pub fn main() {
     let mut input = Vec::new();
     stdin().read_to_end(&mut input).expect("could not read input");
 
+    // Parsers over wider symbols read each byte as a symbol
+    let input = input.into_iter().map(Symbol::from).collect::<Vec<_>>();
+
+    let skip_bom = env::args().any(|arg| arg == "--skip-bom");
+    let cache_stats = env::args().any(|arg| arg == "--cache-stats");
+    let sexp = env::args().any(|arg| arg == "--sexp");
+    let work = env::args().any(|arg| arg == "--work");
+
     let start = Instant::now();
-    let result = parse(input.as_slice());
+
+    let (result, profile) = if skip_bom {
+        let input = SkipBom::new(input.as_slice());
+
+        match run(&input, cache_stats) {
+            (Parse::Matched(result), profile) => {
+                (Parse::Matched(result.offset_by(input.offset())), profile)
+            }
+            result => result,
+        }
+    } else {
+        run(input.as_slice(), cache_stats)
+    };
+
+    let code = match &result {
+        Parse::Matched(result) if result.root().has_error() => EXIT_ERRORS,
+        Parse::Matched(_) => EXIT_CLEAN,
+        _ => EXIT_FAILED,
+    };
+
+    // A single line for peg-pack to read when checking a corpus against a baseline
+    if work {
+        match result {
+            Parse::Matched(result) => {
+                let errors = result.unmerged_errors().count();
+                println!("work {} errors {}", result.work(), errors);
+            }
+            _ => println!("failed"),
+        }
+
+        exit(code);
+    }
 
     match result {
         Parse::Matched(result) => {
             let errors = result.unmerged_errors().count();
             println!("Parsed in {:.1?} with {} error(s)", start.elapsed(), errors);
-            println!("{:#?}", result);
+
+            let end = result.root().position() + result.root().length();
+            if (end as usize) < input.len() {
+                println!(
+                    "Only matched the first {} of {} symbol(s), pass --require-eof to reject trailing input",
+                    end,
+                    input.len()
+                );
+            }
+
+            if sexp {
+                println!("{}", result.to_sexp());
+            } else {
+                println!("{:#?}", result);
+            }
         }
         Parse::Unmatched => {
             println!("Failed to parse in {:.1?}", start.elapsed());
         }
+        Parse::DepthExceeded => {
+            println!("Exceeded maximum depth after {:.1?}", start.elapsed());
+        }
+        Parse::InputTooLong => {
+            println!("Input was too long to parse");
+        }
+    }
+
+    if let Some(profile) = profile {
+        print_cache_stats(&profile);
+    }
+
+    #[cfg(coverage)]
+    print_coverage();
+
+    #[cfg(trace)]
+    print_backtracking();
+
+    exit(code);
+}
+
+fn run<I: Input<Symbol> + ?Sized>(input: &I, cache_stats: bool) -> (Parse, Option<CacheProfile>) {
+    if cache_stats {
+        let (result, profile) = parse_profiled(input, &ParseOptions::new());
+        (result, Some(profile))
+    } else {
+        (parse(input), None)
+    }
+}
+
+fn print_cache_stats(profile: &CacheProfile) {
+    for (slot, (rule, stats)) in CACHE_SLOTS.iter().zip(profile.slots()).enumerate() {
+        println!(
+            "Cache slot {} ({}): {} hit(s), {} miss(es), {} insertion(s), {} work saved",
+            slot, rule, stats.hits, stats.misses, stats.insertions, stats.work_saved
+        );
+    }
+}
+
+#[cfg(coverage)]
+fn print_coverage() {
+    let coverage = coverage();
+    let hit = coverage.iter().filter(|(_, count)| *count > 0).count();
+
+    println!("Covered {}/{} rule(s)", hit, coverage.len());
+
+    for (rule, count) in coverage {
+        if count == 0 {
+            println!("  Missed {}", rule);
+        }
+    }
+}
+
+/// The most positions listed when reporting where the parser backtracked to
+#[cfg(trace)]
+const MAX_BACKTRACK_POSITIONS: usize = 10;
+
+#[cfg(trace)]
+fn print_backtracking() {
+    let mut counts = backtracking()
+        .into_iter()
+        .enumerate()
+        .filter(|(_, count)| *count > 0)
+        .collect::<Vec<_>>();
+
+    let total = counts.iter().map(|(_, count)| count).sum::<usize>();
+    println!("Backtracked {} time(s) to {} position(s)", total, counts.len());
+
+    counts.sort_by_key(|(position, count)| (std::cmp::Reverse(*count), *position));
+
+    for (position, count) in counts.into_iter().take(MAX_BACKTRACK_POSITIONS) {
+        println!("  {} time(s) to {}", count, position);
     }
-}
\ No newline at end of file
+}
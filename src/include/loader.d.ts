@@ -10,23 +10,54 @@ declare class Rule {
   private constructor();
 }
 
+/**
+ * A recovery alternative created with `recovery`, which may only be passed as
+ * the last rule of a choice. Cannot be constructed directly.
+ */
+declare class Recovery {
+  private static readonly BRAND: unique symbol;
+  readonly [Recovery.BRAND]: unknown;
+  private constructor();
+}
+
 /**
  * A value that can be treated as a rule when passed to a grammar operator.
- * Function rules are only evaluated once and may be recursive.
+ * Function rules are only evaluated once and may be recursive. A named
+ * function rule may be documented by setting its `doc` property, which is
+ * copied into the generated parser, and setting its `noCache` property stops
+ * the optimizer from ever caching it.
  */
-type RuleLike = Rule | (() => RuleLike) | string;
+type RuleLike = Rule | FunctionRule | string;
+
+/**
+ * A rule defined by a function, optionally documented for the generated
+ * parser.
+ */
+type FunctionRule = (() => RuleLike) & { doc?: string; noCache?: boolean };
 
 /**
  * A continuous range of characters, or a string character, that can be
- * matched as one element.
+ * matched as one element. A named class stands for all of its characters.
  */
-type Range = [RangeBound, RangeBound] | RangeBound;
+type Range = [RangeBound, RangeBound] | RangeBound | NamedClass;
+
+/**
+ * A POSIX-style named class of ASCII characters: digits, letters, letters and
+ * digits, whitespace, or hexadecimal digits.
+ */
+type NamedClass = ":digit:" | ":alpha:" | ":alnum:" | ":space:" | ":xdigit:";
 
 /**
  * A bound in a range, either a character code or a string with one character.
  */
 type RangeBound = number | string;
 
+/**
+ * Options for a list matched by `sepBy`. By default lists may be empty and
+ * may not end with a separator.
+ */
+type SepByOptions = { min?: number; trailing?: boolean };
+
 /**
  * An interface for creating new rules through parsing operators. Variants of
  * the interface can be constructed that tweak the functionality.
@@ -54,7 +85,7 @@ interface GrammarInterface {
    *
    * The choice operator is associative, but is not commutative.
    */
-  readonly choice: (...rules: RuleLike[]) => Rule;
+  readonly choice: (...rules: RuleLike[] | [...RuleLike[], Recovery]) => Rule;
 
   /**
    * Matches the first rule that successfully matches regardless of error
@@ -62,7 +93,7 @@ interface GrammarInterface {
    *
    * Equivalent to `choice(first, seq(notAhead(first), second))`.
    */
-  readonly strictChoice: (...rules: RuleLike[]) => Rule;
+  readonly strictChoice: (...rules: RuleLike[] | [...RuleLike[], Recovery]) => Rule;
 
   /**
    * Attempts to match the provided rule, recovering if the match failed. The
@@ -83,6 +114,26 @@ interface GrammarInterface {
    */
   readonly recover: (...syncs: RuleLike[]) => (rule: RuleLike) => Rule;
 
+  /**
+   * Creates a recovery alternative to be passed as the last rule of a choice.
+   * It is only taken if none of the other alternatives match, in which case it
+   * discards input until one of the synchronization rules is ahead or the end
+   * of input has been reached, producing a single error.
+   *
+   * `choice(...rules, recovery(...syncs))` is equivalent to:
+   * ```
+   * strictChoice(
+   *   choice(...rules),
+   *   error(...rules)(rep(seq(notAhead(...syncs, eof), any)))
+   * )
+   * ```
+   *
+   * When the synchronization rules are a single literal or character class,
+   * or are all single characters, the discarded input is found with a single
+   * scan rather than a loop.
+   */
+  readonly recovery: (...syncs: RuleLike[]) => Recovery;
+
   /**
    * Matches the empty string if any of the provided rules would match,
    * otherwise does not match. Never matches if no rules are provided.
@@ -109,6 +160,13 @@ interface GrammarInterface {
    */
   readonly label: (label: string, rule: RuleLike) => Rule;
 
+  /**
+   * Matches the provided literal or character class, reporting it as a token
+   * of the provided kind if it does match. Tokens are lighter weight than
+   * labels but cannot contain other nodes.
+   */
+  readonly token: (kind: string, rule: RuleLike) => Rule;
+
   /**
    * Matches a single character if it appears in any of the provided ranges. If
    * no ranges are given, the rule with never match.
@@ -162,6 +220,16 @@ interface GrammarInterface {
    */
   readonly rep: (rule: RuleLike, separator?: RuleLike) => Rule;
 
+  /**
+   * Matches a list of items with separators between them, like `rep` with a
+   * separator, but as a single loop rather than a recursive rule. Options set
+   * the fewest items the list must contain, and whether a separator may follow
+   * the last item. A list stops once a separator and item consume no input.
+   *
+   * With the default options, equivalent to `rep(item, separator)`.
+   */
+  readonly sepBy: (item: RuleLike, separator: RuleLike, options?: SepByOptions) => Rule;
+
   /**
    * Attempts to match the provided rule until one of the synchronization
    * tokens is reached. Although this rule always matches, the result will
@@ -212,6 +280,17 @@ interface GrammarInterface {
    */
   readonly until: (...syncs: RuleLike[]) => (rule: RuleLike, separator?: RuleLike) => Rule;
 
+  /**
+   * Matches the opening delimiter, then skips input up to the closing
+   * delimiter that balances it, counting nested pairs along the way. Both
+   * delimiters must be strings or character classes, and the match fails if
+   * the end of input is reached first. Skipped input is not parsed, so this
+   * suits bodies that are handled later, like embedded code.
+   *
+   * For example, `balanced("{", "}")` matches `{ a { b } c }` in full.
+   */
+  readonly balanced: (open: RuleLike, close: RuleLike) => Rule;
+
   /**
    * Matches any single character.
    *
@@ -241,6 +320,15 @@ interface GrammarInterface {
    */
   readonly whitespace: (...rule: RuleLike[]) => GrammarInterface;
 
+  /**
+   * Designates a skip rule matching any number of occurrences of the provided
+   * rules, and constructs a variant of this grammar interface whose sequences
+   * match the skip rule between their elements. Unlike `whitespace`, the skip
+   * rule is inserted by the compiler rather than the grammar interface. Only
+   * one skip rule can be designated per grammar.
+   */
+  readonly skip: (...rule: RuleLike[]) => GrammarInterface;
+
   /**
    * Constructs a variant of this grammar interface where the `any` rule
    * attempts to match one of the provided rules before falling back to the
@@ -248,6 +336,20 @@ interface GrammarInterface {
    * `recover` rule.
    */
   readonly tokens: (...rules: RuleLike[]) => GrammarInterface;
+
+  /**
+   * Asserts a relation between the first sets of two rules, that is the
+   * characters their matches can start with and whether they can match empty
+   * input. The build fails if the relation does not hold.
+   */
+  readonly assert: (first: RuleLike, relation: "disjoint" | "subset" | "equal", second: RuleLike) => void;
+
+  /**
+   * Marks labels as trivia, such as whitespace and comments. Trivia nodes stay
+   * in the parse tree, but are skipped by `Cursor::significant_children` in
+   * the generated parser.
+   */
+  readonly trivia: (...labels: string[]) => void;
 }
 
 declare global {
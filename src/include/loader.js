@@ -3,13 +3,28 @@ const fs = require("fs");
 
 const grammarPath = process.env.PEG_PACK_GRAMMAR;
 const irPath = process.env.PEG_PACK_IR;
+const maxSymbol = process.env.PEG_PACK_SYMBOLS === "u32" ? 0xFFFFFFFF : 0xFF;
 
 const ruleNameStack = [];
 
+let grammarSource = undefined;
+
+let skipRule = undefined;
+
+const assertions = [];
+
+const triviaLabels = new Set();
+
 const instructions = [];
 const instructionIds = new Map();
 class Instruction {}
 
+class Recovery {
+    constructor(syncs) {
+        this.syncs = syncs;
+    }
+}
+
 class FunctionRuleError extends Error {}
 
 function createInstruction(name, object) {
@@ -34,6 +49,8 @@ function resolveInstruction(instruction) {
         return instructionIds.get(instruction);
     } else if (instruction instanceof Function) {
         return resolveFunctionRule(instruction);
+    } else if (instruction instanceof Recovery) {
+        throw new TypeError("Recovery alternatives can only be the last rule in a choice");
     } else if (typeof instruction === "string") {
         const codePoints = [];
 
@@ -80,7 +97,25 @@ function resolveFunctionRule(rule) {
     } catch (err) {
         rethrowFunctionRuleError(err);
     } finally {
-        instructions[id] = buildInstruction("delegate", { target: result });
+        const object = { target: result };
+
+        if (hasName && typeof rule.doc === "string") {
+            object.ruleDoc = rule.doc;
+        }
+
+        if (hasName && rule.noCache === true) {
+            object.noCache = true;
+        }
+
+        if (hasName) {
+            const span = findGrammarSpan(rule);
+
+            if (span !== undefined) {
+                object.grammarSpan = span;
+            }
+        }
+
+        instructions[id] = buildInstruction("delegate", object);
 
         if (hasName) {
             ruleNameStack.pop();
@@ -90,6 +125,34 @@ function resolveFunctionRule(rule) {
     return id;
 }
 
+function findGrammarSpan(rule) {
+    if (grammarSource === undefined) {
+        try {
+            grammarSource = fs.readFileSync(grammarPath, "utf8");
+        } catch {
+            grammarSource = null;
+        }
+    }
+
+    if (grammarSource === null) {
+        return undefined;
+    }
+
+    // Rules defined in another file, or whose source appears more than once,
+    // can't be located
+    const text = Function.prototype.toString.call(rule);
+    const index = grammarSource.indexOf(text);
+
+    if (index === -1 || grammarSource.indexOf(text, index + 1) !== -1) {
+        return undefined;
+    }
+
+    const start = Buffer.byteLength(grammarSource.slice(0, index));
+    const end = start + Buffer.byteLength(text);
+
+    return { file: grammarPath, start, end };
+}
+
 function rethrowFunctionRuleError(err) {
     const stack = [...ruleNameStack]
         .reverse()
@@ -127,8 +190,8 @@ function normalizeBound(bound) {
         throw new RangeError("Range bounds must be integers");
     }
 
-    if (bound < 0 || bound > 255) {
-        throw new RangeError("Range bounds must be between 0 and 255");
+    if (bound < 0 || bound > maxSymbol) {
+        throw new RangeError(`Range bounds must be between 0 and ${maxSymbol}`);
     }
 
     return bound;
@@ -157,22 +220,47 @@ function normalizeRange(range) {
     return [start, end];
 }
 
+const namedClasses = {
+    ":digit:": [["0", "9"]],
+    ":alpha:": [["A", "Z"], ["a", "z"]],
+    ":alnum:": [["0", "9"], ["A", "Z"], ["a", "z"]],
+    ":space:": [[0x09, 0x0D], " "],
+    ":xdigit:": [["0", "9"], ["A", "F"], ["a", "f"]],
+};
+
 function normalizeRanges(ranges) {
     if (!(ranges instanceof Array)) {
         throw new TypeError("Ranges must be an array");
     }
 
-    return ranges.map(normalizeRange);
+    return ranges.flatMap(range => {
+        if (typeof range === "string" && Object.hasOwn(namedClasses, range)) {
+            return namedClasses[range].map(normalizeRange);
+        } else {
+            return [normalizeRange(range)];
+        }
+    });
 }
 
 function seq(...rules) {
+    return buildSeq(rules, false);
+}
+
+function buildSeq(rules, spaced) {
     const instructions = rules.map(resolveInstruction);
 
     let result = g.empty;
 
-    for (const instruction of instructions) {
+    for (let i = 0; i < instructions.length; i++) {
         const resultInstruction = resolveInstruction(result);
-        result = createInstruction("seq", { first: resultInstruction, second: instruction });
+        const object = { first: resultInstruction, second: instructions[i] };
+
+        // The first element follows the empty series, so nothing is skipped before it
+        if (spaced && i !== 0) {
+            object.spaced = true;
+        }
+
+        result = createInstruction("seq", object);
     }
 
     return result;
@@ -195,6 +283,10 @@ function then(...syncs) {
 }
 
 function choice(...rules) {
+    if (rules.length > 0 && rules[rules.length - 1] instanceof Recovery) {
+        return buildRecoveryChoice.call(this, rules, this.choice);
+    }
+
     const instructions = rules.map(resolveInstruction);
 
     let result = g.never;
@@ -208,6 +300,10 @@ function choice(...rules) {
 }
 
 function strictChoice(...rules) {
+    if (rules.length > 0 && rules[rules.length - 1] instanceof Recovery) {
+        return buildRecoveryChoice.call(this, rules, this.strictChoice);
+    }
+
     let result = g.never;
 
     for (const rule of rules) {
@@ -219,6 +315,63 @@ function strictChoice(...rules) {
     return result;
 }
 
+function buildRecoveryChoice(rules, buildChoice) {
+    const alternatives = rules.slice(0, -1);
+    const { syncs } = rules[rules.length - 1];
+
+    const stops = scanStops.call(this, syncs);
+
+    let discard;
+    if (stops === undefined) {
+        discard = this.rep(this.seq(this.notAhead(...syncs, this.eof), this.any));
+    } else {
+        discard = createInstruction("consumeUntil", { classes: stops });
+    }
+
+    const recovery = this.error(...alternatives)(discard);
+
+    return this.strictChoice(buildChoice(...alternatives), recovery);
+}
+
+// Input can be discarded with a single scan when the synchronization rules
+// form one series, either by being a single series or by all being single
+// characters, and any has not been replaced by a grammar interface
+function scanStops(syncs) {
+    if (interfaceBases.get(this).any !== any) {
+        return undefined;
+    }
+
+    const classes = [];
+
+    for (const sync of syncs) {
+        let instruction = instructions[resolveInstruction(sync)];
+        while (instruction !== null && instruction.name === "delegate") {
+            instruction = instructions[instruction.target];
+        }
+
+        if (instruction === null || instruction.name !== "series") {
+            return undefined;
+        }
+
+        classes.push(instruction.classes);
+    }
+
+    if (classes.length === 1 && classes[0].length > 0) {
+        return classes[0];
+    }
+
+    if (classes.length > 0 && classes.every(series => series.length === 1 && !series[0].negated)) {
+        const ranges = classes.flatMap(series => series[0].ranges);
+        return [{ negated: false, ranges }];
+    }
+
+    return undefined;
+}
+
+function recovery(...syncs) {
+    return new Recovery(syncs);
+}
+
 function recover(...syncs) {
     const sync = this.ahead(...syncs, this.eof);
 
@@ -256,19 +409,38 @@ function error(...expecteds) {
     };
 }
 
-function label(label, rule) {
+function checkLabelName(label, description) {
     if (typeof label !== "string") {
-        throw new TypeError("Labels must be a string");
+        throw new TypeError(`${description} must be a string`);
     }
 
     if (!/[a-z]+(_[a-z]+)*/.test(label)) {
-        throw new TypeError(`Labels must be in all lowercase snakecase: ${label}`);
+        throw new TypeError(`${description} must be in all lowercase snakecase: ${label}`);
     }
+}
+
+function label(label, rule) {
+    checkLabelName(label, "Labels");
 
     const instruction = resolveInstruction(rule);
     return createInstruction("label", { target: instruction, label });
 }
 
+function token(kind, rule) {
+    checkLabelName(kind, "Token kinds");
+
+    let instruction = instructions[resolveInstruction(rule)];
+    while (instruction !== null && instruction.name === "delegate") {
+        instruction = instructions[instruction.target];
+    }
+
+    if (instruction === null || instruction.name !== "series") {
+        throw new TypeError("Tokens can only be created from literals and character classes");
+    }
+
+    return createInstruction("series", { classes: instruction.classes, kind });
+}
+
 function oneOf(...ranges) {
     ranges = normalizeRanges(ranges);
     return createInstruction("series", {
@@ -317,6 +489,20 @@ function rep(rule, separator = this.empty) {
     }
 }
 
+function sepBy(item, sep, { min = 0, trailing = false } = {}) {
+    if (!Number.isInteger(min) || min < 0) {
+        throw new TypeError(`Invalid minimum: ${min}, expected a non-negative integer`);
+    }
+
+    // Sequences around the separator let whitespace interfaces space it out
+    return createInstruction("sepBy", {
+        item: resolveInstruction(item),
+        sep: resolveInstruction(this.seq(this.empty, sep, this.empty)),
+        allowTrailing: Boolean(trailing),
+        min,
+    });
+}
+
 function untilOne(...syncs) {
     return (rule, separator = this.empty) => {
         const more = this.anonymize(() => this.choice(
@@ -354,6 +540,28 @@ function until(...syncs) {
     };
 }
 
+function balanced(open, close) {
+    return createInstruction("balanced", {
+        open: seriesClasses(open, "open"),
+        close: seriesClasses(close, "close"),
+    });
+}
+
+// Follows delegates from a rule to the series it matches, which balanced
+// delimiters must be so they can be scanned for
+function seriesClasses(rule, name) {
+    let instruction = instructions[resolveInstruction(rule)];
+    while (instruction !== null && instruction.name === "delegate") {
+        instruction = instructions[instruction.target];
+    }
+
+    if (instruction === null || instruction.name !== "series") {
+        throw new TypeError(`The ${name} delimiter of balanced must be a string or character class`);
+    }
+
+    return instruction.classes;
+}
+
 function any() {
     return this.noneOf();
 }
@@ -394,6 +602,21 @@ function whitespace(...rules) {
     return prepareInterface(newBase);
 }
 
+function skip(...rules) {
+    if (skipRule !== undefined) {
+        throw new Error("Only one skip rule can be designated per grammar");
+    }
+
+    skipRule = this.anonymize(() => this.rep(this.choice(...rules)));
+
+    const base = interfaceBases.get(this);
+    const newBase = { ...base };
+
+    newBase.seq = (...rules) => buildSeq(rules, true);
+
+    return prepareInterface(newBase);
+}
+
 function tokens(...rules) {
     const base = interfaceBases.get(this);
     const newBase = { ...base };
@@ -403,6 +626,23 @@ function tokens(...rules) {
     return prepareInterface(newBase);
 }
 
+const relations = ["disjoint", "subset", "equal"];
+
+function assert(first, relation, second) {
+    if (!relations.includes(relation)) {
+        throw new TypeError(`Invalid relation: ${relation}, expected one of ${relations.join(", ")}`);
+    }
+
+    assertions.push({ first, relation, second });
+}
+
+function trivia(...labels) {
+    for (const label of labels) {
+        checkLabelName(label, "Trivia labels");
+        triviaLabels.add(label);
+    }
+}
+
 const interfaceBases = new WeakMap();
 
 function prepareInterface(base) {
@@ -424,10 +664,12 @@ globalThis.g = prepareInterface({
     choice,
     strictChoice,
     recover,
+    recovery,
     ahead,
     notAhead,
     error,
     label,
+    token,
     oneOf,
     noneOf,
     empty,
@@ -435,13 +677,18 @@ globalThis.g = prepareInterface({
     opt,
     repOne,
     rep,
+    sepBy,
     untilOne,
     until,
+    balanced,
     any,
     eof,
     anonymize,
     whitespace,
+    skip,
     tokens,
+    assert,
+    trivia,
 });
 
 process.on("uncaughtException", err => {
@@ -458,11 +705,25 @@ const grammar = require(grammarPath);
     if (hasInstructionType(result)) {
         const start = resolveInstruction(result);
 
+        let skip = undefined;
+        if (skipRule !== undefined) {
+            skip = resolveInstruction(skipRule);
+        }
+
+        const resolvedAssertions = assertions.map(({ first, relation, second }) => ({
+            first: resolveInstruction(first),
+            relation,
+            second: resolveInstruction(second),
+        }));
+
         output = {
             version: 0,
             status: "success",
             instructions,
             start,
+            skip,
+            assertions: resolvedAssertions,
+            trivia: [...triviaLabels],
         };
     } else {
         output = {
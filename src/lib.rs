@@ -1,5 +1,40 @@
-//! Peg Pack doesn't currently have a stable Rust API.
-//! Click [here](https://peg-pack.netlify.app) for instructions on using the CLI.
+//! Peg Pack is mostly used through its CLI.
+//! Click [here](https://peg-pack.netlify.app) for instructions on using it.
+//!
+//! Parsers can also be generated from a `build.rs` with [`generate_parser`] and
+//! [`write_runtime`], given the IR of a grammar saved from an earlier run of the
+//! CLI as `build/ir.json`:
+//!
+//! ```no_run
+//! use std::env;
+//! use std::fs;
+//! use std::path::PathBuf;
+//!
+//! use peg_pack::core::CompilerSettings;
+//!
+//! let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+//! let ir = fs::read("grammar.json").unwrap();
+//!
+//! let parser = peg_pack::generate_parser(&ir, CompilerSettings::normal()).unwrap();
+//! fs::write(out_dir.join("parser.rs"), parser).unwrap();
+//! peg_pack::write_runtime(&out_dir).unwrap();
+//! ```
+//!
+//! The parser can then be included into a module of the crate:
+//!
+//! ```ignore
+//! mod parser {
+//!     include!(concat!(env!("OUT_DIR"), "/parser.rs"));
+//! }
+//! ```
+//!
+//! Everything else is unstable.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::core::{CompilerSettings, Error, GenerationSettings, ModuleConfig, Parser};
 
 #[doc(hidden)]
 pub mod cli;
@@ -7,5 +42,53 @@ pub mod cli;
 pub mod core;
 mod ordered_set;
 mod output;
-mod runtime;
 mod store;
+
+/// Compiles the IR of a grammar into the source code of a parser.
+///
+/// The parser declares its runtime as a module at `runtime/mod.rs`, relative to wherever the
+/// source code is written, so [`write_runtime`] should be given the same directory. It has no
+/// inner doc comment, so that it can be pulled into a module with `include!`.
+pub fn generate_parser(ir: &[u8], settings: CompilerSettings) -> Result<String, Error> {
+    let parser = Parser::load(ir, settings)?;
+
+    let module = ModuleConfig {
+        runtime_path: String::from("runtime/mod.rs"),
+        inner_docs: false,
+        ..ModuleConfig::normal()
+    };
+
+    Ok(parser.generate(GenerationSettings::normal(), &module).root)
+}
+
+/// Compiles the IR of a grammar into the source code of a parser that depends on the
+/// `peg-pack-runtime` crate, rather than a copy of the runtime written by [`write_runtime`].
+///
+/// The crate must be a dependency of the project, at the same version as this crate.
+pub fn generate_parser_for_runtime_crate(
+    ir: &[u8],
+    settings: CompilerSettings,
+) -> Result<String, Error> {
+    let parser = Parser::load(ir, settings)?;
+
+    let module = ModuleConfig {
+        inner_docs: false,
+        runtime_crate: true,
+        ..ModuleConfig::normal()
+    };
+
+    Ok(parser.generate(GenerationSettings::normal(), &module).root)
+}
+
+/// Writes the runtime that generated parsers depend on into a `runtime` directory within `dir`,
+/// creating it if necessary.
+pub fn write_runtime(dir: &Path) -> io::Result<()> {
+    let runtime_dir = dir.join("runtime");
+    fs::create_dir_all(&runtime_dir)?;
+
+    for (name, contents) in peg_pack_runtime::SOURCE_FILES {
+        fs::write(runtime_dir.join(name), contents)?;
+    }
+
+    Ok(())
+}
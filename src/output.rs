@@ -20,7 +20,7 @@ impl Codegen {
         let new_len = trimmed.len();
         self.buffer.truncate(new_len);
 
-        self.buffer.push_str("\n");
+        self.buffer.push('\n');
         self.buffer
     }
 
@@ -29,7 +29,18 @@ impl Codegen {
         self.newline();
     }
 
-    pub fn function(&mut self, signature: &str) -> Statements {
+    /// Writes a doc comment, one line of the comment per line of the text
+    pub fn doc(&mut self, doc: &str) {
+        for line in doc.lines() {
+            if line.is_empty() {
+                self.line("///");
+            } else {
+                self.line(&format!("/// {}", line));
+            }
+        }
+    }
+
+    pub fn function(&mut self, signature: &str) -> Statements<'_> {
         self.line("#[allow(unused)]");
         self.write(signature);
         self.space();
@@ -41,7 +52,7 @@ impl Codegen {
         })
     }
 
-    pub fn enumeration(&mut self, name: &str, public: bool) -> Enum {
+    pub fn enumeration(&mut self, name: &str, public: bool) -> Enum<'_> {
         if public {
             self.write("pub ");
         }
@@ -54,7 +65,7 @@ impl Codegen {
         Enum { codegen: self }
     }
 
-    pub fn trait_impl(&mut self, name: &str, target: &str) -> Trait {
+    pub fn trait_impl(&mut self, name: &str, target: &str) -> Trait<'_> {
         self.write("impl ");
         self.write(name);
         self.write(" for ");
@@ -65,6 +76,20 @@ impl Codegen {
         Trait {
             codegen: self,
             first: false,
+            documented: false,
+        }
+    }
+
+    pub fn inherent_impl(&mut self, target: &str) -> Trait<'_> {
+        self.write("impl ");
+        self.write(target);
+        self.space();
+        self.open_brace();
+
+        Trait {
+            codegen: self,
+            first: false,
+            documented: false,
         }
     }
 
@@ -138,11 +163,11 @@ impl<'a> Statements<'a> {
         self.codegen.newline();
     }
 
-    pub fn match_statement(&mut self, control: &str) -> Match {
+    pub fn match_statement(&mut self, control: &str) -> Match<'_> {
         Match::new(self.codegen, control)
     }
 
-    pub fn if_statement(&mut self, control: &str) -> Statements {
+    pub fn if_statement(&mut self, control: &str) -> Statements<'_> {
         self.codegen.write("if ");
         self.codegen.write(control);
         self.codegen.space();
@@ -150,6 +175,15 @@ impl<'a> Statements<'a> {
 
         Statements::new(self.codegen, Codegen::close_brace)
     }
+
+    pub fn while_statement(&mut self, control: &str) -> Statements<'_> {
+        self.codegen.write("while ");
+        self.codegen.write(control);
+        self.codegen.space();
+        self.codegen.open_brace();
+
+        Statements::new(self.codegen, Codegen::close_brace)
+    }
 }
 
 impl<'a> Drop for Statements<'a> {
@@ -196,6 +230,11 @@ impl<'a> Enum<'a> {
         self.codegen.write(name);
         self.codegen.line(",");
     }
+
+    /// Writes a doc comment for the next variant
+    pub fn doc(&mut self, doc: &str) {
+        self.codegen.doc(doc);
+    }
 }
 
 impl<'a> Drop for Enum<'a> {
@@ -208,16 +247,29 @@ impl<'a> Drop for Enum<'a> {
 pub struct Trait<'a> {
     codegen: &'a mut Codegen,
     first: bool,
+    documented: bool,
 }
 
 impl<'a> Trait<'a> {
-    pub fn function(&mut self, signature: &str) -> Statements {
-        if self.first {
-            self.codegen.newline();
-        } else {
-            self.first = true;
+    /// Writes a doc comment for the next function
+    pub fn doc(&mut self, doc: &str) {
+        self.separate();
+        self.documented = true;
+        self.codegen.doc(doc);
+    }
+
+    pub fn associated_type(&mut self, name: &str, value: &str) {
+        self.separate();
+        self.codegen.line(&format!("type {} = {};", name, value));
+    }
+
+    pub fn function(&mut self, signature: &str) -> Statements<'_> {
+        if !self.documented {
+            self.separate();
         }
 
+        self.documented = false;
+
         self.codegen.line("#[allow(unused)]");
         self.codegen.write(signature);
         self.codegen.space();
@@ -229,6 +281,16 @@ impl<'a> Trait<'a> {
     }
 }
 
+impl<'a> Trait<'a> {
+    fn separate(&mut self) {
+        if self.first {
+            self.codegen.newline();
+        } else {
+            self.first = true;
+        }
+    }
+}
+
 impl<'a> Drop for Trait<'a> {
     fn drop(&mut self) {
         self.codegen.close_brace();
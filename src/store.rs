@@ -1,4 +1,3 @@
-use std::collections::BTreeMap;
 use std::fmt::{Debug, Formatter};
 use std::hash::Hash;
 use std::marker::PhantomData;
@@ -17,16 +16,26 @@ pub trait StoreKey: Copy + Eq + Ord + Hash {
 /// type. Insertion automatically generates a new key that has not yet been used
 pub struct Store<K, V> {
     next_id: usize,
-    map: BTreeMap<usize, V>,
+    /// The value of each key, indexed by the key. Keys are handed out in
+    /// order, so this stays dense until values are removed
+    values: Vec<Option<V>>,
+    len: usize,
     marker: PhantomData<K>,
 }
 
 impl<K: StoreKey, V> Store<K, V> {
     pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    /// Creates an empty store with room for a number of values before it
+    /// needs to reallocate
+    pub fn with_capacity(capacity: usize) -> Self {
         Self {
             next_id: 0,
-            map: BTreeMap::new(),
-            marker: PhantomData::default(),
+            values: Vec::with_capacity(capacity),
+            len: 0,
+            marker: PhantomData,
         }
     }
 
@@ -47,28 +56,56 @@ impl<K: StoreKey, V> Store<K, V> {
     pub fn set(&mut self, id: K, value: V) {
         let id = id.into_usize();
         self.next_id = self.next_id.max(id + 1);
-        self.map.insert(id, value);
+
+        if id >= self.values.len() {
+            self.values.resize_with(id + 1, || None);
+        }
+
+        if self.values[id].replace(value).is_none() {
+            self.len += 1;
+        }
     }
 
     pub fn remove(&mut self, id: K) -> Option<V> {
-        self.map.remove(&id.into_usize())
+        let value = self.values.get_mut(id.into_usize())?.take();
+
+        if value.is_some() {
+            self.len -= 1;
+        }
+
+        value
     }
 
     pub fn iter(&self) -> impl DoubleEndedIterator<Item = (K, &V)> {
-        self.map.iter().map(|(k, v)| (K::from_usize(*k), v))
+        self.values
+            .iter()
+            .enumerate()
+            .filter_map(|(k, v)| Some((K::from_usize(k), v.as_ref()?)))
     }
 
     pub fn iter_mut(&mut self) -> impl DoubleEndedIterator<Item = (K, &mut V)> {
-        self.map.iter_mut().map(|(k, v)| (K::from_usize(*k), v))
+        self.values
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(k, v)| Some((K::from_usize(k), v.as_mut()?)))
     }
 
     pub fn drain(&mut self) -> impl DoubleEndedIterator<Item = (K, V)> {
         let store = mem::replace(self, Self::new());
-        store.map.into_iter().map(|(k, v)| (K::from_usize(k), v))
+
+        store
+            .values
+            .into_iter()
+            .enumerate()
+            .filter_map(|(k, v)| Some((K::from_usize(k), v?)))
     }
 
     pub fn len(&self) -> usize {
-        self.map.len()
+        self.len
+    }
+
+    pub fn contains_key(&self, id: K) -> bool {
+        matches!(self.values.get(id.into_usize()), Some(Some(_)))
     }
 }
 
@@ -76,13 +113,13 @@ impl<K: StoreKey, V> Index<K> for Store<K, V> {
     type Output = V;
 
     fn index(&self, index: K) -> &V {
-        self.map.get(&index.into_usize()).unwrap()
+        self.values[index.into_usize()].as_ref().unwrap()
     }
 }
 
 impl<K: StoreKey, V> IndexMut<K> for Store<K, V> {
     fn index_mut(&mut self, index: K) -> &mut V {
-        self.map.get_mut(&index.into_usize()).unwrap()
+        self.values[index.into_usize()].as_mut().unwrap()
     }
 }
 
@@ -96,25 +133,30 @@ impl<K: StoreKey, V: Eq + PartialEq> Eq for Store<K, V> {}
 
 impl<K: StoreKey, V: PartialEq> PartialEq<Self> for Store<K, V> {
     fn eq(&self, other: &Self) -> bool {
-        self.map == other.map
+        self.iter().eq(other.iter())
     }
 }
 
-impl<K, V: Debug> Debug for Store<K, V> {
+impl<K: StoreKey, V: Debug> Debug for Store<K, V> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        Debug::fmt(&self.map, f)
+        f.debug_map()
+            .entries(self.iter().map(|(k, v)| (k.into_usize(), v)))
+            .finish()
     }
 }
 
 impl<K, V: Serialize> Serialize for Store<K, V> {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        let maximum = self.map.keys().map(|value| value + 1).max().unwrap_or(0);
+        let maximum = self
+            .values
+            .iter()
+            .rposition(|value| value.is_some())
+            .map_or(0, |last| last + 1);
 
         let mut seq = serializer.serialize_seq(Some(maximum))?;
 
-        for i in 0..maximum {
-            let value = self.map.get(&i);
-            seq.serialize_element(&value)?;
+        for value in &self.values[..maximum] {
+            seq.serialize_element(value)?;
         }
 
         seq.end()
@@ -0,0 +1,130 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Generates a parser for a grammar script with some extra arguments, then compiles the tests in
+/// `tests/api` of the same name against it and runs them. Each test is given its own output
+/// directory, named after it
+fn check(name: &str, grammar: &str, args: &[&str], tests: &str) {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"))
+        .join("api")
+        .join(name);
+    fs::create_dir_all(&dir).unwrap();
+
+    let grammar_path = dir.join("grammar.js");
+    fs::write(&grammar_path, grammar).unwrap();
+
+    let out = dir.join("out");
+    let output = Command::new(env!("CARGO_BIN_EXE_peg-pack"))
+        .arg(&grammar_path)
+        .arg("--out-dir")
+        .arg(&out)
+        .args(args)
+        .output()
+        .unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // Laid out like the interactive harness, next to the runtime
+    let source = out.join("build/api.rs");
+    let source_text = format!(
+        "#[path = \"../parser.rs\"]\nmod parser;\nuse parser::*;\n\n{}",
+        tests
+    );
+    fs::write(&source, source_text).unwrap();
+
+    let executable = out.join("build/api");
+    let output = Command::new("rustc")
+        .args(["--edition", "2021", "--test", "-o"])
+        .arg(&executable)
+        .arg(&source)
+        .output()
+        .unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let output = Command::new(&executable).output().unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+}
+
+/// Statements are an assignment or a call, recovering up to the semicolon that ends them
+const STATEMENTS: &str = r#"
+    const statement = () => g.choice(
+        g.label("assignment", g.seq("a", "=")),
+        g.label("call", g.seq("a", "()")),
+        g.recovery(";"),
+    );
+
+    module.exports = g.seq(statement, ";");
+"#;
+
+/// Lists of atoms and other lists, with errors in place of anything else
+const LISTS: &str = r#"
+    const list = () => g.label("list", g.seq("(", g.rep(item), ")"));
+    const item = () => g.choice(list, g.label("atom", "a"), g.error(list)(g.noneOf("(", ")")));
+
+    module.exports = g.rep(item);
+"#;
+
+#[test]
+fn try_parse() {
+    check(
+        "try_parse",
+        STATEMENTS,
+        &[],
+        include_str!("api/try_parse.rs"),
+    );
+}
+
+#[test]
+fn annotated_source() {
+    check(
+        "annotated_source",
+        STATEMENTS,
+        &[],
+        include_str!("api/annotated_source.rs"),
+    );
+}
+
+#[test]
+fn parse_from() {
+    check(
+        "parse_from",
+        STATEMENTS,
+        &[],
+        include_str!("api/parse_from.rs"),
+    );
+}
+
+#[test]
+fn flatten() {
+    check("flatten", LISTS, &[], include_str!("api/flatten.rs"));
+}
+
+#[test]
+fn leaves() {
+    check("leaves", LISTS, &[], include_str!("api/leaves.rs"));
+}
+
+#[test]
+fn parse_lenient() {
+    check(
+        "parse_lenient",
+        STATEMENTS,
+        &[],
+        include_str!("api/parse_lenient.rs"),
+    );
+}
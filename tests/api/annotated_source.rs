@@ -0,0 +1,21 @@
+#[test]
+fn errors_are_marked_with_what_was_expected() {
+    let input = b"@@@;";
+    let result = parse(input.as_slice()).unwrap();
+
+    assert_eq!(
+        result.annotated_source(input),
+        "«error: expected assignment or call»@@@;"
+    );
+}
+
+#[test]
+fn labels_are_marked_by_name() {
+    let input = b"a();";
+    let result = parse(input.as_slice()).unwrap();
+
+    assert_eq!(
+        result.annotated_source_with_labels(input),
+        "«call»a()«/call»;"
+    );
+}
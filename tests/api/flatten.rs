@@ -0,0 +1,63 @@
+/// The label of a node, or `None` for an error
+fn label(node: &FlatNode) -> Option<Label> {
+    match node.grouping {
+        Grouping::Label(label) => Some(label),
+        Grouping::Error { .. } => None,
+        grouping => panic!("unexpected {:?}", grouping),
+    }
+}
+
+#[test]
+fn nodes_are_linked_to_their_children_and_siblings() {
+    let tree = parse(b"(a(a@)a)".as_slice()).unwrap().flatten();
+
+    let summary = tree
+        .nodes()
+        .iter()
+        .map(|node| {
+            (
+                label(node),
+                node.position,
+                node.length,
+                node.first_child,
+                node.next_sibling,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        summary,
+        [
+            (Some(Label::List), 0, 8, Some(1), None),
+            (Some(Label::Atom), 1, 1, None, Some(2)),
+            (Some(Label::List), 2, 4, Some(3), Some(5)),
+            (Some(Label::Atom), 3, 1, None, Some(4)),
+            (None, 4, 1, None, None),
+            (Some(Label::Atom), 6, 1, None, None),
+        ]
+    );
+
+    let inner = tree.get(2).unwrap();
+    let children = tree.children(inner).map(label).collect::<Vec<_>>();
+    assert_eq!(children, [Some(Label::Atom), None]);
+
+    assert_eq!(tree.top_level().count(), 1);
+    assert_eq!(tree.nodes()[4].label_id, None);
+    assert_eq!(tree.nodes()[5].label_id, Some(Label::Atom.label_id()));
+}
+
+#[test]
+fn siblings_at_the_top_level_are_linked() {
+    let tree = parse(b"a()".as_slice()).unwrap().flatten();
+
+    let top_level = tree.top_level().map(label).collect::<Vec<_>>();
+    assert_eq!(top_level, [Some(Label::Atom), Some(Label::List)]);
+}
+
+#[test]
+fn empty_trees_have_no_nodes() {
+    let tree = parse(b"".as_slice()).unwrap().flatten();
+
+    assert!(tree.is_empty());
+    assert_eq!(tree.top_level().count(), 0);
+}
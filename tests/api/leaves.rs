@@ -0,0 +1,51 @@
+/// The label of a leaf, or `None` for an error
+fn label(leaf: &LeafInfo) -> Option<Label> {
+    match leaf.grouping {
+        Grouping::Label(label) => Some(label),
+        Grouping::Error { .. } => None,
+        grouping => panic!("unexpected {:?}", grouping),
+    }
+}
+
+fn leaves(input: &[u8]) -> Vec<(Option<Label>, u32, u32)> {
+    let result = parse(input).unwrap();
+
+    result
+        .leaves()
+        .map(|leaf| (label(&leaf), leaf.position, leaf.length))
+        .collect()
+}
+
+#[test]
+fn empty_trees_have_no_leaves() {
+    assert_eq!(leaves(b""), []);
+}
+
+#[test]
+fn leaves_belong_to_the_innermost_node() {
+    assert_eq!(
+        leaves(b"(a(a)a)"),
+        [
+            (Some(Label::List), 0, 1),
+            (Some(Label::Atom), 1, 1),
+            (Some(Label::List), 2, 1),
+            (Some(Label::Atom), 3, 1),
+            (Some(Label::List), 4, 1),
+            (Some(Label::Atom), 5, 1),
+            (Some(Label::List), 6, 1),
+        ]
+    );
+}
+
+#[test]
+fn errors_are_leaves() {
+    assert_eq!(
+        leaves(b"(@@)"),
+        [
+            (Some(Label::List), 0, 1),
+            (None, 1, 1),
+            (None, 2, 1),
+            (Some(Label::List), 3, 1),
+        ]
+    );
+}
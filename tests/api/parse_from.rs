@@ -0,0 +1,38 @@
+#[test]
+fn nodes_are_positioned_from_the_start_of_the_input() {
+    let result = parse_from(b"a();a=;".as_slice(), 4).unwrap();
+
+    assert_eq!(result.root().position(), 4);
+    assert_eq!(result.root().length(), 3);
+
+    let assignment = result.root().first(Label::Assignment).unwrap();
+    assert_eq!(assignment.position(), 4);
+    assert_eq!(assignment.length(), 2);
+}
+
+#[test]
+fn errors_are_positioned_from_the_start_of_the_input() {
+    let result = parse_from(b"a();@@@;".as_slice(), 4).unwrap();
+    let errors = result.errors_sorted().collect::<Vec<_>>();
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].position, 4);
+    assert_eq!(errors[0].length, 3);
+}
+
+#[test]
+fn offsets_past_the_end_are_the_end_of_the_input() {
+    // Nothing is left to parse, which the grammar doesn't match
+    assert!(matches!(parse_from(b"a=;".as_slice(), 3), Parse::Unmatched));
+    assert!(matches!(parse_from(b"a=;".as_slice(), 4), Parse::Unmatched));
+    assert!(matches!(
+        parse_from(b"a=;".as_slice(), u32::MAX),
+        Parse::Unmatched
+    ));
+
+    let options = ParseOptions::new().start_at(100);
+    assert!(matches!(
+        parse_with(b"".as_slice(), &options),
+        Parse::Unmatched
+    ));
+}
@@ -0,0 +1,22 @@
+#[test]
+fn input_that_fails_is_an_error_after_what_matched() {
+    let input = b"a=@;";
+
+    assert!(matches!(parse(input.as_slice()), Parse::Unmatched));
+
+    let result = parse_lenient(input.as_slice()).unwrap();
+    assert_eq!(
+        result.to_sexp(),
+        "(root 0 4\n  (label assignment 0 2)\n  (error (expected assignment call \";\") 2 2))"
+    );
+}
+
+#[test]
+fn input_that_matches_is_unchanged() {
+    for input in [b"a=;".as_slice(), b"@@@;".as_slice()] {
+        let strict = parse(input).unwrap();
+        let lenient = parse_lenient(input).unwrap();
+
+        assert_eq!(lenient.to_sexp(), strict.to_sexp());
+    }
+}
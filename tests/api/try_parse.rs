@@ -0,0 +1,35 @@
+#[test]
+fn valid_input_matches() {
+    let result = try_parse(b"a=;".as_slice()).unwrap();
+    assert_eq!(result.root().length(), 3);
+}
+
+#[test]
+fn unmatched_empty_input_is_reported_at_the_start() {
+    let error = try_parse(b"".as_slice()).unwrap_err();
+
+    // Finding the end of the input examined the position just past it
+    assert_eq!(error.position, 0);
+    assert_eq!(error.scan_distance, 1);
+    assert!(error.expected_labels.is_empty());
+}
+
+#[test]
+fn unmatched_input_is_reported_at_the_last_symbol_examined() {
+    let error = try_parse(b"a=@;".as_slice()).unwrap_err();
+
+    assert_eq!(error.position, 2);
+    assert_eq!(error.scan_distance, 3);
+}
+
+#[test]
+fn errors_are_reported_with_what_was_expected() {
+    let error = try_parse(b"@@@;".as_slice()).unwrap_err();
+
+    assert_eq!(error.position, 0);
+    assert_eq!(error.expected_labels, [Label::Assignment, Label::Call]);
+    assert_eq!(
+        error.to_string(),
+        "syntax error at position 0, expected assignment or call"
+    );
+}
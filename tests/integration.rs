@@ -1,9 +1,16 @@
 extern crate core;
 
-use peg_pack::core::{CompilerSettings, Parser};
+use peg_pack::core::{
+    Class, CompilerSettings, DiffItem, Error, GenerationSettings, Instruction, ModuleConfig,
+    Parser, Pass, SymbolWidth, Warning,
+};
 use serde::Deserialize;
-use serde_json::Value;
+use serde_json::{json, Value};
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::env;
 use std::fs;
+use std::rc::Rc;
 
 macro_rules! case {
     ($name:ident) => {
@@ -12,6 +19,7 @@ macro_rules! case {
             test(
                 include_bytes!(concat!("cases/", stringify!($name), ".input.json")),
                 include_bytes!(concat!("cases/", stringify!($name), ".expected.json")),
+                concat!("tests/cases/", stringify!($name), ".expected.json"),
             );
         }
     };
@@ -37,7 +45,14 @@ cases!(
     fold_never_series,
     merge_series,
     concatenate_series,
+    fold_series_chain,
+    factor_common_suffix,
+    factor_common_suffix_fallible,
+    load_bytes,
+    consume_until,
+    balanced,
     deduplicate_series,
+    deduplicate_complementary_series,
     deduplicate_label,
     deduplicate_components,
     deduplicate_rotated_components,
@@ -45,21 +60,40 @@ cases!(
     infer_expected,
     cache_insertion_low_cost,
     cache_insertion_high_cost,
+    cache_insertion_no_cache,
+    cache_slot_limit,
+    eliminate_redundant_caches,
+    restrict_alphabet,
     reorder_seqs,
     reorder_seqs_loopback,
     reorder_seqs_blowup,
     reorder_choices,
     reorder_choices_loopback,
+    order_choices,
     reduce_infallible_not_ahead,
     reduce_never_not_ahead,
+    reduce_label_prone_not_ahead,
+    reduce_not_ahead_of_never,
     double_not_ahead_elimination,
     double_not_ahead_elimination_irreducible,
     character_replacement_reachable_annotations,
     character_replacement_unreachable_annotations,
     eliminate_redundant_seq,
     eliminate_redundant_choice,
+    eliminate_duplicate_alternatives,
+    grow_left_recursion,
     lower_to_first_choice,
+    collapse_nested_optionals,
+    collapse_nested_labels,
     predicate_state_reduction,
+    eliminate_tail_recursion,
+    factor_optional_series_suffix,
+    deduplicate_label_wrappers,
+    deduplicate_errors_across_components,
+    insert_skips,
+    label_all_rules,
+    preserve_series_kinds,
+    wide_symbols,
 );
 
 #[derive(Deserialize)]
@@ -78,9 +112,29 @@ struct InputSettings {
     #[serde(default = "return_true")]
     cache_insertion: bool,
     #[serde(default = "return_true")]
+    cache_elimination: bool,
+    #[serde(default = "return_true")]
     redundant_junction_elimination: bool,
+    #[serde(default = "return_true")]
+    tail_recursion_elimination: bool,
+    #[serde(default = "return_true")]
+    suffix_factoring: bool,
+    #[serde(default = "return_false")]
+    choice_dispatch: bool,
     #[serde(default = "return_false")]
     state_only: bool,
+    #[serde(default = "default_max_passes")]
+    max_passes: usize,
+    #[serde(default)]
+    alphabet: Option<Vec<(u8, u8)>>,
+    #[serde(default)]
+    max_cache_slots: Option<usize>,
+    #[serde(default)]
+    symbols: Option<String>,
+    #[serde(default = "return_false")]
+    left_recursion: bool,
+    #[serde(default = "return_false")]
+    label_all_rules: bool,
 }
 
 impl Default for InputSettings {
@@ -97,24 +151,63 @@ fn return_false() -> bool {
     false
 }
 
-fn test(input: &[u8], expected: &[u8]) {
+fn default_max_passes() -> usize {
+    CompilerSettings::normal().max_passes
+}
+
+/// Checks a case's output against what is expected. Setting `UPDATE_EXPECTED=1`
+/// rewrites the expected output of failing cases instead
+fn test(input: &[u8], expected: &[u8], expected_path: &str) {
     let settings = serde_json::from_slice::<Input>(input).unwrap().settings;
 
     let settings = CompilerSettings {
         merge_series: settings.merge_series && !settings.state_only,
         character_replacement: settings.character_replacement && !settings.state_only,
         cache_insertion: settings.cache_insertion && !settings.state_only,
+        cache_elimination: settings.cache_elimination && !settings.state_only,
         redundant_junction_elimination: settings.redundant_junction_elimination
             && !settings.state_only,
         state_optimization: settings.state_only,
+        tail_recursion_elimination: settings.tail_recursion_elimination && !settings.state_only,
+        suffix_factoring: settings.suffix_factoring && !settings.state_only,
+        choice_dispatch: settings.choice_dispatch,
+        max_passes: settings.max_passes,
+        alphabet: settings.alphabet.map(|ranges| {
+            let mut class = Class::new(false);
+
+            for (start, end) in ranges {
+                class.insert(start, end);
+            }
+
+            class
+        }),
+        max_cache_slots: settings.max_cache_slots,
+        symbols: match settings.symbols.as_deref() {
+            Some("u32") => SymbolWidth::U32,
+            _ => SymbolWidth::U8,
+        },
+        left_recursion: settings.left_recursion,
+        label_all_rules: settings.label_all_rules,
+        passes: Vec::new(),
+        trace_passes: false,
+        report_unused_rules: false,
     };
 
-    let parser = Parser::load(input, settings).unwrap();
+    let parser = Parser::load(input, settings.clone()).unwrap();
     let output = parser.dump_json();
 
+    let streamed = Parser::load_streaming(input, settings).unwrap();
+    assert_eq!(streamed.dump_json(), output, "streamed load differs");
+
     let actual = serde_json::from_str::<Value>(&output).unwrap();
     let expected = serde_json::from_slice::<Value>(expected).unwrap();
 
+    if actual != expected && env::var("UPDATE_EXPECTED").as_deref() == Ok("1") {
+        let actual = serde_json::to_string_pretty(&actual).unwrap();
+        fs::write(expected_path, actual + "\n").unwrap();
+        return;
+    }
+
     if actual != expected {
         let actual = serde_json::to_string_pretty(&actual)
             .unwrap()
@@ -130,6 +223,1485 @@ fn test(input: &[u8], expected: &[u8]) {
     }
 }
 
+/// Compiles a grammar made of thousands of nested sequences to make sure no
+/// part of the compiler recurses once per level of nesting
+#[test]
+fn deeply_nested_sequences() {
+    const DEPTH: usize = 5000;
+
+    let mut instructions = Vec::new();
+
+    for i in 0..DEPTH {
+        let base = i * 3;
+        let next = if i + 1 == DEPTH { base + 2 } else { base + 3 };
+
+        instructions.push(json!({ "name": "seq", "first": base + 1, "second": next }));
+        instructions.push(json!({ "name": "label", "label": "item", "target": base + 2 }));
+        instructions.push(json!({
+            "name": "series",
+            "classes": [{ "negated": false, "ranges": [[97, 97]] }],
+        }));
+    }
+
+    let ir = json!({
+        "version": 0,
+        "status": "success",
+        "start": 0,
+        "instructions": instructions,
+    });
+
+    let ir = serde_json::to_vec(&ir).unwrap();
+    Parser::load(&ir, CompilerSettings::normal()).unwrap();
+}
+
+/// Rejects grammars with no instructions or a start instruction that doesn't exist
+#[test]
+fn empty_grammars() {
+    let check = |ir: Value, expected: &str| {
+        let ir = serde_json::to_vec(&ir).unwrap();
+
+        match Parser::load(&ir, CompilerSettings::normal()) {
+            Err(Error::Load(message)) => assert_eq!(message, expected),
+            _ => panic!("expected a load error"),
+        }
+
+        match Parser::load_streaming(&ir[..], CompilerSettings::normal()) {
+            Err(Error::Load(message)) => assert_eq!(message, expected),
+            _ => panic!("expected a load error"),
+        }
+    };
+
+    check(
+        json!({ "version": 0, "status": "success", "start": 0, "instructions": [] }),
+        "Invalid IR: Grammar is empty",
+    );
+
+    check(
+        json!({
+            "version": 0,
+            "status": "success",
+            "start": 3,
+            "instructions": [{ "name": "series", "classes": [] }],
+        }),
+        "Invalid IR: Start instruction ID 3 is out of range",
+    );
+}
+
+/// Overrides a rule of one grammar with a rule imported from another
+#[test]
+fn merge_grammars() {
+    let series = |byte: u8| json!([{ "negated": false, "ranges": [[byte, byte]] }]);
+
+    let base = json!({
+        "version": 0,
+        "status": "success",
+        "start": 0,
+        "instructions": [
+            { "name": "delegate", "target": 1, "ruleName": "file" },
+            { "name": "label", "label": "item", "target": 2, "ruleName": "file" },
+            { "name": "delegate", "target": 3, "ruleName": "item" },
+            { "name": "series", "classes": series(b'a'), "ruleName": "item" },
+        ],
+    });
+
+    let dialect = json!({
+        "version": 0,
+        "status": "success",
+        "start": 0,
+        "instructions": [
+            { "name": "delegate", "target": 1, "ruleName": "item" },
+            { "name": "series", "classes": series(b'b'), "ruleName": "item" },
+        ],
+    });
+
+    let mut parser = Parser::load_unprepared(&serde_json::to_vec(&base).unwrap()).unwrap();
+    let dialect = Parser::load_unprepared(&serde_json::to_vec(&dialect).unwrap()).unwrap();
+
+    parser.merge(dialect, |name| format!("dialect_{}", name));
+    parser.override_rule("item", "dialect_item").unwrap();
+    assert!(parser.set_start("missing").is_err());
+
+    let parser = parser.prepare(CompilerSettings::normal()).unwrap();
+    let actual = serde_json::from_str::<Value>(&parser.dump_json()).unwrap();
+
+    let expected = json!({
+        "start": 0,
+        "instructions": [
+            { "label": [1, 0] },
+            { "series": 0 },
+        ],
+        "series": [series(b'b')],
+        "labels": ["item"],
+        "expecteds": [],
+    });
+
+    assert_eq!(actual, expected);
+}
+
+/// Starts a grammar at one of its other rules
+#[test]
+fn set_start_rule() {
+    let series = |byte: u8| json!([{ "negated": false, "ranges": [[byte, byte]] }]);
+
+    let ir = json!({
+        "version": 0,
+        "status": "success",
+        "start": 0,
+        "instructions": [
+            { "name": "delegate", "target": 1, "ruleName": "file" },
+            { "name": "seq", "first": 2, "second": 4, "ruleName": "file" },
+            { "name": "delegate", "target": 3, "ruleName": "statement" },
+            { "name": "series", "classes": series(b'a'), "ruleName": "statement" },
+            { "name": "delegate", "target": 5, "ruleName": "expression" },
+            { "name": "series", "classes": series(b'b'), "ruleName": "expression" },
+        ],
+    });
+
+    let mut parser = Parser::load_unprepared(&serde_json::to_vec(&ir).unwrap()).unwrap();
+    parser.set_start("expression").unwrap();
+
+    let parser = parser.prepare(CompilerSettings::normal()).unwrap();
+    let actual = serde_json::from_str::<Value>(&parser.dump_json()).unwrap();
+
+    let expected = json!({
+        "start": 0,
+        "instructions": [
+            { "series": 0 },
+        ],
+        "series": [series(b'b')],
+        "labels": [],
+        "expecteds": [],
+    });
+
+    assert_eq!(actual, expected);
+}
+
+/// Generates errors in rules given a message with the message in place of
+/// what they expected, leaving other errors alone
+#[test]
+fn error_messages() {
+    let series = |byte: u8| json!([{ "negated": false, "ranges": [[byte, byte]] }]);
+
+    let ir = json!({
+        "version": 0,
+        "status": "success",
+        "start": 0,
+        "instructions": [
+            { "name": "delegate", "target": 1, "ruleName": "file" },
+            { "name": "seq", "first": 2, "second": 4, "ruleName": "file" },
+            { "name": "delegate", "target": 3, "ruleName": "statement" },
+            { "name": "error", "target": 6, "expected": 6, "ruleName": "statement" },
+            { "name": "delegate", "target": 5, "ruleName": "semicolon" },
+            { "name": "error", "target": 7, "expected": 7, "ruleName": "semicolon" },
+            { "name": "series", "classes": series(b'a') },
+            { "name": "series", "classes": series(b';') },
+        ],
+    });
+
+    let ir = serde_json::to_vec(&ir).unwrap();
+
+    let mut parser = Parser::load_unprepared(&ir).unwrap();
+    let unknown = BTreeMap::from([(String::from("missing"), String::from("a rule"))]);
+    assert!(matches!(
+        parser.set_error_messages(unknown),
+        Err(Error::UnknownRule(rule)) if rule == "missing"
+    ));
+
+    let messages = BTreeMap::from([(String::from("statement"), String::from("a \"statement\""))]);
+    parser.set_error_messages(messages).unwrap();
+
+    let parser = parser.prepare(CompilerSettings::normal()).unwrap();
+    let code = parser
+        .generate(GenerationSettings::normal(), &ModuleConfig::normal())
+        .root;
+
+    assert!(code.contains("ctx.state_error_end(Expected::Message0);"));
+    assert!(code.contains(r#"Self::Message0 => Some("a \"statement\""),"#));
+    assert_eq!(code.matches("ctx.state_error_end(Expected::E").count(), 1);
+}
+
+/// Follows the start rule with a check for the end of input
+#[test]
+fn require_eof() {
+    let ir = json!({
+        "version": 0,
+        "status": "success",
+        "start": 0,
+        "instructions": [
+            { "name": "series", "classes": [{ "negated": false, "ranges": [[b'a', b'a']] }] },
+        ],
+    });
+
+    let mut parser = Parser::load_unprepared(&serde_json::to_vec(&ir).unwrap()).unwrap();
+    parser.require_eof();
+
+    let parser = parser.prepare(CompilerSettings::none()).unwrap();
+    let actual = serde_json::from_str::<Value>(&parser.dump_json()).unwrap();
+
+    let expected = json!({
+        "start": 0,
+        "instructions": [
+            { "seq": [1, 2] },
+            { "series": 0 },
+            { "notAhead": 3 },
+            { "series": 1 },
+        ],
+        "series": [
+            [{ "negated": false, "ranges": [[b'a', b'a']] }],
+            [{ "negated": true, "ranges": [] }],
+        ],
+        "labels": [],
+        "expecteds": [],
+    });
+
+    assert_eq!(actual, expected);
+}
+
+/// Lists the labels and expected literals a grammar can produce once each and in order, whether
+/// they are nested or appear several times
+#[test]
+fn all_labels_and_literals() {
+    let literal = |text: &str| {
+        text.bytes()
+            .map(|byte| json!({ "negated": false, "ranges": [[byte, byte]] }))
+            .collect::<Vec<_>>()
+    };
+
+    let ir = json!({
+        "version": 0,
+        "status": "success",
+        "start": 0,
+        "instructions": [
+            { "name": "seq", "first": 1, "second": 8 },
+            { "name": "error", "target": 2, "expected": 2 },
+            { "name": "choice", "first": 3, "second": 5 },
+            { "name": "label", "target": 4, "label": "value" },
+            { "name": "label", "target": 7, "label": "number" },
+            { "name": "label", "target": 6, "label": "value" },
+            { "name": "series", "classes": literal("null") },
+            { "name": "series", "classes": literal("1") },
+            { "name": "seq", "first": 9, "second": 12 },
+            { "name": "error", "target": 10, "expected": 10 },
+            { "name": "choice", "first": 11, "second": 6 },
+            { "name": "series", "classes": literal("true") },
+            { "name": "error", "target": 6, "expected": 6 },
+        ],
+    });
+
+    let parser = Parser::load(
+        &serde_json::to_vec(&ir).unwrap(),
+        CompilerSettings::normal(),
+    )
+    .unwrap();
+
+    assert_eq!(parser.all_labels(), ["number", "value"]);
+    assert_eq!(
+        parser.all_expected_literals(),
+        [
+            b"null".map(u32::from).to_vec(),
+            b"true".map(u32::from).to_vec()
+        ]
+    );
+
+    let code = parser
+        .generate(GenerationSettings::normal(), &ModuleConfig::normal())
+        .root;
+
+    assert!(code.contains("pub const LABELS: &[Label] = &[Label::Number, Label::Value];"));
+}
+
+/// Recursion makes a grammar irregular, but repetition does not
+#[test]
+fn regular_grammars() {
+    let series = |byte: u8| json!([{ "negated": false, "ranges": [[byte, byte]] }]);
+
+    let repeated = json!({
+        "version": 0,
+        "status": "success",
+        "start": 0,
+        "instructions": [
+            { "name": "choice", "first": 1, "second": 3 },
+            { "name": "seq", "first": 2, "second": 0 },
+            { "name": "series", "classes": series(b'a') },
+            { "name": "series", "classes": [] },
+        ],
+    });
+
+    let balanced = json!({
+        "version": 0,
+        "status": "success",
+        "start": 0,
+        "instructions": [
+            { "name": "choice", "first": 1, "second": 5 },
+            { "name": "seq", "first": 2, "second": 3 },
+            { "name": "series", "classes": series(b'(') },
+            { "name": "seq", "first": 0, "second": 4 },
+            { "name": "series", "classes": series(b')') },
+            { "name": "series", "classes": [] },
+        ],
+    });
+
+    let load = |ir: Value| {
+        let ir = serde_json::to_vec(&ir).unwrap();
+        Parser::load(&ir, CompilerSettings::normal()).unwrap()
+    };
+
+    assert!(load(repeated).is_regular());
+    assert!(!load(balanced).is_regular());
+}
+
+/// Identical grammars don't differ, and changing a character only changes its series
+#[test]
+fn diff_grammars() {
+    let grammar = |byte: u8| {
+        json!({
+            "version": 0,
+            "status": "success",
+            "start": 0,
+            "instructions": [
+                { "name": "seq", "first": 1, "second": 2 },
+                { "name": "series", "classes": [{ "negated": false, "ranges": [[b'a', b'a']] }] },
+                { "name": "notAhead", "target": 3 },
+                { "name": "series", "classes": [{ "negated": false, "ranges": [[byte, byte]] }] },
+            ],
+        })
+    };
+
+    let load = |ir: Value| {
+        let ir = serde_json::to_vec(&ir).unwrap();
+        Parser::load(&ir, CompilerSettings::none()).unwrap()
+    };
+
+    assert!(load(grammar(b'b')).diff(&load(grammar(b'b'))).is_empty());
+
+    let differences = load(grammar(b'b')).diff(&load(grammar(b'c')));
+    assert_eq!(differences.len(), 1);
+    assert!(matches!(differences[0].item, DiffItem::Series(_)));
+    assert!(differences[0].old.as_ref().unwrap().contains("98"));
+    assert!(differences[0].new.as_ref().unwrap().contains("99"));
+}
+
+/// Labels are traced back to where the rule producing them was defined
+#[test]
+fn label_spans() {
+    let ir = json!({
+        "version": 0,
+        "status": "success",
+        "start": 0,
+        "instructions": [
+            {
+                "name": "delegate",
+                "target": 1,
+                "ruleName": "number",
+                "grammarSpan": { "file": "grammar.js", "start": 10, "end": 42 },
+            },
+            { "name": "label", "target": 2, "label": "number", "ruleName": "number" },
+            {
+                "name": "series",
+                "classes": [{ "negated": false, "ranges": [[b'0', b'9']] }],
+                "ruleName": "number",
+            },
+        ],
+    });
+
+    let ir = serde_json::to_vec(&ir).unwrap();
+    let parser = Parser::load(&ir, CompilerSettings::normal()).unwrap();
+    let spans = parser.label_spans();
+
+    let span = spans["number"].iter().next().unwrap();
+    assert_eq!(span.file, "grammar.js");
+    assert_eq!((span.start, span.end), (10, 42));
+}
+
+/// Errors around something that can never match are reported by rule, but
+/// errors around the empty string, as used for recovery, are not
+#[test]
+fn unmatchable_errors() {
+    let ir = json!({
+        "version": 0,
+        "status": "success",
+        "start": 0,
+        "instructions": [
+            { "name": "seq", "first": 1, "second": 3 },
+            { "name": "error", "target": 2, "expected": 5, "ruleName": "dead" },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [] }] },
+            { "name": "error", "target": 4, "expected": 5, "ruleName": "recovered" },
+            { "name": "series", "classes": [] },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [[1, 1]] }] },
+        ],
+    });
+
+    let ir = serde_json::to_vec(&ir).unwrap();
+    let parser = Parser::load(&ir, CompilerSettings::normal()).unwrap();
+
+    assert_eq!(parser.unmatchable_errors().collect::<Vec<_>>(), ["dead"]);
+}
+
+/// Reports rules that can never match because of an emptied class, but not rules defined as never
+/// or rules that only refer to one that can never match
+#[test]
+fn never_rules() {
+    let ir = json!({
+        "version": 0,
+        "status": "success",
+        "start": 0,
+        "instructions": [
+            { "name": "seq", "first": 1, "second": 3 },
+            { "name": "delegate", "target": 2, "ruleName": "emptied" },
+            { "name": "series", "classes": [{ "negated": true, "ranges": [[0, 255]] }], "ruleName": "emptied" },
+            { "name": "choice", "first": 4, "second": 6 },
+            { "name": "delegate", "target": 5, "ruleName": "never" },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [] }], "ruleName": "never" },
+            { "name": "delegate", "target": 1, "ruleName": "alias" },
+        ],
+    });
+
+    let ir = serde_json::to_vec(&ir).unwrap();
+    let parser = Parser::load(&ir, CompilerSettings::normal()).unwrap();
+
+    assert_eq!(parser.never_rules().collect::<Vec<_>>(), ["emptied"]);
+}
+
+/// Reports rules the start rule can't reach and rules only ever tried after an alternative that
+/// always matches, but only when asked to, and not rules merged into others by optimization
+#[test]
+fn unused_rules() {
+    let ir = json!({
+        "version": 0,
+        "status": "success",
+        "start": 0,
+        "instructions": [
+            { "name": "seq", "first": 1, "second": 6 },
+            { "name": "choice", "first": 2, "second": 4 },
+            { "name": "delegate", "target": 3, "ruleName": "nothing" },
+            { "name": "label", "label": "blank", "target": 10, "ruleName": "nothing" },
+            { "name": "delegate", "target": 5, "ruleName": "shadowed" },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [[120, 120]] }], "ruleName": "shadowed" },
+            { "name": "delegate", "target": 7, "ruleName": "digit" },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [[48, 57]] }], "ruleName": "digit" },
+            { "name": "delegate", "target": 9, "ruleName": "orphan" },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [[121, 121]] }], "ruleName": "orphan" },
+            { "name": "series", "classes": [] },
+        ],
+    });
+
+    let ir = serde_json::to_vec(&ir).unwrap();
+
+    let parser = Parser::load(&ir, CompilerSettings::normal()).unwrap();
+    assert_eq!(parser.unused_rules().count(), 0);
+
+    let mut settings = CompilerSettings::normal();
+    settings.report_unused_rules = true;
+
+    let parser = Parser::load(&ir, settings).unwrap();
+    assert_eq!(
+        parser.unused_rules().collect::<Vec<_>>(),
+        ["orphan", "shadowed"]
+    );
+    assert!(parser
+        .warnings()
+        .contains(&Warning::UnusedRule(String::from("shadowed"))));
+
+    let mut settings = CompilerSettings::none();
+    settings.report_unused_rules = true;
+
+    let parser = Parser::load(&ir, settings).unwrap();
+    assert_eq!(parser.unused_rules().collect::<Vec<_>>(), ["orphan"]);
+}
+
+/// Reports choices between an alternative and itself, but not choices built on never
+#[test]
+fn duplicate_alternatives() {
+    let ir = json!({
+        "version": 0,
+        "status": "success",
+        "start": 0,
+        "instructions": [
+            { "name": "seq", "first": 1, "second": 5 },
+            { "name": "choice", "first": 2, "second": 4, "ruleName": "twice" },
+            { "name": "choice", "first": 3, "second": 4 },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [] }] },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [[1, 1]] }] },
+            { "name": "choice", "first": 3, "second": 3, "ruleName": "nothing" },
+        ],
+    });
+
+    let ir = serde_json::to_vec(&ir).unwrap();
+    let parser = Parser::load(&ir, CompilerSettings::normal()).unwrap();
+
+    assert_eq!(
+        parser.duplicate_alternatives().collect::<Vec<_>>(),
+        ["twice"]
+    );
+}
+
+/// Finds labels that are only produced inside a lookahead, ignoring those also produced outside one
+#[test]
+fn lookahead_labels() {
+    let ir = json!({
+        "version": 0,
+        "status": "success",
+        "start": 0,
+        "instructions": [
+            { "name": "seq", "first": 1, "second": 4 },
+            { "name": "notAhead", "target": 2 },
+            { "name": "label", "target": 3, "label": "keyword" },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [[105, 105]] }] },
+            { "name": "seq", "first": 5, "second": 6 },
+            { "name": "notAhead", "target": 6 },
+            { "name": "label", "target": 7, "label": "word" },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [[97, 122]] }] },
+        ],
+    });
+
+    let ir = serde_json::to_vec(&ir).unwrap();
+    let parser = Parser::load(&ir, CompilerSettings::normal()).unwrap();
+
+    assert_eq!(parser.lookahead_labels().collect::<Vec<_>>(), ["keyword"]);
+}
+
+/// A rule is followed by what starts the rest of a sequence, and by what follows the sequence when
+/// the rest can match empty input
+#[test]
+fn grammar_sets() {
+    let ir = json!({
+        "version": 0,
+        "status": "success",
+        "start": 0,
+        "instructions": [
+            { "name": "seq", "first": 1, "second": 3 },
+            { "name": "delegate", "target": 2, "ruleName": "word" },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [[97, 122]] }] },
+            { "name": "delegate", "target": 4, "ruleName": "tail" },
+            { "name": "choice", "first": 5, "second": 6 },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [[44, 44]] }] },
+            { "name": "series", "classes": [] },
+        ],
+    });
+
+    let ir = serde_json::to_vec(&ir).unwrap();
+    let parser = Parser::load(&ir, CompilerSettings::normal()).unwrap();
+    let sets = parser.grammar_sets();
+
+    let word = &sets["word"];
+    assert!(!word.nullable);
+    assert_eq!(word.first.specifier(), "'a'-'z'");
+    assert_eq!(word.follow.specifier(), "','");
+    assert!(word.followed_by_end);
+
+    let tail = &sets["tail"];
+    assert!(tail.nullable);
+    assert_eq!(tail.first.specifier(), "','");
+    assert_eq!(tail.follow.specifier(), "[]");
+    assert!(tail.followed_by_end);
+}
+
+/// Children are the nearest labels inside a node, looking through errors but not lookahead
+#[test]
+fn tree_schema() {
+    let ir = json!({
+        "version": 0,
+        "status": "success",
+        "start": 0,
+        "instructions": [
+            { "name": "seq", "first": 1, "second": 6 },
+            { "name": "label", "target": 2, "label": "list" },
+            { "name": "seq", "first": 3, "second": 5 },
+            { "name": "label", "target": 4, "label": "item" },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [[97, 122]] }] },
+            { "name": "error", "target": 3, "expected": 9 },
+            { "name": "notAhead", "target": 7 },
+            { "name": "label", "target": 8, "label": "hidden" },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [[48, 57]] }] },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [[44, 44]] }] },
+        ],
+    });
+
+    let ir = serde_json::to_vec(&ir).unwrap();
+    let parser = Parser::load(&ir, CompilerSettings::none()).unwrap();
+    let schema = serde_json::from_str::<Value>(&parser.dump_tree_schema_json()).unwrap();
+
+    assert_eq!(
+        schema["root"],
+        json!({ "children": ["list"], "errors": false })
+    );
+    assert_eq!(
+        schema["labels"]["list"],
+        json!({ "children": ["item"], "errors": true })
+    );
+    assert_eq!(
+        schema["labels"]["item"],
+        json!({ "children": [], "errors": false })
+    );
+    assert!(schema["labels"].get("hidden").is_none());
+}
+
+/// The state table names the runtime function of each state and links states
+/// by index, starting from the entry state of the start instruction
+#[test]
+fn dump_states() {
+    let ir = json!({
+        "version": 0,
+        "status": "success",
+        "start": 0,
+        "instructions": [
+            { "name": "seq", "first": 1, "second": 2 },
+            { "name": "label", "target": 2, "label": "letter" },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [[97, 122]] }] },
+        ],
+    });
+
+    let ir = serde_json::to_vec(&ir).unwrap();
+    let parser = Parser::load(&ir, CompilerSettings::none()).unwrap();
+    let table = serde_json::from_str::<Value>(&parser.dump_states_json()).unwrap();
+
+    let start = table["start"].as_u64().unwrap() as usize;
+    let states = table["states"].as_array().unwrap();
+    let kinds = states
+        .iter()
+        .map(|state| state["kind"].as_str().unwrap())
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        kinds,
+        [
+            "seq-start",
+            "seq-middle",
+            "seq-end",
+            "label-start",
+            "label-end",
+            "series"
+        ]
+    );
+
+    assert_eq!(kinds[start], "seq-start");
+    assert_eq!(states[start]["targets"], json!([3, 1]));
+    assert_eq!(states[1]["targets"], json!([5, 2]));
+    assert_eq!(states[4]["label"], json!(0));
+    assert_eq!(states[5]["series"], json!([0]));
+    assert_eq!(table["labels"], json!(["letter"]));
+}
+
+/// Dumped states run dispatches and end of input checks the way the
+/// generated parser does
+#[test]
+fn dump_dispatch_states() {
+    let ir = json!({
+        "version": 0,
+        "status": "success",
+        "start": 0,
+        "instructions": [
+            { "name": "seq", "first": 1, "second": 4 },
+            { "name": "choice", "first": 2, "second": 3 },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [[97, 97]] }] },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [[98, 99]] }] },
+            { "name": "notAhead", "target": 5 },
+            { "name": "series", "classes": [{ "negated": true, "ranges": [] }] },
+        ],
+    });
+
+    let ir = serde_json::to_vec(&ir).unwrap();
+    let mut settings = CompilerSettings::none();
+    settings.choice_dispatch = true;
+
+    let parser = Parser::load(&ir, settings).unwrap();
+    let table = serde_json::from_str::<Value>(&parser.dump_states_json()).unwrap();
+    let states = table["states"].as_array().unwrap();
+
+    let state = |kind: &str| {
+        states
+            .iter()
+            .find(|state| state["kind"] == kind)
+            .unwrap_or_else(|| panic!("no {} state", kind))
+    };
+
+    let dispatch = state("dispatch");
+    let targets = dispatch["targets"].as_array().unwrap();
+
+    assert_eq!(targets.len(), 2);
+    assert_eq!(
+        dispatch["classes"],
+        json!([
+            { "negated": false, "ranges": [[97, 97]] },
+            { "negated": false, "ranges": [[98, 99]] },
+        ])
+    );
+
+    for target in targets {
+        assert_eq!(states[target.as_u64().unwrap() as usize]["kind"], "series");
+    }
+
+    assert_eq!(state("end-of-input")["targets"], json!([]));
+    assert!(states
+        .iter()
+        .all(|state| state["kind"] != "not-ahead-start"));
+    assert!(states
+        .iter()
+        .all(|state| state["kind"] != "first-choice-start"));
+}
+
+/// Warnings gather the findings of each analysis along with what they concern
+#[test]
+fn warnings() {
+    let ir = json!({
+        "version": 0,
+        "status": "success",
+        "start": 0,
+        "instructions": [
+            { "name": "seq", "first": 1, "second": 3 },
+            { "name": "notAhead", "target": 2 },
+            { "name": "label", "target": 3, "label": "keyword" },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [[105, 105]] }] },
+        ],
+    });
+
+    let ir = serde_json::to_vec(&ir).unwrap();
+    let parser = Parser::load(&ir, CompilerSettings::normal()).unwrap();
+    let warnings = parser.warnings();
+
+    assert_eq!(warnings, [Warning::LookaheadLabel(String::from("keyword"))]);
+    assert_eq!(warnings[0].code(), "lookahead-label");
+    assert_eq!(warnings[0].subject(), Some("keyword"));
+    assert_eq!(
+        warnings[0].to_string(),
+        "Label keyword is only produced inside a lookahead, so it never appears in the tree"
+    );
+}
+
+/// Rejects left recursion unless asked to grow it, and gives the growth point a cache slot
+#[test]
+fn left_recursion() {
+    let ir = json!({
+        "version": 0,
+        "status": "success",
+        "start": 0,
+        "instructions": [
+            { "name": "choice", "first": 1, "second": 3, "ruleName": "sum" },
+            { "name": "seq", "first": 0, "second": 2 },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [[43, 43]] }] },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [[49, 49]] }] },
+        ],
+    });
+
+    let ir = serde_json::to_vec(&ir).unwrap();
+
+    match Parser::load(&ir, CompilerSettings::normal()) {
+        Err(Error::LeftRecursive(rules)) => {
+            assert_eq!(rules.into_iter().collect::<Vec<_>>(), ["sum"])
+        }
+        _ => panic!("left recursion was accepted"),
+    }
+
+    let settings = CompilerSettings {
+        left_recursion: true,
+        ..CompilerSettings::normal()
+    };
+
+    let parser = Parser::load(&ir, settings).unwrap();
+    assert_eq!(parser.cache_slots(), 1);
+}
+
+/// Lists only match without consuming input when they may be empty, and
+/// their items may not begin with the list itself
+#[test]
+fn sep_by() {
+    let list = |min: u32, item: Value| {
+        json!({
+            "version": 0,
+            "status": "success",
+            "start": 0,
+            "instructions": [
+                { "name": "sepBy", "item": 1, "sep": 2, "min": min, "ruleName": "list" },
+                item,
+                { "name": "series", "classes": [{ "negated": false, "ranges": [[44, 44]] }] },
+                { "name": "series", "classes": [{ "negated": false, "ranges": [[97, 97]] }] },
+            ],
+        })
+    };
+
+    let item = json!({ "name": "delegate", "target": 3 });
+
+    for (min, transparent, fallible) in [(0, true, false), (1, false, true), (2, false, true)] {
+        let ir = serde_json::to_vec(&list(min, item.clone())).unwrap();
+        let parser = Parser::load(&ir, CompilerSettings::none()).unwrap();
+
+        let character = parser.characterize()[&parser.start()];
+        assert_eq!(character.transparent, transparent);
+        assert_eq!(character.fallible, fallible);
+        assert!(character.antitransparent);
+    }
+
+    let item = json!({ "name": "choice", "first": 0, "second": 3 });
+    let ir = serde_json::to_vec(&list(1, item)).unwrap();
+
+    match Parser::load(&ir, CompilerSettings::normal()) {
+        Err(Error::LeftRecursive(rules)) => assert!(rules.contains("list")),
+        _ => panic!("left recursion was accepted"),
+    }
+}
+
+/// Checks assertions between the first sets of rules, reporting a symbol
+/// that violates them
+#[test]
+fn assertions() {
+    let load = |relation: &str| {
+        let ir = json!({
+            "version": 0,
+            "status": "success",
+            "start": 0,
+            "instructions": [
+                { "name": "choice", "first": 1, "second": 3 },
+                { "name": "delegate", "target": 2, "ruleName": "letter" },
+                { "name": "series", "classes": [{ "negated": false, "ranges": [[97, 122]] }] },
+                { "name": "delegate", "target": 4, "ruleName": "word" },
+                { "name": "choice", "first": 6, "second": 7 },
+                { "name": "series", "classes": [{ "negated": false, "ranges": [[48, 57], [97, 122]] }] },
+                { "name": "seq", "first": 5, "second": 3 },
+                { "name": "series", "classes": [] },
+            ],
+            "assertions": [{ "first": 1, "relation": relation, "second": 3 }],
+        });
+
+        Parser::load(
+            &serde_json::to_vec(&ir).unwrap(),
+            CompilerSettings::normal(),
+        )
+    };
+
+    assert!(load("subset").is_ok());
+
+    match load("disjoint") {
+        Err(Error::AssertionFailed(message)) => assert_eq!(
+            message,
+            "letter is not disjoint from word, both can start with 'a'"
+        ),
+        _ => panic!("overlapping rules were accepted as disjoint"),
+    }
+
+    match load("equal") {
+        Err(Error::AssertionFailed(message)) => assert_eq!(
+            message,
+            "letter is not equal to word, word can match empty input"
+        ),
+        _ => panic!("different rules were accepted as equal"),
+    }
+}
+
+/// Reports alternatives with overlapping first sets, but not those guarded
+/// by a negative lookahead
+#[test]
+fn overlapping_alternatives() {
+    let ir = json!({
+        "version": 0,
+        "status": "success",
+        "start": 0,
+        "instructions": [
+            { "name": "choice", "first": 1, "second": 5, "ruleName": "word" },
+            { "name": "choice", "first": 2, "second": 4, "ruleName": "word" },
+            { "name": "choice", "first": 3, "second": 6, "ruleName": "word" },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [] }], "ruleName": "word" },
+            { "name": "bytes", "hex": "6163", "ruleName": "word" },
+            { "name": "seq", "first": 7, "second": 8, "ruleName": "word" },
+            { "name": "bytes", "hex": "6162", "ruleName": "word" },
+            { "name": "notAhead", "target": 6, "ruleName": "word" },
+            { "name": "bytes", "hex": "61", "ruleName": "word" },
+        ],
+    });
+
+    let ir = serde_json::to_vec(&ir).unwrap();
+    let parser = Parser::load(&ir, CompilerSettings::normal()).unwrap();
+
+    let overlaps = parser
+        .overlapping_alternatives()
+        .map(|overlap| {
+            (
+                overlap.rule.as_str(),
+                overlap.first,
+                overlap.second,
+                overlap.symbol,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(overlaps, [("word", 1, 2, Some(b'a' as u32))]);
+}
+
+/// Lists complete literals from before optimization merges them, skipping
+/// classes of more than one byte and scanned delimiters
+#[test]
+fn literals() {
+    let ir = json!({
+        "version": 0,
+        "status": "success",
+        "start": 0,
+        "instructions": [
+            { "name": "choice", "first": 1, "second": 4 },
+            { "name": "seq", "first": 2, "second": 3 },
+            { "name": "bytes", "hex": "6966" },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [[97, 122]] }] },
+            { "name": "seq", "first": 5, "second": 6 },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [[105, 105]] }] },
+            { "name": "consumeUntil", "classes": [{ "negated": false, "ranges": [[59, 59]] }] },
+        ],
+    });
+
+    let ir = serde_json::to_vec(&ir).unwrap();
+    let parser = Parser::load(&ir, CompilerSettings::normal()).unwrap();
+
+    let literals = parser
+        .literals()
+        .iter()
+        .map(Vec::as_slice)
+        .collect::<Vec<_>>();
+    assert_eq!(literals, [&[105][..], &[105, 102]]);
+}
+
+/// Keeps literals of symbols wider than a byte
+#[test]
+fn wide_literals() {
+    let ir = json!({
+        "version": 0,
+        "status": "success",
+        "start": 0,
+        "instructions": [
+            { "name": "choice", "first": 1, "second": 2 },
+            { "name": "series", "classes": [
+                { "negated": false, "ranges": [[97, 97]] },
+                { "negated": false, "ranges": [[1000, 1000]] },
+            ] },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [[70000, 70000]] }] },
+        ],
+    });
+
+    let settings = CompilerSettings {
+        symbols: SymbolWidth::U32,
+        ..CompilerSettings::normal()
+    };
+
+    let ir = serde_json::to_vec(&ir).unwrap();
+    let parser = Parser::load(&ir, settings).unwrap();
+
+    let literals = parser
+        .literals()
+        .iter()
+        .map(Vec::as_slice)
+        .collect::<Vec<_>>();
+    assert_eq!(literals, [&[97, 1000][..], &[70000]]);
+}
+
+/// Runs user passes each round of optimization, keeping what they rewrite
+#[test]
+fn user_passes() {
+    let ir = json!({
+        "version": 0,
+        "status": "success",
+        "start": 0,
+        "instructions": [
+            { "name": "choice", "first": 1, "second": 2 },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [[97, 97]] }] },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [[98, 98]] }] },
+        ],
+    });
+
+    let runs = Rc::new(Cell::new(0));
+    let counter = runs.clone();
+
+    let first_only = Pass::new("first only", move |parser: &mut Parser| {
+        counter.set(counter.get() + 1);
+
+        let characters = parser.characterize();
+        let predecessors = parser.compute_predecessors();
+
+        let choices = parser
+            .walk()
+            .filter(|(_, instruction)| matches!(instruction, Instruction::Choice(_, _)))
+            .collect::<Vec<_>>();
+
+        for (id, instruction) in choices {
+            assert!(characters.contains_key(&id));
+            assert!(predecessors.contains_key(&id));
+
+            if let Instruction::Choice(first, _) = instruction {
+                parser.set_instruction(id, Instruction::Delegate(first));
+            }
+        }
+    });
+
+    let settings = CompilerSettings {
+        passes: vec![first_only],
+        ..CompilerSettings::normal()
+    };
+
+    let ir = serde_json::to_vec(&ir).unwrap();
+    let parser = Parser::load(&ir, settings).unwrap();
+
+    assert!(runs.get() > 0);
+    assert!(parser
+        .walk()
+        .all(|(_, instruction)| !matches!(instruction, Instruction::Choice(_, _))));
+}
+
+/// Tracing records every pass including user supplied ones, ending with the
+/// parser as it is returned
+#[test]
+fn trace_passes() {
+    let ir = json!({
+        "version": 0,
+        "status": "success",
+        "start": 0,
+        "instructions": [
+            { "name": "choice", "first": 1, "second": 2 },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [[97, 97]] }] },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [[98, 98]] }] },
+        ],
+    });
+
+    let ir = serde_json::to_vec(&ir).unwrap();
+
+    let untraced = Parser::load(&ir, CompilerSettings::normal()).unwrap();
+    assert!(untraced.pass_trace().is_empty());
+
+    let settings = CompilerSettings {
+        passes: vec![Pass::new("nothing", |_: &mut Parser| {})],
+        trace_passes: true,
+        ..CompilerSettings::normal()
+    };
+
+    let parser = Parser::load(&ir, settings).unwrap();
+    let trace = parser.pass_trace();
+
+    assert_eq!(trace[0].pass, "infer_expecteds");
+    assert_eq!(trace[0].round, None);
+    assert!(trace
+        .iter()
+        .any(|snapshot| snapshot.pass == "nothing" && snapshot.round == Some(1)));
+
+    let last = trace.last().unwrap();
+    assert_eq!(last.ir, parser.dump_json());
+    assert_eq!(last.ir, untraced.dump_json());
+}
+
+/// Tree depth counts nested groupings, and is only unbounded when recursion passes through one
+#[test]
+fn max_tree_depth() {
+    let depth = |target: Value| {
+        let ir = json!({
+            "version": 0,
+            "status": "success",
+            "start": 0,
+            "instructions": [
+                { "name": "label", "target": 1, "label": "outer" },
+                { "name": "choice", "first": 2, "second": 4 },
+                { "name": "seq", "first": 3, "second": 1 },
+                { "name": "series", "classes": [{ "negated": false, "ranges": [[40, 40]] }] },
+                target,
+                { "name": "series", "classes": [{ "negated": false, "ranges": [[97, 97]] }] },
+                { "name": "series", "classes": [{ "negated": false, "ranges": [[98, 98]] }] },
+                { "name": "choice", "first": 8, "second": 5 },
+                { "name": "seq", "first": 3, "second": 0 },
+            ],
+        });
+
+        let ir = serde_json::to_vec(&ir).unwrap();
+        let parser = Parser::load(&ir, CompilerSettings::normal()).unwrap();
+        parser.max_tree_depth()
+    };
+
+    let inner = json!({ "name": "error", "target": 5, "expected": 6 });
+    assert_eq!(depth(inner), Some(2));
+
+    let recursive = json!({ "name": "label", "target": 7, "label": "inner" });
+    assert_eq!(depth(recursive), None);
+}
+
+/// Lookahead past a match is bounded by the lookaheads it could make, unless
+/// one of them can scan over a repetition
+#[test]
+fn max_lookahead() {
+    let lookahead = |target: Value| {
+        let ir = json!({
+            "version": 0,
+            "status": "success",
+            "start": 0,
+            "instructions": [
+                { "name": "seq", "first": 1, "second": 2 },
+                { "name": "series", "classes": [{ "negated": false, "ranges": [[97, 97]] }] },
+                { "name": "notAhead", "target": 3 },
+                target,
+                { "name": "seq", "first": 5, "second": 3 },
+                { "name": "series", "classes": [{ "negated": false, "ranges": [[98, 98]] }] },
+                { "name": "series", "classes": [{ "negated": false, "ranges": [[99, 99]] }] },
+            ],
+        });
+
+        let ir = serde_json::to_vec(&ir).unwrap();
+        let parser = Parser::load(&ir, CompilerSettings::normal()).unwrap();
+        parser.max_lookahead()
+    };
+
+    let literal = json!({
+        "name": "series",
+        "classes": [
+            { "negated": false, "ranges": [[98, 98]] },
+            { "negated": false, "ranges": [[99, 99]] },
+        ],
+    });
+
+    assert_eq!(lookahead(literal), Some(2));
+
+    let repetition = json!({ "name": "choice", "first": 4, "second": 6 });
+    assert_eq!(lookahead(repetition), None);
+
+    // The first alternative of a choice can scan ahead before failing and
+    // giving way to the second, as in ('a' 'b') / !('a' 'b') 'a' and ('a' 'b')?
+    let choice = |second: Value| {
+        let ir = json!({
+            "version": 0,
+            "status": "success",
+            "start": 0,
+            "instructions": [
+                { "name": "choice", "first": 1, "second": 4 },
+                { "name": "seq", "first": 2, "second": 3 },
+                { "name": "series", "classes": [{ "negated": false, "ranges": [[97, 97]] }] },
+                { "name": "series", "classes": [{ "negated": false, "ranges": [[98, 98]] }] },
+                second,
+                { "name": "notAhead", "target": 1 },
+            ],
+        });
+
+        let ir = serde_json::to_vec(&ir).unwrap();
+        let parser = Parser::load(&ir, CompilerSettings::normal()).unwrap();
+        parser.max_lookahead()
+    };
+
+    // Only the `b` is looked at past the `a` the second alternative matches
+    let negated = json!({ "name": "seq", "first": 5, "second": 2 });
+    assert_eq!(choice(negated), Some(1));
+
+    let empty = json!({ "name": "series", "classes": [] });
+    assert_eq!(choice(empty), Some(2));
+}
+
+/// Walks start from the start and visit shared instructions once
+#[test]
+fn walk_order() {
+    let ir = json!({
+        "version": 0,
+        "status": "success",
+        "start": 0,
+        "instructions": [
+            { "name": "seq", "first": 1, "second": 2 },
+            { "name": "notAhead", "target": 3 },
+            { "name": "seq", "first": 3, "second": 3 },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [[1, 1]] }] },
+        ],
+    });
+
+    let ir = serde_json::to_vec(&ir).unwrap();
+    let parser = Parser::load(&ir, CompilerSettings::none()).unwrap();
+    let walk = parser.walk().collect::<Vec<_>>();
+
+    assert_eq!(walk.len(), 4);
+    assert!(matches!(walk[0].1, Instruction::Seq(_, _)));
+    assert!(matches!(walk[1].1, Instruction::NotAhead(_)));
+    assert!(matches!(walk[2].1, Instruction::Series(_)));
+    assert!(matches!(walk[3].1, Instruction::Seq(_, _)));
+}
+
+/// Generated functions are counted against the rules producing them, and
+/// literals need no class functions
+#[test]
+fn size_profile() {
+    let ir = json!({
+        "version": 0,
+        "status": "success",
+        "start": 0,
+        "instructions": [
+            { "name": "seq", "first": 1, "second": 2 },
+            {
+                "name": "series",
+                "classes": [{ "negated": false, "ranges": [[b'0', b'9'], [b'_', b'_']] }],
+                "ruleName": "digit",
+            },
+            {
+                "name": "series",
+                "classes": [{ "negated": false, "ranges": [[b'a', b'a']] }],
+                "ruleName": "keyword",
+            },
+        ],
+    });
+
+    let ir = serde_json::to_vec(&ir).unwrap();
+    let parser = Parser::load(&ir, CompilerSettings::none()).unwrap();
+    let profile = parser.size_profile();
+
+    let sizes = profile
+        .iter()
+        .map(|size| {
+            let counts = (size.states, size.series_functions, size.class_functions);
+            (size.rule.as_str(), counts)
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        sizes,
+        [
+            ("<anonymous>", (3, 0, 0)),
+            ("digit", (1, 1, 1)),
+            ("keyword", (1, 1, 0)),
+        ]
+    );
+}
+
+/// Generated code is the same every run, and expecteds are ordered by their
+/// contents so the same errors produce the same code wherever they appear
+#[test]
+fn expected_order() {
+    let generate = |first: u8, second: u8| {
+        let ir = json!({
+            "version": 0,
+            "status": "success",
+            "start": 0,
+            "instructions": [
+                { "name": "seq", "first": 1, "second": 2 },
+                { "name": "error", "target": 3, "expected": 6 },
+                { "name": "error", "target": 4, "expected": 7 },
+                { "name": "label", "label": "first", "target": 5 },
+                { "name": "label", "label": "second", "target": 5 },
+                { "name": "series", "classes": [] },
+                { "name": "series", "classes": [{ "negated": false, "ranges": [[first, first]] }] },
+                { "name": "series", "classes": [{ "negated": false, "ranges": [[second, second]] }] },
+            ],
+        });
+
+        let ir = serde_json::to_vec(&ir).unwrap();
+        peg_pack::generate_parser(&ir, CompilerSettings::normal()).unwrap()
+    };
+
+    let expecteds = |code: &str| {
+        let start = code.find("enum Expected {").unwrap();
+        let end = start + code[start..].find('}').unwrap();
+        code[start..end].to_string()
+    };
+
+    let code = generate(b'a', b'b');
+    assert_eq!(code, generate(b'a', b'b'));
+    assert_eq!(expecteds(&code), expecteds(&generate(b'b', b'a')));
+}
+
+/// A restricted alphabet stops anything matching bytes outside of it, while
+/// checks for the end of input still fail before such bytes
+#[test]
+fn restrict_alphabet_any() {
+    let ir = json!({
+        "version": 0,
+        "status": "success",
+        "start": 0,
+        "instructions": [
+            { "name": "seq", "first": 1, "second": 2 },
+            { "name": "series", "classes": [{ "negated": true, "ranges": [] }] },
+            { "name": "notAhead", "target": 1 },
+        ],
+    });
+
+    let ir = serde_json::to_vec(&ir).unwrap();
+
+    let settings = CompilerSettings {
+        alphabet: Some(Class::of(&[(0, 127)])),
+        ..CompilerSettings::normal()
+    };
+
+    let parser = Parser::load(&ir, settings).unwrap();
+    let dump = serde_json::from_str::<Value>(&parser.dump_json()).unwrap();
+
+    // Whether each series matches a byte beyond the alphabet
+    let matches_high_byte = |series: &Value| {
+        let class = &dump["series"][series.as_u64().unwrap() as usize][0];
+        let inside = class["ranges"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|range| range[0].as_u64() <= Some(0x80) && range[1].as_u64() >= Some(0x80));
+
+        inside != class["negated"].as_bool().unwrap()
+    };
+
+    let instructions = dump["instructions"].as_array().unwrap();
+    let series = |id: &Value| &instructions[id.as_u64().unwrap() as usize]["series"];
+
+    let mut consuming = 0;
+    let mut end_checks = 0;
+
+    for instruction in instructions {
+        if let Some(target) = instruction.get("notAhead") {
+            assert!(matches_high_byte(series(target)));
+            end_checks += 1;
+        } else if let Some(id) = instruction.get("series") {
+            if !instructions.iter().any(|other| {
+                other
+                    .get("notAhead")
+                    .is_some_and(|target| series(target) == id)
+            }) {
+                assert!(!matches_high_byte(id));
+                consuming += 1;
+            }
+        }
+    }
+
+    assert_eq!((consuming, end_checks), (1, 1));
+}
+
+/// Identical expecteds are merged even when no optimization passes run,
+/// since generated parsers name them by their contents
+#[test]
+fn expecteds_without_optimization() {
+    let ir = json!({
+        "version": 0,
+        "status": "success",
+        "start": 0,
+        "instructions": [
+            { "name": "seq", "first": 1, "second": 2 },
+            { "name": "error", "target": 3, "expected": 3 },
+            { "name": "error", "target": 4, "expected": 4 },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [[97, 97]] }] },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [[97, 97]] }] },
+        ],
+    });
+
+    let ir = serde_json::to_vec(&ir).unwrap();
+
+    let settings = CompilerSettings {
+        max_passes: 0,
+        ..CompilerSettings::none()
+    };
+
+    let code = peg_pack::generate_parser(&ir, settings).unwrap();
+    let start = code.find("enum Expected {").unwrap();
+    let end = start + code[start..].find('}').unwrap();
+
+    let variants = code[start..end]
+        .lines()
+        .skip(1)
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>();
+
+    assert_eq!(variants.len(), 1);
+}
+
+/// Generates a parser that can be included from a build script's output
+#[test]
+fn generate_parser_for_build_scripts() {
+    let ir = json!({
+        "version": 0,
+        "status": "success",
+        "start": 0,
+        "instructions": [
+            { "name": "series", "classes": [{ "negated": false, "ranges": [[1, 1]] }] },
+        ],
+    });
+
+    let ir = serde_json::to_vec(&ir).unwrap();
+    let code = peg_pack::generate_parser(&ir, CompilerSettings::normal()).unwrap();
+
+    assert!(!code.contains("//!"));
+    assert!(code.contains("#[path = \"runtime/mod.rs\"]"));
+
+    let dir = env::temp_dir().join("peg-pack-write-runtime");
+    peg_pack::write_runtime(&dir).unwrap();
+    let runtime = fs::read_to_string(dir.join("runtime/mod.rs")).unwrap();
+    fs::remove_dir_all(dir).unwrap();
+
+    // The generated parser refuses to compile against a runtime from another version
+    let version = format!("_VERSION: &str = {:?};", env!("CARGO_PKG_VERSION"));
+    assert!(code.contains(&version));
+    assert!(runtime.contains(&version));
+    assert!(code.contains("assert!(runtime_version_is(GENERATED_VERSION)"));
+
+    let code =
+        peg_pack::generate_parser_for_runtime_crate(&ir, CompilerSettings::normal()).unwrap();
+
+    assert!(!code.contains("#[path"));
+    assert!(code.contains("use peg_pack_runtime as runtime;"));
+    assert!(code.contains("macro_rules! generate"));
+}
+
+/// Checks for the end of input by its length instead of reading a symbol
+#[test]
+fn end_of_input() {
+    let generate = |negated| {
+        let ir = json!({
+            "version": 0,
+            "status": "success",
+            "start": 0,
+            "instructions": [
+                { "name": "seq", "first": 1, "second": 2 },
+                { "name": "series", "classes": [{ "negated": false, "ranges": [[1, 1]] }] },
+                { "name": "notAhead", "target": 3 },
+                { "name": "series", "classes": [{ "negated": negated, "ranges": [] }] },
+            ],
+        });
+
+        let ir = serde_json::to_vec(&ir).unwrap();
+        peg_pack::generate_parser(&ir, CompilerSettings::normal()).unwrap()
+    };
+
+    assert!(generate(true).contains("ctx.state_end_of_input();"));
+    assert!(!generate(false).contains("ctx.state_end_of_input();"));
+}
+
+/// Dispatches on the next symbol between alternatives that can't start alike
+#[test]
+fn choice_dispatch() {
+    let ir = json!({
+        "version": 0,
+        "status": "success",
+        "start": 0,
+        "instructions": [
+            { "name": "choice", "first": 1, "second": 2 },
+            { "name": "label", "target": 3, "label": "first" },
+            { "name": "label", "target": 4, "label": "second" },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [[5, 9]] }] },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [[1, 1]] }] },
+        ],
+    });
+
+    let ir = serde_json::to_vec(&ir).unwrap();
+    let code = peg_pack::generate_parser(&ir, CompilerSettings::normal()).unwrap();
+    assert!(!code.contains("ctx.state_dispatch"));
+
+    let mut settings = CompilerSettings::normal();
+    settings.choice_dispatch = true;
+
+    let code = peg_pack::generate_parser(&ir, settings).unwrap();
+    assert!(code.contains("Some(1) => Some(state_"));
+    assert!(code.contains("Some(5..=9) => Some(state_"));
+}
+
+/// Marks labels as trivia in the generated parser, ignoring labels it can't produce
+#[test]
+fn trivia_labels() {
+    let ir = json!({
+        "version": 0,
+        "status": "success",
+        "start": 0,
+        "instructions": [
+            { "name": "choice", "first": 1, "second": 2 },
+            { "name": "label", "target": 3, "label": "comment" },
+            { "name": "label", "target": 4, "label": "word" },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [[1, 1]] }] },
+            { "name": "series", "classes": [{ "negated": false, "ranges": [[2, 2]] }] },
+        ],
+        "trivia": ["comment", "whitespace"],
+    });
+
+    let ir = serde_json::to_vec(&ir).unwrap();
+    let code = peg_pack::generate_parser(&ir, CompilerSettings::normal()).unwrap();
+
+    assert!(code.contains("matches!(*self, Label::Comment)"));
+    assert!(!code.contains("Label::Whitespace"));
+}
+
 fn count_cases() -> usize {
     let count = fs::read_dir("tests/cases").unwrap().count();
     assert_eq!(count % 2, 0);
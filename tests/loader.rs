@@ -0,0 +1,211 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+/// Runs peg-pack on a grammar script with some extra arguments, parsing the input with the
+/// generated parser when the script loads. Each grammar is given its own output directory, named
+/// after it
+fn run(name: &str, grammar: &str, args: &[&str], input: &[u8]) -> Output {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::create_dir_all(&dir).unwrap();
+
+    let grammar_path = dir.join("grammar.js");
+    fs::write(&grammar_path, grammar).unwrap();
+
+    let mut command = Command::new(env!("CARGO_BIN_EXE_peg-pack"));
+    command
+        .arg(&grammar_path)
+        .arg("--out-dir")
+        .arg(dir.join("out"))
+        .args(["--interactive", "--emit", "sexp"])
+        .args(args);
+
+    feed(&mut command, input)
+}
+
+/// Runs a command to completion with the given stdin
+fn feed(command: &mut Command, input: &[u8]) -> Output {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child.stdin.take().unwrap().write_all(input).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+/// A parser compiled by peg-pack, which is run directly so that parsing several inputs only builds
+/// it once
+struct Parser(PathBuf);
+
+impl Parser {
+    fn build(name: &str, grammar: &str, args: &[&str]) -> Self {
+        let output = run(name, grammar, args, b"");
+
+        if !matches!(output.status.code(), Some(0..=2)) {
+            panic!("{}", String::from_utf8_lossy(&output.stderr));
+        }
+
+        let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+        Self(dir.join("out/build/parser"))
+    }
+
+    /// The parse tree of the input and whether it had errors, or `None` when it failed to parse
+    fn parse(&self, input: &[u8]) -> Option<(String, bool)> {
+        let output = feed(Command::new(&self.0).arg("--sexp"), input);
+        let stdout = String::from_utf8(output.stdout).unwrap();
+
+        // The tree follows lines reporting how the parse went
+        let tree = stdout.find("(root").map_or("", |start| &stdout[start..]);
+        let tree = tree.trim_end().to_string();
+
+        match output.status.code() {
+            Some(0) => Some((tree, false)),
+            Some(1) => Some((tree, true)),
+            Some(2) => None,
+            _ => panic!("{}", String::from_utf8_lossy(&output.stderr)),
+        }
+    }
+}
+
+/// A recovery alternative is only taken once every other alternative has failed, and wraps the
+/// input up to its synchronization rule in a single error
+#[test]
+fn recovery_alternatives() {
+    let grammar = r#"
+        const statement = () => g.choice(
+            g.label("assignment", g.seq("a", "=")),
+            g.label("call", g.seq("a", "()")),
+            g.recovery(";"),
+        );
+
+        module.exports = g.seq(statement, ";");
+    "#;
+
+    let parser = Parser::build("recovery_alternatives", grammar, &[]);
+    let parse = |input: &[u8]| parser.parse(input);
+
+    let tree = |tree: &str, errors| Some((String::from(tree), errors));
+
+    assert_eq!(
+        parse(b"a=;"),
+        tree("(root 0 3\n  (label assignment 0 2))", false)
+    );
+    assert_eq!(
+        parse(b"a();"),
+        tree("(root 0 4\n  (label call 0 3))", false)
+    );
+    assert_eq!(
+        parse(b"@@@;"),
+        tree("(root 0 4\n  (error (expected assignment call) 0 3))", true)
+    );
+    assert_eq!(
+        parse(b"a(@;"),
+        tree("(root 0 4\n  (error (expected assignment call) 0 3))", true)
+    );
+
+    // The first alternative matched, so recovery isn't tried when what follows fails
+    assert_eq!(parse(b"a=@;"), None);
+}
+
+/// Recovery alternatives can only be the last rule of a choice
+#[test]
+fn misplaced_recovery_alternatives() {
+    let output = run(
+        "misplaced_recovery_alternatives",
+        r#"module.exports = g.choice(g.recovery(";"), "a");"#,
+        &[],
+        b"a",
+    );
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("Recovery alternatives can only be the last rule in a choice"));
+}
+
+/// Turning optimization off only changes how fast the parser is, not what it produces, including
+/// on malformed input that has to be recovered from
+#[test]
+fn unoptimized_parsers_match_optimized_parsers() {
+    let grammar = include_str!("../examples/json.js");
+
+    let optimized = Parser::build("optimized_json", grammar, &[]);
+    let unoptimized = Parser::build("unoptimized_json", grammar, &["--no-opt"]);
+
+    let inputs: [&[u8]; 8] = [
+        b"null",
+        b"[1, 2.5, \"three\"]",
+        b"{\"a\": {\"b\": [true, false]}}",
+        b"",
+        b"[1, 2",
+        b"{\"a\" 1}",
+        b"[[[[[x]]]]]",
+        b"[[[[[[1}",
+    ];
+
+    for input in inputs {
+        assert_eq!(
+            unoptimized.parse(input),
+            optimized.parse(input),
+            "{}",
+            String::from_utf8_lossy(input)
+        );
+    }
+}
+
+/// Left recursive rules grow their match one repetition at a time, so they associate to the left
+#[test]
+fn left_recursion() {
+    let grammar = r#"
+        const sum = () => g.choice(g.label("add", g.seq(sum, "+", num)), num);
+        const num = () => g.label("num", g.oneOf(["0", "9"]));
+
+        module.exports = sum;
+    "#;
+
+    let parser = Parser::build("left_recursion", grammar, &["--left-recursion"]);
+    let parse = |input: &[u8]| parser.parse(input);
+
+    let tree = |tree: &str| Some((String::from(tree), false));
+
+    assert_eq!(
+        parse(b"1+2+3"),
+        tree(
+            "(root 0 5\n  (label add 0 5\n    (label add 0 3\n      (label num 0 1)\n      \
+             (label num 2 1))\n    (label num 4 1)))"
+        )
+    );
+    assert_eq!(parse(b"1"), tree("(root 0 1\n  (label num 0 1))"));
+
+    // The recursion stops growing at the dangling operator, leaving it unmatched
+    assert_eq!(parse(b"1+"), tree("(root 0 1\n  (label num 0 1))"));
+    assert_eq!(parse(b"+1"), None);
+}
+
+/// Left recursion through another rule is grown the same way
+#[test]
+fn indirect_left_recursion() {
+    let grammar = r#"
+        const call = () => g.choice(g.label("call", g.seq(callee, "()")), g.label("name", "f"));
+        const callee = () => g.label("callee", call);
+
+        module.exports = call;
+    "#;
+
+    let parser = Parser::build("indirect_left_recursion", grammar, &["--left-recursion"]);
+    let parse = |input: &[u8]| parser.parse(input);
+
+    let tree = |tree: &str| Some((String::from(tree), false));
+
+    assert_eq!(
+        parse(b"f()()"),
+        tree(
+            "(root 0 5\n  (label call 0 5\n    (label callee 0 3\n      (label call 0 3\n        \
+             (label callee 0 1\n          (label name 0 1))))))"
+        )
+    );
+    assert_eq!(parse(b"()"), None);
+}
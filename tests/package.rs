@@ -0,0 +1,26 @@
+use std::process::Command;
+
+/// Packages and builds both crates the way they would be published, which fails
+/// if either depends on a file that is left out of its package
+#[test]
+fn crates_build_once_packaged() {
+    // Building the tests already fetched every dependency
+    let output = Command::new(env!("CARGO"))
+        .args([
+            "package",
+            "--workspace",
+            "--allow-dirty",
+            "--offline",
+            "--target-dir",
+        ])
+        .arg(env!("CARGO_TARGET_TMPDIR"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}